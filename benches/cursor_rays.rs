@@ -0,0 +1,74 @@
+use bevy::{
+    ecs::system::RunSystemOnce,
+    prelude::*,
+    render::camera::{camera_system, ManualTextureViews},
+    window::{PrimaryWindow, WindowCreated, WindowResized},
+};
+use bevy_mod_raycast::prelude::*;
+use criterion::{criterion_group, criterion_main, Criterion};
+
+macro_rules! raycast_sets {
+    ($($set:ident),*) => {
+        $(
+            #[derive(Reflect)]
+            struct $set;
+        )*
+
+        /// Adds a raycasting plugin for each set, with a cursor source on the next of `cameras`.
+        fn add_sets(app: &mut App, cameras: &[Entity]) {
+            let mut cameras = cameras.iter().cycle();
+            $(
+                app.add_plugins(DeferredRaycastingPlugin::<$set>::default());
+                let camera = *cameras.next().unwrap();
+                app.world.entity_mut(camera).insert(RaycastSource::<$set>::new_cursor());
+            )*
+        }
+    };
+}
+
+raycast_sets!(A, B, C, D, E, F, G, H);
+
+/// An app with 8 raycasting sets and a window, where each set has a cursor source on one of
+/// `cameras` cameras, which all look at the same point.
+fn cursor_app(cameras: usize) -> App {
+    let mut app = App::new();
+    app.add_plugins((MinimalPlugins, AssetPlugin::default()))
+        .init_asset::<Mesh>()
+        .init_asset::<Image>()
+        .init_resource::<ManualTextureViews>()
+        .add_event::<WindowCreated>()
+        .add_event::<WindowResized>();
+    let mut window = Window::default();
+    window.set_cursor_position(Some(Vec2::new(320.0, 240.0)));
+    app.world.spawn((window, PrimaryWindow));
+    let cameras: Vec<_> = (0..cameras)
+        .map(|_| {
+            let transform = GlobalTransform::from_xyz(0.0, 0.0, 5.0);
+            app.world
+                .spawn((Camera::default(), Projection::default(), transform))
+                .id()
+        })
+        .collect();
+    add_sets(&mut app, &cameras);
+    app.world.run_system_once(camera_system::<Projection>);
+    app
+}
+
+fn cursor_rays(c: &mut Criterion) {
+    let mut group = c.benchmark_group("cursor_rays");
+    group.warm_up_time(std::time::Duration::from_millis(500));
+
+    // Every set's source shares one camera, so one cursor ray is built per frame.
+    group.bench_function("8_sets_shared_camera", |b| {
+        let mut app = cursor_app(1);
+        b.iter(|| app.update());
+    });
+    // Every set's source has its own camera, so each of them builds a cursor ray.
+    group.bench_function("8_sets_separate_cameras", |b| {
+        let mut app = cursor_app(8);
+        b.iter(|| app.update());
+    });
+}
+
+criterion_group!(benches, cursor_rays);
+criterion_main!(benches);
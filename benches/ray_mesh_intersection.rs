@@ -1,5 +1,5 @@
 use bevy::math::{Mat4, Vec3};
-use bevy_mod_raycast::prelude::*;
+use bevy_mod_raycast::{prelude::*, raycast::MeshRaycastOptions};
 use criterion::{black_box, criterion_group, criterion_main, Criterion};
 
 fn ptoxznorm(p: u32, size: u32) -> (f32, f32) {
@@ -50,13 +50,13 @@ fn ray_mesh_intersection(c: &mut Criterion) {
             let mesh = mesh_creation(vertices_per_side);
 
             b.iter(|| {
-                black_box(bevy_mod_raycast::prelude::ray_mesh_intersection(
+                black_box(bevy_mod_raycast::raycast::ray_mesh_intersection(
                     &mesh_to_world,
                     &mesh.positions,
                     Some(&mesh.normals),
                     &ray,
                     Some(&mesh.indices),
-                    Backfaces::Cull,
+                    &MeshRaycastOptions::default(),
                 ));
             });
         });
@@ -74,13 +74,13 @@ fn ray_mesh_intersection_no_intersection(c: &mut Criterion) {
             let mesh = mesh_creation(vertices_per_side);
 
             b.iter(|| {
-                black_box(bevy_mod_raycast::prelude::ray_mesh_intersection(
+                black_box(bevy_mod_raycast::raycast::ray_mesh_intersection(
                     &mesh_to_world,
                     &mesh.positions,
                     Some(&mesh.normals),
                     &ray,
                     Some(&mesh.indices),
-                    Backfaces::Cull,
+                    &MeshRaycastOptions::default(),
                 ));
             });
         });
@@ -0,0 +1,57 @@
+//! This example demonstrates how to use [`Raycast::cast_beam`] to find every entity that comes
+//! close to a ray, rather than only the entities the ray exactly intersects.
+
+use bevy::prelude::*;
+use bevy_mod_raycast::prelude::*;
+
+fn main() {
+    App::new()
+        .add_plugins(DefaultPlugins.set(bevy_mod_raycast::low_latency_window_plugin()))
+        .add_plugins(DefaultRaycastingPlugin)
+        .add_systems(Startup, setup)
+        .add_systems(Update, beam)
+        .run();
+}
+
+const BEAM_RADIUS: f32 = 0.5;
+
+fn beam(cursor_ray: Res<CursorRay>, mut raycast: Raycast, mut gizmos: Gizmos) {
+    let Some(cursor_ray) = **cursor_ray else {
+        return;
+    };
+    for (_, hit) in raycast.cast_beam(cursor_ray, BEAM_RADIUS, &default()) {
+        let on_ray = cursor_ray.position(hit.distance());
+        gizmos.line(on_ray, hit.position(), Color::GREEN);
+        gizmos.sphere(hit.position(), Quat::IDENTITY, 0.05, Color::GREEN);
+    }
+}
+
+fn setup(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    commands.spawn(Camera3dBundle {
+        transform: Transform::from_xyz(0.0, 4.0, 8.0).looking_at(Vec3::ZERO, Vec3::Y),
+        ..default()
+    });
+    commands.spawn(PointLightBundle {
+        transform: Transform::from_xyz(4.0, 8.0, 4.0),
+        ..default()
+    });
+    let mesh = meshes.add(Mesh::from(shape::UVSphere {
+        radius: 0.25,
+        ..default()
+    }));
+    let material = materials.add(Color::GRAY.into());
+    for x in -5..=5 {
+        for z in -5..=5 {
+            commands.spawn(PbrBundle {
+                mesh: mesh.clone(),
+                material: material.clone(),
+                transform: Transform::from_xyz(x as f32, 0.0, z as f32),
+                ..default()
+            });
+        }
+    }
+}
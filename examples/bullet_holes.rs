@@ -0,0 +1,91 @@
+//! This example shows how to attach entities to the surface that was hit with `spawn_at_hit`. Click
+//! to shoot the spinning box, and a bullet hole is spawned as a child of the box where the cursor
+//! hit it, so it keeps moving with the box. The box is stretched, and the holes stay square.
+
+use bevy::prelude::*;
+use bevy_mod_raycast::prelude::*;
+
+fn main() {
+    App::new()
+        .add_plugins(DefaultPlugins.set(bevy_mod_raycast::low_latency_window_plugin()))
+        .add_plugins(DefaultRaycastingPlugin)
+        .add_systems(Startup, setup)
+        .add_systems(Update, (spin, shoot))
+        .run();
+}
+
+#[derive(Component)]
+struct Target;
+
+#[derive(Resource)]
+struct BulletHole {
+    mesh: Handle<Mesh>,
+    material: Handle<StandardMaterial>,
+}
+
+fn setup(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    commands.spawn(Camera3dBundle {
+        transform: Transform::from_xyz(0.0, 2.0, 6.0).looking_at(Vec3::ZERO, Vec3::Y),
+        ..default()
+    });
+    commands.spawn(PointLightBundle {
+        transform: Transform::from_xyz(4.0, 8.0, 4.0),
+        ..default()
+    });
+    commands.spawn((
+        PbrBundle {
+            mesh: meshes.add(Mesh::from(shape::Cube { size: 1.0 })),
+            material: materials.add(Color::GRAY.into()),
+            // The non-uniform scale would shear holes attached directly to the box.
+            transform: Transform::from_scale(Vec3::new(3.0, 1.0, 1.5)),
+            ..default()
+        },
+        Target,
+    ));
+    commands.insert_resource(BulletHole {
+        mesh: meshes.add(Mesh::from(shape::Quad::new(Vec2::splat(0.1)))),
+        material: materials.add(StandardMaterial {
+            base_color: Color::BLACK,
+            // Draw the hole in front of the face it lies on.
+            depth_bias: 100.0,
+            ..default()
+        }),
+    });
+}
+
+fn spin(time: Res<Time>, mut targets: Query<&mut Transform, With<Target>>) {
+    for mut transform in &mut targets {
+        transform.rotate_y(time.delta_seconds() * 0.5);
+    }
+}
+
+fn shoot(
+    mut commands: Commands,
+    cursor_ray: Res<CursorRay>,
+    mouse: Res<Input<MouseButton>>,
+    mut raycast: Raycast,
+    bullet_hole: Res<BulletHole>,
+    targets: Query<(), With<Target>>,
+) {
+    let Some(cursor_ray) = **cursor_ray else {
+        return;
+    };
+    if !mouse.just_pressed(MouseButton::Left) {
+        return;
+    }
+    // Only shoot the box, not the holes already in it.
+    let filter = |entity| targets.contains(entity);
+    let settings = RaycastSettings::default().with_filter(&filter);
+    if let Some((entity, hit)) = raycast.cast_ray(cursor_ray, &settings).first() {
+        let hole = PbrBundle {
+            mesh: bullet_hole.mesh.clone(),
+            material: bullet_hole.material.clone(),
+            ..default()
+        };
+        commands.spawn_at_hit(hole, *entity, hit);
+    }
+}
@@ -0,0 +1,104 @@
+//! This example explains why the cursor isn't hitting an entity. Right click an object to mark it,
+//! then move the cursor anywhere and press E to print why the marked object was or wasn't hit,
+//! such as being hidden, outside the ray's bounds, or behind another object.
+
+use bevy::prelude::*;
+use bevy_mod_raycast::prelude::*;
+
+fn main() {
+    App::new()
+        .add_plugins((
+            DefaultPlugins.set(bevy_mod_raycast::low_latency_window_plugin()),
+            DeferredRaycastingPlugin::<()>::default(),
+        ))
+        .add_systems(Startup, setup)
+        .add_systems(Update, (mark_target, explain, draw_mark))
+        .run();
+}
+
+fn setup(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    commands.spawn((
+        Camera3dBundle {
+            transform: Transform::from_xyz(0.0, 2.0, 10.0).looking_at(Vec3::ZERO, Vec3::Y),
+            ..default()
+        },
+        RaycastCameraCursorBundle::<()>::default(),
+    ));
+    commands.spawn(PointLightBundle {
+        transform: Transform::from_xyz(4.0, 8.0, 8.0),
+        ..default()
+    });
+    let material = materials.add(Color::GRAY.into());
+    let cube = meshes.add(Mesh::from(shape::Cube { size: 1.0 }));
+    let sphere = meshes.add(Mesh::try_from(shape::Icosphere::default()).unwrap());
+    // Two cubes, one behind the other, and a sphere that is not in the raycasting set.
+    for z in [0.0, -3.0] {
+        commands.spawn((
+            PbrBundle {
+                mesh: cube.clone(),
+                material: material.clone(),
+                transform: Transform::from_xyz(-2.0, 0.0, z),
+                ..default()
+            },
+            PickableBundle::<()>::default(),
+        ));
+    }
+    commands.spawn(PbrBundle {
+        mesh: sphere,
+        material,
+        transform: Transform::from_xyz(2.0, 0.0, 0.0),
+        ..default()
+    });
+}
+
+/// Marks the entity under the cursor as the target to explain. Entities that aren't in the
+/// raycasting set can't be clicked, so this uses the immediate mode API to find any mesh.
+fn mark_target(
+    buttons: Res<Input<MouseButton>>,
+    mut raycast: Raycast,
+    mut sources: Query<&mut RaycastSource<()>>,
+) {
+    if !buttons.just_pressed(MouseButton::Right) {
+        return;
+    }
+    for mut source in &mut sources {
+        let Some(ray) = source.get_ray() else {
+            continue;
+        };
+        if let Some((entity, _)) = raycast.cast_ray(ray, &default()).first() {
+            source.explain_target = Some(*entity);
+            info!("Marked {entity:?}, press E to explain why it is or isn't hit");
+        }
+    }
+}
+
+/// Prints why the marked entity was or wasn't hit by the cursor.
+fn explain(keys: Res<Input<KeyCode>>, sources: Query<&RaycastSource<()>>) {
+    if !keys.just_pressed(KeyCode::E) {
+        return;
+    }
+    for source in &sources {
+        if let Some(explanation) = source.explanation() {
+            info!("{explanation:?}");
+        }
+    }
+}
+
+/// Draws a marker over the marked entity.
+fn draw_mark(
+    sources: Query<&RaycastSource<()>>,
+    transforms: Query<&GlobalTransform>,
+    mut gizmos: Gizmos,
+) {
+    for source in &sources {
+        let Some(transform) = source.explain_target.and_then(|e| transforms.get(e).ok()) else {
+            continue;
+        };
+        let marker = transform.translation() + Vec3::Y;
+        gizmos.sphere(marker, Quat::IDENTITY, 0.1, Color::YELLOW);
+    }
+}
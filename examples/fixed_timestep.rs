@@ -0,0 +1,62 @@
+//! This example runs the deferred raycasting plugin in `FixedUpdate` at 10 Hz, as it would be when
+//! raycasts drive fixed timestep gameplay. The red marker is the hit found by the latest raycast,
+//! which jumps ten times per second. The green marker uses `InterpolatedHit` to blend the last two
+//! hits, and moves smoothly at the rendering frame rate.
+
+use bevy::prelude::*;
+use bevy_mod_raycast::prelude::*;
+
+fn main() {
+    App::new()
+        .add_plugins((
+            DefaultPlugins.set(bevy_mod_raycast::low_latency_window_plugin()),
+            DeferredRaycastingPlugin::<()>::default().with_schedule(FixedUpdate),
+        ))
+        .insert_resource(Time::<Fixed>::from_hz(10.0))
+        .add_systems(Startup, setup)
+        .add_systems(Update, draw_markers)
+        .run();
+}
+
+fn setup(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    commands.spawn((
+        Camera3dBundle {
+            transform: Transform::from_xyz(0.0, 6.0, 8.0).looking_at(Vec3::ZERO, Vec3::Y),
+            ..default()
+        },
+        RaycastCameraCursorBundle::<()>::default(),
+        InterpolatedHit::<()>::default(),
+    ));
+    commands.spawn(PointLightBundle {
+        transform: Transform::from_xyz(4.0, 8.0, 4.0),
+        ..default()
+    });
+    commands.spawn((
+        PbrBundle {
+            mesh: meshes.add(Mesh::from(shape::Plane::from_size(10.0))),
+            material: materials.add(Color::GRAY.into()),
+            ..default()
+        },
+        PickableBundle::<()>::default(),
+    ));
+}
+
+/// Draws the authoritative fixed timestep hit, and the interpolated hit for the current frame.
+fn draw_markers(
+    fixed_time: Res<Time<Fixed>>,
+    sources: Query<(&RaycastSource<()>, &InterpolatedHit<()>)>,
+    mut gizmos: Gizmos,
+) {
+    for (source, hit) in &sources {
+        if let Some((_, intersection)) = source.get_nearest_intersection() {
+            gizmos.sphere(intersection.position(), Quat::IDENTITY, 0.2, Color::RED);
+        }
+        if let Some(position) = hit.position(fixed_time.overstep_percentage()) {
+            gizmos.sphere(position, Quat::IDENTITY, 0.15, Color::GREEN);
+        }
+    }
+}
@@ -0,0 +1,60 @@
+//! This example demonstrates how to use [`Raycast::cast_polyline`] to find where a curved path
+//! first hits something. A grappling hook is fired along an arc, and stops at the wall it hits.
+
+use bevy::prelude::*;
+use bevy_mod_raycast::prelude::*;
+
+fn main() {
+    App::new()
+        .add_plugins(DefaultPlugins)
+        .add_systems(Startup, setup)
+        .add_systems(Update, grappling_hook)
+        .run();
+}
+
+/// The number of straight segments used to approximate the arc of the hook.
+const SEGMENTS: usize = 32;
+
+fn grappling_hook(mut raycast: Raycast, mut gizmos: Gizmos, time: Res<Time>) {
+    // Sweep the hook from side to side, curving under gravity.
+    let aim = Vec3::new((time.elapsed_seconds() * 0.5).sin() * 4.0, 6.0, -10.0);
+    let gravity = Vec3::new(0.0, -9.0, 0.0);
+    let points: Vec<Vec3> = (0..=SEGMENTS)
+        .map(|i| {
+            let t = i as f32 / SEGMENTS as f32 * 1.5;
+            aim * t + 0.5 * gravity * t * t
+        })
+        .collect();
+
+    let hit = raycast.cast_polyline(&points, &default());
+    // Only draw the path up to the hook.
+    let end = hit.as_ref().map_or(SEGMENTS, |hit| hit.segment);
+    gizmos.linestrip(points[..=end].iter().copied(), Color::YELLOW);
+    if let Some(hit) = hit {
+        let position = hit.intersection.position();
+        gizmos.line(points[end], position, Color::YELLOW);
+        gizmos.sphere(position, Quat::IDENTITY, 0.1, Color::RED);
+    }
+}
+
+fn setup(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    commands.spawn(Camera3dBundle {
+        transform: Transform::from_xyz(8.0, 4.0, 6.0)
+            .looking_at(Vec3::new(0.0, 0.0, -6.0), Vec3::Y),
+        ..default()
+    });
+    commands.spawn(PointLightBundle {
+        transform: Transform::from_xyz(4.0, 8.0, 4.0),
+        ..default()
+    });
+    commands.spawn(PbrBundle {
+        mesh: meshes.add(Mesh::from(shape::Box::new(6.0, 4.0, 0.5))),
+        material: materials.add(Color::GRAY.into()),
+        transform: Transform::from_xyz(0.0, 0.0, -9.0),
+        ..default()
+    });
+}
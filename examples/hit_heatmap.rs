@@ -0,0 +1,105 @@
+//! This example records how often each object is under the cursor with `RaycastHitStats`, and after
+//! 30 seconds tints every object by how often it was the nearest hit, like a heatmap of what the
+//! player aims at. Press space to clear the counters and record again.
+
+use bevy::prelude::*;
+use bevy_mod_raycast::prelude::*;
+
+const RECORDING_SECONDS: f32 = 30.0;
+
+fn main() {
+    App::new()
+        .add_plugins((
+            DefaultPlugins.set(bevy_mod_raycast::low_latency_window_plugin()),
+            DeferredRaycastingPlugin::<()>::default(),
+        ))
+        .insert_resource(Recording(Timer::from_seconds(
+            RECORDING_SECONDS,
+            TimerMode::Once,
+        )))
+        .add_systems(Startup, setup)
+        .add_systems(Update, (show_heatmap, restart))
+        .run();
+}
+
+#[derive(Resource)]
+struct Recording(Timer);
+
+fn setup(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    commands.spawn((
+        Camera3dBundle {
+            transform: Transform::from_xyz(0.0, 0.0, 12.0),
+            ..default()
+        },
+        RaycastCameraCursorBundle::<()>::default(),
+    ));
+    commands.spawn(PointLightBundle {
+        transform: Transform::from_xyz(4.0, 8.0, 8.0),
+        ..default()
+    });
+    let mesh = meshes.add(Mesh::from(shape::Cube { size: 1.0 }));
+    for x in -4..=4 {
+        for y in -2..=2 {
+            commands.spawn((
+                PbrBundle {
+                    mesh: mesh.clone(),
+                    // Each cube has its own material, so it can be tinted separately.
+                    material: materials.add(Color::GRAY.into()),
+                    transform: Transform::from_xyz(x as f32 * 1.5, y as f32 * 1.5, 0.0),
+                    ..default()
+                },
+                PickableBundle::<()>::default(),
+                RaycastHitStats::<()>::default(),
+            ));
+        }
+    }
+}
+
+/// Once the recording is over, tints each cube from gray to red by how often it was the nearest hit.
+fn show_heatmap(
+    time: Res<Time>,
+    mut recording: ResMut<Recording>,
+    cubes: Query<(&RaycastHitStats<()>, &Handle<StandardMaterial>)>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    if !recording.0.tick(time.delta()).just_finished() {
+        return;
+    }
+    let most_hits = cubes
+        .iter()
+        .map(|(stats, _)| stats.frames_top_hit)
+        .max()
+        .unwrap_or(0)
+        .max(1);
+    for (stats, material) in &cubes {
+        let heat = stats.frames_top_hit as f32 / most_hits as f32;
+        if let Some(material) = materials.get_mut(material) {
+            material.base_color =
+                Color::rgb(0.5 + heat * 0.5, 0.5 * (1.0 - heat), 0.5 * (1.0 - heat));
+        }
+    }
+    info!("Recording finished, press space to record again");
+}
+
+/// Clears the counters and the tint, and starts a new recording.
+fn restart(
+    keys: Res<Input<KeyCode>>,
+    mut recording: ResMut<Recording>,
+    mut cubes: Query<(&mut RaycastHitStats<()>, &Handle<StandardMaterial>)>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    if !keys.just_pressed(KeyCode::Space) {
+        return;
+    }
+    recording.0.reset();
+    for (mut stats, material) in &mut cubes {
+        stats.reset();
+        if let Some(material) = materials.get_mut(material) {
+            material.base_color = Color::GRAY;
+        }
+    }
+}
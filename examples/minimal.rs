@@ -32,7 +32,7 @@ fn setup(
     commands.spawn(Camera3dBundle::default());
     commands.spawn(PointLightBundle::default());
     commands.spawn(PbrBundle {
-        mesh: meshes.add(Mesh::try_from(shape::Capsule::default()).unwrap()),
+        mesh: meshes.add(Mesh::from(shape::Capsule::default())),
         material: materials.add(Color::rgb(1.0, 1.0, 1.0).into()),
         transform: Transform::from_translation(DIST),
         ..default()
@@ -42,16 +42,15 @@ fn setup(
     // spawn an entity and mark it as a raycasting source, using its `GlobalTransform`.
     commands.spawn((
         MovingRaycaster,
-        SpatialBundle::default(),
-        RaycastSource::<MyRaycastSet>::new_transform_empty(),
+        RaycastPointerBundle::<MyRaycastSet>::default(),
     ));
     commands.spawn((
         PbrBundle {
-            mesh: meshes.add(Mesh::try_from(shape::Capsule::default()).unwrap()),
+            mesh: meshes.add(Mesh::from(shape::Capsule::default())),
             material: materials.add(Color::rgb(1.0, 1.0, 1.0).into()),
             transform: Transform::from_translation(DIST),
             ..default()
         },
-        RaycastMesh::<MyRaycastSet>::default(), // Make this mesh ray cast-able
+        PickableBundle::<MyRaycastSet>::default(), // Make this mesh ray cast-able
     ));
 }
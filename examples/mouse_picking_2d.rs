@@ -1,5 +1,5 @@
 use bevy::{prelude::*, sprite::MaterialMesh2dBundle};
-use bevy_mod_raycast::prelude::*;
+use bevy_mod_raycast::{debug::print_intersections, prelude::*};
 
 fn main() {
     App::new()
@@ -15,7 +15,10 @@ fn setup(
     mut meshes: ResMut<Assets<Mesh>>,
     mut materials: ResMut<Assets<ColorMaterial>>,
 ) {
-    commands.spawn((Camera2dBundle::default(), RaycastSource::<()>::new_cursor()));
+    commands.spawn((
+        Camera2dBundle::default(),
+        RaycastCameraCursorBundle::<()>::default(),
+    ));
     commands.spawn((
         MaterialMesh2dBundle {
             mesh: meshes.add(Mesh::from(shape::Circle::default())).into(),
@@ -23,6 +26,6 @@ fn setup(
             material: materials.add(ColorMaterial::from(Color::PURPLE)),
             ..default()
         },
-        RaycastMesh::<()>::default(), // Make this mesh ray cast-able;
+        PickableBundle::<()>::default(), // Make this mesh ray cast-able
     ));
 }
@@ -23,7 +23,8 @@ fn setup(
 ) {
     commands.spawn((
         Camera3dBundle::default(),
-        RaycastSource::<()>::new_cursor(), // Set this camera as a raycaster using the mouse cursor
+        // Set this camera as a raycaster using the mouse cursor
+        RaycastCameraCursorBundle::<()>::default(),
     ));
     commands.spawn(PointLightBundle::default());
     commands.spawn((
@@ -33,6 +34,6 @@ fn setup(
             transform: Transform::from_xyz(0.0, 0.0, -5.0),
             ..default()
         },
-        RaycastMesh::<()>::default(), // Make this mesh ray cast-able;
+        PickableBundle::<()>::default(), // Make this mesh ray cast-able
     ));
 }
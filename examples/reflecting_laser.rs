@@ -18,9 +18,6 @@ fn main() {
 const MAX_BOUNCES: usize = 64;
 const LASER_SPEED: f32 = 0.03;
 
-#[derive(Reflect)]
-struct Laser;
-
 fn bouncing_raycast(
     mut raycast: Raycast,
     mut gizmos: Gizmos,
@@ -53,11 +50,7 @@ fn bounce_ray(
             let bright = 1.0 + 10.0 * (1.0 - i as f32 / MAX_BOUNCES as f32);
             intersections.push((hit.position(), color * bright));
             gizmos.sphere(hit.position(), Quat::IDENTITY, 0.005, color * bright * 2.0);
-            let ray_dir = ray.direction();
-            // reflect the ray
-            let proj = (ray_dir.dot(hit.normal()) / hit.normal().dot(hit.normal())) * hit.normal();
-            ray.set_direction(ray_dir - 2.0 * proj);
-            ray.set_origin(hit.position() + ray.direction() * 1e-6);
+            ray = hit.reflected_ray(ray.direction());
         } else {
             break;
         }
@@ -38,7 +38,7 @@ fn setup_scene(mut commands: Commands, asset_server: Res<AssetServer>) {
 
     commands.spawn((
         Camera3dBundle::default(),
-        RaycastSource::<MyRaycastSet>::new_cursor(),
+        RaycastCameraCursorBundle::<MyRaycastSet>::default(),
     ));
 
     let mut i = 0;
@@ -68,7 +68,7 @@ fn make_scene_pickable(
     for entity in &mesh_query {
         commands
             .entity(entity)
-            .insert(RaycastMesh::<MyRaycastSet>::default()); // Make this mesh ray cast-able
+            .insert(PickableBundle::<MyRaycastSet>::default()); // Make this mesh ray cast-able
     }
 }
 
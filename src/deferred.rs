@@ -7,8 +7,8 @@
 //! built in modes for common use cases. You can set this entity to cast based on where it is
 //! pointing, using [`RaycastMethod::Transform`], or you can use [`RaycastMethod::Screenspace`]
 //! along with a screenspace coordinate if the entity is a camera and you want to shoot out of a
-//! reticle, or you can use [`RaycastMethod::Cursor`] if you want to automatically use the cursor to
-//! build rays.
+//! reticle, or you can use [`RaycastMethod::Cursor`] or [`RaycastMethod::Touch`] if you want to
+//! automatically use the cursor or the touch screen to build rays.
 //!
 //! These components are both generic, and raycasts will only happen between entities with the same
 //! generic parameter. For example, [`RaycastSource<Foo>`] can cast rays against meshes with
@@ -19,44 +19,118 @@ use std::{
     fmt::Debug,
     hash::{Hash, Hasher},
     marker::PhantomData,
+    sync::Arc,
 };
 
 use bevy_app::prelude::*;
-use bevy_ecs::prelude::*;
-use bevy_math::{Mat4, Vec2};
+use bevy_asset::{AssetEvent, AssetId, Handle};
+use bevy_ecs::{
+    prelude::*,
+    schedule::{InternedScheduleLabel, ScheduleLabel, SystemConfigs},
+    system::EntityCommands,
+    world::EntityWorldMut,
+};
+use bevy_hierarchy::{BuildWorldChildren, Children, HierarchyQueryExt};
+use bevy_input::touch::Touches;
+use bevy_math::{Mat4, Vec2, Vec3};
 use bevy_reflect::{Reflect, TypePath};
-use bevy_render::camera::Camera;
-use bevy_transform::components::GlobalTransform;
-use bevy_utils::{default, tracing::*};
-use bevy_window::{PrimaryWindow, Window};
+use bevy_render::{
+    camera::{Camera, RenderTarget},
+    mesh::Mesh,
+    view::VisibilityBundle,
+};
+use bevy_time::Time;
+use bevy_transform::{
+    components::{GlobalTransform, Transform},
+    TransformBundle,
+};
+use bevy_utils::{default, tracing::*, Duration, HashMap, HashSet};
+use bevy_window::{PrimaryWindow, Window, WindowRef};
+use smallvec::SmallVec;
 
-use crate::{immediate::*, primitives::*};
+use crate::{
+    immediate::*,
+    markers::{RaycastLayers, SimplifiedMesh},
+    primitives::*,
+    raycast::{Backfaces, MaxSlope},
+};
 
-pub struct DeferredRaycastingPlugin<T>(pub PhantomData<fn() -> T>);
+pub struct DeferredRaycastingPlugin<T> {
+    settings: RaycastPluginSettings<T>,
+    schedule: InternedScheduleLabel,
+    snapshot_group: Option<u32>,
+}
 impl<T: TypePath + Send + Sync> Plugin for DeferredRaycastingPlugin<T> {
     fn build(&self, app: &mut App) {
-        app.init_resource::<RaycastPluginState<T>>().add_systems(
-            First,
+        app.insert_resource(self.settings.clone());
+        if !app.is_plugin_added::<crate::SweptTargetPlugin>() {
+            app.add_plugins(crate::SweptTargetPlugin);
+        }
+        if !app.is_plugin_added::<crate::MeshBoundsPlugin>() {
+            app.add_plugins(crate::MeshBoundsPlugin);
+        }
+        // The bounds of modified meshes are updated, and their stale intersections cleared, before
+        // the next raycast.
+        app.configure_sets(
+            self.schedule,
+            RaycastSystem::BuildRays::<T>.after(crate::update_modified_mesh_bounds),
+        )
+        .add_systems(
+            self.schedule,
+            clear_modified_mesh_intersections::<T>.before(RaycastSystem::BuildRays::<T>),
+        );
+
+        init_cursor_ray_cache(app);
+
+        app.init_resource::<RaycastPluginState<T>>()
+            .add_event::<RaycastHit<T>>()
+            .add_systems(
+                self.schedule,
+                (
+                    build_rays::<T>
+                        .in_set(RaycastSystem::BuildRays::<T>)
+                        .run_if(|state: Res<RaycastPluginState<T>>| state.build_rays),
+                    update_raycast::<T>
+                        .in_set(RaycastSystem::UpdateRaycast::<T>)
+                        .run_if(|state: Res<RaycastPluginState<T>>| state.update_raycast),
+                    (
+                        update_target_intersections::<T>,
+                        update_interpolated_hits::<T>,
+                        emit_hit_events::<T>,
+                    )
+                        .in_set(RaycastSystem::UpdateIntersections::<T>)
+                        .run_if(|state: Res<RaycastPluginState<T>>| state.update_raycast),
+                )
+                    .chain(),
+            );
+
+        app.init_resource::<RaycastMetrics<T>>();
+        app.init_resource::<RaycastDebugInfo<T>>().add_systems(
+            self.schedule,
             (
-                build_rays::<T>
-                    .in_set(RaycastSystem::BuildRays::<T>)
-                    .run_if(|state: Res<RaycastPluginState<T>>| state.build_rays),
-                update_raycast::<T>
-                    .in_set(RaycastSystem::UpdateRaycast::<T>)
-                    .run_if(|state: Res<RaycastPluginState<T>>| state.update_raycast),
-                update_target_intersections::<T>
-                    .in_set(RaycastSystem::UpdateIntersections::<T>)
-                    .run_if(|state: Res<RaycastPluginState<T>>| state.update_raycast),
+                tag_scene_meshes::<T>,
+                check_raycast_meshes::<T>,
+                check_duplicate_sources::<T>.after(RaycastSystem::BuildRays::<T>),
             )
-                .chain(),
+                .before(RaycastSystem::UpdateRaycast::<T>),
         );
 
+        if let Some(group_id) = self.snapshot_group {
+            app.insert_resource(RaycastFrameSnapshot::<T>::new(group_id))
+                .add_systems(
+                    self.schedule,
+                    update_frame_snapshot::<T>.after(RaycastSystem::UpdateIntersections::<T>),
+                );
+        }
+
         app.register_type::<RaycastMesh<T>>()
-            .register_type::<RaycastSource<T>>();
+            .register_type::<RaycastSceneRoot<T>>()
+            .register_type::<RaycastSource<T>>()
+            .register_type::<RaycastHitStats<T>>();
 
         #[cfg(feature = "debug")]
         app.add_systems(
-            First,
+            self.schedule,
             debug::update_debug_cursor::<T>
                 .in_set(RaycastSystem::UpdateDebugCursor::<T>)
                 .run_if(|state: Res<RaycastPluginState<T>>| state.update_debug_cursor)
@@ -66,10 +140,205 @@ impl<T: TypePath + Send + Sync> Plugin for DeferredRaycastingPlugin<T> {
 }
 impl<T> Default for DeferredRaycastingPlugin<T> {
     fn default() -> Self {
-        DeferredRaycastingPlugin(PhantomData)
+        DeferredRaycastingPlugin {
+            settings: RaycastPluginSettings::default(),
+            schedule: First.intern(),
+            snapshot_group: None,
+        }
+    }
+}
+
+impl<T> DeferredRaycastingPlugin<T> {
+    /// Set the [`RaycastPluginSettings`] this plugin starts with.
+    pub fn with_settings(self, settings: RaycastPluginSettings<T>) -> Self {
+        Self { settings, ..self }
+    }
+
+    /// Set the schedule the raycasting systems run in, [`First`] by default. Use [`FixedUpdate`] to
+    /// raycast at the same rate as fixed timestep gameplay, and [`InterpolatedHit`] to present the
+    /// hits smoothly at the rendering frame rate.
+    pub fn with_schedule(mut self, schedule: impl ScheduleLabel) -> Self {
+        self.schedule = schedule.intern();
+        self
+    }
+
+    /// Flatten the intersections of every source into the [`RaycastFrameSnapshot<T>`] resource each
+    /// frame, tagging each record with `group_id` so the snapshots of several raycasting sets can
+    /// be told apart.
+    pub fn with_frame_snapshot(mut self, group_id: u32) -> Self {
+        self.snapshot_group = Some(group_id);
+        self
+    }
+
+    /// Set how backfaces are treated for meshes without a
+    /// [`NoBackfaceCulling`](crate::markers::NoBackfaceCulling) component.
+    pub fn with_backfaces(mut self, backfaces: Backfaces) -> Self {
+        self.settings.backfaces = backfaces;
+        self
+    }
+
+    /// Set the distance AABBs are grown by before checking if a ray intersects them.
+    pub fn with_aabb_inflation(mut self, aabb_inflation: f32) -> Self {
+        self.settings.aabb_inflation = aabb_inflation;
+        self
+    }
+
+    /// Set the maximum amount of time each raycast can spend testing meshes for intersections.
+    pub fn with_time_budget(mut self, time_budget: Duration) -> Self {
+        self.settings.time_budget = Some(time_budget);
+        self
+    }
+
+    /// Set the triangle count above which a warning is logged for slow meshes, or `None` to disable
+    /// the warning.
+    pub fn with_large_mesh_threshold(mut self, large_mesh_threshold: Option<usize>) -> Self {
+        self.settings.large_mesh_threshold = large_mesh_threshold;
+        self
+    }
+
+    /// Set whether only the first of the cursor sources that share a camera viewport is raycast.
+    pub fn with_disable_duplicate_sources(mut self, disable_duplicate_sources: bool) -> Self {
+        self.settings.disable_duplicate_sources = disable_duplicate_sources;
+        self
+    }
+
+    /// Set the number of intersections each source keeps, or `None` to keep every intersection.
+    pub fn with_max_intersections(mut self, max_intersections: Option<usize>) -> Self {
+        self.settings.max_intersections = max_intersections;
+        self
+    }
+
+    /// Set what to do with cursor positions outside the window.
+    pub fn with_cursor_bounds(mut self, cursor_bounds: CursorBounds) -> Self {
+        self.settings.cursor_bounds = cursor_bounds;
+        self
+    }
+
+    /// Set whether every raycast is checked against a brute force reference, see
+    /// [`RaycastPluginSettings::audit`]. This is expensive, and meant for debug builds and tests.
+    pub fn with_audit(mut self, audit: bool) -> Self {
+        self.settings.audit = audit;
+        self
+    }
+
+    /// Set whether every source only tests the AABBs of meshes, instead of their triangles.
+    pub fn with_volume_only(mut self, volume_only: bool) -> Self {
+        self.settings.volume_only = volume_only;
+        self
+    }
+
+    /// Set how close to the origin of a ray hits are ignored, relative to the magnitude of the
+    /// origin.
+    pub fn with_self_hit_epsilon(mut self, self_hit_epsilon: f32) -> Self {
+        self.settings.self_hit_epsilon = self_hit_epsilon;
+        self
+    }
+}
+
+fn init_cursor_ray_cache(app: &mut App) {
+    app.init_resource::<VirtualPointer>()
+        .register_type::<VirtualPointer>();
+    if !app.world.contains_resource::<CursorRayCache>() {
+        app.init_resource::<CursorRayCache>()
+            .add_systems(Last, clear_cursor_ray_cache);
+    }
+}
+
+/// Raycasts [`RaycastSource<G>`]s against the meshes of several raycasting sets, where `G` is a
+/// tuple of those sets, such as `(Foo, Bar)`. A [`RaycastSource<(Foo, Bar)>`] hits entities with a
+/// [`RaycastMesh<Foo>`] or a [`RaycastMesh<Bar>`], without adding a third mesh component to them.
+/// This is useful for a few special sources, such as an editor cursor that can select anything,
+/// and replaces merging the intersections of a `RaycastSource<Foo>` and a `RaycastSource<Bar>` on
+/// the same camera by hand.
+///
+/// Each set is raycast with its own [`RaycastPluginSettings`], so the [`DeferredRaycastingPlugin`]
+/// of each set in `G` must also be added. The intersections of all sets are merged by distance,
+/// and the set each one came from is given by [`RaycastSource::intersection_groups`]. Entities in
+/// several sets are only hit once, by the first of their sets in `G`.
+///
+/// Intersections at the same distance are ordered by their set, in the order of `G`, and those of
+/// the same set keep the order of that set's raycast, which sorts intersections at the same
+/// distance by the distance to the AABBs of their entities, then by [`Entity`].
+///
+/// Sources cast across sets don't [sweep](RaycastSource::sweep_samples), progressively
+/// [refine](RaycastSource::refinement) or [explain](RaycastSource::explain_target) their
+/// raycasts, don't [keep their last hit](RaycastSource::keep_last_hit), don't send
+/// [`RaycastHit`] events, and their hits are not added to each set's [`RaycastMesh`]es. Their rays
+/// are built with the [`cursor_bounds`](RaycastPluginSettings::cursor_bounds) of the
+/// [`RaycastPluginSettings`] of `G`.
+pub struct CrossGroupRaycastPlugin<G> {
+    schedule: InternedScheduleLabel,
+    _marker: PhantomData<fn() -> G>,
+}
+
+impl<G: RaycastGroups> Plugin for CrossGroupRaycastPlugin<G> {
+    fn build(&self, app: &mut App) {
+        init_cursor_ray_cache(app);
+        app.init_resource::<RaycastMetrics<G>>()
+            .init_resource::<RaycastPluginSettings<G>>();
+        app.add_systems(
+            self.schedule,
+            (
+                build_rays::<G>.in_set(RaycastSystem::BuildRays::<G>),
+                (
+                    clear_cross_group_hits::<G>,
+                    G::cast_systems(),
+                    merge_cross_group_hits::<G>,
+                )
+                    .chain()
+                    .in_set(RaycastSystem::UpdateRaycast::<G>),
+            )
+                .chain(),
+        );
+        app.register_type::<RaycastSource<G>>();
+    }
+}
+
+impl<G> Default for CrossGroupRaycastPlugin<G> {
+    fn default() -> Self {
+        CrossGroupRaycastPlugin {
+            schedule: First.intern(),
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<G> CrossGroupRaycastPlugin<G> {
+    /// Set the schedule the raycasting systems run in, [`First`] by default.
+    pub fn with_schedule(mut self, schedule: impl ScheduleLabel) -> Self {
+        self.schedule = schedule.intern();
+        self
     }
 }
 
+/// A tuple of raycasting sets that a [`RaycastSource`] can cast across. See
+/// [`CrossGroupRaycastPlugin`].
+pub trait RaycastGroups: TypePath + Send + Sync + 'static {
+    /// The systems that raycast the sources of this tuple against the meshes of each set.
+    fn cast_systems() -> SystemConfigs;
+
+    /// The names of the sets, indexed by [`RaycastSource::intersection_groups`].
+    fn group_names() -> Vec<&'static str>;
+}
+
+macro_rules! impl_raycast_groups {
+    ($(($set:ident, $index:tt)),*) => {
+        impl<$($set: TypePath + Send + Sync),*> RaycastGroups for ($($set,)*) {
+            fn cast_systems() -> SystemConfigs {
+                ($(cast_cross_group::<Self, $set, $index>,)*).chain()
+            }
+
+            fn group_names() -> Vec<&'static str> {
+                vec![$($set::short_type_path()),*]
+            }
+        }
+    };
+}
+
+impl_raycast_groups!((A, 0), (B, 1));
+impl_raycast_groups!((A, 0), (B, 1), (C, 2));
+impl_raycast_groups!((A, 0), (B, 1), (C, 2), (D, 3));
+
 #[derive(SystemSet)]
 pub enum RaycastSystem<T> {
     BuildRays,
@@ -149,17 +418,292 @@ impl<T> RaycastPluginState<T> {
     }
 }
 
+/// Settings used by every raycast of the raycasting set `T`. These are read every frame, so changes
+/// take effect immediately. See [`RaycastSettings`] for details on each setting.
+///
+/// There is no batch size setting: each source's ray is tested against the meshes it may hit in a
+/// single pass, so there are no batches to size. Use `time_budget` to bound the work of a frame.
+#[derive(Resource)]
+pub struct RaycastPluginSettings<T> {
+    /// How backfaces are treated for entities without a
+    /// [`NoBackfaceCulling`](crate::markers::NoBackfaceCulling) or
+    /// [`HitFaces`](crate::markers::HitFaces) component, unless a source overrides it with
+    /// [`RaycastSource::backfaces`].
+    pub backfaces: Backfaces,
+    /// The distance AABBs are grown by before checking if a ray intersects them.
+    pub aabb_inflation: f32,
+    /// The maximum amount of time each raycast spends testing meshes for intersections, or `None`
+    /// for no limit.
+    pub time_budget: Option<Duration>,
+    /// The triangle count above which a warning is logged for slow meshes, or `None` to disable
+    /// the warning.
+    pub large_mesh_threshold: Option<usize>,
+    /// When `true`, only the first of the cursor sources that share a camera viewport is raycast.
+    /// See [`RaycastDebugInfo::duplicate_sources`].
+    pub disable_duplicate_sources: bool,
+    /// The number of intersections each source keeps, or `None` to keep every intersection. When a
+    /// ray hits more entities than this, only the nearest are kept, and the source is flagged as
+    /// [overflowed](RaycastSource::overflowed). This stops pathological content, such as thousands
+    /// of overlapping meshes, from stalling every system that reads the intersections.
+    pub max_intersections: Option<usize>,
+    /// What to do with cursor positions outside the window, see [`CursorBounds`].
+    pub cursor_bounds: CursorBounds,
+    /// When `true`, every raycast is cast again with [`Raycast::cast_ray_reference`], which tests
+    /// every mesh without any of the optimizations of the raycast, and any difference between the
+    /// two is logged and recorded in [`RaycastMetrics::audit_divergences`]. This is a safety net
+    /// for the optimizations of this crate, and is **very expensive**: only enable it in debug
+    /// builds and tests.
+    ///
+    /// Raycasts that ran out of time budget, raycasts of sources with
+    /// [`any_hit`](RaycastSource::any_hit), the sampled rays of swept raycasts, and instanced
+    /// intersections aren't audited.
+    pub audit: bool,
+    /// When set, meshes made of lines are picked by rays passing within this distance of them, in
+    /// world space, such as editor guides drawn as a `LineList`. See
+    /// [`RaycastSettings::line_tolerance`].
+    pub line_tolerance: Option<f32>,
+    /// Selects the intersection algorithm of every source: when `true`, only the AABBs of meshes
+    /// are tested with the slab test, instead of their triangles with Möller–Trumbore. Sources
+    /// can also select this individually with [`RaycastSource::volume_only`].
+    pub volume_only: bool,
+    /// How close to the origin of a ray hits are ignored, relative to the magnitude of the origin.
+    /// See [`RaycastSettings::self_hit_epsilon`].
+    pub self_hit_epsilon: f32,
+    _marker: PhantomData<fn() -> T>,
+}
+
+/// The setting named by the warning about large meshes raycasted by [`RaycastSource`]s.
+const LARGE_MESH_SETTING: &str = "RaycastPluginSettings::large_mesh_threshold";
+
+/// The default [`RaycastPluginSettings::max_intersections`].
+pub const DEFAULT_MAX_INTERSECTIONS: usize = 256;
+
+impl<T> Default for RaycastPluginSettings<T> {
+    fn default() -> Self {
+        RaycastPluginSettings {
+            backfaces: Backfaces::Cull,
+            aabb_inflation: 0.0,
+            time_budget: None,
+            large_mesh_threshold: Some(DEFAULT_LARGE_MESH_THRESHOLD),
+            disable_duplicate_sources: false,
+            max_intersections: Some(DEFAULT_MAX_INTERSECTIONS),
+            cursor_bounds: CursorBounds::default(),
+            audit: false,
+            line_tolerance: None,
+            volume_only: false,
+            self_hit_epsilon: DEFAULT_SELF_HIT_EPSILON,
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<T> Clone for RaycastPluginSettings<T> {
+    fn clone(&self) -> Self {
+        RaycastPluginSettings {
+            backfaces: self.backfaces,
+            aabb_inflation: self.aabb_inflation,
+            time_budget: self.time_budget,
+            large_mesh_threshold: self.large_mesh_threshold,
+            disable_duplicate_sources: self.disable_duplicate_sources,
+            max_intersections: self.max_intersections,
+            cursor_bounds: self.cursor_bounds,
+            audit: self.audit,
+            line_tolerance: self.line_tolerance,
+            volume_only: self.volume_only,
+            self_hit_epsilon: self.self_hit_epsilon,
+            _marker: PhantomData,
+        }
+    }
+}
+
+/// Information about misconfigured entities in the raycasting set `T`, useful for surfacing
+/// problems in editor tooling. This is updated every frame by the [`DeferredRaycastingPlugin`].
+#[derive(Resource)]
+pub struct RaycastDebugInfo<T> {
+    /// Entities with a [`RaycastMesh<T>`] but no mesh handle, which can never be hit. This commonly
+    /// happens when the component is added to the root of a scene, instead of the child entity that
+    /// holds the mesh, or a [`RaycastSceneRoot<T>`].
+    pub missing_mesh_handles: Vec<Entity>,
+    /// Groups of cursor [`RaycastSource<T>`]s on cameras with the same render target and viewport,
+    /// which all cast the same ray. Each group is sorted, and only its first source is raycast when
+    /// [`RaycastPluginSettings::disable_duplicate_sources`] is `true`.
+    pub duplicate_sources: Vec<Vec<Entity>>,
+    _marker: PhantomData<fn() -> T>,
+}
+
+impl<T> Default for RaycastDebugInfo<T> {
+    fn default() -> Self {
+        RaycastDebugInfo {
+            missing_mesh_handles: Vec::new(),
+            duplicate_sources: Vec::new(),
+            _marker: PhantomData,
+        }
+    }
+}
+
+/// Measurements of the raycasts performed for the raycasting set `T`, useful for finding
+/// performance problems. This is updated by the [`DeferredRaycastingPlugin`].
+#[derive(Resource)]
+pub struct RaycastMetrics<T> {
+    /// Meshes with more triangles than [`RaycastPluginSettings::large_mesh_threshold`] that have
+    /// been raycasted, along with the first entity they were found on and their triangle count.
+    pub large_meshes: HashMap<AssetId<Mesh>, (Entity, usize)>,
+    /// The number of raycasts that have been performed.
+    pub casts: usize,
+    /// The number of raycasts that were skipped because another source cast the same ray with the
+    /// same settings in the same frame, so its intersections were reused.
+    pub casts_deduplicated: usize,
+    /// The number of raycasts that hit more entities than
+    /// [`RaycastPluginSettings::max_intersections`], so some of their intersections were dropped.
+    pub casts_overflowed: usize,
+    /// The number of cursor rays that have been built for [`RaycastMethod::Cursor`] and
+    /// [`RaycastMethod::Touch`] sources.
+    pub cursor_rays_computed: usize,
+    /// The number of cursor rays that were reused from the [`CursorRayCache`], because the source's
+    /// camera already built its cursor ray in the same frame for another raycasting set.
+    pub cursor_rays_reused: usize,
+    /// The number of raycasts that were checked against a brute force reference, see
+    /// [`RaycastPluginSettings::audit`].
+    pub casts_audited: usize,
+    /// The number of audited raycasts whose intersections differed from the reference.
+    pub audit_divergences: usize,
+    /// The most recent audited raycast whose intersections differed from the reference.
+    pub last_audit_divergence: Option<RaycastAuditDivergence>,
+    _marker: PhantomData<fn() -> T>,
+}
+
+/// The largest distance, relative to the size of their coordinates, between the positions of an
+/// intersection and its reference before they are reported as different. See
+/// [`RaycastPluginSettings::audit`].
+pub const AUDIT_EPSILON: f32 = 1e-4;
+
+/// A raycast whose intersections differed from those of [`Raycast::cast_ray_reference`]. See
+/// [`RaycastPluginSettings::audit`].
+#[derive(Clone, Debug)]
+pub struct RaycastAuditDivergence {
+    /// The entity with the [`RaycastSource`] that cast the ray.
+    pub source: Entity,
+    /// The ray that both raycasts were cast with.
+    pub ray: Ray3d,
+    /// The intersections of the raycast.
+    pub intersections: Vec<(Entity, IntersectionData)>,
+    /// The intersections of the reference raycast.
+    pub reference: Vec<(Entity, IntersectionData)>,
+}
+
+impl RaycastAuditDivergence {
+    /// Returns `true` if `intersections` and `reference` differ: they have a different number of
+    /// intersections, a different nearest entity, or an entity hit at positions further apart than
+    /// [`AUDIT_EPSILON`]. Entities hit at the same distance may be in either order.
+    pub fn diverges(
+        intersections: &[(Entity, IntersectionData)],
+        reference: &[(Entity, IntersectionData)],
+    ) -> bool {
+        let tolerance =
+            |hit: &IntersectionData| AUDIT_EPSILON * hit.position().abs().max_element().max(1.0);
+        let differs = |(entity, hit): &(Entity, IntersectionData)| {
+            reference
+                .iter()
+                .find(|(e, _)| e == entity)
+                .map_or(true, |(_, expected)| {
+                    hit.position().distance(expected.position()) > tolerance(expected)
+                })
+        };
+        let nearest_differs = match (intersections.first(), reference.first()) {
+            (Some((a, a_hit)), Some((b, b_hit))) => {
+                a != b && (a_hit.distance() - b_hit.distance()).abs() > tolerance(b_hit)
+            }
+            _ => false,
+        };
+        intersections.len() != reference.len()
+            || nearest_differs
+            || intersections.iter().any(differs)
+    }
+}
+
+impl<T> Default for RaycastMetrics<T> {
+    fn default() -> Self {
+        RaycastMetrics {
+            large_meshes: HashMap::new(),
+            casts: 0,
+            casts_deduplicated: 0,
+            casts_overflowed: 0,
+            cursor_rays_computed: 0,
+            cursor_rays_reused: 0,
+            casts_audited: 0,
+            audit_divergences: 0,
+            last_audit_divergence: None,
+            _marker: PhantomData,
+        }
+    }
+}
+
+/// One intersection in a [`RaycastFrameSnapshot`], as plain data that can be copied across an FFI or
+/// serialization boundary.
+///
+/// The layout is `#[repr(C)]`, 48 bytes with no padding, in native endianness:
+///
+/// | Offset | Field                | Type       |
+/// |--------|----------------------|------------|
+/// | 0      | `source_entity_bits` | `u64`      |
+/// | 8      | `target_entity_bits` | `u64`      |
+/// | 16     | `group_id`           | `u32`      |
+/// | 20     | `position`           | `[f32; 3]` |
+/// | 32     | `normal`             | `[f32; 3]` |
+/// | 44     | `distance`           | `f32`      |
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct RaycastRecord {
+    /// The [`Entity::to_bits`] of the [`RaycastSource`].
+    pub source_entity_bits: u64,
+    /// The [`Entity::to_bits`] of the entity that was hit.
+    pub target_entity_bits: u64,
+    /// The group id of the raycasting set, see [`DeferredRaycastingPlugin::with_frame_snapshot`].
+    pub group_id: u32,
+    pub position: [f32; 3],
+    pub normal: [f32; 3],
+    pub distance: f32,
+}
+
+/// The intersections of every [`RaycastSource<T>`] in the last frame, flattened into
+/// [`RaycastRecord`]s for scripting layers and consoles that can't hold references into the world.
+/// This is only updated when the plugin is built
+/// [`with_frame_snapshot`](DeferredRaycastingPlugin::with_frame_snapshot).
+#[derive(Resource)]
+pub struct RaycastFrameSnapshot<T> {
+    /// The number of times the snapshot has been rebuilt, starting at `1` after the first frame.
+    pub frame: u64,
+    /// The group id of the raycasting set, copied into every record.
+    pub group_id: u32,
+    /// The intersections of each source, nearest first, in no particular order of sources.
+    pub records: Vec<RaycastRecord>,
+    _marker: PhantomData<fn() -> T>,
+}
+
+impl<T> RaycastFrameSnapshot<T> {
+    pub fn new(group_id: u32) -> Self {
+        RaycastFrameSnapshot {
+            frame: 0,
+            group_id,
+            records: Vec::new(),
+            _marker: PhantomData,
+        }
+    }
+}
+
 /// Marks an entity as pickable, with type T.
 ///
 /// # Requirements
 ///
-/// The marked entity must also have a [Mesh](bevy_render::mesh::Mesh) component.
+/// The marked entity must also have a [Mesh] component.
 #[derive(Component, Debug, Reflect)]
 #[reflect(Component)]
 pub struct RaycastMesh<T: TypePath> {
     #[reflect(ignore)]
     pub intersections: Vec<(Entity, IntersectionData)>,
     #[reflect(ignore)]
+    local_intersections: Vec<(Entity, LocalIntersection)>,
+    #[reflect(ignore)]
     _marker: PhantomData<T>,
 }
 
@@ -169,12 +713,20 @@ impl<T: TypePath> RaycastMesh<T> {
     pub fn intersections(&self) -> &[(Entity, IntersectionData)] {
         &self.intersections
     }
+
+    /// Get the intersections in the local frame of this entity, in the same order as
+    /// [`intersections`](Self::intersections). This is only recorded for entities with a
+    /// [`TrackHitLocally`](crate::markers::TrackHitLocally) component.
+    pub fn local_intersections(&self) -> &[(Entity, LocalIntersection)] {
+        &self.local_intersections
+    }
 }
 
 impl<T: TypePath> Default for RaycastMesh<T> {
     fn default() -> Self {
         RaycastMesh {
             intersections: Vec::new(),
+            local_intersections: Vec::new(),
             _marker: PhantomData,
         }
     }
@@ -184,11 +736,152 @@ impl<T: TypePath> Clone for RaycastMesh<T> {
     fn clone(&self) -> Self {
         RaycastMesh {
             intersections: self.intersections.clone(),
+            local_intersections: self.local_intersections.clone(),
+            _marker: PhantomData,
+        }
+    }
+}
+
+/// Makes every mesh in the hierarchy below this entity pickable, with type T, by adding a
+/// [`RaycastMesh<T>`] to each descendant with a mesh handle. This is meant for the root of a
+/// scene, such as a glTF, whose meshes are on child entities that are spawned later.
+///
+/// Descendants are tagged by [`tag_scene_meshes`] every frame, so meshes added to the hierarchy
+/// later are tagged too, and become pickable on the next frame.
+#[derive(Component, Debug, Reflect)]
+#[reflect(Component)]
+pub struct RaycastSceneRoot<T: TypePath> {
+    #[reflect(ignore)]
+    _marker: PhantomData<T>,
+}
+
+impl<T: TypePath> Default for RaycastSceneRoot<T> {
+    fn default() -> Self {
+        RaycastSceneRoot {
+            _marker: PhantomData,
+        }
+    }
+}
+
+/// Counts how often this entity is hit by the [`RaycastSource`]s of set `T`, for tooling such as a
+/// heatmap of the objects a player aims at. The counters are only updated for entities with this
+/// component, and can be cleared with [`RaycastHitStats::reset`].
+///
+/// The entity must also have a [`RaycastMesh<T>`].
+#[derive(Component, Debug, Reflect)]
+#[reflect(Component)]
+pub struct RaycastHitStats<T: TypePath> {
+    /// The number of frames in which this entity was intersected by at least one source.
+    pub frames_hit: u32,
+    /// The number of frames in which this entity was the nearest intersection of at least one
+    /// source.
+    pub frames_top_hit: u32,
+    #[reflect(ignore)]
+    _marker: PhantomData<T>,
+}
+
+impl<T: TypePath> RaycastHitStats<T> {
+    /// Sets the counters back to zero.
+    pub fn reset(&mut self) {
+        self.frames_hit = 0;
+        self.frames_top_hit = 0;
+    }
+}
+
+impl<T: TypePath> Default for RaycastHitStats<T> {
+    fn default() -> Self {
+        RaycastHitStats {
+            frames_hit: 0,
+            frames_top_hit: 0,
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<T: TypePath> Clone for RaycastHitStats<T> {
+    fn clone(&self) -> Self {
+        RaycastHitStats {
+            frames_hit: self.frames_hit,
+            frames_top_hit: self.frames_top_hit,
+            _marker: PhantomData,
+        }
+    }
+}
+
+/// Records the position of the nearest hit of a [`RaycastSource<T>`] on this entity during its last
+/// two raycasts, so it can be interpolated when raycasts run less often than frames are rendered,
+/// such as when the plugin runs in [`FixedUpdate`]. This is only for presentation, like drawing a
+/// marker under the cursor; gameplay should use the hits of the [`RaycastSource`] itself.
+#[derive(Component, Debug)]
+pub struct InterpolatedHit<T> {
+    previous: Option<Vec3>,
+    current: Option<Vec3>,
+    _marker: PhantomData<fn() -> T>,
+}
+
+impl<T> InterpolatedHit<T> {
+    /// The position of the nearest hit of the raycast before the last one.
+    pub fn previous(&self) -> Option<Vec3> {
+        self.previous
+    }
+
+    /// The position of the nearest hit of the last raycast.
+    pub fn current(&self) -> Option<Vec3> {
+        self.current
+    }
+
+    /// Blends the positions of the last two nearest hits, from the previous hit at a `fraction` of
+    /// `0.0` to the current hit at `1.0`. In [`FixedUpdate`], use
+    /// [`Time::<Fixed>::overstep_percentage`](bevy_time::Time::overstep_percentage) as the
+    /// fraction. If the previous raycast didn't hit anything, this is the current hit.
+    pub fn position(&self, fraction: f32) -> Option<Vec3> {
+        let current = self.current?;
+        Some(match self.previous {
+            Some(previous) => previous.lerp(current, fraction.clamp(0.0, 1.0)),
+            None => current,
+        })
+    }
+}
+
+impl<T> Default for InterpolatedHit<T> {
+    fn default() -> Self {
+        InterpolatedHit {
+            previous: None,
+            current: None,
+            _marker: PhantomData,
+        }
+    }
+}
+
+/// Sent for the nearest intersection of a [`RaycastSource<T>`], as often as its
+/// [`emit_hit_events`](RaycastSource::emit_hit_events) allows. The intersections of the source are
+/// still updated every frame.
+#[derive(Event, Debug)]
+pub struct RaycastHit<T> {
+    /// The entity with the [`RaycastSource<T>`].
+    pub source: Entity,
+    /// The entity that was hit.
+    pub entity: Entity,
+    /// The nearest intersection of the source, with the entity that was hit.
+    pub intersection: IntersectionData,
+    _marker: PhantomData<fn() -> T>,
+}
+
+impl<T> Clone for RaycastHit<T> {
+    fn clone(&self) -> Self {
+        RaycastHit {
+            source: self.source,
+            entity: self.entity,
+            intersection: self.intersection.clone(),
             _marker: PhantomData,
         }
     }
 }
 
+/// How far the nearest intersection of a [`RaycastSource`] has to move, for
+/// [`HitEventMode::OnChange`] to send another [`RaycastHit`] for the same entity.
+pub const HIT_EVENT_EPSILON: f32 = 1e-3;
+
 /// The `RaycastSource` component is used to generate rays with the specified `cast_method`. A `ray`
 /// is generated when the RaycastSource is initialized, either by waiting for update_raycast system
 /// to process the ray, or by using a `with_ray` function.`
@@ -200,14 +893,132 @@ pub struct RaycastSource<T: TypePath> {
     /// When `true`, raycasting will only hit the nearest entity, skipping any entities that are
     /// further away. This can significantly improve performance in cases where a ray intersects
     /// many AABBs.
+    ///
+    /// Entities are tested in the order the ray enters their AABBs, and the raycast stops as soon
+    /// as the nearest hit so far is nearer than the next AABB, as no entity after it can be hit
+    /// any nearer. The [`intersections`](Self::intersections) then only contain the nearest hit,
+    /// along with any other hits at exactly the same distance.
     pub should_early_exit: bool,
+    /// When `true`, raycasting stops at the first intersection it finds, and records only that
+    /// one, which isn't necessarily the nearest. This is much cheaper for line of sight checks,
+    /// where only whether anything is hit matters. See [`RaycastSettings::any_hit`].
+    pub any_hit: bool,
+    /// The maximum number of intersections recorded with the triangles of each mesh, such as where
+    /// a piercing ray enters and exits it. Defaults to 1, only the nearest. See
+    /// [`RaycastSettings::max_hits_per_mesh`].
+    pub max_hits_per_mesh: usize,
+    /// When `true`, only the AABBs of entities are tested, with the slab test, instead of testing
+    /// their triangles with the Möller–Trumbore test, as if every entity had a
+    /// [`RaycastVolumeOnly`](crate::markers::RaycastVolumeOnly) component. Each hit is where the
+    /// ray enters an AABB, and its [provenance](IntersectionData::provenance) has a
+    /// [`RaycastAlgorithm::SlabTest`] algorithm. This is much cheaper, but also hits the empty
+    /// space around meshes, and sources with it are never [refined](Self::refinement).
+    pub volume_only: bool,
     /// Determines how raycasting should consider entity visibility.
     pub visibility: RaycastVisibility,
+    /// When greater than zero, and the ray has changed since the last raycast, this many rays are
+    /// interpolated between the previous and current ray and also tested. Entities that are only
+    /// hit by these interpolated rays are [flagged as swept](IntersectionData::is_swept). This
+    /// prevents fast moving rays from skipping over small entities between frames.
+    pub sweep_samples: u8,
+    /// When `true`, the entities whose AABBs were intersected by the ray during the last raycast,
+    /// before any meshes were tested, are kept. See [`RaycastSource::culled_candidates`].
+    pub record_candidates: bool,
+    /// When `true`, a raycast that hits nothing keeps the intersections of the previous raycast
+    /// instead of clearing them, so a ray slipping off a thin entity for a frame doesn't make a
+    /// hover flicker. This also applies to sources without a ray, such as a cursor source while the
+    /// cursor is outside of the window. The kept intersections are [not current](Self::is_current), and keep their
+    /// [`intersections_time`](Self::intersections_time), so
+    /// [`intersections_newer_than`](Self::intersections_newer_than) can limit how long they are
+    /// kept. Intersections with entities that were despawned, or removed from the raycasting set,
+    /// are dropped.
+    pub keep_last_hit: bool,
+    /// An infinite plane, such as an editor's ground grid, that the ray is intersected with when
+    /// it doesn't hit any meshes. See [`RaycastSource::fallback_hit`].
+    pub fallback_plane: Option<Primitive3d>,
+    /// Restricts which entities this source can hit.
+    #[reflect(ignore)]
+    pub target_filter: TargetFilter,
+    /// When set, only entities for which this returns `true` can be hit, such as to change which
+    /// entities are pickable every frame. This is evaluated after the AABBs of entities are
+    /// culled, at most once per entity for each raycast, and rejected entities are never tested
+    /// against their meshes. See [`RaycastSource::set_filter`].
+    #[reflect(ignore)]
+    pub filter: Option<Arc<dyn Fn(Entity) -> bool + Send + Sync>>,
+    /// Entities this source never hits, such as the player's own mesh, or an object being dragged.
+    /// See [`RaycastSource::exclude`].
+    #[reflect(ignore)]
+    exclusions: SmallVec<[Entity; 4]>,
+    /// This source only hits entities in at least one of these layers. See [`RaycastLayers`].
+    /// Defaults to every layer.
+    pub layer_mask: u32,
+    /// When set, mesh triangles that are steeper than this are skipped, so the nearest hit is the
+    /// nearest surface that is flat enough, such as the ground under a placement tool.
+    pub max_slope: Option<MaxSlope>,
+    /// How this source treats triangles that face away from the ray, overriding
+    /// [`RaycastPluginSettings::backfaces`]. Entities with a
+    /// [`NoBackfaceCulling`](crate::markers::NoBackfaceCulling) or a
+    /// [`HitFaces`](crate::markers::HitFaces) component still override this.
+    pub backfaces: Option<Backfaces>,
+    /// Geometry closer than this along the ray is ignored, such as the character a ray starts
+    /// inside of. Entities whose bounds extend beyond this distance are still tested. This is
+    /// measured from the ray's origin, like [`max_distance`](Self::max_distance), and also limits
+    /// the [`fallback_hit`](Self::fallback_hit).
+    pub min_distance: f32,
+    /// When set, geometry farther than this along the ray is ignored, such as to limit an
+    /// interaction range. This is measured from the ray's origin, like the distances of
+    /// intersections, and also limits the [`fallback_hit`](Self::fallback_hit).
+    pub max_distance: Option<f32>,
+    /// Camera spaces that intersections are also represented in, in addition to world space. These
+    /// are only computed for sources with a camera.
+    #[reflect(ignore)]
+    pub output_spaces: OutputSpaces,
+    /// How the intersections of this source are refined over multiple frames.
+    pub refinement: CastRefinement,
+    /// When set, each raycast of this source also explains why this entity was or wasn't hit. See
+    /// [`RaycastSource::explanation`].
+    pub explain_target: Option<Entity>,
+    /// How far along the ray raycasts start, skipping any geometry closer to the ray's origin, such
+    /// as walls a camera is clipping through. Distances are still measured from the ray's origin,
+    /// so they can be compared with those of other sources.
+    pub ray_start_offset: f32,
+    /// How often a [`RaycastHit`] event is sent for the nearest intersection of this source.
+    pub emit_hit_events: HitEventMode,
     #[reflect(skip_serializing)]
     pub ray: Option<Ray3d>,
     #[reflect(ignore)]
+    previous_ray: Option<Ray3d>,
+    /// Where the last touch of a [`RaycastMethod::Touch`] source was, or ended.
+    #[reflect(ignore)]
+    last_touch: Option<Vec2>,
+    #[reflect(ignore)]
     intersections: Vec<(Entity, IntersectionData)>,
     #[reflect(ignore)]
+    intersections_time: Option<Duration>,
+    #[reflect(ignore)]
+    is_current: bool,
+    #[reflect(ignore)]
+    outcome: Option<CastSkips>,
+    /// The nearest entity hit by the coarse raycast, and for how many frames it has been hit since.
+    #[reflect(ignore)]
+    settle_candidate: Option<(Entity, u8)>,
+    #[reflect(ignore)]
+    culled_candidates: Vec<(Entity, f32)>,
+    #[reflect(ignore)]
+    instance_intersections: Vec<(Entity, usize, IntersectionData)>,
+    #[reflect(ignore)]
+    explanation: Option<HitExplanation>,
+    #[reflect(ignore)]
+    intersection_groups: Vec<usize>,
+    #[reflect(ignore)]
+    overflowed: bool,
+    /// The entity and position of the last [`RaycastHit`], while it is still the nearest hit.
+    #[reflect(ignore)]
+    hit_event_target: Option<(Entity, Vec3)>,
+    /// The entity of the last [`RaycastHit`], and the time it was sent at.
+    #[reflect(ignore)]
+    last_hit_event: Option<(Entity, Duration)>,
+    #[reflect(ignore)]
     _marker: PhantomData<fn() -> T>,
 }
 
@@ -216,9 +1027,42 @@ impl<T: TypePath> Default for RaycastSource<T> {
         RaycastSource {
             cast_method: RaycastMethod::Screenspace(Vec2::ZERO),
             should_early_exit: true,
+            any_hit: false,
+            max_hits_per_mesh: 1,
+            volume_only: false,
             visibility: RaycastVisibility::MustBeVisibleAndInView,
+            sweep_samples: 0,
+            record_candidates: false,
+            keep_last_hit: false,
+            fallback_plane: None,
+            target_filter: TargetFilter::All,
+            filter: None,
+            exclusions: SmallVec::new(),
+            layer_mask: u32::MAX,
+            max_slope: None,
+            backfaces: None,
+            min_distance: 0.0,
+            max_distance: None,
+            output_spaces: OutputSpaces::empty(),
+            refinement: CastRefinement::Full,
+            explain_target: None,
+            ray_start_offset: 0.0,
+            emit_hit_events: HitEventMode::EveryFrame,
             ray: None,
+            previous_ray: None,
+            last_touch: None,
             intersections: Vec::new(),
+            intersections_time: None,
+            is_current: true,
+            outcome: None,
+            settle_candidate: None,
+            culled_candidates: Vec::new(),
+            instance_intersections: Vec::new(),
+            explanation: None,
+            intersection_groups: Vec::new(),
+            overflowed: false,
+            hit_event_target: None,
+            last_hit_event: None,
             _marker: PhantomData,
         }
     }
@@ -229,9 +1073,42 @@ impl<T: TypePath> Clone for RaycastSource<T> {
         Self {
             cast_method: self.cast_method.clone(),
             should_early_exit: self.should_early_exit,
+            any_hit: self.any_hit,
+            max_hits_per_mesh: self.max_hits_per_mesh,
+            volume_only: self.volume_only,
             visibility: self.visibility,
+            sweep_samples: self.sweep_samples,
+            record_candidates: self.record_candidates,
+            keep_last_hit: self.keep_last_hit,
+            fallback_plane: self.fallback_plane,
+            target_filter: self.target_filter.clone(),
+            filter: self.filter.clone(),
+            exclusions: self.exclusions.clone(),
+            layer_mask: self.layer_mask,
+            max_slope: self.max_slope,
+            backfaces: self.backfaces,
+            min_distance: self.min_distance,
+            max_distance: self.max_distance,
+            output_spaces: self.output_spaces,
+            refinement: self.refinement,
+            explain_target: self.explain_target,
+            ray_start_offset: self.ray_start_offset,
+            emit_hit_events: self.emit_hit_events,
             ray: self.ray,
+            previous_ray: self.previous_ray,
+            last_touch: self.last_touch,
             intersections: self.intersections.clone(),
+            intersections_time: self.intersections_time,
+            is_current: self.is_current,
+            outcome: self.outcome,
+            settle_candidate: self.settle_candidate,
+            culled_candidates: self.culled_candidates.clone(),
+            instance_intersections: self.instance_intersections.clone(),
+            explanation: self.explanation.clone(),
+            intersection_groups: self.intersection_groups.clone(),
+            overflowed: self.overflowed,
+            hit_event_target: self.hit_event_target,
+            last_hit_event: self.last_hit_event,
             _marker: PhantomData,
         }
     }
@@ -266,6 +1143,18 @@ impl<T: TypePath> RaycastSource<T> {
         }
     }
 
+    /// Casts a ray from the `origin` towards the `target`, such as from a turret to the player, until
+    /// the cast method is changed. See [`Ray3d::from_points`]; the source has no ray if the points
+    /// are the same.
+    pub fn with_ray_from_points(self, origin: Vec3, target: Vec3) -> Self {
+        let ray = Ray3d::from_points(origin, target);
+        RaycastSource {
+            cast_method: RaycastMethod::Ray(ray),
+            ray,
+            ..self
+        }
+    }
+
     /// Set the `should_early_exit` field of this raycast source.
     pub fn with_early_exit(self, should_early_exit: bool) -> Self {
         Self {
@@ -274,35 +1163,269 @@ impl<T: TypePath> RaycastSource<T> {
         }
     }
 
-    /// Set the `visibility` field of this raycast source.
-    pub fn with_visibility(self, visibility: RaycastVisibility) -> Self {
-        Self { visibility, ..self }
+    /// Set the `any_hit` field of this raycast source.
+    pub fn with_any_hit(self, any_hit: bool) -> Self {
+        Self { any_hit, ..self }
     }
 
-    /// Instantiates and initializes a [RaycastSource] with a valid screenspace ray.
-    pub fn new_screenspace(
-        cursor_pos_screen: Vec2,
-        camera: &Camera,
-        camera_transform: &GlobalTransform,
-        window: &Window,
-    ) -> Self {
-        RaycastSource::new().with_ray_screenspace(
-            cursor_pos_screen,
-            camera,
-            camera_transform,
-            window,
-        )
+    /// Set the `max_hits_per_mesh` field of this raycast source.
+    pub fn with_max_hits_per_mesh(self, max_hits_per_mesh: usize) -> Self {
+        Self {
+            max_hits_per_mesh,
+            ..self
+        }
     }
 
-    /// Initializes a [RaycastSource] for cursor raycasting.
-    pub fn new_cursor() -> Self {
-        RaycastSource {
-            cast_method: RaycastMethod::Cursor,
-            ..default()
+    /// Set the `volume_only` field of this raycast source.
+    pub fn with_volume_only(self, volume_only: bool) -> Self {
+        Self {
+            volume_only,
+            ..self
         }
     }
 
-    /// Initializes a [RaycastSource] with a valid ray derived from a transform.
+    /// Set the `visibility` field of this raycast source.
+    pub fn with_visibility(self, visibility: RaycastVisibility) -> Self {
+        Self { visibility, ..self }
+    }
+
+    /// Set the `sweep_samples` field of this raycast source.
+    pub fn with_sweep_samples(self, sweep_samples: u8) -> Self {
+        Self {
+            sweep_samples,
+            ..self
+        }
+    }
+
+    /// Set the `record_candidates` field of this raycast source.
+    pub fn with_record_candidates(self, record_candidates: bool) -> Self {
+        Self {
+            record_candidates,
+            ..self
+        }
+    }
+
+    /// Set the `keep_last_hit` field of this raycast source.
+    pub fn with_keep_last_hit(self, keep_last_hit: bool) -> Self {
+        Self {
+            keep_last_hit,
+            ..self
+        }
+    }
+
+    /// Set the `explain_target` field of this raycast source.
+    pub fn with_explain_target(self, explain_target: Entity) -> Self {
+        Self {
+            explain_target: Some(explain_target),
+            ..self
+        }
+    }
+
+    /// Set the `ray_start_offset` field of this raycast source.
+    pub fn with_ray_start_offset(self, ray_start_offset: f32) -> Self {
+        Self {
+            ray_start_offset,
+            ..self
+        }
+    }
+
+    /// Set the `fallback_plane` field of this raycast source.
+    pub fn with_fallback_plane(self, fallback_plane: Primitive3d) -> Self {
+        Self {
+            fallback_plane: Some(fallback_plane),
+            ..self
+        }
+    }
+
+    /// Set the `target_filter` field of this raycast source.
+    pub fn with_target_filter(self, target_filter: TargetFilter) -> Self {
+        Self {
+            target_filter,
+            ..self
+        }
+    }
+
+    /// Set the `layer_mask` field of this raycast source.
+    pub fn with_layer_mask(self, layer_mask: u32) -> Self {
+        Self { layer_mask, ..self }
+    }
+
+    /// Only hit entities for which `filter` returns `true`. See [`RaycastSource::filter`].
+    pub fn with_filter(mut self, filter: impl Fn(Entity) -> bool + Send + Sync + 'static) -> Self {
+        self.set_filter(filter);
+        self
+    }
+
+    /// Only hit entities for which `filter` returns `true`, replacing any previous filter. See
+    /// [`RaycastSource::filter`].
+    pub fn set_filter(&mut self, filter: impl Fn(Entity) -> bool + Send + Sync + 'static) {
+        self.filter = Some(Arc::new(filter));
+    }
+
+    /// Remove the [`filter`](RaycastSource::filter) of this source, so it can hit every entity
+    /// again.
+    pub fn clear_filter(&mut self) {
+        self.filter = None;
+    }
+
+    /// Never hit `entity` with this source, until it is removed with
+    /// [`remove_exclusion`](Self::remove_exclusion) or
+    /// [`clear_exclusions`](Self::clear_exclusions). Excluded entities are skipped before their
+    /// meshes are tested, and their [`RaycastMesh::intersections`] aren't updated by this source.
+    pub fn exclude(&mut self, entity: Entity) {
+        if !self.exclusions.contains(&entity) {
+            self.exclusions.push(entity);
+        }
+    }
+
+    /// Allow this source to hit an entity excluded with [`exclude`](Self::exclude) again.
+    pub fn remove_exclusion(&mut self, entity: Entity) {
+        self.exclusions.retain(|excluded| *excluded != entity);
+    }
+
+    /// Allow this source to hit every entity excluded with [`exclude`](Self::exclude) again.
+    pub fn clear_exclusions(&mut self) {
+        self.exclusions.clear();
+    }
+
+    /// Get the entities excluded from this source with [`exclude`](Self::exclude).
+    pub fn exclusions(&self) -> &[Entity] {
+        &self.exclusions
+    }
+
+    /// Also represent intersections in these camera `output_spaces`, e.g.
+    /// `OutputSpaces::VIEW | OutputSpaces::NDC`. See [`RaycastSource::output_spaces`].
+    pub fn with_output_spaces(self, output_spaces: OutputSpaces) -> Self {
+        Self {
+            output_spaces,
+            ..self
+        }
+    }
+
+    /// Set how the intersections of this source are refined. See [`CastRefinement`].
+    pub fn with_refinement(self, refinement: CastRefinement) -> Self {
+        Self { refinement, ..self }
+    }
+
+    /// Set how often a [`RaycastHit`] event is sent for the nearest intersection of this source.
+    pub fn with_emit_hit_events(self, emit_hit_events: HitEventMode) -> Self {
+        Self {
+            emit_hit_events,
+            ..self
+        }
+    }
+
+    /// Only hit mesh triangles with a normal within `angle` radians of `up`. See
+    /// [`RaycastSource::max_slope`].
+    pub fn with_max_slope(self, angle: f32, up: Vec3) -> Self {
+        Self {
+            max_slope: Some(MaxSlope::new(angle, up)),
+            ..self
+        }
+    }
+
+    /// Set how this source treats backfaces. See [`RaycastSource::backfaces`].
+    pub fn with_backfaces(self, backfaces: Backfaces) -> Self {
+        Self {
+            backfaces: Some(backfaces),
+            ..self
+        }
+    }
+
+    /// Set the `min_distance` field of this raycast source.
+    pub fn with_min_distance(self, min_distance: f32) -> Self {
+        Self {
+            min_distance,
+            ..self
+        }
+    }
+
+    /// Set the `max_distance` field of this raycast source.
+    pub fn with_max_distance(self, max_distance: f32) -> Self {
+        Self {
+            max_distance: Some(max_distance),
+            ..self
+        }
+    }
+
+    /// Instantiates and initializes a [RaycastSource] with a valid screenspace ray.
+    pub fn new_screenspace(
+        cursor_pos_screen: Vec2,
+        camera: &Camera,
+        camera_transform: &GlobalTransform,
+        window: &Window,
+    ) -> Self {
+        RaycastSource::new().with_ray_screenspace(
+            cursor_pos_screen,
+            camera,
+            camera_transform,
+            window,
+        )
+    }
+
+    /// Initializes a [RaycastSource] for cursor raycasting.
+    pub fn new_cursor() -> Self {
+        RaycastSource {
+            cast_method: RaycastMethod::Cursor,
+            ..default()
+        }
+    }
+
+    /// Initializes a [RaycastSource] for touch screen raycasting. See [`RaycastMethod::Touch`].
+    pub fn new_touch(keep_released: bool) -> Self {
+        RaycastSource {
+            cast_method: RaycastMethod::Touch { keep_released },
+            ..default()
+        }
+    }
+
+    /// Initializes a [RaycastSource] that casts the given `ray` every frame. See
+    /// [`RaycastMethod::Ray`].
+    pub fn new_ray(ray: Ray3d) -> Self {
+        RaycastSource {
+            cast_method: RaycastMethod::Ray(Some(ray)),
+            ..default()
+        }
+    }
+
+    /// Initializes a [RaycastSource] that casts from the [`VirtualPointer`] on its camera. See
+    /// [`RaycastMethod::VirtualPointer`].
+    pub fn new_virtual_pointer() -> Self {
+        RaycastSource {
+            cast_method: RaycastMethod::VirtualPointer,
+            ..default()
+        }
+    }
+
+    /// Initializes a [RaycastSource] that casts through the center of a UI `node`. See
+    /// [`RaycastMethod::UiNode`].
+    #[cfg(feature = "ui")]
+    pub fn new_ui_node(node: Entity) -> Self {
+        RaycastSource {
+            cast_method: RaycastMethod::UiNode(node),
+            ..default()
+        }
+    }
+
+    /// Initializes a [RaycastSource] that casts through the center of its camera's viewport. See
+    /// [`RaycastMethod::ScreenCenter`].
+    pub fn new_screen_center() -> Self {
+        RaycastSource {
+            cast_method: RaycastMethod::ScreenCenter,
+            ..default()
+        }
+    }
+
+    /// Initializes a [RaycastSource] that casts through a point of its camera's render target. See
+    /// [`RaycastMethod::TargetUv`].
+    pub fn new_target_uv(uv: Vec2) -> Self {
+        RaycastSource {
+            cast_method: RaycastMethod::TargetUv(uv),
+            ..default()
+        }
+    }
+
+    /// Initializes a [RaycastSource] with a valid ray derived from a transform.
     pub fn new_transform(transform: Mat4) -> Self {
         RaycastSource::new().with_ray_transform(transform)
     }
@@ -312,7 +1435,7 @@ impl<T: TypePath> RaycastSource<T> {
     /// present on this entity.
     ///
     /// # Warning
-    /// Only use this if the entity this is associated with will have its [Transform](bevy_transform::components::Transform) or
+    /// Only use this if the entity this is associated with will have its [Transform] or
     /// [GlobalTransform] specified elsewhere. If the [GlobalTransform] is not set, this ray casting
     /// source will never be able to generate a raycast.
     pub fn new_transform_empty() -> Self {
@@ -337,6 +1460,47 @@ impl<T: TypePath> RaycastSource<T> {
         &self.intersections
     }
 
+    /// Get the outcome of the last raycast of this source, with its
+    /// [`intersections`](Self::intersections), and whether any entities were skipped. See
+    /// [`CastOutcome`].
+    ///
+    /// This is `None` if no ray could be generated for this source during the last update, e.g.
+    /// because the cursor is outside the window, or the source's camera is missing.
+    pub fn outcome(&self) -> Option<CastOutcome<'_>> {
+        self.outcome
+            .map(|skips| skips.with_hits(&self.intersections))
+    }
+
+    /// Get the time since startup, as given by [`Time::elapsed`], at which the intersections of
+    /// this source were last produced. This is `None` if this source hasn't raycasted yet, or if
+    /// there is no [`Time`] resource.
+    pub fn intersections_time(&self) -> Option<Duration> {
+        self.intersections_time
+    }
+
+    /// Returns `false` if the intersections of this source were kept from an earlier raycast,
+    /// because the last raycast hit nothing. See [`RaycastSource::keep_last_hit`].
+    pub fn is_current(&self) -> bool {
+        self.is_current
+    }
+
+    /// Get a reference to the ray cast source's intersections, if they were produced less than
+    /// `secs` seconds ago. Returns an empty list if the intersections are older than this, which is
+    /// useful for sources that aren't updated every frame, where old results may be stale.
+    pub fn intersections_newer_than(
+        &self,
+        secs: f32,
+        time: &Time,
+    ) -> &[(Entity, IntersectionData)] {
+        // Intersections produced after `time`, such as by another clock, count as new.
+        match self.intersections_time {
+            Some(produced) if time.elapsed().saturating_sub(produced).as_secs_f32() < secs => {
+                &self.intersections
+            }
+            _ => &[],
+        }
+    }
+
     /// Get a reference to the nearest intersection point, if there is one.
     pub fn get_nearest_intersection(&self) -> Option<(Entity, &IntersectionData)> {
         if self.intersections.is_empty() {
@@ -351,11 +1515,90 @@ impl<T: TypePath> RaycastSource<T> {
         Some(self.ray?.intersects_primitive(shape)?.into())
     }
 
+    /// Get the intersection of the ray with the [`fallback_plane`](Self::fallback_plane).
+    ///
+    /// This is a synthetic hit that does not belong to any entity, and is only returned when the
+    /// ray did not intersect any meshes. Rays that are parallel to or pointing away from the plane
+    /// produce no fallback hit, and neither do planes closer than the
+    /// [`min_distance`](Self::min_distance) or beyond the [`max_distance`](Self::max_distance).
+    pub fn fallback_hit(&self) -> Option<IntersectionData> {
+        if !self.intersections.is_empty() {
+            return None;
+        }
+        self.intersect_primitive(self.fallback_plane?)
+            .filter(|hit| hit.distance() > 0.0 && hit.distance() >= self.min_distance)
+            .filter(|hit| self.max_distance.map_or(true, |max| hit.distance() <= max))
+    }
+
     /// Get a copy of the ray cast source's ray.
     pub fn get_ray(&self) -> Option<Ray3d> {
         self.ray
     }
 
+    /// Get the entities whose AABBs were intersected by the ray, along with the distance to the
+    /// AABB, sorted nearest first. This is the list of candidates that were tested for
+    /// intersections, and can be reused as a broad phase for other systems.
+    ///
+    /// This reflects the last raycast performed by this source, and is only recorded when
+    /// [`record_candidates`](Self::record_candidates) is `true`.
+    pub fn culled_candidates(&self) -> &[(Entity, f32)] {
+        &self.culled_candidates
+    }
+
+    /// Get a reference to the intersections with entities that have an
+    /// [`InstancedRaycast`](crate::markers::InstancedRaycast) component, as a list of
+    /// `(entity, instance_index, intersection)`, sorted nearest first. These are not included in
+    /// [`intersections`](Self::intersections), and are not swept. Instances behind the nearest
+    /// intersection that passes the early exit test are occluded by it, and are not included.
+    pub fn instance_intersections(&self) -> &[(Entity, usize, IntersectionData)] {
+        &self.instance_intersections
+    }
+
+    /// Get the explanation of why the [`explain_target`](Self::explain_target) was or wasn't hit
+    /// by the last raycast of this source. This is `None` if there is no target, or if the source
+    /// had no ray. Only the ray itself is explained, not the rays of a
+    /// [sweep](Self::sweep_samples).
+    pub fn explanation(&self) -> Option<&HitExplanation> {
+        self.explanation.as_ref()
+    }
+
+    /// Returns `true` if the last raycast of this source hit more entities than
+    /// [`RaycastPluginSettings::max_intersections`], so only the nearest of them were kept in the
+    /// [`intersections`](Self::intersections).
+    pub fn overflowed(&self) -> bool {
+        self.overflowed
+    }
+
+    /// Get the raycasting set each of the [`intersections`](Self::intersections) came from, in the
+    /// same order, for a source that is raycast across sets by the [`CrossGroupRaycastPlugin`]. The
+    /// sets are indexed like [`RaycastGroups::group_names`]. This is empty for other sources.
+    pub fn intersection_groups(&self) -> &[usize] {
+        &self.intersection_groups
+    }
+
+    /// Removes everything this source recorded about the `entity` during its last raycast.
+    fn forget(&mut self, entity: Entity) {
+        if !self.intersection_groups.is_empty() {
+            let mut intersections = self.intersections.iter();
+            self.intersection_groups
+                .retain(|_| intersections.next().is_some_and(|(e, _)| *e != entity));
+        }
+        self.intersections.retain(|(e, _)| *e != entity);
+        self.instance_intersections.retain(|(e, _, _)| *e != entity);
+        self.culled_candidates.retain(|(e, _)| *e != entity);
+        if self.settle_candidate.is_some_and(|(e, _)| e == entity) {
+            self.settle_candidate = None;
+        }
+    }
+
+    /// Removes the intersections of this source, and returns them by value, nearest first. The
+    /// buffer they were stored in is kept, and reused by the next raycast of this source, so long
+    /// lists of intersections don't need to be reallocated every frame.
+    pub fn drain_intersections(&mut self) -> std::vec::Drain<'_, (Entity, IntersectionData)> {
+        self.intersection_groups.clear();
+        self.intersections.drain(..)
+    }
+
     /// Get a mutable reference to the ray cast source's intersections.
     pub fn intersections_mut(&mut self) -> &mut Vec<(Entity, IntersectionData)> {
         &mut self.intersections
@@ -367,187 +1610,5504 @@ impl<T: TypePath> RaycastSource<T> {
     }
 }
 
-/// Specifies the method used to generate rays.
-#[derive(Clone, Debug, Reflect)]
-pub enum RaycastMethod {
-    /// Use the mouse cursor to build a ray.
-    Cursor,
-    /// Specify screen coordinates relative to the camera component associated with this entity.
-    ///
-    /// # Component Requirements
-    ///
-    /// This requires a [Camera] component on this [RaycastSource]'s entity, to determine where the
-    /// screenspace ray is firing from in the world.
-    Screenspace(Vec2),
-    /// Use a transform in world space to define a pick ray. This transform is applied to a vector
-    /// at the origin pointing up to generate a ray.
-    ///
-    /// # Component Requirements
-    ///
-    /// Requires a [GlobalTransform] component associated with this [RaycastSource]'s entity.
-    Transform,
+/// A [`RaycastSource`] that casts rays using the mouse cursor. Insert this alongside a camera
+/// bundle, e.g. `(Camera3dBundle::default(), RaycastCameraCursorBundle::<T>::default())`.
+#[derive(Bundle)]
+pub struct RaycastCameraCursorBundle<T: TypePath + Send + Sync + 'static> {
+    pub source: RaycastSource<T>,
 }
 
-pub fn build_rays<T: TypePath>(
-    mut pick_source_query: Query<(
-        &mut RaycastSource<T>,
-        Option<&GlobalTransform>,
-        Option<&Camera>,
-    )>,
-    window: Query<&Window, With<PrimaryWindow>>,
-) {
-    for (mut pick_source, transform, camera) in &mut pick_source_query {
-        pick_source.ray = match &mut pick_source.cast_method {
-            RaycastMethod::Cursor => {
-                query_window(&window, camera, transform).and_then(|(window, camera, transform)| {
-                    window.cursor_position().and_then(|cursor_pos| {
-                        Ray3d::from_screenspace(cursor_pos, camera, transform, window)
-                    })
-                })
-            }
-            RaycastMethod::Screenspace(cursor_pos_screen) => {
-                query_window(&window, camera, transform).and_then(|(window, camera, transform)| {
-                    Ray3d::from_screenspace(*cursor_pos_screen, camera, transform, window)
-                })
-            }
-            RaycastMethod::Transform => transform
-                .map(|t| t.compute_matrix())
-                .map(Ray3d::from_transform),
-        };
+impl<T: TypePath + Send + Sync + 'static> Default for RaycastCameraCursorBundle<T> {
+    fn default() -> Self {
+        Self {
+            source: RaycastSource::new_cursor(),
+        }
     }
 }
 
-fn query_window<'q, 'a: 'q, 'b>(
-    window: &'q Query<'_, '_, &'a Window, With<PrimaryWindow>>,
-    camera: Option<&'b Camera>,
-    transform: Option<&'b GlobalTransform>,
-) -> Option<(&'q Window, &'b Camera, &'b GlobalTransform)> {
-    let window = match window.get_single() {
-        Ok(window) => window,
-        Err(_) => {
-            error!("No primary window found, cannot cast ray");
-            return None;
-        }
-    };
-    let camera = match camera {
-        Some(camera) => camera,
-        None => {
-            error!(
-                "The PickingSource is a CameraScreenSpace but has no associated Camera component"
-            );
-            return None;
+impl<T: TypePath + Send + Sync + 'static> RaycastCameraCursorBundle<T> {
+    /// Set the `should_early_exit` field of this bundle's raycast source.
+    pub fn with_early_exit(self, should_early_exit: bool) -> Self {
+        Self {
+            source: self.source.with_early_exit(should_early_exit),
         }
-    };
-    let camera_transform = match transform {
-        Some(transform) => transform,
-        None => {
-            error!(
-        "The PickingSource is a CameraScreenSpace but has no associated GlobalTransform component"
-    );
-            return None;
+    }
+
+    /// Set the `visibility` field of this bundle's raycast source.
+    pub fn with_visibility(self, visibility: RaycastVisibility) -> Self {
+        Self {
+            source: self.source.with_visibility(visibility),
         }
-    };
-    Some((window, camera, camera_transform))
+    }
 }
 
-/// Iterates through all entities with the [RaycastMesh] component, checking for
-/// intersections. If these entities have bounding volumes, these will be checked first, greatly
-/// accelerating the process.
-pub fn update_raycast<T: TypePath + Send + Sync + 'static>(
-    mut raycast: crate::immediate::Raycast,
-    mut pick_source_query: Query<&mut RaycastSource<T>>,
-    targets: Query<&RaycastMesh<T>>,
-) {
-    for mut pick_source in &mut pick_source_query {
-        if let Some(ray) = pick_source.ray {
-            pick_source.intersections.clear();
+/// A [`RaycastSource`] that casts rays in the direction its [`GlobalTransform`] is pointing, along
+/// with the transform components it needs.
+#[derive(Bundle)]
+pub struct RaycastPointerBundle<T: TypePath + Send + Sync + 'static> {
+    pub source: RaycastSource<T>,
+    pub transform: TransformBundle,
+}
 
-            let filter = |entity| targets.contains(entity);
-            let test = |_| pick_source.should_early_exit;
-            let settings = RaycastSettings::default()
-                .with_filter(&filter)
-                .with_early_exit_test(&test)
-                .with_visibility(pick_source.visibility);
-            pick_source.intersections = raycast.cast_ray(ray, &settings).to_vec();
+impl<T: TypePath + Send + Sync + 'static> Default for RaycastPointerBundle<T> {
+    fn default() -> Self {
+        Self {
+            source: RaycastSource::new_transform_empty(),
+            transform: TransformBundle::default(),
         }
     }
 }
 
-pub fn update_target_intersections<T: TypePath + Send + Sync>(
-    sources: Query<(Entity, &RaycastSource<T>)>,
-    mut meshes: Query<&mut RaycastMesh<T>>,
-    mut previously_updated_raycast_meshes: Local<Vec<Entity>>,
-) {
-    // Clear any entities with intersections last frame
-    for entity in previously_updated_raycast_meshes.drain(..) {
-        if let Ok(mesh) = meshes.get_mut(entity).as_mut() {
-            mesh.intersections.clear();
+impl<T: TypePath + Send + Sync + 'static> RaycastPointerBundle<T> {
+    /// Set the [`Transform`] of this bundle.
+    pub fn with_transform(self, transform: Transform) -> Self {
+        Self {
+            transform: TransformBundle::from_transform(transform),
+            ..self
         }
     }
 
-    for (source_entity, source) in sources.iter() {
-        for (mesh_entity, intersection) in source.intersections().iter() {
-            if let Ok(mut mesh) = meshes.get_mut(*mesh_entity) {
-                mesh.intersections
-                    .push((source_entity, intersection.to_owned()));
-                previously_updated_raycast_meshes.push(*mesh_entity);
+    /// Set the `should_early_exit` field of this bundle's raycast source.
+    pub fn with_early_exit(self, should_early_exit: bool) -> Self {
+        Self {
+            source: self.source.with_early_exit(should_early_exit),
+            ..self
+        }
+    }
+
+    /// Set the `visibility` field of this bundle's raycast source.
+    pub fn with_visibility(self, visibility: RaycastVisibility) -> Self {
+        Self {
+            source: self.source.with_visibility(visibility),
+            ..self
+        }
+    }
+}
+
+/// Makes a mesh entity pickable by [`RaycastSource`]s of type `T`. Insert this alongside a mesh
+/// bundle, e.g. `(PbrBundle::default(), PickableBundle::<T>::default())`.
+///
+/// Raycasts cull entities by their [`Aabb`](bevy_render::primitives::Aabb), so the entity also
+/// needs one. This bundle doesn't include it, because Bevy's `calculate_bounds` system only
+/// computes the AABB of mesh entities that don't have one yet. Entities without an AABB are never
+/// hit.
+#[derive(Bundle)]
+pub struct PickableBundle<T: TypePath + Send + Sync + 'static> {
+    pub mesh: RaycastMesh<T>,
+}
+
+impl<T: TypePath + Send + Sync + 'static> Default for PickableBundle<T> {
+    fn default() -> Self {
+        Self {
+            mesh: RaycastMesh::default(),
+        }
+    }
+}
+
+/// Adds [`change_raycast_set`](Self::change_raycast_set) to [`EntityCommands`].
+pub trait RaycastEntityCommandsExt {
+    /// Moves this entity from raycasting set `From` to set `To`, e.g. when a prop is picked up and
+    /// becomes a carried item. The [`RaycastMesh<From>`] is replaced by a [`RaycastMesh<To>`], and
+    /// the entity is removed from the intersections of every [`RaycastSource<From>`], so no source
+    /// of the old set reports it on the following frame.
+    ///
+    /// Components that aren't specific to a set, such as [`SimplifiedMesh`] and
+    /// [`NoBackfaceCulling`](crate::markers::NoBackfaceCulling), are kept. A [`RaycastHitStats`]
+    /// is replaced with one for the new set, with its counters at zero.
+    fn change_raycast_set<From, To>(&mut self) -> &mut Self
+    where
+        From: TypePath + Send + Sync + 'static,
+        To: TypePath + Send + Sync + 'static;
+}
+
+impl RaycastEntityCommandsExt for EntityCommands<'_, '_, '_> {
+    fn change_raycast_set<From, To>(&mut self) -> &mut Self
+    where
+        From: TypePath + Send + Sync + 'static,
+        To: TypePath + Send + Sync + 'static,
+    {
+        self.add(|mut entity: EntityWorldMut| {
+            if entity.take::<RaycastMesh<From>>().is_some() {
+                entity.insert(RaycastMesh::<To>::default());
+            }
+            if entity.take::<RaycastHitStats<From>>().is_some() {
+                entity.insert(RaycastHitStats::<To>::default());
+            }
+            let id = entity.id();
+            let world = entity.into_world_mut();
+            let mut sources = world.query::<&mut RaycastSource<From>>();
+            for mut source in sources.iter_mut(world) {
+                source.forget(id);
             }
+        })
+    }
+}
+
+/// Adds [`spawn_at_hit`](Self::spawn_at_hit) to [`Commands`].
+pub trait RaycastCommandsExt<'w, 's> {
+    /// Spawns `bundle` as a child of `parent` at an `intersection` with it, e.g. a bullet hole or a
+    /// decal, so it moves with `parent`. The child's +Z axis points along the normal, the direction
+    /// a [`shape::Quad`](bevy_render::mesh::shape::Quad) faces, and its scale in world space is 1.
+    /// Use [`spawn_at_hit_with`](Self::spawn_at_hit_with) to choose the axis and scale.
+    ///
+    /// The [`Transform`] and [`GlobalTransform`] of the child are computed from the
+    /// [`GlobalTransform`] of `parent` when the command is applied, and replace those in `bundle`.
+    /// If `parent` has a non-uniform scale, the child is attached through a pivot entity instead,
+    /// so it isn't sheared, see [`IntersectionData::attachment`]. Despawn the parent of the child
+    /// to remove both.
+    fn spawn_at_hit<B: Bundle>(
+        &mut self,
+        bundle: B,
+        parent: Entity,
+        intersection: &IntersectionData,
+    ) -> EntityCommands<'w, 's, '_> {
+        self.spawn_at_hit_with(bundle, parent, intersection, Vec3::Z, 1.0)
+    }
+
+    /// Like [`spawn_at_hit`](Self::spawn_at_hit), with the child's `axis` pointing along the
+    /// normal, and a uniform `scale` in world space.
+    fn spawn_at_hit_with<B: Bundle>(
+        &mut self,
+        bundle: B,
+        parent: Entity,
+        intersection: &IntersectionData,
+        axis: Vec3,
+        scale: f32,
+    ) -> EntityCommands<'w, 's, '_>;
+}
+
+impl<'w, 's> RaycastCommandsExt<'w, 's> for Commands<'w, 's> {
+    fn spawn_at_hit_with<B: Bundle>(
+        &mut self,
+        bundle: B,
+        parent: Entity,
+        intersection: &IntersectionData,
+        axis: Vec3,
+        scale: f32,
+    ) -> EntityCommands<'w, 's, '_> {
+        let intersection = intersection.clone();
+        let mut child = self.spawn(bundle);
+        child.add(move |entity: EntityWorldMut| {
+            let id = entity.id();
+            let world = entity.into_world_mut();
+            let Some(&parent_transform) = world.get::<GlobalTransform>(parent) else {
+                warn!("Can't attach {id:?} to {parent:?}, which has no GlobalTransform");
+                return;
+            };
+            let attachment = intersection.attachment(&parent_transform, axis, scale);
+            let (attach_to, attach_transform) = match attachment.pivot {
+                Some(pivot) => {
+                    let pivot_transform = parent_transform.mul_transform(pivot);
+                    let pivot = world
+                        .spawn((
+                            TransformBundle {
+                                local: pivot,
+                                global: pivot_transform,
+                            },
+                            VisibilityBundle::default(),
+                        ))
+                        .set_parent(parent)
+                        .id();
+                    (pivot, pivot_transform)
+                }
+                None => (parent, parent_transform),
+            };
+            world
+                .entity_mut(id)
+                .insert((
+                    attachment.child,
+                    attach_transform.mul_transform(attachment.child),
+                ))
+                .set_parent(attach_to);
+        });
+        child
+    }
+}
+
+/// Restricts which entities a [`RaycastSource`] can hit.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub enum TargetFilter {
+    /// Raycast against every [`RaycastMesh`] with the same type as the source.
+    #[default]
+    All,
+    /// Only raycast against these entities. Entities in the list must still have a
+    /// [`RaycastMesh`] with the same type as the source. When the list is short, only the listed
+    /// entities are culled, which makes narrow raycasts very cheap.
+    Only(SmallVec<[Entity; 4]>),
+}
+
+/// How a [`RaycastSource`] refines its intersections over multiple frames.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Reflect)]
+pub enum CastRefinement {
+    /// Every raycast tests the meshes of the entities it hits.
+    #[default]
+    Full,
+    /// While the nearest entity hit by the ray keeps changing, e.g. while the cursor sweeps over
+    /// many entities, only AABBs are tested, and the intersections are
+    /// [flagged as approximate](IntersectionData::is_approximate). Once the same entity has been
+    /// the nearest hit for `settle_frames` more frames, meshes are tested to refine the position
+    /// and normal of the intersections.
+    ///
+    /// This makes picking cheap when there are many entities with detailed meshes.
+    Progressive { settle_frames: u8 },
+}
+
+/// How often a [`RaycastSource`] sends a [`RaycastHit`] event for its nearest intersection.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Reflect)]
+pub enum HitEventMode {
+    /// Send an event every frame the source hits an entity.
+    #[default]
+    EveryFrame,
+    /// Only send an event when the nearest entity changes, or when the nearest intersection moves
+    /// further than [`HIT_EVENT_EPSILON`] since the last event. A ray resting on an entity sends a
+    /// single event, and another once it hits the entity again after missing it.
+    OnChange,
+    /// Like [`HitEventMode::OnChange`], but an event for the entity of the last event is not sent
+    /// until this long after the last event, so rays flickering on and off an entity don't send an
+    /// event every time. This needs a [`Time`] resource, without one there is no cooldown.
+    OnChangeWithCooldown(Duration),
+}
+
+/// Specifies the method used to generate rays.
+#[derive(Clone, Debug, Reflect)]
+pub enum RaycastMethod {
+    /// Use the mouse cursor on the window the camera renders to to build a ray. Cameras rendering
+    /// to an image use the cursor on the primary window.
+    Cursor,
+    /// Use the latest touch to build a ray, the same way as the cursor, for
+    /// touch screens. When several fingers touch the window, the one with the highest id is used,
+    /// which is usually the latest one.
+    ///
+    /// While no finger touches the window, the ray is built from where the last touch ended if
+    /// `keep_released` is `true`, and there is no ray otherwise. This needs the [`Touches`]
+    /// resource, which is added by the `InputPlugin`.
+    Touch { keep_released: bool },
+    /// Specify screen coordinates relative to the camera component associated with this entity.
+    ///
+    /// The coordinates are in logical pixels of the window the camera renders to, from its top left
+    /// corner, the same as [`Window::cursor_position`], and are converted with the current size of
+    /// the window and viewport every time a ray is built. Use [`RaycastMethod::ScreenCenter`] to
+    /// cast through the center of the viewport instead. What happens to coordinates outside the
+    /// window is set by the [`cursor_bounds`](RaycastPluginSettings::cursor_bounds);
+    /// [`CursorBounds::Allow`] still builds their rays.
+    ///
+    /// # Component Requirements
+    ///
+    /// This requires a [Camera] component on this [RaycastSource]'s entity, to determine where the
+    /// screenspace ray is firing from in the world.
+    Screenspace(Vec2),
+    /// Use the [`VirtualPointer`] resource to build a ray, the same way as the cursor, for software
+    /// cursors such as one moved with a gamepad. There is no ray while the pointer has no position,
+    /// or is on another window than the one the camera renders to.
+    ///
+    /// # Component Requirements
+    ///
+    /// This requires a [Camera] component on this [RaycastSource]'s entity.
+    VirtualPointer,
+    /// Cast a ray through the center of a UI node, such as a crosshair widget, on the window the
+    /// camera renders to. The position of the node is scaled by the `UiScale`, so it matches the
+    /// window even if the UI is scaled. There is no ray if the entity isn't a UI node. Requires the
+    /// `ui` feature, which isn't enabled by default.
+    ///
+    /// # Component Requirements
+    ///
+    /// This requires a [Camera] component on this [RaycastSource]'s entity.
+    #[cfg(feature = "ui")]
+    UiNode(Entity),
+    /// Cast a ray through the center of the camera's viewport, such as for the crosshair of a first
+    /// person game. This doesn't depend on the cursor or on a window, and follows changes to the
+    /// camera's viewport and projection.
+    ///
+    /// # Component Requirements
+    ///
+    /// This requires a [Camera] component on this [RaycastSource]'s entity.
+    ScreenCenter,
+    /// Cast a ray through a point of the camera's render target, in coordinates from `(0, 0)` at
+    /// its top left corner to `(1, 1)` at its bottom right corner, the same as texture UVs. This
+    /// works with cameras rendering to an image, such as the texture of an in-world screen, using
+    /// the UV of a hit on the screen's mesh, and never reads a window. See
+    /// [`Ray3d::from_target_uv`].
+    ///
+    /// # Component Requirements
+    ///
+    /// This requires a [Camera] component on this [RaycastSource]'s entity.
+    TargetUv(Vec2),
+    /// Use a transform in world space to define a pick ray. The ray starts at the translation of
+    /// the transform, and points along its forward axis, local -Z, the same direction a camera
+    /// looks in. See [`Ray3d::from_transform`].
+    ///
+    /// # Component Requirements
+    ///
+    /// Requires a [GlobalTransform] component associated with this [RaycastSource]'s entity.
+    Transform,
+    /// Like [`RaycastMethod::Transform`], but the ray points along this axis, in the local space of
+    /// the transform, such as [`Vec3::Y`] for a source pointing up. The direction only depends on
+    /// the rotation of the transform, not on its scale. See [`Ray3d::from_transform_axis`].
+    ///
+    /// # Component Requirements
+    ///
+    /// Requires a [GlobalTransform] component associated with this [RaycastSource]'s entity.
+    TransformAxis(Vec3),
+    /// Use this ray, in world space, as is, such as a ray computed from the pose of a VR
+    /// controller, or received over the network. There is no ray while this is `None`.
+    ///
+    /// # Component Requirements
+    ///
+    /// None, the entity doesn't need a [Camera] or a [GlobalTransform].
+    Ray(Option<Ray3d>),
+}
+
+/// What a [`RaycastSource`] does with a cursor position outside the window it is on, which some
+/// platforms report by a few pixels while the cursor is dragged quickly past the edge of the
+/// window. Such positions build rays that graze past the geometry at the edges of the screen.
+///
+/// The position is checked against the window in logical pixels, before it is converted to a ray.
+/// [`Window::cursor_position`] is always inside the window, so this mostly affects positions given
+/// with [`RaycastMethod::Screenspace`]. Cameras with a [`Viewport`](bevy_render::camera::Viewport)
+/// don't build a ray from positions outside of their viewport, except with [`CursorBounds::Allow`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Reflect)]
+pub enum CursorBounds {
+    /// Move the position to the nearest point of the window.
+    #[default]
+    Clamp,
+    /// Treat the position as if the cursor left the window, so no ray is built.
+    Reject,
+    /// Build a ray from the position as it is.
+    Allow,
+}
+
+impl CursorBounds {
+    /// Applies this policy to a `position` on the `window`, in logical pixels. Returns `None` if the
+    /// position is rejected.
+    pub fn apply(self, position: Vec2, window: &Window) -> Option<Vec2> {
+        let size = Vec2::new(window.width(), window.height());
+        let inside = position.cmpge(Vec2::ZERO).all() && position.cmple(size).all();
+        match self {
+            CursorBounds::Clamp => Some(position.clamp(Vec2::ZERO, size)),
+            CursorBounds::Reject => inside.then_some(position),
+            CursorBounds::Allow => Some(position),
         }
     }
 }
 
-#[cfg(feature = "debug")]
-pub mod debug {
-    #![allow(unused)]
+/// Cursor rays built in the current frame, keyed by the camera entity, the window, and the cursor
+/// position, so the cursor [`RaycastSource`]s of every raycasting set on one camera share a single
+/// ray. A ray is only reused if the camera hasn't moved since it was built. This is shared by all
+/// [`DeferredRaycastingPlugin`]s, and cleared at the end of every frame.
+#[derive(Resource, Default)]
+pub struct CursorRayCache {
+    rays: HashMap<(Entity, Entity, [u32; 2]), (GlobalTransform, Option<Ray3d>)>,
+}
 
-    use bevy_ecs::system::{Commands, Query};
-    use bevy_gizmos::gizmos::Gizmos;
-    use bevy_math::{Quat, Vec3};
-    use bevy_reflect::TypePath;
-    use bevy_render::color::Color;
-    use bevy_utils::tracing::info;
-    use std::marker::PhantomData;
+pub fn clear_cursor_ray_cache(mut cache: ResMut<CursorRayCache>) {
+    cache.rays.clear();
+}
 
-    use crate::prelude::*;
+/// A software cursor, such as one moved with a gamepad, that [`RaycastMethod::VirtualPointer`]
+/// sources cast from instead of the mouse cursor. It can also be set by tests or input replays to
+/// pick without a real cursor. This is added by the [`DeferredRaycastingPlugin`].
+#[derive(Resource, Clone, Debug, Default, Reflect)]
+#[reflect(Resource)]
+pub struct VirtualPointer {
+    /// The position of the pointer, in logical pixels from the top left corner of the `window`,
+    /// the same as [`Window::cursor_position`]. Sources have no ray while this is `None`.
+    pub position: Option<Vec2>,
+    /// The window the pointer is on. Only sources on cameras rendering to this window cast from
+    /// the pointer.
+    pub window: WindowRef,
+}
 
-    /// Updates the 3d cursor to be in the pointed world coordinates
-    #[allow(clippy::too_many_arguments)]
-    pub fn update_debug_cursor<T: TypePath + Send + Sync>(
-        mut commands: Commands,
-        mut sources: Query<&RaycastSource<T>>,
-        mut gizmos: Gizmos,
-    ) {
-        for ray in sources.iter().filter_map(|s| s.ray) {
-            let orientation = Quat::from_rotation_arc(Vec3::NEG_Z, ray.direction());
-            gizmos.ray(ray.origin(), ray.direction(), Color::BLUE);
-            gizmos.sphere(ray.origin(), orientation, 0.1, Color::BLUE);
+#[allow(clippy::too_many_arguments)]
+pub fn build_rays<T: TypePath + Send + Sync>(
+    mut pick_source_query: Query<(
+        Entity,
+        &mut RaycastSource<T>,
+        Option<&GlobalTransform>,
+        Option<&Camera>,
+    )>,
+    windows: Query<(Entity, &Window)>,
+    primary_window: Query<Entity, With<PrimaryWindow>>,
+    touches: Option<Res<Touches>>,
+    virtual_pointer: Res<VirtualPointer>,
+    #[cfg(feature = "ui")] ui_nodes: Query<&GlobalTransform, With<bevy_ui::Node>>,
+    #[cfg(feature = "ui")] ui_scale: Option<Res<bevy_ui::UiScale>>,
+    mut cursor_rays: ResMut<CursorRayCache>,
+    mut metrics: ResMut<RaycastMetrics<T>>,
+    settings: Res<RaycastPluginSettings<T>>,
+    mut invalid_projections: Local<HashSet<Entity>>,
+) {
+    let cursor_bounds = settings.cursor_bounds;
+    let primary_window = primary_window.get_single().ok();
+    for (entity, mut pick_source, transform, camera) in &mut pick_source_query {
+        // The projection of a camera is only computed after it is spawned, and inverting it before
+        // then would build garbage rays.
+        let uses_camera = !matches!(
+            pick_source.cast_method,
+            RaycastMethod::Transform | RaycastMethod::TransformAxis(_) | RaycastMethod::Ray(_)
+        );
+        if uses_camera && camera.is_some_and(|camera| !has_invertible_projection(camera)) {
+            if invalid_projections.insert(entity) {
+                debug!(
+                    "The projection of the camera of {entity:?} can't be inverted, so it won't \
+                    cast rays until its projection is valid"
+                );
+            }
+            pick_source.ray = None;
+            continue;
         }
+        invalid_projections.remove(&entity);
+        let pick_source = &mut *pick_source;
+        pick_source.ray = match &mut pick_source.cast_method {
+            method @ (RaycastMethod::Cursor | RaycastMethod::Touch { .. }) => {
+                query_window(&windows, primary_window, camera, transform).and_then(
+                    |(window_entity, window, camera, transform)| {
+                        let last_touch = &mut pick_source.last_touch;
+                        let pointer =
+                            pointer_position(method, window, touches.as_deref(), last_touch)?;
+                        let cursor_pos = viewport_position(cursor_bounds, pointer, camera, window)?;
+                        let key = (
+                            entity,
+                            window_entity,
+                            cursor_pos.to_array().map(f32::to_bits),
+                        );
+                        match cursor_rays.rays.get(&key) {
+                            Some((built_from, ray)) if built_from == transform => {
+                                metrics.cursor_rays_reused += 1;
+                                *ray
+                            }
+                            _ => {
+                                let ray =
+                                    Ray3d::from_screenspace(cursor_pos, camera, transform, window);
+                                cursor_rays.rays.insert(key, (*transform, ray));
+                                metrics.cursor_rays_computed += 1;
+                                ray
+                            }
+                        }
+                    },
+                )
+            }
+            RaycastMethod::Screenspace(cursor_pos_screen) => {
+                query_window(&windows, primary_window, camera, transform).and_then(
+                    |(_, window, camera, transform)| {
+                        let cursor_pos =
+                            viewport_position(cursor_bounds, *cursor_pos_screen, camera, window)?;
+                        Ray3d::from_screenspace(cursor_pos, camera, transform, window)
+                    },
+                )
+            }
+            RaycastMethod::VirtualPointer => {
+                query_window(&windows, primary_window, camera, transform).and_then(
+                    |(window_entity, window, camera, transform)| {
+                        let pointer_window = virtual_pointer.window.normalize(primary_window)?;
+                        if pointer_window.entity() != window_entity {
+                            return None;
+                        }
+                        let position = virtual_pointer.position?;
+                        let cursor_pos =
+                            viewport_position(cursor_bounds, position, camera, window)?;
+                        Ray3d::from_screenspace(cursor_pos, camera, transform, window)
+                    },
+                )
+            }
+            #[cfg(feature = "ui")]
+            RaycastMethod::UiNode(node) => {
+                query_window(&windows, primary_window, camera, transform).and_then(
+                    |(_, window, camera, transform)| {
+                        // UI nodes are laid out in logical pixels divided by the UI scale, from the top left
+                        // corner of the window.
+                        let scale = ui_scale.as_ref().map_or(1.0, |scale| scale.0 as f32);
+                        let position = ui_nodes.get(*node).ok()?.translation().truncate() * scale;
+                        let cursor_pos =
+                            viewport_position(cursor_bounds, position, camera, window)?;
+                        Ray3d::from_screenspace(cursor_pos, camera, transform, window)
+                    },
+                )
+            }
+            RaycastMethod::ScreenCenter => camera
+                .zip(transform)
+                .and_then(|(camera, transform)| Ray3d::from_ndc(Vec2::ZERO, camera, transform)),
+            RaycastMethod::TargetUv(uv) => camera
+                .zip(transform)
+                .and_then(|(camera, transform)| Ray3d::from_target_uv(*uv, camera, transform)),
+            RaycastMethod::Transform => transform
+                .map(|t| t.compute_matrix())
+                .map(Ray3d::from_transform),
+            RaycastMethod::TransformAxis(axis) => {
+                transform.map(|t| Ray3d::from_transform_axis(t.compute_matrix(), *axis))
+            }
+            RaycastMethod::Ray(ray) => *ray,
+        };
+    }
+}
 
-        for (is_first, intersection) in sources.iter().flat_map(|m| {
-            m.intersections()
-                .iter()
-                .map(|i| i.1.clone())
-                .enumerate()
-                .map(|(i, hit)| (i == 0, hit))
-        }) {
-            let color = match is_first {
-                true => Color::GREEN,
-                false => Color::PINK,
-            };
-            gizmos.ray(intersection.position(), intersection.normal(), color);
-            gizmos.circle(intersection.position(), intersection.normal(), 0.1, color);
-            gizmos.circle_2d(intersection.position().truncate(), 10.0, color);
+/// The position of the pointer of a [`RaycastMethod::Cursor`] or [`RaycastMethod::Touch`] source on
+/// the `window`, in logical pixels. The position of the latest touch is kept in `last_touch`.
+fn pointer_position(
+    method: &RaycastMethod,
+    window: &Window,
+    touches: Option<&Touches>,
+    last_touch: &mut Option<Vec2>,
+) -> Option<Vec2> {
+    let RaycastMethod::Touch { keep_released } = *method else {
+        return window.cursor_position();
+    };
+    let Some(touches) = touches else {
+        error!("No `Touches` resource found, cannot cast touch ray");
+        return None;
+    };
+    if let Some(touch) = touches.iter().max_by_key(|touch| touch.id()) {
+        *last_touch = Some(touch.position());
+        return *last_touch;
+    }
+    let ended = touches
+        .iter_just_released()
+        .chain(touches.iter_just_canceled());
+    if let Some(touch) = ended.max_by_key(|touch| touch.id()) {
+        *last_touch = Some(touch.position());
+    }
+    keep_released.then_some(*last_touch).flatten()
+}
+
+/// Applies the `bounds` to a `position` on the `window`. Unless the bounds are
+/// [`CursorBounds::Allow`], positions outside the viewport of the camera are rejected, so only the
+/// camera under the cursor of a split screen builds a ray.
+fn viewport_position(
+    bounds: CursorBounds,
+    position: Vec2,
+    camera: &Camera,
+    window: &Window,
+) -> Option<Vec2> {
+    let position = bounds.apply(position, window)?;
+    let Some(viewport) = camera
+        .viewport
+        .as_ref()
+        .filter(|_| bounds != CursorBounds::Allow)
+    else {
+        return Some(position);
+    };
+    let scale_factor = window.scale_factor() as f32;
+    let min = viewport.physical_position.as_vec2() / scale_factor;
+    let max = min + viewport.physical_size.as_vec2() / scale_factor;
+    (position.cmpge(min).all() && position.cmple(max).all()).then_some(position)
+}
+
+/// Finds the window a camera renders to, and the camera's components. Cameras that don't render to
+/// a window use the primary window.
+fn query_window<'q, 'a: 'q, 'b>(
+    windows: &'q Query<'_, '_, (Entity, &'a Window)>,
+    primary_window: Option<Entity>,
+    camera: Option<&'b Camera>,
+    transform: Option<&'b GlobalTransform>,
+) -> Option<(Entity, &'q Window, &'b Camera, &'b GlobalTransform)> {
+    let camera = match camera {
+        Some(camera) => camera,
+        None => {
+            error!(
+                "The PickingSource is a CameraScreenSpace but has no associated Camera component"
+            );
+            return None;
+        }
+    };
+    let camera_transform = match transform {
+        Some(transform) => transform,
+        None => {
+            error!(
+        "The PickingSource is a CameraScreenSpace but has no associated GlobalTransform component"
+    );
+            return None;
+        }
+    };
+    let window_entity = match &camera.target {
+        RenderTarget::Window(window_ref) => {
+            window_ref.normalize(primary_window).map(|w| w.entity())
+        }
+        _ => primary_window,
+    };
+    let Some(window_entity) = window_entity else {
+        error!("No primary window found, cannot cast ray");
+        return None;
+    };
+    // The window may have been closed since the camera was set up.
+    let (window_entity, window) = windows.get(window_entity).ok()?;
+    Some((window_entity, window, camera, camera_transform))
+}
+
+#[cfg(feature = "2d")]
+type NoMeshFilter = (Without<Handle<Mesh>>, Without<bevy_sprite::Mesh2dHandle>);
+#[cfg(not(feature = "2d"))]
+type NoMeshFilter = Without<Handle<Mesh>>;
+#[cfg(feature = "2d")]
+type HasMeshFilter = Or<(With<Handle<Mesh>>, With<bevy_sprite::Mesh2dHandle>)>;
+#[cfg(not(feature = "2d"))]
+type HasMeshFilter = With<Handle<Mesh>>;
+
+/// Adds a [`RaycastMesh<T>`] to the descendants with a mesh handle of every
+/// [`RaycastSceneRoot<T>`] that don't have one yet.
+pub fn tag_scene_meshes<T: TypePath + Send + Sync>(
+    mut commands: Commands,
+    roots: Query<Entity, With<RaycastSceneRoot<T>>>,
+    children: Query<&Children>,
+    untagged: Query<(), (HasMeshFilter, Without<RaycastMesh<T>>)>,
+) {
+    for root in &roots {
+        for descendant in children.iter_descendants(root) {
+            if untagged.contains(descendant) {
+                commands
+                    .entity(descendant)
+                    .insert(RaycastMesh::<T>::default());
+            }
         }
     }
+}
 
-    /// Used to debug [`RaycastMesh`] intersections.
-    pub fn print_intersections<T: TypePath + Send + Sync>(query: Query<&RaycastMesh<T>>) {
-        for (_, intersection) in query.iter().flat_map(|mesh| mesh.intersections.iter()) {
-            info!(
-                "Distance {:?}, Position {:?}",
-                intersection.distance(),
-                intersection.position()
+/// Finds [`RaycastMesh`] entities without a mesh handle, warning once per entity, and records them
+/// in [`RaycastDebugInfo`].
+pub fn check_raycast_meshes<T: TypePath + Send + Sync>(
+    meshes: Query<Entity, (With<RaycastMesh<T>>, NoMeshFilter)>,
+    mut debug_info: ResMut<RaycastDebugInfo<T>>,
+    mut warned: Local<HashSet<Entity>>,
+) {
+    debug_info.missing_mesh_handles.clear();
+    for entity in &meshes {
+        if warned.insert(entity) {
+            warn!(
+                "{entity:?} has a `RaycastMesh<{0}>` but no mesh, so it can never be hit. If this \
+                entity is the root of a scene, move the component to the child entity holding the \
+                mesh, or replace it with a `RaycastSceneRoot<{0}>` to tag every mesh in the scene.",
+                T::short_type_path()
+            );
+        }
+        debug_info.missing_mesh_handles.push(entity);
+    }
+    warned.retain(|entity| debug_info.missing_mesh_handles.contains(entity));
+}
+
+/// Finds cursor [`RaycastSource`]s on cameras with the same render target and viewport, which cast
+/// the same ray and duplicate each other's work, warning once per group, and records them in
+/// [`RaycastDebugInfo`]. The rays of all but the first source of each group are removed if
+/// [`RaycastPluginSettings::disable_duplicate_sources`] is `true`.
+pub fn check_duplicate_sources<T: TypePath + Send + Sync>(
+    mut sources: Query<(Entity, &mut RaycastSource<T>, &Camera)>,
+    primary_window: Query<Entity, With<PrimaryWindow>>,
+    plugin_settings: Res<RaycastPluginSettings<T>>,
+    mut debug_info: ResMut<RaycastDebugInfo<T>>,
+    mut warned: Local<HashSet<Vec<Entity>>>,
+) {
+    let primary_window = primary_window.get_single().ok();
+    let mut viewports: HashMap<_, Vec<Entity>> = HashMap::new();
+    for (entity, source, camera) in &sources {
+        if !camera.is_active || !matches!(source.cast_method, RaycastMethod::Cursor) {
+            continue;
+        }
+        let viewport = camera
+            .viewport
+            .as_ref()
+            .map(|viewport| (viewport.physical_position, viewport.physical_size));
+        let key = (camera.target.normalize(primary_window), viewport);
+        viewports.entry(key).or_default().push(entity);
+    }
+
+    debug_info.duplicate_sources.clear();
+    for mut duplicates in viewports.into_values().filter(|group| group.len() > 1) {
+        duplicates.sort();
+        if warned.insert(duplicates.clone()) {
+            warn!(
+                "{duplicates:?} are `RaycastSource<{}>`s that use the cursor of the same camera \
+                viewport, so they cast the same ray. Remove all but one of them, or set \
+                `RaycastPluginSettings::disable_duplicate_sources` to only raycast the first.",
+                T::short_type_path()
             );
         }
+        if plugin_settings.disable_duplicate_sources {
+            for entity in &duplicates[1..] {
+                if let Ok((_, mut source, _)) = sources.get_mut(*entity) {
+                    source.ray = None;
+                    source.intersections.clear();
+                }
+            }
+        }
+        debug_info.duplicate_sources.push(duplicates);
+    }
+    debug_info.duplicate_sources.sort();
+    warned.retain(|group| debug_info.duplicate_sources.contains(group));
+}
+
+/// Everything that affects the raycast of a [`RaycastSource`], to share it between sources.
+#[doc(hidden)]
+#[derive(PartialEq, Eq, Hash)]
+pub struct CastKey {
+    ray: [u32; 6],
+    should_early_exit: bool,
+    any_hit: bool,
+    max_hits_per_mesh: usize,
+    volume_only: bool,
+    visibility: RaycastVisibility,
+    record_candidates: bool,
+    target_filter: TargetFilter,
+    /// Sources only share casts with the same filter closure.
+    filter: Option<usize>,
+    exclusions: SmallVec<[Entity; 4]>,
+    layer_mask: u32,
+    max_slope: Option<[u32; 4]>,
+    backfaces: Option<Backfaces>,
+    min_distance: u32,
+    max_distance: Option<u32>,
+    ray_start_offset: u32,
+}
+
+impl CastKey {
+    fn new<T: TypePath>(ray: Ray3d, source: &RaycastSource<T>) -> Self {
+        let (origin, direction) = (ray.origin(), ray.direction());
+        CastKey {
+            ray: [
+                origin.x,
+                origin.y,
+                origin.z,
+                direction.x,
+                direction.y,
+                direction.z,
+            ]
+            .map(f32::to_bits),
+            should_early_exit: source.should_early_exit,
+            any_hit: source.any_hit,
+            max_hits_per_mesh: source.max_hits_per_mesh,
+            volume_only: source.volume_only,
+            visibility: source.visibility,
+            record_candidates: source.record_candidates,
+            target_filter: source.target_filter.clone(),
+            filter: source
+                .filter
+                .as_ref()
+                .map(|filter| Arc::as_ptr(filter) as *const () as usize),
+            exclusions: source.exclusions.clone(),
+            layer_mask: source.layer_mask,
+            max_slope: source
+                .max_slope
+                .map(|slope| [slope.angle, slope.up.x, slope.up.y, slope.up.z].map(f32::to_bits)),
+            backfaces: source.backfaces,
+            min_distance: source.min_distance.to_bits(),
+            max_distance: source.max_distance.map(f32::to_bits),
+            ray_start_offset: source.ray_start_offset.to_bits(),
+        }
+    }
+}
+
+/// Adds `offset` to the distances of everything the source recorded during its last raycast.
+fn offset_distances<T: TypePath>(source: &mut RaycastSource<T>, offset: f32) {
+    let intersections = source.intersections.iter_mut().map(|(_, hit)| hit);
+    let instance_intersections = source.instance_intersections.iter_mut();
+    for hit in intersections.chain(instance_intersections.map(|(_, _, hit)| hit)) {
+        hit.set_distance(hit.distance() + offset);
+    }
+    for (_, near) in &mut source.culled_candidates {
+        *near += offset;
+    }
+    match &mut source.explanation {
+        Some(HitExplanation::Hit(hit)) => hit.set_distance(hit.distance() + offset),
+        Some(HitExplanation::Occluded { distance, .. }) => *distance += offset,
+        Some(HitExplanation::BeyondMaxDistance { distance }) => *distance += offset,
+        Some(HitExplanation::BeforeMinDistance { distance }) => *distance += offset,
+        _ => (),
+    }
+}
+
+/// The skipped entity counts of a [`CastOutcome`], kept by a source without borrowing the hits.
+#[derive(Clone, Copy, Debug, Default)]
+struct CastSkips {
+    unready: usize,
+    unsupported_topology: usize,
+    invalid_mesh: usize,
+    truncated_by_budget: bool,
+}
+
+impl CastSkips {
+    fn new(outcome: &CastOutcome) -> Self {
+        Self {
+            unready: outcome.skipped_unready,
+            unsupported_topology: outcome.skipped_unsupported_topology,
+            invalid_mesh: outcome.skipped_invalid_mesh,
+            truncated_by_budget: outcome.truncated_by_budget,
+        }
+    }
+
+    fn with_hits(self, hits: &[(Entity, IntersectionData)]) -> CastOutcome<'_> {
+        CastOutcome {
+            hits,
+            skipped_unready: self.unready,
+            skipped_unsupported_topology: self.unsupported_topology,
+            skipped_invalid_mesh: self.invalid_mesh,
+            truncated_by_budget: self.truncated_by_budget,
+        }
+    }
+}
+
+/// The results of a raycast, shared by every source with the same [`CastKey`].
+#[doc(hidden)]
+pub struct SharedCast {
+    intersections: Vec<(Entity, IntersectionData)>,
+    instance_intersections: Vec<(Entity, usize, IntersectionData)>,
+    culled_candidates: Vec<(Entity, f32)>,
+    outcome: Option<CastSkips>,
+    overflowed: bool,
+}
+
+/// Iterates through all entities with the [RaycastMesh] component, checking for
+/// intersections. If these entities have bounding volumes, these will be checked first, greatly
+/// accelerating the process.
+pub fn update_raycast<T: TypePath + Send + Sync + 'static>(
+    mut raycast: crate::immediate::Raycast,
+    mut pick_source_query: Query<(
+        Entity,
+        &mut RaycastSource<T>,
+        Option<(&Camera, &GlobalTransform)>,
+    )>,
+    targets: Query<Option<&RaycastLayers>, With<RaycastMesh<T>>>,
+    plugin_settings: Res<RaycastPluginSettings<T>>,
+    mut metrics: ResMut<RaycastMetrics<T>>,
+    time: Option<Res<Time>>,
+    mut shared_casts: Local<HashMap<CastKey, SharedCast>>,
+) {
+    *raycast.large_mesh_setting = Some(LARGE_MESH_SETTING);
+    shared_casts.clear();
+    for (source_entity, mut pick_source, camera) in &mut pick_source_query {
+        let current_ray = pick_source.ray;
+        let previous_ray = std::mem::replace(&mut pick_source.previous_ray, current_ray);
+        pick_source.outcome = None;
+        pick_source.explanation = None;
+        let last_hits = pick_source.keep_last_hit.then(|| {
+            let intersections = std::mem::take(&mut pick_source.intersections);
+            (intersections, pick_source.intersections_time)
+        });
+        pick_source.intersections.clear();
+        pick_source.intersections_time = time.as_ref().map(|time| time.elapsed());
+        if let Some(ray) = pick_source.ray {
+            let sweep_samples = pick_source.sweep_samples;
+            let swept_from = previous_ray.filter(|p| sweep_samples > 0 && *p != ray);
+            // Swept and progressive raycasts depend on the previous frames of each source, so they
+            // are never shared, and neither are explained raycasts.
+            let is_shareable = swept_from.is_none()
+                && pick_source.refinement == CastRefinement::Full
+                && pick_source.explain_target.is_none();
+            let key = is_shareable.then(|| CastKey::new(ray, &pick_source));
+            if let Some(shared) = key.as_ref().and_then(|key| shared_casts.get(key)) {
+                pick_source.intersections.clone_from(&shared.intersections);
+                pick_source.instance_intersections = shared.instance_intersections.clone();
+                pick_source.culled_candidates = shared.culled_candidates.clone();
+                pick_source.outcome = shared.outcome;
+                pick_source.overflowed = shared.overflowed;
+                metrics.casts_deduplicated += 1;
+                set_camera_outputs(&mut pick_source, camera);
+                keep_last_hits(&mut pick_source, last_hits, |entity| {
+                    targets.contains(entity)
+                });
+                continue;
+            }
+            metrics.casts += 1;
+
+            // Rays are cast from the start offset, and the distances of their hits are measured
+            // from the original origin again below.
+            let offset = pick_source.ray_start_offset;
+            let start_at_offset = |ray: Ray3d| Ray3d::new(ray.position(offset), ray.direction());
+            let ray = start_at_offset(ray);
+            let swept_from = swept_from.map(start_at_offset);
+
+            let source_filter = pick_source.filter.clone();
+            let exclusions = pick_source.exclusions.clone();
+            let layer_mask = pick_source.layer_mask;
+            let filter = |entity| {
+                targets
+                    .get(entity)
+                    .is_ok_and(|layers| layers.map_or(true, |layers| layers.intersects(layer_mask)))
+                    && !exclusions.contains(&entity)
+                    && source_filter.as_ref().map_or(true, |filter| filter(entity))
+            };
+            let should_early_exit = pick_source.should_early_exit;
+            let test = |_| should_early_exit;
+            let target_filter = pick_source.target_filter.clone();
+            let volume_only = pick_source.volume_only || plugin_settings.volume_only;
+            let mut settings = RaycastSettings {
+                max_slope: pick_source.max_slope,
+                min_distance: pick_source.min_distance - offset,
+                max_distance: pick_source.max_distance.map(|distance| distance - offset),
+                ..default()
+            }
+            .with_filter(&filter)
+            .with_early_exit_test(&test)
+            .with_visibility(pick_source.visibility)
+            .with_any_hit(pick_source.any_hit)
+            .with_max_hits_per_mesh(pick_source.max_hits_per_mesh)
+            .with_volume_only(volume_only)
+            .with_backfaces(pick_source.backfaces.unwrap_or(plugin_settings.backfaces))
+            .with_aabb_inflation(plugin_settings.aabb_inflation)
+            .with_time_budget(plugin_settings.time_budget)
+            .with_large_mesh_threshold(plugin_settings.large_mesh_threshold)
+            .with_line_tolerance(plugin_settings.line_tolerance)
+            .with_self_hit_epsilon(plugin_settings.self_hit_epsilon);
+            if let TargetFilter::Only(targets) = &target_filter {
+                settings = settings.with_targets(targets);
+            }
+
+            let mut is_refined = true;
+            let refinement = (pick_source.refinement, volume_only);
+            if let (CastRefinement::Progressive { settle_frames }, false) = refinement {
+                let coarse_settings = settings.clone().with_volume_only(true);
+                let candidate = raycast
+                    .cast_ray(ray, &coarse_settings)
+                    .first()
+                    .map(|(e, _)| *e);
+                let frames = match (pick_source.settle_candidate, candidate) {
+                    (Some((previous, frames)), Some(candidate)) if previous == candidate => {
+                        frames.saturating_add(1)
+                    }
+                    _ => 0,
+                };
+                pick_source.settle_candidate = candidate.map(|candidate| (candidate, frames));
+                is_refined = candidate.is_none() || frames >= settle_frames;
+                settings = settings.with_volume_only(!is_refined);
+            }
+            let outcome = raycast.cast_ray_with_outcome(ray, &settings);
+            // The intersections were cleared above, and are refilled in place to reuse their buffer.
+            pick_source.intersections.extend_from_slice(outcome.hits);
+            let skips = CastSkips::new(&outcome);
+            pick_source.outcome = Some(skips);
+            // The audit and explanation below cull again, so the candidates are copied first.
+            if pick_source.record_candidates {
+                let candidates = raycast
+                    .culled_list
+                    .iter()
+                    .filter(|(_, entity)| targets.contains(*entity))
+                    .map(|(near, entity)| (*entity, near.0))
+                    .collect();
+                pick_source.culled_candidates = candidates;
+            } else {
+                pick_source.culled_candidates.clear();
+            }
+            if plugin_settings.audit && !skips.truncated_by_budget && !pick_source.any_hit {
+                metrics.casts_audited += 1;
+                let reference = raycast.cast_ray_reference(ray, &settings);
+                let intersections = &pick_source.intersections;
+                if RaycastAuditDivergence::diverges(intersections, reference) {
+                    let divergence = RaycastAuditDivergence {
+                        source: source_entity,
+                        ray,
+                        intersections: intersections.clone(),
+                        reference: reference.to_vec(),
+                    };
+                    error!("Raycast differs from the brute force reference: {divergence:?}");
+                    metrics.audit_divergences += 1;
+                    metrics.last_audit_divergence = Some(divergence);
+                }
+            }
+            // Instances behind the nearest blocking mesh hit are occluded by it.
+            let nearest_blocking_hit = pick_source
+                .intersections
+                .iter()
+                .find(|(entity, _)| test(*entity))
+                .map(|(_, hit)| hit.distance());
+            let instance_settings = RaycastSettings {
+                max_distance: match (settings.max_distance, nearest_blocking_hit) {
+                    (Some(max), Some(blocking)) => Some(max.min(blocking)),
+                    (max, blocking) => max.or(blocking),
+                },
+                ..settings.clone()
+            };
+            pick_source.instance_intersections =
+                raycast.cast_ray_instanced(ray, &instance_settings).to_vec();
+            if let Some(target) = pick_source.explain_target {
+                pick_source.explanation = Some(match targets.contains(target) {
+                    true => raycast.explain(ray, target, &settings),
+                    false => HitExplanation::NotInRaycastSet,
+                });
+            }
+
+            for (mesh, large_mesh) in raycast.large_meshes.iter() {
+                metrics.large_meshes.entry(*mesh).or_insert(*large_mesh);
+            }
+
+            if let Some(previous_ray) = swept_from {
+                for i in 1..=sweep_samples {
+                    let t = i as f32 / (sweep_samples as f32 + 1.0);
+                    let swept_ray = previous_ray.interpolate(&ray, t);
+                    for (entity, hit) in raycast.cast_ray(swept_ray, &settings) {
+                        let intersections = &mut pick_source.intersections;
+                        match intersections.iter_mut().find(|(e, _)| e == entity) {
+                            Some((_, existing)) if existing.is_swept() => {
+                                if hit.distance() < existing.distance() {
+                                    *existing = hit.clone();
+                                    existing.set_swept(true);
+                                }
+                            }
+                            Some(_) => (),
+                            None => {
+                                let mut hit = hit.clone();
+                                hit.set_swept(true);
+                                intersections.push((*entity, hit));
+                            }
+                        }
+                    }
+                }
+                pick_source
+                    .intersections
+                    .sort_by(|(_, a), (_, b)| a.distance().total_cmp(&b.distance()));
+            }
+
+            if !is_refined {
+                for (_, intersection) in pick_source.intersections.iter_mut() {
+                    intersection.set_approximate(true);
+                }
+            }
+
+            if offset != 0.0 {
+                offset_distances(&mut pick_source, offset);
+            }
+
+            let max_intersections = plugin_settings.max_intersections.unwrap_or(usize::MAX);
+            pick_source.overflowed = pick_source.intersections.len() > max_intersections;
+            if pick_source.overflowed {
+                pick_source.intersections.truncate(max_intersections);
+                metrics.casts_overflowed += 1;
+            }
+
+            if let Some(key) = key {
+                let shared = SharedCast {
+                    intersections: pick_source.intersections.clone(),
+                    instance_intersections: pick_source.instance_intersections.clone(),
+                    culled_candidates: pick_source.culled_candidates.clone(),
+                    outcome: pick_source.outcome,
+                    overflowed: pick_source.overflowed,
+                };
+                shared_casts.insert(key, shared);
+            }
+            set_camera_outputs(&mut pick_source, camera);
+            keep_last_hits(&mut pick_source, last_hits, |entity| {
+                targets.contains(entity)
+            });
+        } else {
+            // Without a ray, e.g. while the cursor is outside the window, nothing is hit.
+            pick_source.instance_intersections.clear();
+            pick_source.culled_candidates.clear();
+            pick_source.overflowed = false;
+            keep_last_hits(&mut pick_source, last_hits, |entity| {
+                targets.contains(entity)
+            });
+        }
+    }
+}
+
+/// Restores the `last_hits` of a source that keeps its last hit, and the time they were produced
+/// at, if its raycast hit nothing. Intersections with entities that aren't targets are dropped.
+fn keep_last_hits<T: TypePath>(
+    source: &mut RaycastSource<T>,
+    last_hits: Option<(Vec<(Entity, IntersectionData)>, Option<Duration>)>,
+    is_target: impl Fn(Entity) -> bool,
+) {
+    source.is_current = true;
+    let Some((mut intersections, time)) = last_hits else {
+        return;
+    };
+    intersections.retain(|(entity, _)| is_target(*entity));
+    if source.intersections.is_empty() && !intersections.is_empty() {
+        source.intersections = intersections;
+        source.intersections_time = time;
+        source.is_current = false;
+    }
+}
+
+/// Computes the camera depth and the requested output spaces of a source's intersections.
+fn set_camera_outputs<T: TypePath>(
+    source: &mut RaycastSource<T>,
+    camera: Option<(&Camera, &GlobalTransform)>,
+) {
+    let Some((camera, camera_transform)) = camera else {
+        return;
+    };
+    let world_to_view = camera_transform.compute_matrix().inverse();
+    let projection = camera.projection_matrix();
+    let spaces = source.output_spaces;
+    for (_, intersection) in source.intersections.iter_mut() {
+        intersection.set_camera_depth(&world_to_view, &projection);
+        intersection.set_output_spaces(&world_to_view, &projection, spaces);
+    }
+    for (_, _, intersection) in source.instance_intersections.iter_mut() {
+        intersection.set_camera_depth(&world_to_view, &projection);
+        intersection.set_output_spaces(&world_to_view, &projection, spaces);
+    }
+}
+
+/// Rebuilds the [`RaycastFrameSnapshot`] from the intersections of every [`RaycastSource`].
+pub fn update_frame_snapshot<T: TypePath + Send + Sync>(
+    sources: Query<(Entity, &RaycastSource<T>)>,
+    mut snapshot: ResMut<RaycastFrameSnapshot<T>>,
+) {
+    let snapshot = &mut *snapshot;
+    snapshot.frame += 1;
+    snapshot.records.clear();
+    for (source_entity, source) in &sources {
+        let records = source
+            .intersections()
+            .iter()
+            .map(|(entity, hit)| RaycastRecord {
+                source_entity_bits: source_entity.to_bits(),
+                target_entity_bits: entity.to_bits(),
+                group_id: snapshot.group_id,
+                position: hit.position().to_array(),
+                normal: hit.normal().to_array(),
+                distance: hit.distance(),
+            });
+        snapshot.records.extend(records);
+    }
+}
+
+/// Records the nearest hit of each [`RaycastSource`] with an [`InterpolatedHit`].
+pub fn update_interpolated_hits<T: TypePath + Send + Sync>(
+    mut sources: Query<(&RaycastSource<T>, &mut InterpolatedHit<T>)>,
+) {
+    for (source, mut hit) in &mut sources {
+        let nearest = source.get_nearest_intersection();
+        hit.previous = std::mem::replace(&mut hit.current, nearest.map(|(_, i)| i.position()));
+    }
+}
+
+/// Clears the intersections of every [`RaycastSource<G>`] with a ray, before they are raycast
+/// against each set in `G`.
+pub fn clear_cross_group_hits<G: RaycastGroups>(
+    mut sources: Query<&mut RaycastSource<G>>,
+    time: Option<Res<Time>>,
+) {
+    for mut source in &mut sources {
+        source.outcome = None;
+        if source.ray.is_some() {
+            source.intersections.clear();
+            source.intersection_groups.clear();
+            source.intersections_time = time.as_ref().map(|time| time.elapsed());
+        }
+    }
+}
+
+/// Raycasts every [`RaycastSource<G>`] against the meshes of the set `T`, the set at `GROUP` in
+/// `G`, with the [`RaycastPluginSettings<T>`].
+pub fn cast_cross_group<G: RaycastGroups, T: TypePath + Send + Sync, const GROUP: usize>(
+    mut raycast: crate::immediate::Raycast,
+    mut sources: Query<&mut RaycastSource<G>>,
+    targets: Query<Option<&RaycastLayers>, With<RaycastMesh<T>>>,
+    plugin_settings: Res<RaycastPluginSettings<T>>,
+    mut metrics: ResMut<RaycastMetrics<G>>,
+) {
+    *raycast.large_mesh_setting = Some(LARGE_MESH_SETTING);
+    for mut source in &mut sources {
+        let Some(ray) = source.ray else {
+            continue;
+        };
+        metrics.casts += 1;
+        let source_filter = source.filter.clone();
+        let exclusions = source.exclusions.clone();
+        let layer_mask = source.layer_mask;
+        let filter = |entity| {
+            targets
+                .get(entity)
+                .is_ok_and(|layers| layers.map_or(true, |layers| layers.intersects(layer_mask)))
+                && !exclusions.contains(&entity)
+                && source_filter.as_ref().map_or(true, |filter| filter(entity))
+        };
+        let should_early_exit = source.should_early_exit;
+        let test = |_| should_early_exit;
+        let target_filter = source.target_filter.clone();
+        let offset = source.ray_start_offset;
+        let mut settings = RaycastSettings {
+            max_slope: source.max_slope,
+            min_distance: source.min_distance - offset,
+            max_distance: source.max_distance.map(|distance| distance - offset),
+            ..default()
+        }
+        .with_filter(&filter)
+        .with_early_exit_test(&test)
+        .with_visibility(source.visibility)
+        .with_any_hit(source.any_hit)
+        .with_max_hits_per_mesh(source.max_hits_per_mesh)
+        .with_volume_only(source.volume_only || plugin_settings.volume_only)
+        .with_backfaces(source.backfaces.unwrap_or(plugin_settings.backfaces))
+        .with_aabb_inflation(plugin_settings.aabb_inflation)
+        .with_time_budget(plugin_settings.time_budget)
+        .with_large_mesh_threshold(plugin_settings.large_mesh_threshold)
+        .with_line_tolerance(plugin_settings.line_tolerance)
+        .with_self_hit_epsilon(plugin_settings.self_hit_epsilon);
+        if let TargetFilter::Only(targets) = &target_filter {
+            settings = settings.with_targets(targets);
+        }
+        let ray = Ray3d::new(ray.position(offset), ray.direction());
+        let hits = raycast
+            .cast_ray(ray, &settings)
+            .iter()
+            .map(|(entity, hit)| {
+                let mut hit = hit.clone();
+                hit.set_distance(hit.distance() + offset);
+                (*entity, hit)
+            });
+        let source = &mut *source;
+        source.intersections.extend(hits);
+        source
+            .intersection_groups
+            .resize(source.intersections.len(), GROUP);
+    }
+}
+
+/// Sorts the intersections of every [`RaycastSource<G>`] by distance, once it has been raycast
+/// against every set in `G`, keeping only the intersections of each entity in the set it was hit
+/// nearest in.
+pub fn merge_cross_group_hits<G: RaycastGroups>(mut sources: Query<&mut RaycastSource<G>>) {
+    for mut source in &mut sources {
+        if source.ray.is_none() {
+            continue;
+        }
+        let source = &mut *source;
+        let mut hits: Vec<_> = source
+            .intersections
+            .drain(..)
+            .zip(source.intersection_groups.drain(..))
+            .collect();
+        // The sort is stable, so hits at the same distance keep the order of their sets.
+        hits.sort_by(|((_, a), _), ((_, b), _)| a.distance().total_cmp(&b.distance()));
+        // An entity in several sets is only kept in the set it was hit nearest in, along with all
+        // of its hits in that set.
+        let mut entity_groups = HashMap::new();
+        hits.retain(|((entity, _), group)| {
+            *entity_groups.entry(*entity).or_insert(*group) == *group
+        });
+        // Each set stops at its own nearest hit, or at any hit, so only the nearest hit of all sets
+        // is kept.
+        if source.should_early_exit || source.any_hit {
+            hits.truncate(1);
+        }
+        for (hit, group) in hits {
+            source.intersections.push(hit);
+            source.intersection_groups.push(group);
+        }
+    }
+}
+
+pub fn update_target_intersections<T: TypePath + Send + Sync>(
+    sources: Query<(Entity, &RaycastSource<T>)>,
+    mut meshes: Query<(
+        &mut RaycastMesh<T>,
+        Option<(&GlobalTransform, &crate::markers::TrackHitLocally)>,
+    )>,
+    mut stats: Query<(Entity, &mut RaycastHitStats<T>)>,
+    mut previously_updated_raycast_meshes: Local<Vec<Entity>>,
+) {
+    // Clear any entities with intersections last frame
+    for entity in previously_updated_raycast_meshes.drain(..) {
+        if let Ok((mut mesh, _)) = meshes.get_mut(entity) {
+            mesh.intersections.clear();
+            mesh.local_intersections.clear();
+        }
+    }
+
+    for (source_entity, source) in sources.iter() {
+        for (mesh_entity, intersection) in source.intersections().iter() {
+            if let Ok((mut mesh, tracked)) = meshes.get_mut(*mesh_entity) {
+                mesh.intersections
+                    .push((source_entity, intersection.to_owned()));
+                if let Some((transform, _)) = tracked {
+                    let local = intersection.to_local(transform);
+                    mesh.local_intersections.push((source_entity, local));
+                }
+                previously_updated_raycast_meshes.push(*mesh_entity);
+            }
+        }
+    }
+
+    if stats.is_empty() {
+        return;
+    }
+    let top_hits: HashSet<Entity> = sources
+        .iter()
+        .filter_map(|(_, source)| source.get_nearest_intersection())
+        .map(|(entity, _)| entity)
+        .collect();
+    for (entity, mut stats) in &mut stats {
+        if meshes
+            .get(entity)
+            .is_ok_and(|(mesh, _)| !mesh.intersections.is_empty())
+        {
+            stats.frames_hit += 1;
+        }
+        if top_hits.contains(&entity) {
+            stats.frames_top_hit += 1;
+        }
+    }
+}
+
+/// Clears the intersections of [`RaycastMesh`]es whose mesh asset, 2D mesh asset, or
+/// [`SimplifiedMesh`] was modified, which were found with the old mesh, until they are raycast
+/// again.
+pub fn clear_modified_mesh_intersections<T: TypePath + Send + Sync>(
+    mut mesh_events: EventReader<AssetEvent<Mesh>>,
+    mut meshes: Query<(
+        Entity,
+        Option<&Handle<Mesh>>,
+        Option<&SimplifiedMesh>,
+        &mut RaycastMesh<T>,
+    )>,
+    #[cfg(feature = "2d")] meshes_2d: Query<&bevy_sprite::Mesh2dHandle>,
+) {
+    let modified: HashSet<AssetId<Mesh>> = mesh_events
+        .read()
+        .filter_map(|event| match event {
+            AssetEvent::Modified { id } => Some(*id),
+            _ => None,
+        })
+        .collect();
+    if modified.is_empty() {
+        return;
+    }
+    // The mesh of an entity is its `Handle<Mesh>`, or its `Mesh2dHandle` with the `2d` feature.
+    #[cfg(feature = "2d")]
+    let mesh_of = |entity, handle: Option<&Handle<Mesh>>| {
+        let handle_2d = || meshes_2d.get(entity).ok().map(|handle| handle.0.id());
+        handle.map(Handle::id).or_else(handle_2d)
+    };
+    #[cfg(not(feature = "2d"))]
+    let mesh_of = |_, handle: Option<&Handle<Mesh>>| handle.map(Handle::id);
+    for (entity, handle, simplified, mut mesh) in &mut meshes {
+        let simplified = simplified.map(|simplified| simplified.mesh.id());
+        if mesh_of(entity, handle)
+            .into_iter()
+            .chain(simplified)
+            .any(|id| modified.contains(&id))
+        {
+            mesh.intersections.clear();
+            mesh.local_intersections.clear();
+        }
+    }
+}
+
+/// Sends a [`RaycastHit`] for the nearest intersection of each source, following the source's
+/// [`HitEventMode`].
+pub fn emit_hit_events<T: TypePath + Send + Sync>(
+    mut sources: Query<(Entity, &mut RaycastSource<T>)>,
+    mut hits: EventWriter<RaycastHit<T>>,
+    time: Option<Res<Time>>,
+) {
+    let now = time.map(|time| time.elapsed());
+    for (source_entity, mut source) in &mut sources {
+        let Some((entity, intersection)) = source.get_nearest_intersection() else {
+            source.hit_event_target = None;
+            continue;
+        };
+        let intersection = intersection.clone();
+        let changed = source.hit_event_target.map_or(true, |(target, position)| {
+            target != entity || position.distance(intersection.position()) > HIT_EVENT_EPSILON
+        });
+        let cooling = |cooldown| match (source.last_hit_event, now) {
+            (Some((last, sent)), Some(now)) => last == entity && now - sent < cooldown,
+            _ => false,
+        };
+        let emit = match source.emit_hit_events {
+            HitEventMode::EveryFrame => true,
+            HitEventMode::OnChange => changed,
+            HitEventMode::OnChangeWithCooldown(cooldown) => changed && !cooling(cooldown),
+        };
+        if !emit {
+            continue;
+        }
+        source.hit_event_target = Some((entity, intersection.position()));
+        source.last_hit_event = now.map(|now| (entity, now));
+        hits.send(RaycastHit {
+            source: source_entity,
+            entity,
+            intersection,
+            _marker: PhantomData,
+        });
+    }
+}
+
+#[cfg(feature = "debug")]
+pub mod debug {
+    #![allow(unused)]
+
+    use bevy_ecs::{
+        prelude::{Entity, With},
+        system::{Commands, Query},
+    };
+    use bevy_gizmos::gizmos::Gizmos;
+    use bevy_math::{Quat, Vec3};
+    use bevy_reflect::TypePath;
+    use bevy_render::color::Color;
+    use bevy_transform::components::GlobalTransform;
+    use bevy_utils::tracing::info;
+    use std::marker::PhantomData;
+
+    use crate::prelude::*;
+
+    /// Updates the 3d cursor to be in the pointed world coordinates. Hits on entities with a
+    /// [`TrackHitLocally`] component are drawn relative to the entity's latest transform.
+    #[allow(clippy::too_many_arguments)]
+    pub fn update_debug_cursor<T: TypePath + Send + Sync>(
+        mut commands: Commands,
+        mut sources: Query<(Entity, &RaycastSource<T>)>,
+        tracked: Query<(&RaycastMesh<T>, &GlobalTransform), With<TrackHitLocally>>,
+        mut gizmos: Gizmos,
+    ) {
+        for ray in sources.iter().filter_map(|(_, s)| s.ray) {
+            let orientation = Quat::from_rotation_arc(Vec3::NEG_Z, ray.direction());
+            gizmos.ray(ray.origin(), ray.direction(), Color::BLUE);
+            gizmos.sphere(ray.origin(), orientation, 0.1, Color::BLUE);
+        }
+
+        for (source, is_first, (entity, intersection)) in sources.iter().flat_map(|(source, m)| {
+            m.intersections()
+                .iter()
+                .enumerate()
+                .map(move |(i, hit)| (source, i == 0, hit))
+        }) {
+            let color = match is_first {
+                true => Color::GREEN,
+                false => Color::PINK,
+            };
+            let local = tracked.get(*entity).ok().and_then(|(mesh, transform)| {
+                let local = mesh
+                    .local_intersections()
+                    .iter()
+                    .find(|(s, _)| *s == source);
+                local.map(|(_, local)| {
+                    (
+                        local.world_position(transform),
+                        local.world_normal(transform),
+                    )
+                })
+            });
+            let (position, normal) =
+                local.unwrap_or((intersection.position(), intersection.normal()));
+            gizmos.ray(position, normal, color);
+            gizmos.circle(position, normal, 0.1, color);
+            gizmos.circle_2d(position.truncate(), 10.0, color);
+        }
+    }
+
+    /// Used to debug [`RaycastMesh`] intersections.
+    pub fn print_intersections<T: TypePath + Send + Sync>(query: Query<&RaycastMesh<T>>) {
+        for (_, intersection) in query.iter().flat_map(|mesh| mesh.intersections.iter()) {
+            info!(
+                "Distance {:?}, Position {:?}, Provenance {:?}",
+                intersection.distance(),
+                intersection.position(),
+                intersection.provenance()
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::{
+        ecs::system::{CommandQueue, RunSystemOnce, SystemState},
+        prelude::*,
+        render::{
+            camera::{camera_system, ManualTextureViews, Viewport},
+            mesh::morph::{MeshMorphWeights, MorphAttributes, MorphTargetImage},
+            mesh::skinning::{SkinnedMesh, SkinnedMeshInverseBindposes},
+            mesh::{MeshVertexAttribute, VertexAttributeValues},
+            primitives::Aabb,
+            render_resource::{PrimitiveTopology, VertexFormat},
+        },
+        window::{WindowCreated, WindowResized},
+    };
+
+    use super::*;
+    use crate::markers::{
+        HitFaces, InstancedRaycast, NoBackfaceCulling, RaycastMorphTargets, RaycastPositionDecoder,
+        RaycastScreenSizeScale, RaycastSections, RaycastSkinnedMesh, RaycastSweptTarget,
+        RaycastTransformOffset, RaycastVolumeOnly, RaycastWinding, SimplifiedMesh,
+        SmoothRaycastNormals, TrackHitLocally,
+    };
+    use bevy_render::mesh::Indices;
+    use std::{ops::ControlFlow, sync::Mutex};
+
+    #[derive(Reflect)]
+    struct TestSet;
+
+    fn test_app() -> App {
+        let mut app = App::new();
+        app.add_plugins((
+            MinimalPlugins,
+            AssetPlugin::default(),
+            DeferredRaycastingPlugin::<TestSet>::default(),
+        ))
+        .init_asset::<Mesh>();
+        app
+    }
+
+    fn spawn_mesh(app: &mut App, mesh: Mesh, transform: Transform) -> Entity {
+        let aabb: Aabb = mesh.compute_aabb().unwrap();
+        let handle = app.world.resource_mut::<Assets<Mesh>>().add(mesh);
+        app.world
+            .spawn((
+                handle,
+                aabb,
+                GlobalTransform::from(transform),
+                InheritedVisibility::VISIBLE,
+                ViewVisibility::default(),
+                RaycastMesh::<TestSet>::default(),
+            ))
+            .id()
+    }
+
+    fn spawn_source(app: &mut App, transform: Transform) -> Entity {
+        let source = RaycastSource::<TestSet>::new_transform_empty()
+            .with_visibility(RaycastVisibility::Ignore);
+        app.world
+            .spawn((GlobalTransform::from(transform), source))
+            .id()
+    }
+
+    fn intersections(app: &App, source: Entity) -> &[(Entity, IntersectionData)] {
+        app.world
+            .get::<RaycastSource<TestSet>>(source)
+            .unwrap()
+            .intersections()
+    }
+
+    #[test]
+    fn plugin_settings_changes_take_effect() {
+        let mut app = test_app();
+        spawn_mesh(&mut app, shape::Quad::default().into(), Transform::IDENTITY);
+        // Look at the back of the quad, which faces +Z.
+        let source = spawn_source(
+            &mut app,
+            Transform::from_xyz(0.0, 0.0, -5.0).looking_at(Vec3::ZERO, Vec3::Y),
+        );
+
+        app.update();
+        assert!(intersections(&app, source).is_empty());
+
+        app.world
+            .resource_mut::<RaycastPluginSettings<TestSet>>()
+            .backfaces = Backfaces::Include;
+        app.update();
+        assert_eq!(intersections(&app, source).len(), 1);
+    }
+
+    #[test]
+    fn deprecated_prelude_systems_still_raycast() {
+        use crate::prelude::{build_rays, update_raycast, update_target_intersections};
+
+        // The old paths are the same systems, so ordering against them still applies.
+        assert_eq!(
+            update_raycast::<TestSet>.into_system_set().intern(),
+            crate::deferred::update_raycast::<TestSet>
+                .into_system_set()
+                .intern()
+        );
+        let mut app = test_app();
+        let mut state = app.world.resource_mut::<RaycastPluginState<TestSet>>();
+        state.build_rays = false;
+        state.update_raycast = false;
+        app.add_systems(
+            Update,
+            (
+                build_rays::<TestSet>,
+                update_raycast::<TestSet>,
+                update_target_intersections::<TestSet>,
+            )
+                .chain(),
+        );
+        let cube = spawn_mesh(&mut app, shape::Cube::default().into(), Transform::IDENTITY);
+        let source = spawn_source(&mut app, Transform::from_xyz(0.0, 0.0, 5.0));
+        app.update();
+        assert_eq!(intersections(&app, source)[0].0, cube);
+        let mesh = app.world.get::<RaycastMesh<TestSet>>(cube).unwrap();
+        assert_eq!(mesh.intersections().len(), 1);
+
+        // The systems keep their state between runs, so old mesh intersections are cleared.
+        app.world.entity_mut(source).despawn();
+        app.update();
+        let mesh = app.world.get::<RaycastMesh<TestSet>>(cube).unwrap();
+        assert!(mesh.intersections().is_empty());
+    }
+
+    #[test]
+    fn plugin_settings_select_the_algorithm_and_epsilon() {
+        let mut app = test_app();
+        let sphere = spawn_mesh(
+            &mut app,
+            shape::UVSphere::default().into(),
+            Transform::IDENTITY,
+        );
+        // The ray passes near the edge of the sphere, well inside its AABB.
+        let source = spawn_source(&mut app, Transform::from_xyz(0.9, 0.0, 5.0));
+        let distance = |app: &App| {
+            let hits = intersections(app, source);
+            hits.first().map(|(entity, hit)| {
+                assert_eq!(*entity, sphere);
+                hit.distance()
+            })
+        };
+        app.update();
+        let sphere_distance = 5.0 - (1.0f32 - 0.81).sqrt();
+        assert!((distance(&app).unwrap() - sphere_distance).abs() < 0.05);
+
+        app.world
+            .resource_mut::<RaycastPluginSettings<TestSet>>()
+            .volume_only = true;
+        app.update();
+        assert!((distance(&app).unwrap() - 4.0).abs() < 1e-5);
+
+        // Mesh hits closer to the origin than the epsilon, scaled by the origin, are ignored.
+        let mut settings = app.world.resource_mut::<RaycastPluginSettings<TestSet>>();
+        settings.volume_only = false;
+        settings.self_hit_epsilon = 1.0;
+        app.update();
+        assert_eq!(distance(&app), None);
+    }
+
+    #[test]
+    fn lines_are_only_hit_with_a_line_tolerance() {
+        let mut app = test_app();
+        let mut lines = Mesh::new(PrimitiveTopology::LineList);
+        let positions = vec![[-1.0, 0.0, 0.0], [1.0, 0.0, 0.0]];
+        lines.insert_attribute(Mesh::ATTRIBUTE_POSITION, positions);
+        let line = spawn_mesh(&mut app, lines, Transform::from_xyz(0.0, 0.0, -3.0));
+        let quad = spawn_mesh(
+            &mut app,
+            shape::Quad::default().into(),
+            Transform::from_xyz(0.0, 0.0, -5.0),
+        );
+        // The ray passes beside the flat AABB of the line.
+        let source = spawn_source(&mut app, Transform::from_xyz(0.0, 0.05, 0.0));
+
+        app.update();
+        let hits = intersections(&app, source);
+        assert_eq!(hits.len(), 1);
+        assert_eq!(hits[0].0, quad);
+        assert!((hits[0].1.distance() - 5.0).abs() < 1e-5);
+
+        app.world
+            .resource_mut::<RaycastPluginSettings<TestSet>>()
+            .line_tolerance = Some(0.1);
+        app.update();
+        let hits = intersections(&app, source);
+        assert_eq!(hits.len(), 1);
+        assert_eq!(hits[0].0, line);
+        assert!(hits[0]
+            .1
+            .position()
+            .abs_diff_eq(Vec3::new(0.0, 0.0, -3.0), 1e-5));
+        assert!((hits[0].1.distance() - 3.0).abs() < 1e-5);
+    }
+
+    #[test]
+    fn source_backfaces_override_plugin_backfaces() {
+        let mut app = test_app();
+        spawn_mesh(&mut app, shape::Quad::default().into(), Transform::IDENTITY);
+        let below = Transform::from_xyz(0.0, 0.0, -5.0).looking_at(Vec3::ZERO, Vec3::Y);
+        let mut spawn = |transform, backfaces| {
+            let source = spawn_source(&mut app, transform);
+            let mut raycast_source = app.world.get_mut::<RaycastSource<TestSet>>(source).unwrap();
+            raycast_source.backfaces = backfaces;
+            source
+        };
+        let culled_above = spawn(Transform::from_xyz(0.0, 0.0, 5.0), Some(Backfaces::Cull));
+        let culled_below = spawn(below, Some(Backfaces::Cull));
+        let included_below = spawn(below, Some(Backfaces::Include));
+        let plugin_below = spawn(below, None);
+        app.world
+            .resource_mut::<RaycastPluginSettings<TestSet>>()
+            .backfaces = Backfaces::Include;
+        app.update();
+        assert_eq!(intersections(&app, culled_above).len(), 1);
+        assert!(intersections(&app, culled_below).is_empty());
+        assert_eq!(intersections(&app, included_below).len(), 1);
+        assert_eq!(intersections(&app, plugin_below).len(), 1);
+
+        // The outside of a mirrored cube still faces out, so only its near side is hit.
+        let mut app = test_app();
+        let mirrored = Transform::from_xyz(5.0, 0.0, 0.0).with_scale(Vec3::new(1.0, 1.0, -1.0));
+        spawn_mesh(&mut app, shape::Cube::default().into(), mirrored);
+        let source = spawn_source(&mut app, Transform::from_xyz(5.0, 0.2, 5.0));
+        app.world
+            .get_mut::<RaycastSource<TestSet>>(source)
+            .unwrap()
+            .backfaces = Some(Backfaces::Cull);
+        app.update();
+        let [(_, hit)] = intersections(&app, source) else {
+            panic!("expected one hit");
+        };
+        assert!(hit.position().abs_diff_eq(Vec3::new(5.0, 0.2, 0.5), 1e-5));
+        assert!(!hit.is_backface());
+    }
+
+    #[test]
+    fn hit_faces_override_plugin_backfaces() {
+        let mut app = test_app();
+        app.world
+            .resource_mut::<RaycastPluginSettings<TestSet>>()
+            .backfaces = Backfaces::Include;
+        let quad = spawn_mesh(&mut app, shape::Quad::default().into(), Transform::IDENTITY);
+        app.world
+            .entity_mut(quad)
+            .insert((NoBackfaceCulling, HitFaces::Front));
+        let above = spawn_source(&mut app, Transform::from_xyz(0.0, 0.0, 5.0));
+        let below = spawn_source(
+            &mut app,
+            Transform::from_xyz(0.0, 0.0, -5.0).looking_at(Vec3::ZERO, Vec3::Y),
+        );
+        app.update();
+        assert_eq!(intersections(&app, above).len(), 1);
+        assert!(intersections(&app, below).is_empty());
+
+        *app.world.get_mut::<HitFaces>(quad).unwrap() = HitFaces::Back;
+        app.update();
+        assert!(intersections(&app, above).is_empty());
+        assert_eq!(intersections(&app, below).len(), 1);
+
+        // Two sided meshes have no back faces.
+        app.world.entity_mut(quad).insert(RaycastWinding::TwoSided);
+        app.update();
+        assert!(intersections(&app, above).is_empty());
+        assert!(intersections(&app, below).is_empty());
+        *app.world.get_mut::<HitFaces>(quad).unwrap() = HitFaces::Front;
+        app.update();
+        assert_eq!(intersections(&app, above).len(), 1);
+        assert_eq!(intersections(&app, below).len(), 1);
+    }
+
+    #[test]
+    fn sweep_hits_thin_quad() {
+        let mut app = test_app();
+        let quad = shape::Quad::new(Vec2::new(0.2, 1.0));
+        let target = spawn_mesh(&mut app, quad.into(), Transform::from_xyz(0.0, 0.0, -5.0));
+        let source = spawn_source(
+            &mut app,
+            Transform::from_xyz(0.0, 0.0, 0.0).looking_at(Vec3::new(-1.0, 0.0, -5.0), Vec3::Y),
+        );
+        app.world
+            .get_mut::<RaycastSource<TestSet>>(source)
+            .unwrap()
+            .sweep_samples = 16;
+
+        app.update();
+        assert!(intersections(&app, source).is_empty());
+
+        // Swing the ray across the quad in a single frame.
+        *app.world.get_mut::<GlobalTransform>(source).unwrap() = Transform::from_xyz(0.0, 0.0, 0.0)
+            .looking_at(Vec3::new(1.0, 0.0, -5.0), Vec3::Y)
+            .into();
+        app.update();
+        let intersections = intersections(&app, source);
+        assert_eq!(intersections.len(), 1);
+        assert_eq!(intersections[0].0, target);
+        assert!(intersections[0].1.is_swept());
+    }
+
+    #[test]
+    fn culled_candidates_match_brute_force() {
+        let mut app = test_app();
+        for i in 0..10 {
+            let transform = Transform::from_xyz(i as f32 * 0.3 - 1.5, 0.0, -2.0 * i as f32)
+                .with_scale(Vec3::splat(1.0 + i as f32 * 0.1));
+            spawn_mesh(&mut app, shape::Cube::default().into(), transform);
+        }
+        let source = spawn_source(
+            &mut app,
+            Transform::from_xyz(0.0, 0.0, 5.0).looking_at(Vec3::new(0.2, 0.0, 0.0), Vec3::Y),
+        );
+        app.world
+            .get_mut::<RaycastSource<TestSet>>(source)
+            .unwrap()
+            .record_candidates = true;
+        app.update();
+
+        let ray = app
+            .world
+            .get::<RaycastSource<TestSet>>(source)
+            .unwrap()
+            .ray
+            .unwrap();
+        let mut expected: Vec<_> = app
+            .world
+            .query_filtered::<(Entity, &Aabb, &GlobalTransform), With<RaycastMesh<TestSet>>>()
+            .iter(&app.world)
+            .filter_map(|(entity, aabb, transform)| {
+                ray.intersects_aabb(aabb, &transform.compute_matrix())
+                    .filter(|[_, far]| *far >= 0.0)
+                    .map(|[near, _]| (entity, near))
+            })
+            .collect();
+        expected.sort_by(|a, b| a.1.total_cmp(&b.1));
+
+        let source = app.world.get::<RaycastSource<TestSet>>(source).unwrap();
+        assert!(!expected.is_empty());
+        assert_eq!(source.culled_candidates(), expected.as_slice());
+    }
+
+    #[test]
+    fn audit_keeps_culled_candidates() {
+        let mut app = test_app();
+        app.world
+            .resource_mut::<RaycastPluginSettings<TestSet>>()
+            .audit = true;
+        let cube = || shape::Cube::default().into();
+        let hit = spawn_mesh(&mut app, cube(), Transform::IDENTITY);
+        spawn_mesh(&mut app, cube(), Transform::from_xyz(5.0, 0.0, 0.0));
+        let source = spawn_source(&mut app, Transform::from_xyz(0.0, 0.0, 5.0));
+        app.world
+            .get_mut::<RaycastSource<TestSet>>(source)
+            .unwrap()
+            .record_candidates = true;
+        app.update();
+
+        let metrics = app.world.resource::<RaycastMetrics<TestSet>>();
+        assert_eq!(metrics.casts_audited, 1);
+        let source = app.world.get::<RaycastSource<TestSet>>(source).unwrap();
+        assert_eq!(source.culled_candidates(), &[(hit, 4.5)]);
+    }
+
+    #[test]
+    fn cull_matches_raycast_candidates() {
+        let mut app = test_app();
+        app.world
+            .resource_mut::<RaycastPluginSettings<TestSet>>()
+            .aabb_inflation = 0.3;
+        for i in 0..10 {
+            // Odd cubes are only hit because of the inflation.
+            let x = 0.5 + 0.2 * i as f32 + (i % 2) as f32 * 0.4;
+            let transform = Transform::from_xyz(x, 0.0, -2.0 * i as f32);
+            let entity = spawn_mesh(&mut app, shape::Cube::new(0.5).into(), transform);
+            if i % 3 == 0 {
+                // Not part of the raycast set, so neither should be considered.
+                app.world
+                    .entity_mut(entity)
+                    .remove::<RaycastMesh<TestSet>>();
+            }
+        }
+        let source = spawn_source(
+            &mut app,
+            Transform::from_xyz(0.0, 0.0, 5.0).looking_at(Vec3::new(0.5, 0.0, 0.0), Vec3::Y),
+        );
+        app.world
+            .get_mut::<RaycastSource<TestSet>>(source)
+            .unwrap()
+            .record_candidates = true;
+        app.update();
+
+        let ray = app.world.get::<RaycastSource<TestSet>>(source).unwrap().ray;
+        let mut state = SystemState::<(Raycast, Query<&RaycastMesh<TestSet>>)>::new(&mut app.world);
+        let (mut raycast, targets) = state.get_mut(&mut app.world);
+        let filter = |entity| targets.contains(entity);
+        let settings = RaycastSettings::default()
+            .with_filter(&filter)
+            .with_visibility(RaycastVisibility::Ignore)
+            .with_aabb_inflation(0.3);
+        let culled = raycast.cull(ray.unwrap(), &settings);
+
+        let source = app.world.get::<RaycastSource<TestSet>>(source).unwrap();
+        assert!(culled.len() > 1);
+        assert_eq!(source.culled_candidates(), culled.as_slice());
+    }
+
+    #[test]
+    fn sources_choose_the_intersection_algorithm() {
+        let mut app = test_app();
+        let sphere = spawn_mesh(
+            &mut app,
+            shape::UVSphere::default().into(),
+            Transform::IDENTITY,
+        );
+        let mut spawn_source = |x, volume_only| {
+            let source = RaycastSource::<TestSet>::new_transform_empty()
+                .with_visibility(RaycastVisibility::Ignore)
+                .with_volume_only(volume_only);
+            let transform = GlobalTransform::from_xyz(x, x, 5.0);
+            app.world.spawn((transform, source)).id()
+        };
+        let sources =
+            [false, true].map(|volume_only| [0.0, 0.8].map(|x| spawn_source(x, volume_only)));
+        app.update();
+
+        let hit = |source| {
+            intersections(&app, source).first().map(|(entity, hit)| {
+                let distance = (hit.distance() * 1e4).round() / 1e4;
+                (*entity, distance, hit.provenance().algorithm)
+            })
+        };
+        let [[mesh_center, mesh_corner], [volume_center, volume_corner]] = sources;
+        // Through the center, both algorithms hit the front of the sphere.
+        let center_hit = |algorithm| Some((sphere, 4.0, algorithm));
+        assert_eq!(
+            hit(mesh_center),
+            center_hit(RaycastAlgorithm::MollerTrumbore)
+        );
+        assert_eq!(hit(volume_center), center_hit(RaycastAlgorithm::SlabTest));
+        // Through a corner of the AABB, only the slab test hits.
+        assert_eq!(hit(mesh_corner), None);
+        assert_eq!(
+            hit(volume_corner),
+            Some((sphere, 4.0, RaycastAlgorithm::SlabTest))
+        );
+    }
+
+    #[test]
+    fn volume_only_distance_matches_mesh() {
+        let mut app = test_app();
+        let cube = spawn_mesh(&mut app, shape::Cube::default().into(), Transform::IDENTITY);
+        let volume = spawn_mesh(
+            &mut app,
+            shape::Cube::default().into(),
+            Transform::from_xyz(3.0, 0.0, 0.0),
+        );
+        app.world.entity_mut(volume).insert(RaycastVolumeOnly);
+        let cube_source = spawn_source(&mut app, Transform::from_xyz(0.0, 0.0, 5.0));
+        let volume_source = spawn_source(&mut app, Transform::from_xyz(3.0, 0.0, 5.0));
+        app.update();
+
+        let (cube_hit, volume_hit) = (
+            &intersections(&app, cube_source)[0],
+            &intersections(&app, volume_source)[0],
+        );
+        assert_eq!(cube_hit.0, cube);
+        assert_eq!(volume_hit.0, volume);
+        assert!((cube_hit.1.distance() - volume_hit.1.distance()).abs() < 1e-5);
+        assert_eq!(volume_hit.1.normal(), Vec3::Z);
+    }
+
+    #[test]
+    fn instanced_hits_report_instance_index() {
+        let mut app = test_app();
+        let target = spawn_mesh(
+            &mut app,
+            shape::Cube::default().into(),
+            Transform::from_xyz(0.0, 1.0, 0.0),
+        );
+        let transforms = [-2.0, 0.0, 2.0]
+            .map(|x| Mat4::from_translation(Vec3::new(x, -1.0, 0.0)))
+            .to_vec();
+        app.world
+            .entity_mut(target)
+            .insert(InstancedRaycast { transforms });
+        let source = spawn_source(&mut app, Transform::from_xyz(2.0, 0.0, 5.0));
+        app.update();
+
+        let source = app.world.get::<RaycastSource<TestSet>>(source).unwrap();
+        assert!(source.intersections().is_empty());
+        let [(entity, instance, intersection)] = source.instance_intersections() else {
+            panic!("expected exactly one instance hit");
+        };
+        assert_eq!((*entity, *instance), (target, 2));
+        assert!((intersection.distance() - 4.5).abs() < 1e-5);
+    }
+
+    #[test]
+    fn instances_behind_meshes_are_occluded() {
+        let mut app = test_app();
+        let wall = spawn_mesh(&mut app, shape::Cube::default().into(), Transform::IDENTITY);
+        let target = spawn_mesh(
+            &mut app,
+            shape::Cube::default().into(),
+            Transform::from_xyz(5.0, 0.0, 0.0),
+        );
+        let transforms = vec![Mat4::from_translation(Vec3::new(-5.0, 0.0, -3.0))];
+        app.world
+            .entity_mut(target)
+            .insert(InstancedRaycast { transforms });
+        let source = spawn_source(&mut app, Transform::from_xyz(0.0, 0.0, 5.0));
+        app.update();
+
+        let raycast_source = app.world.get::<RaycastSource<TestSet>>(source).unwrap();
+        assert_eq!(raycast_source.intersections()[0].0, wall);
+        assert!(raycast_source.instance_intersections().is_empty());
+
+        // Without early exits, nothing blocks the ray.
+        app.world
+            .get_mut::<RaycastSource<TestSet>>(source)
+            .unwrap()
+            .should_early_exit = false;
+        app.update();
+        let raycast_source = app.world.get::<RaycastSource<TestSet>>(source).unwrap();
+        let [(entity, instance, _)] = raycast_source.instance_intersections() else {
+            panic!("expected exactly one instance hit");
+        };
+        assert_eq!((*entity, *instance), (target, 0));
+    }
+
+    #[test]
+    fn instanced_rays_from_a_surface_skip_it() {
+        let mut app = test_app();
+        let target = spawn_mesh(&mut app, shape::Quad::default().into(), Transform::IDENTITY);
+        let transforms = vec![Mat4::IDENTITY, Mat4::from_translation(Vec3::Z * -2.0)];
+        app.world
+            .entity_mut(target)
+            .insert((InstancedRaycast { transforms }, NoBackfaceCulling));
+
+        let mut state = SystemState::<Raycast>::new(&mut app.world);
+        let mut raycast = state.get_mut(&mut app.world);
+        let settings = RaycastSettings::default().with_visibility(RaycastVisibility::Ignore);
+        // Like a ray bounced off the first instance, starting just in front of it.
+        let ray = Ray3d::new(Vec3::new(0.1, 0.1, 1e-7), Vec3::NEG_Z);
+        let [(entity, instance, intersection)] = raycast.cast_ray_instanced(ray, &settings) else {
+            panic!("expected exactly one instance hit");
+        };
+        assert_eq!((*entity, *instance), (target, 1));
+        assert!((intersection.distance() - 2.0).abs() < 1e-5);
+    }
+
+    #[test]
+    fn instanced_meshes_that_cant_be_read_are_skipped() {
+        let mut app = test_app();
+        let target = spawn_mesh(&mut app, shape::Quad::default().into(), Transform::IDENTITY);
+        let mut incomplete = Mesh::new(PrimitiveTopology::TriangleList);
+        incomplete.insert_attribute(
+            Mesh::ATTRIBUTE_POSITION,
+            vec![[-1.0, -1.0, 0.0], [1.0, -1.0, 0.0], [0.0, 1.0, 0.0]],
+        );
+        incomplete.set_indices(Some(Indices::U32(vec![0, 1])));
+        let mut lines = Mesh::new(PrimitiveTopology::LineList);
+        lines.insert_attribute(
+            Mesh::ATTRIBUTE_POSITION,
+            vec![[-1.0, 0.0, 0.0], [1.0, 0.0, 0.0]],
+        );
+        let mut invalid = Vec::new();
+        for mesh in [incomplete, lines] {
+            let handle = app.world.resource_mut::<Assets<Mesh>>().add(mesh);
+            invalid.push(handle.id());
+            app.world.spawn((
+                handle,
+                Aabb::from_min_max(Vec3::splat(-1.0), Vec3::splat(1.0)),
+                GlobalTransform::default(),
+                InheritedVisibility::VISIBLE,
+                ViewVisibility::default(),
+                RaycastMesh::<TestSet>::default(),
+                InstancedRaycast {
+                    transforms: vec![Mat4::IDENTITY],
+                },
+            ));
+        }
+        app.world.entity_mut(target).insert(InstancedRaycast {
+            transforms: vec![Mat4::from_translation(Vec3::Z * -2.0)],
+        });
+
+        let mut state = SystemState::<Raycast>::new(&mut app.world);
+        let mut raycast = state.get_mut(&mut app.world);
+        let settings = RaycastSettings::default().with_visibility(RaycastVisibility::Ignore);
+        let ray = Ray3d::new(Vec3::new(0.1, 0.1, 5.0), Vec3::NEG_Z);
+        let [(entity, _, _)] = raycast.cast_ray_instanced(ray, &settings) else {
+            panic!("expected exactly one instance hit");
+        };
+        assert_eq!(*entity, target);
+        // The meshes are recorded, so the warning is only logged once.
+        assert!(invalid
+            .iter()
+            .all(|id| raycast.unraycastable_meshes.contains(id)));
+    }
+
+    #[cfg(feature = "2d")]
+    #[test]
+    fn instanced_2d_meshes_are_hit() {
+        let mut app = test_app();
+        let mesh = app
+            .world
+            .resource_mut::<Assets<Mesh>>()
+            .add(shape::Quad::default().into());
+        let transforms = [-2.0, 0.0, 2.0]
+            .map(|x| Mat4::from_translation(Vec3::new(x, 0.0, 0.0)))
+            .to_vec();
+        let target = app
+            .world
+            .spawn((
+                bevy::sprite::Mesh2dHandle(mesh),
+                Aabb::from_min_max(Vec3::new(-0.5, -0.5, 0.0), Vec3::new(0.5, 0.5, 0.0)),
+                GlobalTransform::default(),
+                InheritedVisibility::VISIBLE,
+                ViewVisibility::default(),
+                RaycastMesh::<TestSet>::default(),
+                InstancedRaycast { transforms },
+            ))
+            .id();
+        // 2D meshes are hit from both sides.
+        let source = spawn_source(
+            &mut app,
+            Transform::from_xyz(-2.0, 0.0, -5.0).looking_to(Vec3::Z, Vec3::Y),
+        );
+        app.update();
+
+        let source = app.world.get::<RaycastSource<TestSet>>(source).unwrap();
+        let [(entity, instance, intersection)] = source.instance_intersections() else {
+            panic!("expected exactly one instance hit");
+        };
+        assert_eq!((*entity, *instance), (target, 0));
+        assert!((intersection.distance() - 5.0).abs() < 1e-5);
+    }
+
+    #[test]
+    fn large_meshes_are_recorded() {
+        let mut app = test_app();
+        spawn_mesh(
+            &mut app,
+            shape::Plane::default().into(),
+            Transform::IDENTITY,
+        );
+        let cube = Transform::from_xyz(0.0, 0.0, -3.0);
+        let large = spawn_mesh(&mut app, shape::Cube::default().into(), cube);
+        let source = spawn_source(&mut app, Transform::from_xyz(0.0, 0.0, 5.0));
+        app.world
+            .get_mut::<RaycastSource<TestSet>>(source)
+            .unwrap()
+            .should_early_exit = false;
+
+        // Disabled, so nothing is recorded.
+        app.world
+            .resource_mut::<RaycastPluginSettings<TestSet>>()
+            .large_mesh_threshold = None;
+        app.update();
+        assert!(app
+            .world
+            .resource::<RaycastMetrics<TestSet>>()
+            .large_meshes
+            .is_empty());
+
+        // A plane has two triangles, and a cube has twelve.
+        app.world
+            .resource_mut::<RaycastPluginSettings<TestSet>>()
+            .large_mesh_threshold = Some(4);
+        app.update();
+        let metrics = app.world.resource::<RaycastMetrics<TestSet>>();
+        let recorded: Vec<_> = metrics.large_meshes.values().copied().collect();
+        assert_eq!(recorded, [(large, 12)]);
+    }
+
+    #[test]
+    fn target_filter_only_hits_listed_entities() {
+        let mut app = test_app();
+        let cubes: Vec<_> = (1..=5)
+            .map(|i| {
+                let transform = Transform::from_xyz(0.0, 0.0, -2.0 * i as f32);
+                spawn_mesh(&mut app, shape::Cube::default().into(), transform)
+            })
+            .collect();
+        // Enough entities off to the side that the whitelist is too long to cull directly.
+        let mut long_list: Vec<_> = (0..40)
+            .map(|i| {
+                let transform = Transform::from_xyz(5.0 + i as f32 * 2.0, 0.0, -2.0);
+                spawn_mesh(&mut app, shape::Cube::default().into(), transform)
+            })
+            .collect();
+        long_list.push(cubes[3]);
+        let source = spawn_source(&mut app, Transform::IDENTITY);
+
+        let filters = [
+            (
+                SmallVec::from_slice(&[cubes[2], cubes[4]]),
+                vec![cubes[2], cubes[4]],
+            ),
+            // Entities listed twice are only hit once.
+            (
+                SmallVec::from_slice(&[cubes[4], cubes[2], cubes[4]]),
+                vec![cubes[2], cubes[4]],
+            ),
+            (SmallVec::from_vec(long_list), vec![cubes[3]]),
+        ];
+        for (targets, expected) in filters {
+            let mut raycast_source = app.world.get_mut::<RaycastSource<TestSet>>(source).unwrap();
+            raycast_source.should_early_exit = false;
+            raycast_source.target_filter = TargetFilter::Only(targets);
+            app.update();
+            let hits: Vec<_> = intersections(&app, source)
+                .iter()
+                .map(|(e, _)| *e)
+                .collect();
+            assert_eq!(hits, expected);
+        }
+    }
+
+    #[test]
+    fn targets_skip_entities_that_cant_be_raycasted() {
+        let mut app = test_app();
+        let cube = |z| Transform::from_xyz(0.0, 0.0, z);
+        let near = spawn_mesh(&mut app, shape::Cube::default().into(), cube(2.0));
+        let far = spawn_mesh(&mut app, shape::Cube::default().into(), cube(-2.0));
+        let despawned = spawn_mesh(&mut app, shape::Cube::default().into(), cube(0.0));
+        app.world.despawn(despawned);
+        let meshless = spawn_mesh(&mut app, shape::Cube::default().into(), cube(1.0));
+        app.world.entity_mut(meshless).remove::<Handle<Mesh>>();
+        // Not a target, so the ray passes through it.
+        spawn_mesh(&mut app, shape::Cube::default().into(), cube(-1.0));
+
+        let mut state = SystemState::<Raycast>::new(&mut app.world);
+        let mut raycast = state.get_mut(&mut app.world);
+        let ray = Ray3d::new(Vec3::new(0.0, 0.0, 5.0), Vec3::NEG_Z);
+        let targets = [far, despawned, meshless, near];
+        let settings = RaycastSettings::default()
+            .with_visibility(RaycastVisibility::Ignore)
+            .with_targets(&targets)
+            .never_early_exit();
+        let hits: Vec<_> = raycast
+            .cast_ray(ray, &settings)
+            .iter()
+            .map(|(entity, _)| *entity)
+            .collect();
+        assert_eq!(hits, [near, far]);
+    }
+
+    #[test]
+    fn local_hits_follow_moving_platform() {
+        let mut app = test_app();
+        let platform_transform = Transform::from_xyz(0.0, -1.0, 0.0);
+        let platform = spawn_mesh(&mut app, shape::Cube::default().into(), platform_transform);
+        app.world.entity_mut(platform).insert(TrackHitLocally);
+        let source = spawn_source(&mut app, Transform::from_xyz(0.2, -0.9, 5.0));
+        app.update();
+        let world_hit = intersections(&app, source)[0].1.position();
+
+        // The platform moves after the raycast, but before the hit is consumed.
+        let movement =
+            Transform::from_xyz(3.0, -1.0, 2.0).with_rotation(Quat::from_rotation_z(0.5));
+        let moved = GlobalTransform::from(movement * platform_transform);
+        *app.world.get_mut::<GlobalTransform>(platform).unwrap() = moved;
+
+        let mesh = app.world.get::<RaycastMesh<TestSet>>(platform).unwrap();
+        let [(hit_source, local)] = mesh.local_intersections() else {
+            panic!("expected exactly one local intersection");
+        };
+        assert_eq!(*hit_source, source);
+        let expected = movement.transform_point(world_hit);
+        assert!(local.world_position(&moved).distance(expected) < 1e-5);
+        assert!(
+            local
+                .world_normal(&moved)
+                .distance(movement.rotation * Vec3::Z)
+                < 1e-5
+        );
+    }
+
+    #[test]
+    fn morph_targets_move_hits() {
+        let mut app = test_app();
+        app.init_asset::<Image>();
+        let mut mesh = Mesh::new(PrimitiveTopology::TriangleList);
+        let positions = vec![[-1.0, -1.0, 0.0], [1.0, -1.0, 0.0], [0.0, 1.0, 0.0]];
+        mesh.insert_attribute(Mesh::ATTRIBUTE_POSITION, positions);
+        // A single target, which displaces the top vertex one unit towards the source.
+        let target = [Vec3::ZERO, Vec3::ZERO, Vec3::Z].map(|position| MorphAttributes {
+            position,
+            ..default()
+        });
+        let image = MorphTargetImage::new([target.into_iter()].into_iter(), 3).unwrap();
+        mesh.set_morph_targets(app.world.resource_mut::<Assets<Image>>().add(image.0));
+        let target = spawn_mesh(&mut app, mesh, Transform::IDENTITY);
+        let weights = MeshMorphWeights::new(vec![0.0]).unwrap();
+        app.world
+            .entity_mut(target)
+            .insert((RaycastMorphTargets, weights));
+        let source = spawn_source(&mut app, Transform::from_xyz(0.0, 0.0, 5.0));
+
+        // The ray passes halfway between the bottom edge and the displaced vertex.
+        for (weight, distance) in [(0.0, 5.0), (1.0, 4.5), (0.5, 4.75)] {
+            let mut weights = app.world.get_mut::<MeshMorphWeights>(target).unwrap();
+            weights.weights_mut()[0] = weight;
+            app.update();
+            let (entity, hit) = &intersections(&app, source)[0];
+            assert_eq!(*entity, target);
+            assert!(
+                (hit.distance() - distance).abs() < 1e-4,
+                "weight {weight}: expected a hit at {distance}, got {}",
+                hit.distance()
+            );
+        }
+    }
+
+    #[test]
+    fn skinned_meshes_are_posed_by_their_joints() {
+        let mut app = test_app();
+        app.init_asset::<SkinnedMeshInverseBindposes>();
+        let mut mesh = Mesh::new(PrimitiveTopology::TriangleList);
+        let positions = vec![[-1.0, -1.0, 0.0], [1.0, -1.0, 0.0], [0.0, 1.0, 0.0]];
+        mesh.insert_attribute(Mesh::ATTRIBUTE_POSITION, positions);
+        // The bottom vertices follow the first joint, and the top vertex follows the second.
+        let joint_indices =
+            VertexAttributeValues::Uint16x4(vec![[0, 0, 0, 0], [0, 0, 0, 0], [1, 0, 0, 0]]);
+        mesh.insert_attribute(Mesh::ATTRIBUTE_JOINT_INDEX, joint_indices);
+        mesh.insert_attribute(Mesh::ATTRIBUTE_JOINT_WEIGHT, vec![[1.0, 0.0, 0.0, 0.0]; 3]);
+        let target = spawn_mesh(&mut app, mesh, Transform::IDENTITY);
+        let joints = vec![
+            app.world.spawn(GlobalTransform::IDENTITY).id(),
+            app.world.spawn(GlobalTransform::IDENTITY).id(),
+        ];
+        let inverse_bindposes = app
+            .world
+            .resource_mut::<Assets<SkinnedMeshInverseBindposes>>()
+            .add(vec![Mat4::IDENTITY; 2].into());
+        app.world.entity_mut(target).insert((
+            RaycastSkinnedMesh,
+            SkinnedMesh {
+                inverse_bindposes,
+                joints: joints.clone(),
+            },
+        ));
+        let source = spawn_source(&mut app, Transform::from_xyz(0.0, 0.0, 5.0));
+        let nearest = |app: &App| {
+            let (entity, hit) = &intersections(app, source)[0];
+            assert_eq!(*entity, target);
+            (hit.distance(), hit.provenance().geometry)
+        };
+
+        // The ray passes halfway between the bottom edge and the top vertex.
+        for (offset, distance) in [(0.0, 5.0), (1.0, 4.5), (0.5, 4.75)] {
+            *app.world.get_mut::<GlobalTransform>(joints[1]).unwrap() =
+                GlobalTransform::from_xyz(0.0, 0.0, offset);
+            app.update();
+            let (hit_distance, geometry) = nearest(&app);
+            assert!(
+                (hit_distance - distance).abs() < 1e-4,
+                "{hit_distance} != {distance}"
+            );
+            assert_eq!(geometry, HitGeometry::SkinnedMesh);
+        }
+
+        // Without all of its joints, the mesh is raycast in its bind pose.
+        app.world.entity_mut(joints[1]).despawn();
+        app.update();
+        let (hit_distance, geometry) = nearest(&app);
+        assert!((hit_distance - 5.0).abs() < 1e-4);
+        assert_eq!(geometry, HitGeometry::Mesh);
+    }
+
+    #[test]
+    fn meshes_with_unexpected_position_layouts_are_skipped() {
+        let mut app = test_app();
+        app.init_asset::<Image>()
+            .init_asset::<SkinnedMeshInverseBindposes>();
+        app.world
+            .resource_mut::<RaycastPluginSettings<TestSet>>()
+            .line_tolerance = Some(0.1);
+        let behind = spawn_mesh(
+            &mut app,
+            shape::Quad::default().into(),
+            Transform::from_xyz(0.0, 0.0, -2.0),
+        );
+        // Positions with two components, under the id of `Mesh::ATTRIBUTE_POSITION`.
+        let position_2d = MeshVertexAttribute::new("Vertex_Position", 0, VertexFormat::Float32x2);
+        let unexpected = |topology| {
+            let mut mesh = Mesh::new(topology);
+            mesh.insert_attribute(
+                position_2d.clone(),
+                vec![[-1.0, -1.0], [1.0, -1.0], [0.0, 1.0]],
+            );
+            mesh
+        };
+        let meshes = [
+            unexpected(PrimitiveTopology::TriangleList),
+            unexpected(PrimitiveTopology::TriangleStrip),
+            unexpected(PrimitiveTopology::LineStrip),
+        ];
+        for mesh in meshes {
+            let handle = app.world.resource_mut::<Assets<Mesh>>().add(mesh);
+            app.world.spawn((
+                handle,
+                Aabb::from_min_max(Vec3::splat(-1.0), Vec3::splat(1.0)),
+                GlobalTransform::default(),
+                InheritedVisibility::VISIBLE,
+                ViewVisibility::default(),
+                RaycastMesh::<TestSet>::default(),
+                RaycastSkinnedMesh,
+                SkinnedMesh::default(),
+            ));
+        }
+        let source = spawn_source(&mut app, Transform::from_xyz(0.0, 0.0, 5.0));
+
+        app.update();
+        let hits = intersections(&app, source);
+        assert_eq!(hits.len(), 1);
+        assert_eq!(hits[0].0, behind);
+    }
+
+    #[test]
+    fn meshes_that_cant_be_read_are_skipped() {
+        let mut app = test_app();
+        let behind = spawn_mesh(
+            &mut app,
+            shape::Quad::default().into(),
+            Transform::from_xyz(0.0, 0.0, -2.0),
+        );
+        let triangle = vec![[-1.0, -1.0, 0.0], [1.0, -1.0, 0.0], [0.0, 1.0, 0.0]];
+        let no_positions = Mesh::new(PrimitiveTopology::TriangleList);
+        let mut incomplete = Mesh::new(PrimitiveTopology::TriangleList);
+        incomplete.insert_attribute(Mesh::ATTRIBUTE_POSITION, triangle.clone());
+        incomplete.set_indices(Some(Indices::U32(vec![0, 1])));
+        let mut out_of_bounds = Mesh::new(PrimitiveTopology::TriangleList);
+        out_of_bounds.insert_attribute(Mesh::ATTRIBUTE_POSITION, triangle);
+        out_of_bounds.set_indices(Some(Indices::U32(vec![0, 1, 7])));
+        for mesh in [no_positions, incomplete, out_of_bounds] {
+            let handle = app.world.resource_mut::<Assets<Mesh>>().add(mesh);
+            app.world.spawn((
+                handle,
+                Aabb::from_min_max(Vec3::splat(-1.0), Vec3::splat(1.0)),
+                GlobalTransform::default(),
+                InheritedVisibility::VISIBLE,
+                ViewVisibility::default(),
+                RaycastMesh::<TestSet>::default(),
+            ));
+        }
+        let source = spawn_source(&mut app, Transform::from_xyz(0.0, 0.0, 5.0));
+        app.world
+            .get_mut::<RaycastSource<TestSet>>(source)
+            .unwrap()
+            .should_early_exit = false;
+
+        // Skipping is reported every frame, even though the warning is only logged once.
+        for _ in 0..2 {
+            app.update();
+            let hits = intersections(&app, source);
+            assert_eq!(hits.len(), 1);
+            assert_eq!(hits[0].0, behind);
+            let source = app.world.get::<RaycastSource<TestSet>>(source).unwrap();
+            assert_eq!(source.outcome().unwrap().skipped_invalid_mesh, 2);
+        }
+    }
+
+    #[test]
+    fn sections_report_hit_range() {
+        let mut app = test_app();
+        // Three triangles side by side, the last of which isn't covered by any section.
+        let mut mesh = Mesh::new(PrimitiveTopology::TriangleList);
+        let positions: Vec<_> = [-2.0, 0.0, 2.0]
+            .into_iter()
+            .flat_map(|x| [[x - 1.0, -1.0, 0.0], [x + 1.0, -1.0, 0.0], [x, 1.0, 0.0]])
+            .collect();
+        mesh.insert_attribute(Mesh::ATTRIBUTE_POSITION, positions);
+        mesh.set_indices(Some(Indices::U32((0..9).collect())));
+        let target = spawn_mesh(&mut app, mesh, Transform::IDENTITY);
+        app.world
+            .entity_mut(target)
+            .insert(RaycastSections(vec![0..3, 3..6]));
+        let sources =
+            [-2.0, 0.0, 2.0].map(|x| spawn_source(&mut app, Transform::from_xyz(x, 0.0, 5.0)));
+
+        app.update();
+        let expected = [(0, Some(0)), (1, Some(1)), (2, None)];
+        for (source, (triangle, section)) in sources.into_iter().zip(expected) {
+            let (entity, hit) = &intersections(&app, source)[0];
+            assert_eq!(*entity, target);
+            assert_eq!(hit.triangle_index(), Some(triangle));
+            assert_eq!(hit.section(), section);
+        }
+    }
+
+    #[test]
+    fn sections_of_triangle_strips() {
+        let mut app = test_app();
+        // In a strip, triangle `i` starts at index `i` instead of `3i`.
+        let mut mesh = Mesh::new(PrimitiveTopology::TriangleStrip);
+        let positions: Vec<[f32; 3]> = [-3.0, -2.0, -1.0, 0.0, 1.0]
+            .into_iter()
+            .zip([1.0, -1.0, 1.0, -1.0, 1.0])
+            .map(|(x, y)| [x, y, 0.0])
+            .collect();
+        mesh.insert_attribute(Mesh::ATTRIBUTE_POSITION, positions);
+        mesh.set_indices(Some(Indices::U32((0..5).collect())));
+        let target = spawn_mesh(&mut app, mesh, Transform::IDENTITY);
+        app.world
+            .entity_mut(target)
+            .insert(RaycastSections(vec![0..1, 1..2]));
+        let centroids = [(-2.0, 0.3), (-1.0, -0.3), (0.0, 0.3)];
+        let sources =
+            centroids.map(|(x, y)| spawn_source(&mut app, Transform::from_xyz(x, y, 5.0)));
+
+        app.update();
+        let expected = [(0, Some(0)), (1, Some(1)), (2, None)];
+        for (source, (triangle, section)) in sources.into_iter().zip(expected) {
+            let (entity, hit) = &intersections(&app, source)[0];
+            assert_eq!(*entity, target);
+            assert_eq!(hit.triangle_index(), Some(triangle));
+            assert_eq!(hit.section(), section);
+        }
+    }
+
+    #[test]
+    fn smooth_normals_without_authored_normals() {
+        let mut app = test_app();
+        let mut sphere = Mesh::from(shape::UVSphere {
+            radius: 1.0,
+            sectors: 8,
+            stacks: 6,
+        });
+        sphere.remove_attribute(Mesh::ATTRIBUTE_NORMAL);
+        let target = spawn_mesh(&mut app, sphere, Transform::IDENTITY);
+        let source = spawn_source(&mut app, Transform::from_xyz(0.3, 0.2, 5.0));
+
+        app.update();
+        let faceted = intersections(&app, source)[0].1.clone();
+        app.world
+            .entity_mut(target)
+            .insert(SmoothRaycastNormals::default());
+        app.update();
+        let smooth = intersections(&app, source)[0].1.clone();
+
+        let expected = smooth.position().normalize();
+        assert!(smooth.normal().angle_between(expected) < faceted.normal().angle_between(expected));
+    }
+
+    #[test]
+    fn output_spaces_need_a_camera() {
+        let mut app = test_app();
+        spawn_mesh(&mut app, shape::Cube::default().into(), Transform::IDENTITY);
+        let source = spawn_source(&mut app, Transform::from_xyz(0.0, 0.0, 5.0));
+        app.world
+            .get_mut::<RaycastSource<TestSet>>(source)
+            .unwrap()
+            .output_spaces = OutputSpaces::all();
+        app.update();
+
+        let hit = &intersections(&app, source)[0].1;
+        assert_eq!(hit.position(), Vec3::new(0.0, 0.0, 0.5));
+        assert_eq!(hit.view_position(), None);
+        assert_eq!(hit.ndc_position(), None);
+    }
+
+    #[test]
+    fn early_exit_never_tests_meshes_behind_the_nearest_hit() {
+        let mut app = test_app();
+        let sphere = spawn_mesh(
+            &mut app,
+            shape::UVSphere::default().into(),
+            Transform::IDENTITY,
+        );
+        // A line of spheres behind the first one, whose meshes aren't loaded, so testing any of
+        // them would be reported as a skipped entity.
+        for i in 1..=100 {
+            app.world.spawn((
+                Handle::<Mesh>::weak_from_u128(0x5eed),
+                Aabb::from_min_max(Vec3::splat(-1.0), Vec3::splat(1.0)),
+                GlobalTransform::from_xyz(0.0, 0.0, -3.0 * i as f32),
+                InheritedVisibility::VISIBLE,
+                ViewVisibility::default(),
+                RaycastMesh::<TestSet>::default(),
+            ));
+        }
+        let mut spawn_source = |should_early_exit| {
+            let mut source = RaycastSource::<TestSet>::new_transform_empty()
+                .with_visibility(RaycastVisibility::Ignore);
+            source.should_early_exit = should_early_exit;
+            let transform = GlobalTransform::from_xyz(0.0, 0.0, 5.0);
+            app.world.spawn((transform, source)).id()
+        };
+        let source = spawn_source(true);
+        let exhaustive_source = spawn_source(false);
+        app.update();
+
+        let outcome = |source| {
+            let source = app.world.get::<RaycastSource<TestSet>>(source).unwrap();
+            let outcome = source.outcome().unwrap();
+            let hits: Vec<_> = outcome.hits.iter().map(|(entity, _)| *entity).collect();
+            (hits, outcome.skipped_unready)
+        };
+        assert_eq!(outcome(source), (vec![sphere], 0));
+        assert_eq!(outcome(exhaustive_source), (vec![sphere], 100));
+    }
+
+    #[test]
+    fn outcome_reports_skipped_entities() {
+        let mut app = test_app();
+        let source = spawn_source(&mut app, Transform::from_xyz(0.0, 0.0, 5.0));
+        let outcome = |app: &App| {
+            let source = app.world.get::<RaycastSource<TestSet>>(source).unwrap();
+            let outcome = source.outcome().unwrap();
+            let counts = (
+                outcome.skipped_unready,
+                outcome.skipped_unsupported_topology,
+            );
+            (outcome.hits.len(), counts, outcome.truncated_by_budget)
+        };
+
+        // Hitting nothing is a complete raycast.
+        app.update();
+        assert_eq!(outcome(&app), (0, (0, 0), false));
+
+        let cube = spawn_mesh(&mut app, shape::Cube::default().into(), Transform::IDENTITY);
+        let unready = Handle::<Mesh>::weak_from_u128(0x5eed);
+        app.world.spawn((
+            unready,
+            Aabb::from_min_max(Vec3::splat(-1.0), Vec3::splat(1.0)),
+            GlobalTransform::default(),
+            InheritedVisibility::VISIBLE,
+            ViewVisibility::default(),
+            RaycastMesh::<TestSet>::default(),
+        ));
+        let mut lines = Mesh::new(PrimitiveTopology::LineList);
+        let positions = vec![[-1.0, -1.0, -1.0], [1.0, 1.0, 1.0]];
+        lines.insert_attribute(Mesh::ATTRIBUTE_POSITION, positions);
+        spawn_mesh(&mut app, lines, Transform::from_xyz(0.0, 0.0, -3.0));
+        app.world
+            .get_mut::<RaycastSource<TestSet>>(source)
+            .unwrap()
+            .should_early_exit = false;
+        app.update();
+        assert_eq!(outcome(&app), (1, (1, 1), false));
+
+        app.world
+            .resource_mut::<RaycastPluginSettings<TestSet>>()
+            .time_budget = Some(Duration::ZERO);
+        app.update();
+        assert_eq!(outcome(&app), (0, (0, 0), true));
+        app.world.entity_mut(cube).despawn();
+
+        // A screenspace source without a camera can't build a ray, so there is no outcome.
+        app.world
+            .get_mut::<RaycastSource<TestSet>>(source)
+            .unwrap()
+            .cast_method = RaycastMethod::Screenspace(Vec2::ZERO);
+        app.update();
+        let raycast_source = app.world.get::<RaycastSource<TestSet>>(source).unwrap();
+        assert!(raycast_source.outcome().is_none());
+    }
+
+    #[test]
+    fn identical_sources_share_casts() {
+        let mut app = test_app();
+        let cube = spawn_mesh(&mut app, shape::Cube::default().into(), Transform::IDENTITY);
+        let transform = Transform::from_xyz(0.2, 0.1, 5.0);
+        let hover = spawn_source(&mut app, transform);
+        let click = spawn_source(&mut app, transform);
+        app.update();
+
+        let metrics = app.world.resource::<RaycastMetrics<TestSet>>();
+        assert_eq!((metrics.casts, metrics.casts_deduplicated), (1, 1));
+        let [hover_hits, click_hits] = [hover, click].map(|source| intersections(&app, source));
+        assert_eq!(hover_hits.len(), 1);
+        assert_eq!(click_hits.len(), 1);
+        assert_eq!(hover_hits[0].0, click_hits[0].0);
+        assert_eq!(hover_hits[0].1.position(), click_hits[0].1.position());
+
+        // Different filters can't share a cast.
+        app.world
+            .get_mut::<RaycastSource<TestSet>>(click)
+            .unwrap()
+            .target_filter = TargetFilter::Only(SmallVec::from_slice(&[cube]));
+        app.update();
+        let metrics = app.world.resource::<RaycastMetrics<TestSet>>();
+        assert_eq!((metrics.casts, metrics.casts_deduplicated), (3, 1));
+    }
+
+    #[test]
+    fn polyline_stops_at_first_hit() {
+        let mut app = test_app();
+        // In line with the first segment, but past its end.
+        spawn_mesh(
+            &mut app,
+            shape::Cube::default().into(),
+            Transform::from_xyz(0.0, 0.0, -6.0),
+        );
+        let wall = spawn_mesh(
+            &mut app,
+            shape::Cube::default().into(),
+            Transform::from_xyz(2.0, 0.0, -6.0),
+        );
+        let points = [
+            Vec3::ZERO,
+            Vec3::new(0.0, 0.0, -2.0),
+            Vec3::new(2.0, 0.0, -4.0),
+            Vec3::new(2.0, 0.0, -8.0),
+        ];
+
+        let mut state = SystemState::<Raycast>::new(&mut app.world);
+        let mut raycast = state.get_mut(&mut app.world);
+        let settings = RaycastSettings::default().with_visibility(RaycastVisibility::Ignore);
+        let hit = raycast.cast_polyline(&points, &settings).unwrap();
+        assert_eq!(hit.entity, wall);
+        assert_eq!(hit.segment, 2);
+        assert!((hit.segment_fraction - 0.375).abs() < 1e-5);
+        assert!((hit.distance - (3.5 + 2.0 * 2f32.sqrt())).abs() < 1e-5);
+        assert!(
+            hit.intersection
+                .position()
+                .distance(Vec3::new(2.0, 0.0, -5.5))
+                < 1e-5
+        );
+
+        assert!(raycast.cast_polyline(&points[..3], &settings).is_none());
+    }
+
+    #[test]
+    fn beam_finds_entities_near_the_ray() {
+        let mut app = test_app();
+        let cube = || shape::Cube::default().into();
+        let near = spawn_mesh(&mut app, cube(), Transform::from_xyz(0.8, 0.0, 0.0));
+        spawn_mesh(&mut app, cube(), Transform::from_xyz(2.0, 0.0, 0.0));
+        // A triangle whose bounds start near the source, but which only comes close to the ray
+        // far from it.
+        let mut triangle = Mesh::new(PrimitiveTopology::TriangleList);
+        let positions = vec![[3.0, 0.0, 0.0], [3.0, 1.0, 0.0], [0.2, 0.0, -10.0]];
+        triangle.insert_attribute(Mesh::ATTRIBUTE_POSITION, positions);
+        let far = spawn_mesh(&mut app, triangle, Transform::IDENTITY);
+
+        let mut state = SystemState::<Raycast>::new(&mut app.world);
+        let mut raycast = state.get_mut(&mut app.world);
+        let ray = Ray3d::new(Vec3::new(0.0, 0.0, 5.0), Vec3::NEG_Z);
+        let settings = RaycastSettings::default().with_visibility(RaycastVisibility::Ignore);
+        let hits = raycast.cast_beam(ray, 0.5, &settings);
+        let entities: Vec<_> = hits.iter().map(|(entity, _)| *entity).collect();
+        assert_eq!(entities, [near, far]);
+        assert!((hits[1].1.distance() - 15.0).abs() < 1e-4);
+        assert!(hits[1]
+            .1
+            .position()
+            .abs_diff_eq(Vec3::new(0.2, 0.0, -10.0), 1e-4));
+
+        let settings = RaycastSettings {
+            max_distance: Some(8.0),
+            ..settings
+        };
+        let hits = raycast.cast_beam(ray, 0.5, &settings);
+        let entities: Vec<_> = hits.iter().map(|(entity, _)| *entity).collect();
+        assert_eq!(entities, [near]);
+    }
+
+    #[test]
+    fn polyline_culls_like_rays() {
+        let mut app = test_app();
+        let mut lines = Mesh::new(PrimitiveTopology::LineList);
+        let positions = vec![[-1.0, 0.05, 0.0], [1.0, 0.05, 0.0]];
+        lines.insert_attribute(Mesh::ATTRIBUTE_POSITION, positions);
+        let line = spawn_mesh(&mut app, lines, Transform::from_xyz(0.0, 0.0, -3.0));
+        let sphere = shape::UVSphere {
+            radius: 0.1,
+            ..default()
+        };
+        let swept = spawn_mesh(
+            &mut app,
+            sphere.into(),
+            Transform::from_xyz(-1.0, 5.0, -3.0),
+        );
+        app.world
+            .entity_mut(swept)
+            .insert(RaycastSweptTarget::default());
+        app.update();
+        app.update();
+        // The sphere passes through the path between two frames, without being on it in either.
+        *app.world.get_mut::<GlobalTransform>(swept).unwrap() =
+            GlobalTransform::from_xyz(1.0, 5.0, -3.0);
+
+        let mut state = SystemState::<Raycast>::new(&mut app.world);
+        let mut raycast = state.get_mut(&mut app.world);
+        let settings = RaycastSettings::default()
+            .with_visibility(RaycastVisibility::Ignore)
+            .with_line_tolerance(Some(0.1));
+        // Both entities are outside the bounds of the points of each path.
+        let path = |y| [Vec3::new(0.0, y, 0.0), Vec3::new(0.0, y, -6.0)];
+        let hit = raycast.cast_polyline(&path(0.0), &settings).unwrap();
+        assert_eq!(hit.entity, line);
+        let hit = raycast.cast_polyline(&path(5.0), &settings).unwrap();
+        assert_eq!(hit.entity, swept);
+        assert_eq!(hit.intersection.time_of_impact(), Some(0.5));
+    }
+
+    #[test]
+    fn change_raycast_set_moves_entity() {
+        #[derive(Reflect)]
+        struct OtherSet;
+
+        let mut app = test_app();
+        app.add_plugins(DeferredRaycastingPlugin::<OtherSet>::default());
+        let cube = spawn_mesh(&mut app, shape::Cube::default().into(), Transform::IDENTITY);
+        let proxy = app
+            .world
+            .resource_mut::<Assets<Mesh>>()
+            .add(shape::Cube::default().into());
+        app.world.entity_mut(cube).insert((
+            SimplifiedMesh { mesh: proxy },
+            RaycastHitStats::<TestSet>::default(),
+        ));
+        let source = spawn_source(&mut app, Transform::from_xyz(0.0, 0.0, 5.0));
+        let other_source = app
+            .world
+            .spawn((
+                GlobalTransform::from_xyz(0.0, 0.0, 5.0),
+                RaycastSource::<OtherSet>::new_transform_empty()
+                    .with_visibility(RaycastVisibility::Ignore),
+            ))
+            .id();
+        app.update();
+        assert_eq!(intersections(&app, source)[0].0, cube);
+
+        let mut queue = CommandQueue::default();
+        Commands::new(&mut queue, &app.world)
+            .entity(cube)
+            .change_raycast_set::<TestSet, OtherSet>();
+        queue.apply(&mut app.world);
+        assert!(intersections(&app, source).is_empty());
+
+        app.update();
+        assert!(intersections(&app, source).is_empty());
+        let other = app
+            .world
+            .get::<RaycastSource<OtherSet>>(other_source)
+            .unwrap();
+        assert_eq!(other.intersections()[0].0, cube);
+        let entity = app.world.entity(cube);
+        assert!(!entity.contains::<RaycastMesh<TestSet>>());
+        assert!(!entity.contains::<RaycastHitStats<TestSet>>());
+        assert!(entity.contains::<SimplifiedMesh>());
+        assert_eq!(
+            entity
+                .get::<RaycastHitStats<OtherSet>>()
+                .unwrap()
+                .frames_hit,
+            1
+        );
+    }
+
+    #[test]
+    fn hit_stats_count_frames() {
+        let mut app = test_app();
+        let near = spawn_mesh(&mut app, shape::Cube::default().into(), Transform::IDENTITY);
+        let far = spawn_mesh(
+            &mut app,
+            shape::Cube::default().into(),
+            Transform::from_xyz(0.0, 0.0, -3.0),
+        );
+        let missed = spawn_mesh(
+            &mut app,
+            shape::Cube::default().into(),
+            Transform::from_xyz(5.0, 0.0, 0.0),
+        );
+        for entity in [near, far, missed] {
+            app.world
+                .entity_mut(entity)
+                .insert(RaycastHitStats::<TestSet>::default());
+        }
+        // Two sources hitting the same entities only count once per frame.
+        for _ in 0..2 {
+            let source = spawn_source(&mut app, Transform::from_xyz(0.0, 0.0, 5.0));
+            app.world
+                .get_mut::<RaycastSource<TestSet>>(source)
+                .unwrap()
+                .should_early_exit = false;
+        }
+        for _ in 0..3 {
+            app.update();
+        }
+
+        let stats = |app: &App, entity| {
+            let stats = app.world.get::<RaycastHitStats<TestSet>>(entity).unwrap();
+            (stats.frames_hit, stats.frames_top_hit)
+        };
+        assert_eq!(stats(&app, near), (3, 3));
+        assert_eq!(stats(&app, far), (3, 0));
+        assert_eq!(stats(&app, missed), (0, 0));
+
+        app.world
+            .get_mut::<RaycastHitStats<TestSet>>(near)
+            .unwrap()
+            .reset();
+        app.update();
+        assert_eq!(stats(&app, near), (1, 1));
+    }
+
+    #[test]
+    fn fixed_schedule_hits_interpolate() {
+        let mut app = App::new();
+        app.add_plugins((
+            MinimalPlugins,
+            AssetPlugin::default(),
+            DeferredRaycastingPlugin::<TestSet>::default().with_schedule(FixedUpdate),
+        ))
+        .init_asset::<Mesh>();
+        spawn_mesh(&mut app, shape::Quad::default().into(), Transform::IDENTITY);
+        let source = spawn_source(&mut app, Transform::from_xyz(0.0, 0.0, 5.0));
+        app.world
+            .entity_mut(source)
+            .insert(InterpolatedHit::<TestSet>::default());
+        // The schedule is run directly, as the fixed timestep doesn't elapse between updates.
+        app.world.run_schedule(FixedUpdate);
+
+        let hit = app.world.get::<InterpolatedHit<TestSet>>(source).unwrap();
+        assert_eq!(hit.previous(), None);
+        assert_eq!(hit.position(0.0), Some(Vec3::ZERO));
+
+        app.world
+            .entity_mut(source)
+            .insert(GlobalTransform::from_xyz(0.4, 0.0, 5.0));
+        app.world.run_schedule(FixedUpdate);
+        let hit = app.world.get::<InterpolatedHit<TestSet>>(source).unwrap();
+        assert_eq!(hit.previous(), Some(Vec3::ZERO));
+        assert_eq!(hit.current(), Some(Vec3::new(0.4, 0.0, 0.0)));
+        let halfway = hit.position(0.5).unwrap();
+        assert!(halfway.abs_diff_eq(Vec3::new(0.2, 0.0, 0.0), 1e-5));
+    }
+
+    #[test]
+    fn reflected_rays_bounce_inside_box() {
+        let mut app = test_app();
+        let room = spawn_mesh(&mut app, shape::Cube::new(4.0).into(), Transform::IDENTITY);
+
+        let mut state = SystemState::<Raycast>::new(&mut app.world);
+        let mut raycast = state.get_mut(&mut app.world);
+        let settings = RaycastSettings::default()
+            .with_visibility(RaycastVisibility::Ignore)
+            .with_backfaces(Backfaces::Include);
+        let mut ray = Ray3d::new(
+            Vec3::new(0.1, 0.2, -0.3),
+            Vec3::new(1.0, 0.7, 0.3).normalize(),
+        );
+        for _ in 0..10 {
+            let (entity, hit) = raycast.cast_ray(ray, &settings).first().unwrap().clone();
+            assert_eq!(entity, room);
+            // Every bounce travels across the room, instead of hitting the wall it started on.
+            assert!(hit.distance() > 1e-2, "{}", hit.distance());
+            assert!((hit.position().abs().max_element() - 2.0).abs() < 1e-4);
+            // Casting from exactly the hit position also skips the wall it is on.
+            let exact = Ray3d::new(
+                hit.position(),
+                hit.reflected_ray(ray.direction()).direction(),
+            );
+            let exact_hit = raycast
+                .cast_ray(exact, &settings)
+                .first()
+                .unwrap()
+                .1
+                .clone();
+            assert!(exact_hit.distance() > 1e-2, "{}", exact_hit.distance());
+            ray = hit.reflected_ray(ray.direction());
+        }
+    }
+
+    #[test]
+    fn thickness_sums_closed_meshes() {
+        let mut app = test_app();
+        let cube = spawn_mesh(&mut app, shape::Cube::default().into(), Transform::IDENTITY);
+        let slab = spawn_mesh(
+            &mut app,
+            shape::Box::new(1.0, 1.0, 2.0).into(),
+            Transform::from_xyz(0.0, 0.0, -4.0),
+        );
+        let quad = spawn_mesh(
+            &mut app,
+            shape::Quad::default().into(),
+            Transform::from_xyz(0.0, 0.0, -7.0),
+        );
+        // Past the end of the segment.
+        spawn_mesh(
+            &mut app,
+            shape::Cube::default().into(),
+            Transform::from_xyz(0.0, 0.0, -12.0),
+        );
+
+        let mut state = SystemState::<Raycast>::new(&mut app.world);
+        let mut raycast = state.get_mut(&mut app.world);
+        let settings = RaycastSettings::default().with_visibility(RaycastVisibility::Ignore);
+        let (start, end) = (Vec3::new(0.1, 0.2, 5.0), Vec3::new(0.1, 0.2, -10.0));
+        let thickness = raycast.cast_thickness(start, end, 0.25, &settings);
+        assert!((thickness.total - 3.25).abs() < 1e-5);
+        let entities: Vec<_> = thickness
+            .entities
+            .iter()
+            .map(|e| (e.entity, e.is_open))
+            .collect();
+        assert_eq!(entities, [(cube, false), (slab, false), (quad, true)]);
+        assert!((thickness.entities[0].thickness - 1.0).abs() < 1e-5);
+        assert!((thickness.entities[1].thickness - 2.0).abs() < 1e-5);
+        assert_eq!(thickness.entities[2].thickness, 0.25);
+    }
+
+    #[test]
+    fn progressive_refinement_settles() {
+        let mut app = test_app();
+        let sphere = Mesh::from(shape::UVSphere::default());
+        let first = spawn_mesh(&mut app, sphere.clone(), Transform::from_xyz(0.0, 0.0, 0.0));
+        let second = spawn_mesh(&mut app, sphere, Transform::from_xyz(5.0, 0.0, 0.0));
+        let source = spawn_source(&mut app, Transform::from_xyz(0.3, 0.3, 5.0));
+        app.world
+            .get_mut::<RaycastSource<TestSet>>(source)
+            .unwrap()
+            .refinement = CastRefinement::Progressive { settle_frames: 2 };
+        let update = |app: &mut App| {
+            app.update();
+            let (entity, hit) = &intersections(app, source)[0];
+            (*entity, hit.is_approximate(), hit.distance())
+        };
+
+        // The AABB of the sphere is hit before its surface.
+        let (entity, approximate, aabb_distance) = update(&mut app);
+        assert_eq!((entity, approximate), (first, true));
+        assert!(update(&mut app).1);
+        let (entity, approximate, distance) = update(&mut app);
+        assert_eq!((entity, approximate), (first, false));
+        assert!(distance > aabb_distance + 0.01);
+
+        // Moving to another entity before settling restarts the count.
+        *app.world.get_mut::<GlobalTransform>(source).unwrap() =
+            Transform::from_xyz(5.3, 0.3, 5.0).into();
+        assert_eq!(update(&mut app), (second, true, aabb_distance));
+        *app.world.get_mut::<GlobalTransform>(source).unwrap() =
+            Transform::from_xyz(0.3, 0.3, 5.0).into();
+        assert_eq!(update(&mut app), (first, true, aabb_distance));
+        assert!(update(&mut app).1);
+        assert_eq!(update(&mut app), (first, false, distance));
+    }
+
+    #[test]
+    fn transform_offset_moves_geometry() {
+        let mut app = test_app();
+        let offset = Transform::from_xyz(1.0, 0.0, -0.5);
+        let cube = spawn_mesh(&mut app, shape::Cube::default().into(), Transform::IDENTITY);
+        app.world
+            .entity_mut(cube)
+            .insert(RaycastTransformOffset(offset));
+        let identity = spawn_mesh(
+            &mut app,
+            shape::Cube::default().into(),
+            Transform::from_xyz(-3.0, 0.0, 0.0),
+        );
+        app.world
+            .entity_mut(identity)
+            .insert(RaycastTransformOffset::default());
+        // Only the offset cube is at `x = 1.2`, and the cube at the origin is only hit at `x = 0.0`
+        // if the offset is ignored.
+        let offset_source = spawn_source(&mut app, Transform::from_xyz(1.2, 0.0, 5.0));
+        let unoffset_source = spawn_source(&mut app, Transform::from_xyz(0.0, 0.0, 5.0));
+        let identity_source = spawn_source(&mut app, Transform::from_xyz(-3.2, 0.1, 5.0));
+        app.update();
+
+        let (entity, hit) = &intersections(&app, offset_source)[0];
+        assert_eq!(*entity, cube);
+        assert!(hit.position().abs_diff_eq(Vec3::new(1.2, 0.0, 0.0), 1e-5));
+        assert!(intersections(&app, unoffset_source).is_empty());
+        let (entity, hit) = &intersections(&app, identity_source)[0];
+        assert_eq!(*entity, identity);
+        assert_eq!(hit.position(), Vec3::new(-3.2, 0.1, 0.5));
+    }
+
+    #[test]
+    fn screen_size_scale_keeps_handles_pickable() {
+        let mut app = test_app();
+        let (size, distance, fov, height) = (0.01, 100.0, std::f32::consts::FRAC_PI_4, 720.0);
+        let cube = shape::Cube { size }.into();
+        let handle = spawn_mesh(&mut app, cube, Transform::from_xyz(0.0, 0.0, -distance));
+        // The size in world space of a pixel on screen, at the distance of the handle.
+        let pixel = 2.0 * distance * (fov / 2.0).tan() / height;
+        let mut state = SystemState::<Raycast>::new(&mut app.world);
+        let mut cast = |app: &mut App, pixels: f32| {
+            let mut raycast = state.get_mut(&mut app.world);
+            let ray = Ray3d::new(Vec3::ZERO, Vec3::new(pixels * pixel, 0.0, -distance));
+            let settings = RaycastSettings::default().with_visibility(RaycastVisibility::Ignore);
+            raycast
+                .cast_ray(ray, &settings)
+                .first()
+                .map(|(entity, _)| *entity)
+        };
+        assert_eq!(cast(&mut app, 0.0), Some(handle));
+        assert_eq!(cast(&mut app, 5.0), None);
+
+        // The handle is drawn 20 pixels across, so it is hit up to 10 pixels from its center.
+        let screen_size = RaycastScreenSizeScale::from_pixel_size(20.0, size, fov, height);
+        app.world.entity_mut(handle).insert(screen_size);
+        assert_eq!(cast(&mut app, 5.0), Some(handle));
+        assert_eq!(cast(&mut app, -9.5), Some(handle));
+        assert_eq!(cast(&mut app, 10.5), None);
+    }
+
+    /// A small xorshift generator, so the randomized scenes are the same on every run.
+    struct Random(u32);
+
+    impl Random {
+        fn next(&mut self) -> f32 {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 17;
+            self.0 ^= self.0 << 5;
+            self.0 as f32 / u32::MAX as f32
+        }
+
+        fn range(&mut self, min: f32, max: f32) -> f32 {
+            min + (max - min) * self.next()
+        }
+
+        fn vec3(&mut self, min: f32, max: f32) -> Vec3 {
+            Vec3::new(
+                self.range(min, max),
+                self.range(min, max),
+                self.range(min, max),
+            )
+        }
+    }
+
+    #[test]
+    fn audit_matches_reference_in_random_scenes() {
+        let mut random = Random(0x2545_f491);
+        let mut sources_hit = 0;
+        for _ in 0..10 {
+            let mut app = test_app();
+            app.world
+                .resource_mut::<RaycastPluginSettings<TestSet>>()
+                .audit = true;
+            let shapes: [fn() -> Mesh; 4] = [
+                || shape::Cube::default().into(),
+                || shape::UVSphere::default().into(),
+                || shape::Torus::default().into(),
+                || shape::Capsule::default().into(),
+            ];
+            for i in 0..12 {
+                let transform = Transform::from_translation(random.vec3(-3.0, 3.0))
+                    .with_rotation(Quat::from_scaled_axis(random.vec3(-3.0, 3.0)))
+                    .with_scale(random.vec3(0.3, 2.0));
+                let entity = spawn_mesh(&mut app, shapes[i % 4](), transform);
+                let mut entity = app.world.entity_mut(entity);
+                match i % 3 {
+                    0 => entity.insert(NoBackfaceCulling),
+                    1 => entity.insert(RaycastTransformOffset(Transform::from_translation(
+                        random.vec3(-0.5, 0.5),
+                    ))),
+                    _ => entity.insert(()),
+                };
+            }
+            let sources: Vec<_> = (0..8)
+                .map(|i| {
+                    let origin = random.vec3(-8.0, 8.0);
+                    let transform = Transform::from_translation(origin)
+                        .looking_at(random.vec3(-2.0, 2.0), Vec3::Y);
+                    let source = spawn_source(&mut app, transform);
+                    let mut raycast_source =
+                        app.world.get_mut::<RaycastSource<TestSet>>(source).unwrap();
+                    raycast_source.should_early_exit = i % 2 == 0;
+                    source
+                })
+                .collect();
+            app.update();
+            sources_hit += sources
+                .iter()
+                .filter(|source| !intersections(&app, **source).is_empty())
+                .count();
+
+            let metrics = app.world.resource::<RaycastMetrics<TestSet>>();
+            assert_eq!(metrics.casts_audited, 8);
+            assert_eq!(
+                metrics.audit_divergences, 0,
+                "{:?}",
+                metrics.last_audit_divergence
+            );
+        }
+        assert!(sources_hit > 20, "{sources_hit}");
+    }
+
+    #[test]
+    fn audit_reports_stale_bounds() {
+        let mut app = test_app();
+        app.world
+            .resource_mut::<RaycastPluginSettings<TestSet>>()
+            .audit = true;
+        let cube = spawn_mesh(&mut app, shape::Cube::default().into(), Transform::IDENTITY);
+        // An AABB that doesn't contain the mesh, so the optimized raycast culls the cube.
+        app.world
+            .entity_mut(cube)
+            .insert(Aabb::from_min_max(Vec3::splat(5.0), Vec3::splat(6.0)));
+        let source = spawn_source(&mut app, Transform::from_xyz(0.0, 0.0, 5.0));
+        app.update();
+
+        assert!(intersections(&app, source).is_empty());
+        let metrics = app.world.resource::<RaycastMetrics<TestSet>>();
+        assert_eq!((metrics.casts_audited, metrics.audit_divergences), (1, 1));
+        let divergence = metrics.last_audit_divergence.as_ref().unwrap();
+        assert_eq!(divergence.source, source);
+        assert_eq!(divergence.reference[0].0, cube);
+    }
+
+    #[test]
+    fn explain_reports_first_failing_stage() {
+        let mut app = test_app();
+        let cube = || shape::Cube::default().into();
+        let near = spawn_mesh(&mut app, cube(), Transform::IDENTITY);
+        let far = spawn_mesh(&mut app, cube(), Transform::from_xyz(0.0, 0.0, -3.0));
+        let aside = spawn_mesh(&mut app, cube(), Transform::from_xyz(5.0, 0.0, 0.0));
+        let hidden = spawn_mesh(&mut app, cube(), Transform::from_xyz(0.0, 0.0, -6.0));
+        app.world
+            .entity_mut(hidden)
+            .insert(InheritedVisibility::HIDDEN);
+        // The ray passes through a corner of the sphere's AABB, outside of the sphere.
+        let sphere = spawn_mesh(
+            &mut app,
+            shape::UVSphere::default().into(),
+            Transform::from_xyz(0.9, 0.9, -9.0),
+        );
+        let unloaded = spawn_mesh(&mut app, cube(), Transform::from_xyz(0.0, 0.0, -12.0));
+        app.world
+            .entity_mut(unloaded)
+            .insert(Handle::<Mesh>::default());
+        let not_in_set = spawn_mesh(&mut app, cube(), Transform::from_xyz(0.0, 0.0, -15.0));
+        app.world
+            .entity_mut(not_in_set)
+            .remove::<RaycastMesh<TestSet>>();
+        let source = spawn_source(&mut app, Transform::from_xyz(0.0, 0.0, 5.0));
+        app.world
+            .get_mut::<RaycastSource<TestSet>>(source)
+            .unwrap()
+            .explain_target = Some(not_in_set);
+        app.update();
+
+        let explanation = app.world.get::<RaycastSource<TestSet>>(source).unwrap();
+        assert!(matches!(
+            explanation.explanation(),
+            Some(HitExplanation::NotInRaycastSet)
+        ));
+
+        let mut state = SystemState::<Raycast>::new(&mut app.world);
+        let mut raycast = state.get_mut(&mut app.world);
+        let ray = Ray3d::new(Vec3::new(0.0, 0.0, 5.0), Vec3::NEG_Z);
+        let settings = RaycastSettings::default().with_visibility(RaycastVisibility::MustBeVisible);
+        let all = settings.clone().never_early_exit();
+        let filter = |entity| entity != near;
+        let filtered = all.clone().with_filter(&filter);
+
+        assert!(matches!(
+            raycast.explain(ray, near, &settings),
+            HitExplanation::Hit(hit) if hit.distance() == 4.5
+        ));
+        assert!(matches!(
+            raycast.explain(ray, far, &settings),
+            HitExplanation::Occluded { by, distance } if by == near && distance == 4.5
+        ));
+        assert!(matches!(
+            raycast.explain(ray, far, &all),
+            HitExplanation::Hit(_)
+        ));
+        assert!(matches!(
+            raycast.explain(ray, aside, &settings),
+            HitExplanation::OutsideBounds { miss_distance } if (miss_distance - 4.5).abs() < 1e-5
+        ));
+        assert!(matches!(
+            raycast.explain(ray, hidden, &all),
+            HitExplanation::Invisible
+        ));
+        assert!(matches!(
+            raycast.explain(ray, sphere, &all),
+            HitExplanation::NoTriangleHit
+        ));
+        assert!(matches!(
+            raycast.explain(ray, unloaded, &all),
+            HitExplanation::MeshNotLoaded
+        ));
+        assert!(matches!(
+            raycast.explain(ray, near, &filtered),
+            HitExplanation::Filtered
+        ));
+        assert!(matches!(
+            raycast.explain(ray, source, &all),
+            HitExplanation::NotRaycastable
+        ));
+    }
+
+    #[test]
+    fn quantized_positions_pick_like_floats() {
+        let mut app = test_app();
+        let sphere = Mesh::from(shape::UVSphere::default());
+        let Some(VertexAttributeValues::Float32x3(positions)) =
+            sphere.attribute(Mesh::ATTRIBUTE_POSITION)
+        else {
+            unreachable!()
+        };
+        // Quantize the positions to `-1.0..=1.0`, with an offset and a scale of 2.
+        let offset = Vec3::new(0.5, 0.0, 0.0);
+        let quantized: Vec<[i16; 4]> = positions
+            .iter()
+            .map(|p| {
+                let p = (Vec3::from(*p) - offset) / 2.0 * i16::MAX as f32;
+                [
+                    p.x.round() as i16,
+                    p.y.round() as i16,
+                    p.z.round() as i16,
+                    0,
+                ]
+            })
+            .collect();
+        let attribute =
+            MeshVertexAttribute::new("Quantized_Position", 988_540_917, VertexFormat::Snorm16x4);
+        // Only the quantized positions are used.
+        let mut quantized_sphere = sphere.clone();
+        quantized_sphere
+            .insert_attribute(Mesh::ATTRIBUTE_POSITION, vec![[0.0f32; 3]; quantized.len()]);
+        quantized_sphere.insert_attribute(
+            attribute.clone(),
+            VertexAttributeValues::Snorm16x4(quantized),
+        );
+
+        let float = spawn_mesh(&mut app, sphere.clone(), Transform::IDENTITY);
+        // Bevy can't compute the AABB of the quantized mesh, so it is spawned with the float mesh's
+        // AABB.
+        let decoded = spawn_mesh(&mut app, sphere, Transform::from_xyz(0.0, 5.0, 0.0));
+        let handle = app
+            .world
+            .resource_mut::<Assets<Mesh>>()
+            .add(quantized_sphere);
+        app.world.entity_mut(decoded).insert((
+            handle,
+            RaycastPositionDecoder::new(attribute, Vec3::splat(2.0), offset),
+        ));
+
+        let mut state = SystemState::<Raycast>::new(&mut app.world);
+        let mut raycast = state.get_mut(&mut app.world);
+        let settings = RaycastSettings::default().with_visibility(RaycastVisibility::Ignore);
+        for origin in [Vec2::ZERO, Vec2::new(0.3, -0.2), Vec2::new(-0.7, 0.5)] {
+            let mut hit = |y, target| {
+                let ray = Ray3d::new(origin.extend(5.0) + Vec3::Y * y, Vec3::NEG_Z);
+                let (entity, hit) = raycast.cast_ray(ray, &settings)[0].clone();
+                assert_eq!(entity, target);
+                hit.position() - Vec3::Y * y
+            };
+            let expected = hit(0.0, float);
+            let actual = hit(5.0, decoded);
+            assert!(actual.abs_diff_eq(expected, 1e-3), "{actual} != {expected}");
+        }
+    }
+
+    #[test]
+    fn frame_snapshot_matches_sources() {
+        assert_eq!(std::mem::size_of::<RaycastRecord>(), 48);
+
+        let mut app = App::new();
+        app.add_plugins((
+            MinimalPlugins,
+            AssetPlugin::default(),
+            DeferredRaycastingPlugin::<TestSet>::default().with_frame_snapshot(7),
+        ))
+        .init_asset::<Mesh>();
+        spawn_mesh(&mut app, shape::Cube::default().into(), Transform::IDENTITY);
+        spawn_mesh(
+            &mut app,
+            shape::Cube::default().into(),
+            Transform::from_xyz(0.0, 0.0, -3.0),
+        );
+        let sources = [
+            spawn_source(&mut app, Transform::from_xyz(0.0, 0.0, 5.0)),
+            spawn_source(&mut app, Transform::from_xyz(0.2, 0.0, 5.0)),
+        ];
+        app.world
+            .get_mut::<RaycastSource<TestSet>>(sources[1])
+            .unwrap()
+            .should_early_exit = false;
+
+        for frame in 1..=3 {
+            // Move a source each frame, so the snapshot has to follow it.
+            *app.world.get_mut::<GlobalTransform>(sources[0]).unwrap() =
+                GlobalTransform::from_xyz(0.1 * frame as f32, 0.0, 5.0);
+            app.update();
+
+            let snapshot = app.world.resource::<RaycastFrameSnapshot<TestSet>>();
+            assert_eq!(snapshot.frame, frame);
+            let mut expected: Vec<_> = sources
+                .iter()
+                .flat_map(|source| {
+                    intersections(&app, *source)
+                        .iter()
+                        .map(|(entity, hit)| RaycastRecord {
+                            source_entity_bits: source.to_bits(),
+                            target_entity_bits: entity.to_bits(),
+                            group_id: 7,
+                            position: hit.position().to_array(),
+                            normal: hit.normal().to_array(),
+                            distance: hit.distance(),
+                        })
+                })
+                .collect();
+            let mut records = snapshot.records.clone();
+            let key = |r: &RaycastRecord| (r.source_entity_bits, r.distance.to_bits());
+            expected.sort_by_key(key);
+            records.sort_by_key(key);
+            assert_eq!(records.len(), 3);
+            assert_eq!(records, expected);
+        }
+    }
+
+    #[test]
+    fn duplicate_cursor_sources_are_detected() {
+        let mut app = test_app();
+        // Without a window, cursor rays can't be built, so they are set by hand.
+        app.world
+            .resource_mut::<RaycastPluginState<TestSet>>()
+            .build_rays = false;
+        spawn_mesh(&mut app, shape::Cube::default().into(), Transform::IDENTITY);
+        let viewport = |x| Viewport {
+            physical_position: UVec2::new(x, 0),
+            physical_size: UVec2::new(100, 100),
+            ..default()
+        };
+        let mut spawn_camera = |viewport: Option<Viewport>| {
+            let mut source = RaycastSource::<TestSet>::new_cursor();
+            source.visibility = RaycastVisibility::Ignore;
+            source.ray = Some(Ray3d::new(Vec3::Z * 5.0, Vec3::NEG_Z));
+            let camera = Camera {
+                viewport,
+                ..default()
+            };
+            app.world.spawn((camera, source)).id()
+        };
+        let first = spawn_camera(Some(viewport(0)));
+        let duplicate = spawn_camera(Some(viewport(0)));
+        let other_viewport = spawn_camera(Some(viewport(100)));
+        app.update();
+
+        let debug_info = app.world.resource::<RaycastDebugInfo<TestSet>>();
+        assert_eq!(debug_info.duplicate_sources, vec![vec![first, duplicate]]);
+        for source in [first, duplicate, other_viewport] {
+            assert_eq!(intersections(&app, source).len(), 1);
+        }
+
+        app.world
+            .resource_mut::<RaycastPluginSettings<TestSet>>()
+            .disable_duplicate_sources = true;
+        app.update();
+        assert_eq!(intersections(&app, first).len(), 1);
+        assert!(intersections(&app, duplicate).is_empty());
+        assert_eq!(intersections(&app, other_viewport).len(), 1);
+
+        app.world.despawn(duplicate);
+        app.update();
+        let debug_info = app.world.resource::<RaycastDebugInfo<TestSet>>();
+        assert!(debug_info.duplicate_sources.is_empty());
+    }
+
+    #[test]
+    fn swept_targets_report_time_of_impact() {
+        let mut app = test_app();
+        let sphere = || {
+            shape::UVSphere {
+                radius: 0.1,
+                ..default()
+            }
+            .into()
+        };
+        let start = Transform::from_xyz(-1.0, 0.0, 0.0);
+        let swept = spawn_mesh(&mut app, sphere(), start);
+        app.world
+            .entity_mut(swept)
+            .insert(RaycastSweptTarget::default());
+        let unswept = spawn_mesh(&mut app, sphere(), start.with_translation(Vec3::Y * -3.0));
+        let swept_source = spawn_source(&mut app, Transform::from_xyz(0.0, 0.0, 5.0));
+        let unswept_source = spawn_source(&mut app, Transform::from_xyz(0.0, -3.0, 5.0));
+        app.update();
+        app.update();
+
+        // Both spheres pass through the rays between two frames, without being under them in
+        // either frame.
+        for (entity, offset) in [(swept, Vec3::ZERO), (unswept, Vec3::Y * -3.0)] {
+            *app.world.get_mut::<GlobalTransform>(entity).unwrap() =
+                GlobalTransform::from_translation(Vec3::X + offset);
+        }
+        app.update();
+
+        let (entity, hit) = &intersections(&app, swept_source)[0];
+        assert_eq!(*entity, swept);
+        assert_eq!(hit.time_of_impact(), Some(0.5));
+        assert!(hit.position().abs_diff_eq(Vec3::Z * 0.1, 1e-5));
+        assert!(intersections(&app, unswept_source).is_empty());
+
+        // Once the sphere stops, it is missed again.
+        app.update();
+        assert!(intersections(&app, swept_source).is_empty());
+    }
+
+    #[test]
+    fn cursor_rays_are_shared_between_sets() {
+        #[derive(Reflect)]
+        struct OtherSet;
+
+        let mut app = test_app();
+        app.add_plugins(DeferredRaycastingPlugin::<OtherSet>::default())
+            .init_asset::<Image>()
+            .init_resource::<ManualTextureViews>()
+            .add_event::<WindowCreated>()
+            .add_event::<WindowResized>();
+        let cube = spawn_mesh(&mut app, shape::Cube::default().into(), Transform::IDENTITY);
+        let mut window = Window {
+            resolution: (800.0, 600.0).into(),
+            ..default()
+        };
+        window.set_cursor_position(Some(Vec2::new(420.0, 300.0)));
+        app.world.spawn((window, PrimaryWindow));
+        let mut source = RaycastSource::<TestSet>::new_cursor();
+        source.visibility = RaycastVisibility::Ignore;
+        let mut other_source = RaycastSource::<OtherSet>::new_cursor();
+        other_source.visibility = RaycastVisibility::Ignore;
+        let camera_transform = GlobalTransform::from_xyz(0.0, 0.0, 5.0);
+        let camera = app
+            .world
+            .spawn((
+                Camera::default(),
+                Projection::default(),
+                camera_transform,
+                source,
+                other_source,
+            ))
+            .id();
+        // The camera's viewport is computed by the render plugin, which isn't added here.
+        app.world.run_system_once(camera_system::<Projection>);
+
+        let rays = |app: &App| {
+            let entity = app.world.entity(camera);
+            let ray = entity.get::<RaycastSource<TestSet>>().unwrap().ray;
+            let other_ray = entity.get::<RaycastSource<OtherSet>>().unwrap().ray;
+            (ray, other_ray)
+        };
+        let counts = |app: &App| {
+            let metrics = app.world.resource::<RaycastMetrics<TestSet>>();
+            let other_metrics = app.world.resource::<RaycastMetrics<OtherSet>>();
+            (
+                metrics.cursor_rays_computed + other_metrics.cursor_rays_computed,
+                metrics.cursor_rays_reused + other_metrics.cursor_rays_reused,
+            )
+        };
+        for frame in 1..=2 {
+            app.update();
+            let (ray, other_ray) = rays(&app);
+            let window = app.world.query::<&Window>().single(&app.world);
+            let camera = app.world.get::<Camera>(camera).unwrap();
+            let expected = Ray3d::from_screenspace(
+                window.cursor_position().unwrap(),
+                camera,
+                &camera_transform,
+                window,
+            );
+            assert!(expected.is_some());
+            assert_eq!(ray, expected);
+            assert_eq!(other_ray, expected);
+            assert_eq!(counts(&app), (frame, frame));
+        }
+        let source = app.world.get::<RaycastSource<TestSet>>(camera).unwrap();
+        assert_eq!(source.intersections()[0].0, cube);
+
+        // A camera that moves between the sets builds its ray again.
+        app.world.run_system_once(build_rays::<TestSet>);
+        *app.world.get_mut::<GlobalTransform>(camera).unwrap() =
+            GlobalTransform::from_xyz(1.0, 0.0, 5.0);
+        app.world.run_system_once(build_rays::<OtherSet>);
+        let (ray, other_ray) = rays(&app);
+        assert_ne!(ray, other_ray);
+        assert_eq!(counts(&app), (4, 2));
+    }
+
+    #[test]
+    fn explicit_rays_need_no_camera_or_window() {
+        let mut app = test_app();
+        let cube = spawn_mesh(
+            &mut app,
+            shape::Cube::default().into(),
+            Transform::from_xyz(2.0, 0.0, 0.0),
+        );
+        let ray = Ray3d::new(Vec3::new(2.0, 5.0, 0.0), Vec3::NEG_Y);
+        let source =
+            RaycastSource::<TestSet>::new_ray(ray).with_visibility(RaycastVisibility::Ignore);
+        let source = app.world.spawn(source).id();
+        app.update();
+
+        let raycast_source = app.world.get::<RaycastSource<TestSet>>(source).unwrap();
+        assert_eq!(raycast_source.ray, Some(ray));
+        let (entity, hit) = &raycast_source.intersections()[0];
+        assert_eq!(*entity, cube);
+        assert_eq!(hit.position(), Vec3::new(2.0, 0.5, 0.0));
+        assert_eq!(hit.distance(), 4.5);
+    }
+
+    #[test]
+    fn meshes_without_indices_are_hit_like_indexed_ones() {
+        let mut app = test_app();
+        let indexed = Mesh::from(shape::Cube::default());
+        let mut unindexed = indexed.clone();
+        unindexed.duplicate_vertices();
+        assert!(unindexed.indices().is_none());
+        let ray = Ray3d::new(Vec3::new(0.25, 5.0, 0.1), Vec3::NEG_Y);
+        let mut sources = Vec::new();
+        for (x, mesh) in [(0.0, indexed), (10.0, unindexed)] {
+            let offset = Vec3::new(x, 0.0, 0.0);
+            spawn_mesh(&mut app, mesh, Transform::from_translation(offset));
+            let ray = Ray3d::new(ray.origin() + offset, ray.direction());
+            let source = RaycastSource::<TestSet>::new_ray(ray);
+            let source = source.with_visibility(RaycastVisibility::Ignore);
+            sources.push(app.world.spawn(source).id());
+        }
+        app.update();
+
+        let [indexed, unindexed] = [sources[0], sources[1]].map(|source| {
+            let intersections = intersections(&app, source);
+            assert_eq!(intersections.len(), 1);
+            intersections[0].1.clone()
+        });
+        let offset = Vec3::new(10.0, 0.0, 0.0);
+        assert_eq!(unindexed.position(), indexed.position() + offset);
+        assert_eq!(unindexed.normal(), indexed.normal());
+        assert_eq!(unindexed.distance(), indexed.distance());
+        assert_eq!(unindexed.triangle_index(), indexed.triangle_index());
+    }
+
+    #[test]
+    fn rays_from_points_point_at_the_target() {
+        let mut app = test_app();
+        let cube = spawn_mesh(
+            &mut app,
+            shape::Cube::default().into(),
+            Transform::from_xyz(0.0, 0.0, -4.0),
+        );
+        let turret = Vec3::new(3.0, 0.0, 0.0);
+        let source = |origin, target| {
+            RaycastSource::<TestSet>::new()
+                .with_ray_from_points(origin, target)
+                .with_visibility(RaycastVisibility::Ignore)
+        };
+        let aiming = app
+            .world
+            .spawn(source(turret, Vec3::new(0.0, 0.0, -4.0)))
+            .id();
+        let degenerate = app.world.spawn(source(turret, turret)).id();
+        app.update();
+        app.update();
+
+        let aiming = app.world.get::<RaycastSource<TestSet>>(aiming).unwrap();
+        assert!(aiming
+            .ray
+            .unwrap()
+            .direction()
+            .abs_diff_eq(Vec3::new(-0.6, 0.0, -0.8), 1e-6));
+        assert_eq!(aiming.intersections()[0].0, cube);
+        let degenerate = app.world.get::<RaycastSource<TestSet>>(degenerate).unwrap();
+        assert_eq!(degenerate.ray, None);
+        assert!(degenerate.intersections().is_empty());
+    }
+
+    #[test]
+    fn screen_center_follows_the_viewport() {
+        let mut app = test_app();
+        app.init_asset::<Image>()
+            .init_resource::<ManualTextureViews>()
+            .add_event::<WindowCreated>()
+            .add_event::<WindowResized>();
+        let window = Window {
+            resolution: (800.0, 600.0).into(),
+            ..default()
+        };
+        app.world.spawn((window, PrimaryWindow));
+        let camera_transform = GlobalTransform::from_xyz(0.0, 0.0, 5.0);
+        let camera = app
+            .world
+            .spawn((
+                Camera::default(),
+                Projection::default(),
+                camera_transform,
+                RaycastSource::<TestSet>::new_screen_center(),
+            ))
+            .id();
+
+        let mut ray_through = |viewport, center| {
+            app.world.get_mut::<Camera>(camera).unwrap().viewport = viewport;
+            app.world.run_system_once(camera_system::<Projection>);
+            app.world.run_system_once(build_rays::<TestSet>);
+            let window = app.world.query::<&Window>().single(&app.world);
+            let camera_component = app.world.get::<Camera>(camera).unwrap();
+            let expected =
+                Ray3d::from_screenspace(center, camera_component, &camera_transform, window);
+            assert!(expected.is_some());
+            let ray = app.world.get::<RaycastSource<TestSet>>(camera).unwrap().ray;
+            let (ray, expected) = (ray.unwrap(), expected.unwrap());
+            assert!(ray.origin().abs_diff_eq(expected.origin(), 1e-5));
+            assert!(ray.direction().abs_diff_eq(expected.direction(), 1e-5));
+        };
+        ray_through(None, Vec2::new(400.0, 300.0));
+        let viewport = Viewport {
+            physical_position: UVec2::new(400, 0),
+            physical_size: UVec2::new(400, 200),
+            ..default()
+        };
+        ray_through(Some(viewport), Vec2::new(600.0, 100.0));
+    }
+
+    #[test]
+    fn touch_sources_follow_the_latest_touch() {
+        use bevy::input::touch::{touch_screen_input_system, TouchInput, TouchPhase};
+
+        let mut app = test_app();
+        app.init_asset::<Image>()
+            .init_resource::<ManualTextureViews>()
+            .init_resource::<Touches>()
+            .add_event::<TouchInput>()
+            .add_event::<WindowCreated>()
+            .add_event::<WindowResized>();
+        let window = Window {
+            resolution: (800.0, 600.0).into(),
+            ..default()
+        };
+        app.world.spawn((window, PrimaryWindow));
+        let camera_transform = GlobalTransform::from_xyz(0.0, 0.0, 5.0);
+        let [releasing, keeping] = [false, true].map(|keep_released| {
+            let source = RaycastSource::<TestSet>::new_touch(keep_released);
+            let camera = (Camera::default(), Projection::default(), camera_transform);
+            app.world.spawn((camera, source)).id()
+        });
+        app.world.run_system_once(camera_system::<Projection>);
+
+        let mut touch = |phase, id, position| {
+            app.world.send_event(TouchInput {
+                phase,
+                position,
+                force: None,
+                id,
+            });
+            app.world.run_system_once(touch_screen_input_system);
+            app.world.run_system_once(build_rays::<TestSet>);
+            [releasing, keeping]
+                .map(|source| app.world.get::<RaycastSource<TestSet>>(source).unwrap().ray)
+        };
+        let (first, second) = (Vec2::new(100.0, 200.0), Vec2::new(300.0, 400.0));
+        let rays = [
+            touch(TouchPhase::Started, 1, first),
+            touch(TouchPhase::Started, 2, second),
+            touch(TouchPhase::Ended, 2, second),
+            touch(TouchPhase::Moved, 1, second),
+            touch(TouchPhase::Ended, 1, second),
+        ];
+
+        let window = app.world.query::<&Window>().single(&app.world);
+        let camera = app.world.get::<Camera>(keeping).unwrap();
+        let ray = |position| Ray3d::from_screenspace(position, camera, &camera_transform, window);
+        assert!(ray(first).is_some());
+        assert_eq!(rays[0], [ray(first); 2]);
+        // The latest touch is used, until it ends.
+        assert_eq!(rays[1], [ray(second); 2]);
+        assert_eq!(rays[2], [ray(first); 2]);
+        assert_eq!(rays[3], [ray(second); 2]);
+        assert_eq!(rays[4], [None, ray(second)]);
+    }
+
+    #[test]
+    fn cursor_positions_outside_the_window_follow_cursor_bounds() {
+        let mut app = test_app();
+        app.init_asset::<Image>()
+            .init_resource::<ManualTextureViews>()
+            .add_event::<WindowCreated>()
+            .add_event::<WindowResized>();
+        let window = Window {
+            resolution: (800.0, 600.0).into(),
+            ..default()
+        };
+        app.world.spawn((window, PrimaryWindow));
+        let outside = Vec2::new(-2.0, 603.0);
+        let camera_transform = GlobalTransform::from_xyz(0.0, 0.0, 5.0);
+        let camera = app
+            .world
+            .spawn((
+                Camera::default(),
+                Projection::default(),
+                camera_transform,
+                RaycastSource::<TestSet> {
+                    cast_method: RaycastMethod::Screenspace(outside),
+                    ..default()
+                },
+            ))
+            .id();
+        app.world.run_system_once(camera_system::<Projection>);
+
+        let mut ray = |cursor_bounds| {
+            app.world
+                .resource_mut::<RaycastPluginSettings<TestSet>>()
+                .cursor_bounds = cursor_bounds;
+            app.world.run_system_once(build_rays::<TestSet>);
+            app.world.get::<RaycastSource<TestSet>>(camera).unwrap().ray
+        };
+        let clamped = ray(CursorBounds::Clamp);
+        let rejected = ray(CursorBounds::Reject);
+        let allowed = ray(CursorBounds::Allow);
+
+        let window = app.world.query::<&Window>().single(&app.world);
+        let camera = app.world.get::<Camera>(camera).unwrap();
+        let corner = Vec2::new(0.0, 600.0);
+        let expected = Ray3d::from_screenspace(corner, camera, &camera_transform, window);
+        assert!(expected.is_some());
+        assert_eq!(clamped, expected);
+        assert_eq!(rejected, None);
+        assert!(allowed.is_some());
+        assert_ne!(allowed, expected);
+    }
+
+    #[test]
+    fn orthographic_rays_are_parallel_to_the_view_axis() {
+        let mut app = test_app();
+        app.init_asset::<Image>()
+            .init_resource::<ManualTextureViews>()
+            .add_event::<WindowCreated>()
+            .add_event::<WindowResized>();
+        let mut window = Window {
+            resolution: (800.0, 600.0).into(),
+            ..default()
+        };
+        window.set_cursor_position(Some(Vec2::ZERO));
+        app.world.spawn((window, PrimaryWindow));
+        let projection = OrthographicProjection {
+            scale: 0.01,
+            near: -10.0,
+            ..default()
+        };
+        let rotation = Quat::from_rotation_y(0.5);
+        let camera_transform = Transform::from_xyz(1.0, 2.0, 3.0).with_rotation(rotation);
+        let mut source = |cast_method| {
+            app.world
+                .spawn((
+                    Camera::default(),
+                    Projection::Orthographic(projection.clone()),
+                    GlobalTransform::from(camera_transform),
+                    RaycastSource::<TestSet> {
+                        cast_method,
+                        ..default()
+                    },
+                ))
+                .id()
+        };
+        let corner = source(RaycastMethod::Cursor);
+        let center = source(RaycastMethod::ScreenCenter);
+        app.world.run_system_once(camera_system::<Projection>);
+        app.world.run_system_once(build_rays::<TestSet>);
+
+        let ray = |source| app.world.get::<RaycastSource<TestSet>>(source).unwrap().ray;
+        let (corner, center) = (ray(corner).unwrap(), ray(center).unwrap());
+        let forward = rotation * Vec3::NEG_Z;
+        // The near plane is 10 units behind the camera, and the window is 8 by 6 units wide.
+        let expected = camera_transform.transform_point(Vec3::new(-4.0, 3.0, 10.0));
+        assert!(corner.origin().abs_diff_eq(expected, 1e-4));
+        assert!(corner.direction().abs_diff_eq(forward, 1e-6));
+        assert!(center.direction().abs_diff_eq(forward, 1e-6));
+        let center_expected = camera_transform.transform_point(Vec3::new(0.0, 0.0, 10.0));
+        assert!(center.origin().abs_diff_eq(center_expected, 1e-4));
+    }
+
+    #[test]
+    fn cursor_sources_use_the_window_of_their_camera() {
+        let mut app = test_app();
+        app.init_asset::<Image>()
+            .init_resource::<ManualTextureViews>()
+            .add_event::<WindowCreated>()
+            .add_event::<WindowResized>();
+        let mut spawn_window = |cursor| {
+            let mut window = Window {
+                resolution: (800.0, 600.0).into(),
+                ..default()
+            };
+            window.set_cursor_position(Some(cursor));
+            app.world.spawn(window).id()
+        };
+        let primary = spawn_window(Vec2::new(100.0, 100.0));
+        let secondary = spawn_window(Vec2::new(700.0, 500.0));
+        app.world.entity_mut(primary).insert(PrimaryWindow);
+        let mut spawn_camera = |target| {
+            let camera = Camera {
+                target,
+                ..default()
+            };
+            let transform = GlobalTransform::from_xyz(0.0, 0.0, 5.0);
+            let source = RaycastSource::<TestSet>::new_cursor();
+            app.world
+                .spawn((camera, Projection::default(), transform, source))
+                .id()
+        };
+        let sources = [
+            spawn_camera(RenderTarget::Window(bevy::window::WindowRef::Primary)),
+            spawn_camera(RenderTarget::Window(bevy::window::WindowRef::Entity(
+                secondary,
+            ))),
+        ];
+        app.world.run_system_once(camera_system::<Projection>);
+        app.world.run_system_once(build_rays::<TestSet>);
+
+        for (source, window) in sources.into_iter().zip([primary, secondary]) {
+            let window = app.world.get::<Window>(window).unwrap();
+            let camera = app.world.get::<Camera>(source).unwrap();
+            let transform = app.world.get::<GlobalTransform>(source).unwrap();
+            let cursor = window.cursor_position().unwrap();
+            let expected = Ray3d::from_screenspace(cursor, camera, transform, window);
+            let ray = app.world.get::<RaycastSource<TestSet>>(source).unwrap().ray;
+            assert!(ray.is_some());
+            assert_eq!(ray, expected);
+        }
+
+        // Closing the secondary window removes the ray of its camera.
+        app.world.despawn(secondary);
+        app.world.run_system_once(build_rays::<TestSet>);
+        let ray = |source| app.world.get::<RaycastSource<TestSet>>(source).unwrap().ray;
+        assert!(ray(sources[0]).is_some());
+        assert_eq!(ray(sources[1]), None);
+    }
+
+    #[test]
+    fn cursor_rays_follow_the_scale_factor_of_the_window() {
+        let mut app = test_app();
+        app.init_asset::<Image>()
+            .init_resource::<ManualTextureViews>()
+            .add_event::<WindowCreated>()
+            .add_event::<WindowResized>();
+        let window = app
+            .world
+            .spawn((
+                Window {
+                    resolution: (800.0, 600.0).into(),
+                    ..default()
+                },
+                PrimaryWindow,
+            ))
+            .id();
+        let camera = Camera {
+            viewport: Some(Viewport {
+                physical_position: UVec2::new(400, 300),
+                physical_size: UVec2::new(400, 300),
+                ..default()
+            }),
+            ..default()
+        };
+        let camera_transform = GlobalTransform::from_xyz(0.0, 0.0, 5.0);
+        let source = app
+            .world
+            .spawn((
+                camera,
+                Projection::default(),
+                camera_transform,
+                RaycastSource::<TestSet>::new_cursor(),
+            ))
+            .id();
+        app.world.run_system_once(camera_system::<Projection>);
+
+        // The window moves to a monitor with twice the scale factor. Its logical size halves, and
+        // the camera doesn't know about it until the window is resized.
+        let mut window = app.world.get_mut::<Window>(window).unwrap();
+        window.resolution.set_scale_factor_override(Some(2.0));
+        // The center of the viewport, which covers the bottom right quarter of the window.
+        window.set_cursor_position(Some(Vec2::new(300.0, 225.0)));
+        app.world.run_system_once(build_rays::<TestSet>);
+
+        let camera = app.world.get::<Camera>(source).unwrap();
+        let expected = Ray3d::from_ndc(Vec2::ZERO, camera, &camera_transform);
+        let ray = app.world.get::<RaycastSource<TestSet>>(source).unwrap().ray;
+        assert!(expected.is_some());
+        assert_eq!(ray, expected);
+    }
+
+    #[test]
+    fn split_screen_sources_only_pick_in_their_viewport() {
+        let mut app = test_app();
+        app.init_asset::<Image>()
+            .init_resource::<ManualTextureViews>()
+            .add_event::<WindowCreated>()
+            .add_event::<WindowResized>();
+        let mut window = Window {
+            resolution: (800.0, 600.0).into(),
+            ..default()
+        };
+        window.set_cursor_position(Some(Vec2::new(700.0, 150.0)));
+        app.world.spawn((window, PrimaryWindow));
+        let camera_transform = GlobalTransform::from_xyz(0.0, 0.0, 5.0);
+        let mut spawn_camera = |x, cast_method| {
+            let camera = Camera {
+                viewport: Some(Viewport {
+                    physical_position: UVec2::new(x, 0),
+                    physical_size: UVec2::new(400, 600),
+                    ..default()
+                }),
+                ..default()
+            };
+            let source = RaycastSource::<TestSet> {
+                cast_method,
+                ..default()
+            };
+            app.world
+                .spawn((camera, Projection::default(), camera_transform, source))
+                .id()
+        };
+        let left = spawn_camera(0, RaycastMethod::Cursor);
+        let right = spawn_camera(400, RaycastMethod::Cursor);
+        let left_screenspace = spawn_camera(0, RaycastMethod::Screenspace(Vec2::new(100.0, 450.0)));
+        let right_screenspace =
+            spawn_camera(400, RaycastMethod::Screenspace(Vec2::new(100.0, 450.0)));
+        app.world.run_system_once(camera_system::<Projection>);
+        app.world.run_system_once(build_rays::<TestSet>);
+
+        let ray = |source| app.world.get::<RaycastSource<TestSet>>(source).unwrap().ray;
+        let ndc_ray = |source, ndc| {
+            let camera = app.world.get::<Camera>(source).unwrap();
+            Ray3d::from_ndc(ndc, camera, &camera_transform)
+        };
+        // The cursor is halfway right and a quarter down the right viewport.
+        assert_eq!(ray(left), None);
+        assert!(ray(right).is_some());
+        assert_eq!(ray(right), ndc_ray(right, Vec2::new(0.5, 0.5)));
+        assert!(ray(left_screenspace).is_some());
+        assert_eq!(
+            ray(left_screenspace),
+            ndc_ray(left_screenspace, Vec2::new(-0.5, -0.5))
+        );
+        assert_eq!(ray(right_screenspace), None);
+    }
+
+    #[test]
+    fn intersections_are_cleared_when_the_cursor_leaves_the_window() {
+        let mut app = test_app();
+        app.init_asset::<Image>()
+            .init_resource::<ManualTextureViews>()
+            .add_event::<WindowCreated>()
+            .add_event::<WindowResized>();
+        let mesh = spawn_mesh(&mut app, shape::Cube::default().into(), Transform::IDENTITY);
+        let window = app.world.spawn((Window::default(), PrimaryWindow)).id();
+        let mut spawn_camera = |keep_last_hit| {
+            let source = RaycastSource::<TestSet>::new_cursor()
+                .with_visibility(RaycastVisibility::Ignore)
+                .with_keep_last_hit(keep_last_hit);
+            let transform = GlobalTransform::from_xyz(0.0, 0.0, 5.0);
+            app.world
+                .spawn((Camera::default(), Projection::default(), transform, source))
+                .id()
+        };
+        let cleared = spawn_camera(false);
+        let kept = spawn_camera(true);
+        app.world.run_system_once(camera_system::<Projection>);
+        let move_cursor = |app: &mut App, position| {
+            let mut window = app.world.get_mut::<Window>(window).unwrap();
+            window.set_cursor_position(position);
+            app.update();
+        };
+        let mesh_hits = |app: &App| {
+            let mesh = app.world.get::<RaycastMesh<TestSet>>(mesh).unwrap();
+            mesh.intersections()
+                .iter()
+                .map(|(e, _)| *e)
+                .collect::<Vec<_>>()
+        };
+        let is_current = |app: &App, source| {
+            let source = app.world.get::<RaycastSource<TestSet>>(source).unwrap();
+            source.is_current()
+        };
+
+        move_cursor(&mut app, Some(Vec2::new(640.0, 360.0)));
+        assert_eq!(intersections(&app, cleared).len(), 1);
+        assert_eq!(mesh_hits(&app).len(), 2);
+
+        move_cursor(&mut app, None);
+        assert!(intersections(&app, cleared).is_empty());
+        assert_eq!(intersections(&app, kept).len(), 1);
+        assert!(!is_current(&app, kept));
+        assert_eq!(mesh_hits(&app), [kept]);
+
+        move_cursor(&mut app, Some(Vec2::new(640.0, 360.0)));
+        assert_eq!(intersections(&app, cleared).len(), 1);
+        assert!(is_current(&app, kept));
+        assert_eq!(mesh_hits(&app).len(), 2);
+    }
+
+    #[test]
+    fn target_uv_sources_cast_from_cameras_rendering_to_images() {
+        use bevy::render::render_resource::{Extent3d, TextureDimension, TextureFormat};
+
+        let mut app = test_app();
+        app.init_asset::<Image>()
+            .init_resource::<ManualTextureViews>()
+            .add_event::<WindowCreated>()
+            .add_event::<WindowResized>();
+        let size = Extent3d {
+            width: 400,
+            height: 200,
+            ..default()
+        };
+        let image = Image::new_fill(
+            size,
+            TextureDimension::D2,
+            &[0, 0, 0, 255],
+            TextureFormat::Rgba8UnormSrgb,
+        );
+        let image = app.world.resource_mut::<Assets<Image>>().add(image);
+        let camera_transform = GlobalTransform::from_xyz(0.0, 0.0, 5.0);
+        let mut spawn_camera = |viewport, uv| {
+            let camera = Camera {
+                target: RenderTarget::Image(image.clone()),
+                viewport,
+                ..default()
+            };
+            let source = RaycastSource::<TestSet>::new_target_uv(uv);
+            app.world
+                .spawn((camera, Projection::default(), camera_transform, source))
+                .id()
+        };
+        let center = spawn_camera(None, Vec2::splat(0.5));
+        let corner = spawn_camera(None, Vec2::ZERO);
+        // The viewport covers the right half of the image.
+        let viewport = Viewport {
+            physical_position: UVec2::new(200, 0),
+            physical_size: UVec2::new(200, 200),
+            ..default()
+        };
+        let in_viewport = spawn_camera(Some(viewport), Vec2::new(0.75, 0.25));
+        app.world.run_system_once(camera_system::<Projection>);
+        app.world.run_system_once(build_rays::<TestSet>);
+
+        let ray = |source| app.world.get::<RaycastSource<TestSet>>(source).unwrap().ray;
+        let ndc_ray = |source, ndc| {
+            let camera = app.world.get::<Camera>(source).unwrap();
+            Ray3d::from_ndc(ndc, camera, &camera_transform)
+        };
+        assert!(ray(center).is_some());
+        assert_eq!(ray(center), ndc_ray(center, Vec2::ZERO));
+        assert_eq!(ray(corner), ndc_ray(corner, Vec2::new(-1.0, 1.0)));
+        assert_eq!(ray(in_viewport), ndc_ray(in_viewport, Vec2::new(0.0, 0.5)));
+    }
+
+    #[test]
+    fn virtual_pointer_sources_follow_the_resource() {
+        let mut app = test_app();
+        app.init_asset::<Image>()
+            .init_resource::<ManualTextureViews>()
+            .add_event::<WindowCreated>()
+            .add_event::<WindowResized>();
+        let mut window = Window::default();
+        // The mouse cursor is ignored.
+        window.set_cursor_position(Some(Vec2::new(10.0, 10.0)));
+        app.world.spawn((window, PrimaryWindow));
+        let other_window = app.world.spawn(Window::default()).id();
+        let source = app
+            .world
+            .spawn((
+                Camera::default(),
+                Projection::default(),
+                GlobalTransform::from_xyz(0.0, 0.0, 5.0),
+                RaycastSource::<TestSet>::new_virtual_pointer(),
+            ))
+            .id();
+        app.world.run_system_once(camera_system::<Projection>);
+        let mut ray = |pointer| {
+            *app.world.resource_mut::<VirtualPointer>() = pointer;
+            app.world.run_system_once(build_rays::<TestSet>);
+            app.world.get::<RaycastSource<TestSet>>(source).unwrap().ray
+        };
+
+        assert_eq!(ray(VirtualPointer::default()), None);
+        let position = Vec2::new(300.0, 200.0);
+        let on_window = ray(VirtualPointer {
+            position: Some(position),
+            window: WindowRef::Primary,
+        });
+        let on_other_window = ray(VirtualPointer {
+            position: Some(position),
+            window: WindowRef::Entity(other_window),
+        });
+
+        let mut windows = app.world.query_filtered::<&Window, With<PrimaryWindow>>();
+        let window = windows.single(&app.world);
+        let camera = app.world.get::<Camera>(source).unwrap();
+        let transform = app.world.get::<GlobalTransform>(source).unwrap();
+        let expected = Ray3d::from_screenspace(position, camera, transform, window);
+        assert!(on_window.is_some());
+        assert_eq!(on_window, expected);
+        assert_eq!(on_other_window, None);
+    }
+
+    #[cfg(feature = "ui")]
+    #[test]
+    fn ui_node_sources_cast_through_the_node() {
+        let mut app = test_app();
+        app.init_asset::<Image>()
+            .init_resource::<ManualTextureViews>()
+            .add_event::<WindowCreated>()
+            .add_event::<WindowResized>();
+        let window = Window {
+            resolution: (800.0, 600.0).into(),
+            ..default()
+        };
+        app.world.spawn((window, PrimaryWindow));
+        // The crosshair is at the center of the window, with the UI scaled up twice.
+        let node = app
+            .world
+            .spawn((
+                bevy::ui::Node::default(),
+                GlobalTransform::from_xyz(200.0, 150.0, 0.0),
+            ))
+            .id();
+        app.insert_resource(bevy::ui::UiScale(2.0));
+        let rotation = Quat::from_rotation_x(-0.4);
+        let camera_transform =
+            GlobalTransform::from(Transform::from_xyz(1.0, 2.0, 5.0).with_rotation(rotation));
+        let source = app
+            .world
+            .spawn((
+                Camera::default(),
+                Projection::default(),
+                camera_transform,
+                RaycastSource::<TestSet>::new_ui_node(node),
+            ))
+            .id();
+        app.world.run_system_once(camera_system::<Projection>);
+        app.world.run_system_once(build_rays::<TestSet>);
+        let ray = app.world.get::<RaycastSource<TestSet>>(source).unwrap().ray;
+
+        let ray = ray.unwrap();
+        assert!(ray.direction().abs_diff_eq(rotation * Vec3::NEG_Z, 1e-5));
+        let offset = ray.origin() - camera_transform.translation();
+        assert!(offset.cross(ray.direction()).length() < 1e-5);
+
+        app.world.despawn(node);
+        app.world.run_system_once(build_rays::<TestSet>);
+        let ray = app.world.get::<RaycastSource<TestSet>>(source).unwrap().ray;
+        assert_eq!(ray, None);
+    }
+
+    #[test]
+    fn rectangles_select_the_entities_inside_them() {
+        let mut app = test_app();
+        app.init_asset::<Image>()
+            .init_resource::<ManualTextureViews>()
+            .add_event::<WindowCreated>()
+            .add_event::<WindowResized>();
+        let window = Window {
+            resolution: (800.0, 600.0).into(),
+            ..default()
+        };
+        app.world.spawn((window, PrimaryWindow));
+        let cube = |app: &mut App, x| {
+            let transform = Transform::from_xyz(x, 0.0, 0.0);
+            spawn_mesh(app, shape::Cube::default().into(), transform)
+        };
+        let left = cube(&mut app, -2.0);
+        let center = cube(&mut app, 0.0);
+        let right = cube(&mut app, 2.0);
+        // A triangle below the diagonal of its AABB, which covers the top right of the screen.
+        let mut mesh = Mesh::new(PrimitiveTopology::TriangleList);
+        let positions = vec![[-1.0, -1.0, 0.0], [1.0, -1.0, 0.0], [-1.0, 1.0, 0.0]];
+        mesh.insert_attribute(Mesh::ATTRIBUTE_POSITION, positions);
+        let transform = Transform::from_xyz(1.0, 1.0, 1.0).with_scale(Vec3::splat(0.5));
+        let triangle = spawn_mesh(&mut app, mesh, transform);
+        let camera = app
+            .world
+            .spawn((
+                Camera::default(),
+                Projection::default(),
+                GlobalTransform::from_xyz(0.0, 0.0, 5.0),
+            ))
+            .id();
+        app.world.run_system_once(camera_system::<Projection>);
+
+        let mut state =
+            SystemState::<(Raycast, Query<(&Camera, &GlobalTransform)>, Query<&Window>)>::new(
+                &mut app.world,
+            );
+        let (mut raycast, cameras, windows) = state.get_mut(&mut app.world);
+        let (camera, camera_transform) = cameras.get(camera).unwrap();
+        let window = windows.single();
+        let settings = RaycastSettings::default().with_visibility(RaycastVisibility::Ignore);
+        let mut select = |corners, settings: &RaycastSettings| {
+            let mut selected =
+                raycast.select_rect(corners, camera, camera_transform, window, settings);
+            selected.sort();
+            selected
+        };
+
+        let sorted = |mut entities: Vec<Entity>| {
+            entities.sort();
+            entities
+        };
+        let everything = [Vec2::ZERO, Vec2::new(800.0, 600.0)];
+        assert_eq!(
+            select(everything, &settings),
+            sorted(vec![left, center, right, triangle])
+        );
+        // The corners can be given in any order.
+        let left_side = [Vec2::new(300.0, 600.0), Vec2::ZERO];
+        assert_eq!(select(left_side, &settings), [left]);
+        let near_center = [Vec2::new(380.0, 280.0), Vec2::new(420.0, 320.0)];
+        assert_eq!(select(near_center, &settings), [center]);
+
+        // The AABB of the triangle is in the rectangle, but the triangle itself isn't.
+        let top_right = [Vec2::new(600.0, 50.0), Vec2::new(650.0, 100.0)];
+        assert!(select(top_right, &settings).is_empty());
+        let volume_only = settings.clone().with_volume_only(true);
+        assert_eq!(select(top_right, &volume_only), [triangle]);
+
+        // The cubes are further from the camera than the triangle.
+        let short = settings.clone().with_max_distance(4.2);
+        assert_eq!(select(everything, &short), [triangle]);
+
+        // A rectangle without a width is a ray pick.
+        let line = [Vec2::new(400.0, 250.0), Vec2::new(400.0, 350.0)];
+        assert_eq!(select(line, &settings), [center]);
+    }
+
+    #[test]
+    fn uninitialized_camera_builds_no_ray() {
+        let mut app = test_app();
+        app.init_asset::<Image>()
+            .init_resource::<ManualTextureViews>()
+            .add_event::<WindowCreated>()
+            .add_event::<WindowResized>();
+        spawn_mesh(&mut app, shape::Cube::default().into(), Transform::IDENTITY);
+        let mut window = Window::default();
+        window.set_cursor_position(Some(Vec2::new(640.0, 360.0)));
+        app.world.spawn((window, PrimaryWindow));
+        let spawn_camera = |app: &mut App, method| {
+            let mut source = RaycastSource::<TestSet>::new_cursor();
+            source.cast_method = method;
+            source.visibility = RaycastVisibility::Ignore;
+            let projection = Projection::Perspective(PerspectiveProjection {
+                fov: 0.0,
+                ..default()
+            });
+            let transform = GlobalTransform::from_xyz(0.0, 0.0, 5.0);
+            app.world
+                .spawn((Camera::default(), projection, transform, source))
+                .id()
+        };
+        let cursor = spawn_camera(&mut app, RaycastMethod::Cursor);
+        let screenspace = spawn_camera(&mut app, RaycastMethod::Screenspace(Vec2::new(10.0, 10.0)));
+        let ray = |app: &App, camera| app.world.get::<RaycastSource<TestSet>>(camera).unwrap().ray;
+
+        // The cameras' projections haven't been computed yet.
+        app.update();
+        for camera in [cursor, screenspace] {
+            assert_eq!(ray(&app, camera), None);
+            assert!(intersections(&app, camera).is_empty());
+        }
+
+        // A field of view of zero gives a projection that can't be inverted.
+        app.world.run_system_once(camera_system::<Projection>);
+        let camera = app.world.get::<Camera>(cursor).unwrap();
+        assert!(!has_invertible_projection(camera));
+        app.update();
+        for camera in [cursor, screenspace] {
+            assert_eq!(ray(&app, camera), None);
+            assert!(intersections(&app, camera).is_empty());
+        }
+
+        for camera in [cursor, screenspace] {
+            *app.world.get_mut::<Projection>(camera).unwrap() = default();
+        }
+        app.world.run_system_once(camera_system::<Projection>);
+        app.update();
+        assert!(ray(&app, cursor).is_some());
+        assert_eq!(intersections(&app, cursor).len(), 1);
+        assert!(ray(&app, screenspace).is_some());
+
+        // The determinant of an orthographic projection the size of the window is tiny, but it
+        // can still be inverted.
+        *app.world.get_mut::<Projection>(cursor).unwrap() =
+            Projection::Orthographic(OrthographicProjection::default());
+        app.world.run_system_once(camera_system::<Projection>);
+        let camera = app.world.get::<Camera>(cursor).unwrap();
+        assert!(camera.projection_matrix().determinant().abs() < f32::EPSILON);
+        assert!(has_invertible_projection(camera));
+        app.update();
+        assert!(ray(&app, cursor).is_some());
+    }
+
+    #[test]
+    fn cross_group_sources_hit_every_group() {
+        #[derive(Reflect)]
+        struct OtherSet;
+        type BothSets = (TestSet, OtherSet);
+
+        let mut app = test_app();
+        app.add_plugins((
+            DeferredRaycastingPlugin::<OtherSet>::default().with_backfaces(Backfaces::Include),
+            CrossGroupRaycastPlugin::<BothSets>::default(),
+        ));
+        let cube = spawn_mesh(&mut app, shape::Cube::default().into(), Transform::IDENTITY);
+        // The quad faces away from the sources, so it's only hit with the backfaces of `OtherSet`.
+        let quad = spawn_mesh(
+            &mut app,
+            shape::Quad::default().into(),
+            Transform::from_xyz(0.0, 0.0, 2.0)
+                .with_rotation(Quat::from_rotation_y(std::f32::consts::PI)),
+        );
+        app.world
+            .entity_mut(quad)
+            .remove::<RaycastMesh<TestSet>>()
+            .insert(RaycastMesh::<OtherSet>::default());
+        let shared = spawn_mesh(
+            &mut app,
+            shape::Cube::default().into(),
+            Transform::from_xyz(3.0, 0.0, 0.0),
+        );
+        app.world
+            .entity_mut(shared)
+            .insert(RaycastMesh::<OtherSet>::default());
+        let mut spawn_source = |x, should_early_exit| {
+            let mut source = RaycastSource::<BothSets>::new_transform_empty()
+                .with_visibility(RaycastVisibility::Ignore);
+            source.should_early_exit = should_early_exit;
+            let transform = GlobalTransform::from_xyz(x, 0.0, 5.0);
+            app.world.spawn((transform, source)).id()
+        };
+        let source_entity = spawn_source(0.0, false);
+        let early_exit = spawn_source(0.0, true);
+        let shared_source = spawn_source(3.0, false);
+        app.update();
+
+        let source = |entity| app.world.get::<RaycastSource<BothSets>>(entity).unwrap();
+        let hits: Vec<_> = source(source_entity)
+            .intersections()
+            .iter()
+            .map(|(entity, hit)| (*entity, hit.distance()))
+            .collect();
+        assert_eq!(hits, vec![(quad, 3.0), (cube, 4.5)]);
+        assert_eq!(source(source_entity).intersection_groups(), [1, 0]);
+        assert_eq!(
+            BothSets::group_names(),
+            vec![TestSet::short_type_path(), OtherSet::short_type_path()]
+        );
+        let early_exit = source(early_exit);
+        assert_eq!(early_exit.intersections().len(), 1);
+        assert_eq!(early_exit.intersections()[0].0, quad);
+        let shared_source = source(shared_source);
+        assert_eq!(shared_source.intersections().len(), 1);
+        assert_eq!(shared_source.intersections()[0].0, shared);
+        assert_eq!(shared_source.intersection_groups(), [0]);
+    }
+
+    #[test]
+    fn cross_group_ties_are_ordered_by_group() {
+        #[derive(Reflect)]
+        struct OtherSet;
+        type BothSets = (TestSet, OtherSet);
+
+        let mut app = test_app();
+        app.add_plugins((
+            DeferredRaycastingPlugin::<OtherSet>::default(),
+            CrossGroupRaycastPlugin::<BothSets>::default(),
+        ));
+        // Two cubes in the same place, the one in the second set spawned first.
+        let spawn_cube =
+            |app: &mut App| spawn_mesh(app, shape::Cube::default().into(), Transform::IDENTITY);
+        let other = spawn_cube(&mut app);
+        app.world
+            .entity_mut(other)
+            .remove::<RaycastMesh<TestSet>>()
+            .insert(RaycastMesh::<OtherSet>::default());
+        let test = spawn_cube(&mut app);
+        let mut source = RaycastSource::<BothSets>::new_transform_empty()
+            .with_visibility(RaycastVisibility::Ignore);
+        source.should_early_exit = false;
+        let transform = GlobalTransform::from_xyz(0.0, 0.0, 5.0);
+        let source = app.world.spawn((transform, source)).id();
+        app.update();
+
+        let source = app.world.get::<RaycastSource<BothSets>>(source).unwrap();
+        let hits: Vec<_> = source.intersections().iter().map(|(e, _)| *e).collect();
+        assert_eq!(hits, vec![test, other]);
+        assert_eq!(source.intersection_groups(), [0, 1]);
+    }
+
+    #[test]
+    fn too_many_hits_overflow() {
+        let mut app = test_app();
+        app.world
+            .resource_mut::<RaycastPluginSettings<TestSet>>()
+            .max_intersections = Some(4);
+        let quads: Vec<_> = (0..6)
+            .map(|i| {
+                let transform = Transform::from_xyz(0.0, 0.0, -(i as f32) * 0.1);
+                spawn_mesh(&mut app, shape::Quad::default().into(), transform)
+            })
+            .collect();
+        let source = spawn_source(&mut app, Transform::from_xyz(0.0, 0.0, 5.0));
+        app.world
+            .get_mut::<RaycastSource<TestSet>>(source)
+            .unwrap()
+            .should_early_exit = false;
+        app.update();
+
+        let hits: Vec<_> = intersections(&app, source)
+            .iter()
+            .map(|(e, _)| *e)
+            .collect();
+        assert_eq!(hits, quads[..4]);
+        let pick_source = app.world.get::<RaycastSource<TestSet>>(source).unwrap();
+        assert!(pick_source.overflowed());
+        let metrics = app.world.resource::<RaycastMetrics<TestSet>>();
+        assert_eq!(metrics.casts_overflowed, 1);
+
+        for quad in &quads[3..] {
+            app.world.despawn(*quad);
+        }
+        app.update();
+        assert_eq!(intersections(&app, source).len(), 3);
+        let pick_source = app.world.get::<RaycastSource<TestSet>>(source).unwrap();
+        assert!(!pick_source.overflowed());
+        let metrics = app.world.resource::<RaycastMetrics<TestSet>>();
+        assert_eq!(metrics.casts_overflowed, 1);
+    }
+
+    #[test]
+    fn ray_start_offset_skips_near_geometry() {
+        let mut app = test_app();
+        let near = spawn_mesh(
+            &mut app,
+            shape::Quad::default().into(),
+            Transform::from_xyz(0.0, 0.0, 4.5),
+        );
+        let cube = spawn_mesh(&mut app, shape::Cube::default().into(), Transform::IDENTITY);
+        let mut spawn_source = |ray_start_offset| {
+            let mut source = RaycastSource::<TestSet>::new_transform_empty()
+                .with_visibility(RaycastVisibility::Ignore)
+                .with_ray_start_offset(ray_start_offset);
+            source.should_early_exit = false;
+            let transform = GlobalTransform::from_xyz(0.0, 0.0, 5.0);
+            app.world.spawn((transform, source)).id()
+        };
+        let source = spawn_source(0.0);
+        let offset_source = spawn_source(1.0);
+        app.update();
+
+        let hits = |source| -> Vec<_> {
+            intersections(&app, source)
+                .iter()
+                .map(|(entity, hit)| (*entity, hit.distance()))
+                .collect()
+        };
+        assert_eq!(hits(source), vec![(near, 0.5), (cube, 4.5)]);
+        assert_eq!(hits(offset_source), vec![(cube, 4.5)]);
+    }
+
+    #[test]
+    fn fallback_hit_is_only_returned_on_a_miss() {
+        let mut app = test_app();
+        let cube = spawn_mesh(&mut app, shape::Cube::default().into(), Transform::IDENTITY);
+        let source = spawn_source(&mut app, Transform::from_xyz(0.0, 0.0, 5.0));
+        let plane = Primitive3d::Plane {
+            point: Vec3::new(0.0, 0.0, -5.0),
+            normal: Vec3::Z,
+        };
+        app.world
+            .get_mut::<RaycastSource<TestSet>>(source)
+            .unwrap()
+            .fallback_plane = Some(plane);
+        app.update();
+        let pick_source = app.world.get::<RaycastSource<TestSet>>(source).unwrap();
+        assert_eq!(pick_source.intersections().len(), 1);
+        assert!(pick_source.fallback_hit().is_none());
+
+        app.world.entity_mut(cube).despawn();
+        app.update();
+        let pick_source = app.world.get::<RaycastSource<TestSet>>(source).unwrap();
+        assert!(pick_source.intersections().is_empty());
+        let hit = pick_source.fallback_hit().unwrap();
+        assert!(hit.position().abs_diff_eq(Vec3::new(0.0, 0.0, -5.0), 1e-5));
+        assert!((hit.distance() - 10.0).abs() < 1e-5);
+        assert!(hit.normal().abs_diff_eq(Vec3::Z, 1e-5));
+
+        // Planes behind the ray, or past its maximum distance, give no fallback hit.
+        for (point, max_distance) in [
+            (Vec3::new(0.0, 0.0, 6.0), None),
+            (Vec3::new(0.0, 0.0, -5.0), Some(9.0)),
+        ] {
+            let mut pick_source = app.world.get_mut::<RaycastSource<TestSet>>(source).unwrap();
+            pick_source.fallback_plane = Some(Primitive3d::Plane {
+                point,
+                normal: Vec3::Z,
+            });
+            pick_source.max_distance = max_distance;
+            app.update();
+            let pick_source = app.world.get::<RaycastSource<TestSet>>(source).unwrap();
+            assert!(pick_source.fallback_hit().is_none());
+        }
+    }
+
+    #[test]
+    fn pickable_bundles_are_hit_once_their_bounds_are_computed() {
+        let mut app = test_app();
+        let mesh = app
+            .world
+            .resource_mut::<Assets<Mesh>>()
+            .add(shape::Cube::default().into());
+        let cube = app
+            .world
+            .spawn((
+                mesh,
+                SpatialBundle::default(),
+                PickableBundle::<TestSet>::default(),
+            ))
+            .id();
+        let source = spawn_source(&mut app, Transform::from_xyz(0.0, 0.0, 5.0));
+        app.update();
+        assert!(intersections(&app, source).is_empty());
+
+        app.world
+            .run_system_once(bevy::render::view::visibility::calculate_bounds);
+        app.update();
+        assert_eq!(intersections(&app, source)[0].0, cube);
+    }
+
+    #[test]
+    fn scene_roots_tag_their_meshes() {
+        let mut app = test_app();
+        let mesh = app
+            .world
+            .resource_mut::<Assets<Mesh>>()
+            .add(shape::Cube::default().into());
+        let child = app
+            .world
+            .spawn((
+                mesh,
+                Aabb::from_min_max(Vec3::splat(-0.5), Vec3::splat(0.5)),
+                GlobalTransform::default(),
+                InheritedVisibility::VISIBLE,
+                ViewVisibility::default(),
+            ))
+            .id();
+        // The root of the scene is tagged instead of the child holding the mesh.
+        let root = app
+            .world
+            .spawn((
+                GlobalTransform::default(),
+                RaycastMesh::<TestSet>::default(),
+            ))
+            .push_children(&[child])
+            .id();
+        let source = spawn_source(&mut app, Transform::from_xyz(0.0, 0.0, 5.0));
+        app.update();
+        let debug_info = app.world.resource::<RaycastDebugInfo<TestSet>>();
+        assert_eq!(debug_info.missing_mesh_handles, [root]);
+        assert!(intersections(&app, source).is_empty());
+
+        app.world
+            .entity_mut(root)
+            .remove::<RaycastMesh<TestSet>>()
+            .insert(RaycastSceneRoot::<TestSet>::default());
+        app.update();
+        app.update();
+        let debug_info = app.world.resource::<RaycastDebugInfo<TestSet>>();
+        assert!(debug_info.missing_mesh_handles.is_empty());
+        assert_eq!(intersections(&app, source)[0].0, child);
+    }
+
+    #[test]
+    fn max_distance_limits_hits() {
+        let mut app = test_app();
+        let near = spawn_mesh(
+            &mut app,
+            shape::Cube::default().into(),
+            Transform::from_xyz(0.0, 0.0, 2.0),
+        );
+        // The ray enters this cube before the maximum distance, and leaves it after.
+        let straddling = spawn_mesh(
+            &mut app,
+            shape::Cube::new(2.0).into(),
+            Transform::from_xyz(0.0, 0.0, -0.5),
+        );
+        spawn_mesh(
+            &mut app,
+            shape::Cube::default().into(),
+            Transform::from_xyz(0.0, 0.0, -5.0),
+        );
+        let mut source = RaycastSource::<TestSet>::new_transform_empty()
+            .with_visibility(RaycastVisibility::Ignore)
+            .with_max_distance(5.0)
+            .with_ray_start_offset(1.0)
+            .with_fallback_plane(Primitive3d::Plane {
+                point: Vec3::new(0.0, 0.0, -10.0),
+                normal: Vec3::Z,
+            });
+        source.should_early_exit = false;
+        let source = app
+            .world
+            .spawn((GlobalTransform::from_xyz(0.0, 0.0, 5.0), source))
+            .id();
+        app.update();
+
+        let hits: Vec<_> = intersections(&app, source)
+            .iter()
+            .map(|(entity, hit)| (*entity, hit.distance()))
+            .collect();
+        assert_eq!(hits, vec![(near, 2.5), (straddling, 4.5)]);
+
+        // Moving the meshes out of the way leaves only the fallback plane, which is also too far.
+        let mut meshes = app
+            .world
+            .query_filtered::<&mut GlobalTransform, With<Handle<Mesh>>>();
+        for mut transform in meshes.iter_mut(&mut app.world) {
+            *transform = GlobalTransform::from_xyz(10.0, 0.0, 0.0);
+        }
+        app.update();
+        let pick_source = app.world.get::<RaycastSource<TestSet>>(source).unwrap();
+        assert!(pick_source.intersections().is_empty());
+        assert!(pick_source.fallback_hit().is_none());
+    }
+
+    #[test]
+    fn ignoring_visibility_hits_entities_without_visibility() {
+        let mut app = test_app();
+        let hidden = spawn_mesh(&mut app, shape::Cube::default().into(), Transform::IDENTITY);
+        app.world
+            .entity_mut(hidden)
+            .insert(InheritedVisibility::HIDDEN);
+        // A trigger volume without any visibility components.
+        let trigger = spawn_mesh(
+            &mut app,
+            shape::Cube::default().into(),
+            Transform::from_xyz(3.0, 0.0, 0.0),
+        );
+        app.world
+            .entity_mut(trigger)
+            .remove::<(InheritedVisibility, ViewVisibility)>();
+        let mut spawn_source = |x, visibility| {
+            let source =
+                RaycastSource::<TestSet>::new_transform_empty().with_visibility(visibility);
+            let transform = GlobalTransform::from_xyz(x, 0.0, 5.0);
+            app.world.spawn((transform, source)).id()
+        };
+        let sources = [RaycastVisibility::Ignore, RaycastVisibility::MustBeVisible]
+            .map(|visibility| [0.0, 3.0].map(|x| spawn_source(x, visibility)));
+        app.update();
+
+        let hit = |source| {
+            intersections(&app, source)
+                .first()
+                .map(|(entity, _)| *entity)
+        };
+        let [[ignore_hidden, ignore_trigger], [visible_hidden, visible_trigger]] = sources;
+        assert_eq!(hit(ignore_hidden), Some(hidden));
+        assert_eq!(hit(ignore_trigger), Some(trigger));
+        assert_eq!(hit(visible_hidden), None);
+        assert_eq!(hit(visible_trigger), None);
+    }
+
+    #[test]
+    fn min_distance_limits_hits() {
+        let mut app = test_app();
+        spawn_mesh(
+            &mut app,
+            shape::Cube::default().into(),
+            Transform::from_xyz(0.0, 0.0, 4.0),
+        );
+        // The ray enters this cube before the minimum distance, and leaves it after.
+        let straddling = spawn_mesh(
+            &mut app,
+            shape::Cube::new(2.0).into(),
+            Transform::from_xyz(0.0, 0.0, 2.5),
+        );
+        let far = spawn_mesh(&mut app, shape::Cube::default().into(), Transform::IDENTITY);
+        let mut source = RaycastSource::<TestSet>::new_transform_empty()
+            .with_visibility(RaycastVisibility::Ignore)
+            .with_backfaces(Backfaces::Include)
+            .with_min_distance(2.0)
+            .with_ray_start_offset(1.0)
+            .with_fallback_plane(Primitive3d::Plane {
+                point: Vec3::new(0.0, 0.0, 4.0),
+                normal: Vec3::Z,
+            });
+        source.should_early_exit = false;
+        let source = app
+            .world
+            .spawn((GlobalTransform::from_xyz(0.0, 0.0, 5.0), source))
+            .id();
+        app.update();
+
+        let hits: Vec<_> = intersections(&app, source)
+            .iter()
+            .map(|(entity, hit)| (*entity, hit.distance()))
+            .collect();
+        assert_eq!(hits, vec![(straddling, 3.5), (far, 4.5)]);
+
+        // Moving the meshes out of the way leaves only the fallback plane, which is too close.
+        let mut meshes = app
+            .world
+            .query_filtered::<&mut GlobalTransform, With<Handle<Mesh>>>();
+        for mut transform in meshes.iter_mut(&mut app.world) {
+            *transform = GlobalTransform::from_xyz(10.0, 0.0, 0.0);
+        }
+        app.update();
+        let pick_source = app.world.get::<RaycastSource<TestSet>>(source).unwrap();
+        assert!(pick_source.intersections().is_empty());
+        assert!(pick_source.fallback_hit().is_none());
+    }
+
+    #[test]
+    fn any_hit_stops_at_the_first_hit_entity() {
+        let mut app = test_app();
+        // A slanted triangle, whose AABB the ray enters first, but which is hit far away.
+        let mut mesh = Mesh::new(PrimitiveTopology::TriangleList);
+        let positions = vec![[-1.0, -0.5, -10.0], [1.0, -0.5, -10.0], [0.0, 2.0, 0.0]];
+        mesh.insert_attribute(Mesh::ATTRIBUTE_POSITION, positions);
+        let slanted = spawn_mesh(&mut app, mesh, Transform::IDENTITY);
+        let cube = spawn_mesh(
+            &mut app,
+            shape::Cube::default().into(),
+            Transform::from_xyz(0.0, 0.0, -4.0),
+        );
+        let mut spawn_source = |any_hit| {
+            let mut source = RaycastSource::<TestSet>::new_transform_empty()
+                .with_visibility(RaycastVisibility::Ignore)
+                .with_backfaces(Backfaces::Include)
+                .with_any_hit(any_hit);
+            source.should_early_exit = false;
+            let transform = GlobalTransform::from_xyz(0.0, 0.0, 1.0);
+            app.world.spawn((transform, source)).id()
+        };
+        let source = spawn_source(false);
+        let any_hit_source = spawn_source(true);
+        app.update();
+
+        let hits = |source| -> Vec<_> {
+            intersections(&app, source)
+                .iter()
+                .map(|(entity, hit)| (*entity, hit.distance().round()))
+                .collect()
+        };
+        assert_eq!(hits(source), vec![(cube, 5.0), (slanted, 9.0)]);
+        // The cube is nearer, but the slanted triangle is tested first, so its hit is returned.
+        assert_eq!(hits(any_hit_source), vec![(slanted, 9.0)]);
+    }
+
+    #[test]
+    fn max_hits_per_mesh_records_entry_and_exit() {
+        let mut app = test_app();
+        let near = spawn_mesh(&mut app, shape::Cube::default().into(), Transform::IDENTITY);
+        let far = spawn_mesh(
+            &mut app,
+            shape::Cube::default().into(),
+            Transform::from_xyz(0.0, 0.0, -3.0),
+        );
+        let mut spawn_source = |max_hits_per_mesh| {
+            let mut source = RaycastSource::<TestSet>::new_transform_empty()
+                .with_visibility(RaycastVisibility::Ignore)
+                .with_backfaces(Backfaces::Include)
+                .with_max_hits_per_mesh(max_hits_per_mesh);
+            source.should_early_exit = false;
+            // Off center, so the ray doesn't hit the edge between the two triangles of a face.
+            let transform = GlobalTransform::from_xyz(0.1, 0.2, 5.0);
+            app.world.spawn((transform, source)).id()
+        };
+        let source = spawn_source(1);
+        let piercing_source = spawn_source(16);
+        app.update();
+
+        let hits = |source| -> Vec<_> {
+            intersections(&app, source)
+                .iter()
+                .map(|(entity, hit)| (*entity, hit.distance(), hit.is_backface()))
+                .collect()
+        };
+        assert_eq!(hits(source), vec![(near, 4.5, false), (far, 7.5, false)]);
+        assert_eq!(
+            hits(piercing_source),
+            vec![
+                (near, 4.5, false),
+                (near, 5.5, true),
+                (far, 7.5, false),
+                (far, 8.5, true)
+            ]
+        );
+    }
+
+    #[test]
+    fn source_filter_excludes_entities() {
+        let mut app = test_app();
+        let cubes: Vec<_> = (0..3)
+            .map(|i| {
+                let transform = Transform::from_xyz(0.0, 0.0, -3.0 * i as f32);
+                spawn_mesh(&mut app, shape::Cube::default().into(), transform)
+            })
+            .collect();
+        let calls = Arc::new(Mutex::new(HashMap::<Entity, usize>::new()));
+        let rejected = cubes[1];
+        let filter_calls = calls.clone();
+        let mut source = RaycastSource::<TestSet>::new_transform_empty()
+            .with_visibility(RaycastVisibility::Ignore)
+            .with_filter(move |entity| {
+                *filter_calls.lock().unwrap().entry(entity).or_default() += 1;
+                entity != rejected
+            });
+        source.should_early_exit = false;
+        let transform = GlobalTransform::from_xyz(0.0, 0.0, 5.0);
+        let source = app.world.spawn((transform, source)).id();
+        app.update();
+
+        let hit_entities = |app: &App| -> Vec<_> {
+            let hits = intersections(app, source);
+            hits.iter().map(|(entity, _)| *entity).collect()
+        };
+        assert_eq!(hit_entities(&app), vec![cubes[0], cubes[2]]);
+        let calls = calls.lock().unwrap().clone();
+        assert_eq!(calls, cubes.iter().map(|cube| (*cube, 1)).collect());
+
+        app.world
+            .get_mut::<RaycastSource<TestSet>>(source)
+            .unwrap()
+            .clear_filter();
+        app.update();
+        assert_eq!(hit_entities(&app), cubes);
+    }
+
+    #[test]
+    fn excluded_entities_are_not_hit() {
+        let mut app = test_app();
+        let player = spawn_mesh(&mut app, shape::Cube::default().into(), Transform::IDENTITY);
+        let wall = spawn_mesh(
+            &mut app,
+            shape::Cube::default().into(),
+            Transform::from_xyz(0.0, 0.0, -3.0),
+        );
+        let source = spawn_source(&mut app, Transform::from_xyz(0.0, 0.0, 5.0));
+        let mut source_mut = app.world.get_mut::<RaycastSource<TestSet>>(source).unwrap();
+        source_mut.exclude(player);
+        source_mut.exclude(player);
+        assert_eq!(source_mut.exclusions(), &[player]);
+
+        let hit = |app: &App| {
+            intersections(app, source)
+                .first()
+                .map(|(entity, _)| *entity)
+        };
+        let mesh_hits = |app: &App, entity| {
+            let mesh = app.world.get::<RaycastMesh<TestSet>>(entity).unwrap();
+            mesh.intersections().len()
+        };
+        // The exclusion lasts until it is removed.
+        for _ in 0..2 {
+            app.update();
+            assert_eq!(hit(&app), Some(wall));
+            assert_eq!((mesh_hits(&app, player), mesh_hits(&app, wall)), (0, 1));
+        }
+
+        app.world
+            .get_mut::<RaycastSource<TestSet>>(source)
+            .unwrap()
+            .remove_exclusion(player);
+        app.update();
+        assert_eq!(hit(&app), Some(player));
+
+        let mut source_mut = app.world.get_mut::<RaycastSource<TestSet>>(source).unwrap();
+        source_mut.exclude(player);
+        source_mut.exclude(wall);
+        source_mut.clear_exclusions();
+        assert!(source_mut.exclusions().is_empty());
+        app.update();
+        assert_eq!(hit(&app), Some(player));
+    }
+
+    #[test]
+    fn layer_mask_filters_by_raycast_layers() {
+        const SELECTABLE: u32 = 0;
+        const GHOST: u32 = 1;
+        let mut app = test_app();
+        let near = spawn_mesh(&mut app, shape::Cube::default().into(), Transform::IDENTITY);
+        let far = spawn_mesh(
+            &mut app,
+            shape::Cube::default().into(),
+            Transform::from_xyz(0.0, 0.0, -3.0),
+        );
+        app.world
+            .entity_mut(near)
+            .insert(RaycastLayers::layer(SELECTABLE));
+        let mut source = RaycastSource::<TestSet>::new_transform_empty()
+            .with_visibility(RaycastVisibility::Ignore)
+            .with_layer_mask(RaycastLayers::layer(SELECTABLE).0);
+        source.should_early_exit = false;
+        let transform = GlobalTransform::from_xyz(0.0, 0.0, 5.0);
+        let source = app.world.spawn((transform, source)).id();
+
+        let hit_entities = |app: &mut App| -> Vec<_> {
+            app.update();
+            let hits = intersections(app, source);
+            hits.iter().map(|(entity, _)| *entity).collect()
+        };
+        // The far cube has no layers, so it's in every layer.
+        assert_eq!(hit_entities(&mut app), vec![near, far]);
+
+        *app.world.get_mut::<RaycastLayers>(near).unwrap() = RaycastLayers::layer(GHOST);
+        assert_eq!(hit_entities(&mut app), vec![far]);
+
+        app.world
+            .entity_mut(far)
+            .insert(RaycastLayers::layer(GHOST).with(SELECTABLE).without(GHOST));
+        *app.world.get_mut::<RaycastLayers>(near).unwrap() = RaycastLayers::NONE.with(GHOST);
+        app.world
+            .get_mut::<RaycastSource<TestSet>>(source)
+            .unwrap()
+            .layer_mask = RaycastLayers::layer(GHOST).0;
+        assert_eq!(hit_entities(&mut app), vec![near]);
+    }
+
+    #[test]
+    fn hit_events_follow_hit_event_mode() {
+        let count_events = |mode, visible: fn(usize) -> bool| {
+            let mut app = test_app();
+            let cube = spawn_mesh(&mut app, shape::Cube::default().into(), Transform::IDENTITY);
+            let source = spawn_source(&mut app, Transform::from_xyz(0.0, 0.0, 5.0));
+            app.world
+                .get_mut::<RaycastSource<TestSet>>(source)
+                .unwrap()
+                .emit_hit_events = mode;
+            let mut reader = app
+                .world
+                .resource::<Events<RaycastHit<TestSet>>>()
+                .get_reader();
+            let mut events = 0;
+            for frame in 0..100 {
+                // Moving the cube away makes the ray miss it.
+                let x = if visible(frame) { 0.0 } else { 10.0 };
+                *app.world.get_mut::<GlobalTransform>(cube).unwrap() =
+                    GlobalTransform::from_xyz(x, 0.0, 0.0);
+                app.update();
+                let hits = app.world.resource::<Events<RaycastHit<TestSet>>>();
+                events += reader
+                    .read(hits)
+                    .inspect(|hit| assert_eq!((hit.source, hit.entity), (source, cube)))
+                    .count();
+            }
+            events
+        };
+        let resting = |_| true;
+        let flickering = |frame| frame % 10 < 5;
+        assert_eq!(count_events(HitEventMode::EveryFrame, resting), 100);
+        assert_eq!(count_events(HitEventMode::OnChange, resting), 1);
+        assert_eq!(count_events(HitEventMode::OnChange, flickering), 10);
+        let cooldown = HitEventMode::OnChangeWithCooldown(Duration::from_secs(3600));
+        assert_eq!(count_events(cooldown, flickering), 1);
+    }
+
+    #[test]
+    fn modified_meshes_are_raycast_with_new_bounds() {
+        let mut app = test_app();
+        let cube = spawn_mesh(&mut app, shape::Cube::default().into(), Transform::IDENTITY);
+        let old = spawn_source(&mut app, Transform::from_xyz(0.0, 0.0, 5.0));
+        let new = spawn_source(&mut app, Transform::from_xyz(3.0, 0.0, 5.0));
+        app.update();
+        assert_eq!(intersections(&app, old)[0].0, cube);
+        assert!(intersections(&app, new).is_empty());
+
+        // Replace the cube with one to the side, like a hot reload of its mesh asset, which also
+        // sends the asset's events before the next frame.
+        let moved = shape::Box::from_corners(Vec3::new(2.5, -0.5, -0.5), Vec3::new(3.5, 0.5, 0.5));
+        let handle = app.world.get::<Handle<Mesh>>(cube).unwrap().clone();
+        app.world
+            .resource_mut::<Assets<Mesh>>()
+            .insert(&handle, moved.into());
+        app.world.run_system_once(Assets::<Mesh>::asset_events);
+        app.update();
+
+        let aabb = app.world.get::<Aabb>(cube).unwrap();
+        assert_eq!(Vec3::from(aabb.center), Vec3::new(3.0, 0.0, 0.0));
+        assert!(intersections(&app, old).is_empty());
+        let (entity, hit) = &intersections(&app, new)[0];
+        assert_eq!(*entity, cube);
+        assert!((hit.distance() - 4.5).abs() < 1e-5);
+        let mesh = app.world.get::<RaycastMesh<TestSet>>(cube).unwrap();
+        let sources: Vec<_> = mesh.intersections().iter().map(|(e, _)| *e).collect();
+        assert_eq!(sources, [new]);
+    }
+
+    #[cfg(feature = "2d")]
+    #[test]
+    fn modified_2d_and_simplified_meshes_are_updated() {
+        let mut app = test_app();
+        let mut add_mesh = |mesh: Mesh| app.world.resource_mut::<Assets<Mesh>>().add(mesh);
+        let quad = add_mesh(shape::Quad::default().into());
+        let proxy = add_mesh(shape::Cube::default().into());
+        let mesh_2d = app
+            .world
+            .spawn((
+                bevy::sprite::Mesh2dHandle(quad.clone()),
+                Aabb::from_min_max(Vec3::new(-0.5, -0.5, 0.0), Vec3::new(0.5, 0.5, 0.0)),
+                GlobalTransform::default(),
+                InheritedVisibility::VISIBLE,
+                ViewVisibility::default(),
+                RaycastMesh::<TestSet>::default(),
+            ))
+            .id();
+        let cube = Transform::from_xyz(5.0, 0.0, 0.0);
+        let simplified = spawn_mesh(&mut app, shape::Cube::default().into(), cube);
+        app.world.entity_mut(simplified).insert(SimplifiedMesh {
+            mesh: proxy.clone(),
+        });
+        spawn_source(&mut app, Transform::from_xyz(0.0, 0.0, 5.0));
+        spawn_source(&mut app, Transform::from_xyz(5.0, 0.0, 5.0));
+        app.update();
+        let intersections = |app: &App, entity| {
+            let mesh = app.world.get::<RaycastMesh<TestSet>>(entity).unwrap();
+            mesh.intersections().len()
+        };
+        assert_eq!(intersections(&app, mesh_2d), 1);
+        assert_eq!(intersections(&app, simplified), 1);
+
+        // Move both meshes to the side, like a hot reload of their assets.
+        let moved = |min: Vec3| shape::Box::from_corners(min, min + Vec3::ONE).into();
+        let mut meshes = app.world.resource_mut::<Assets<Mesh>>();
+        meshes.insert(&quad, moved(Vec3::new(2.5, -0.5, -0.5)));
+        meshes.insert(&proxy, moved(Vec3::new(-0.5, 9.5, -0.5)));
+        app.world.run_system_once(Assets::<Mesh>::asset_events);
+        app.world
+            .run_system_once(clear_modified_mesh_intersections::<TestSet>);
+        assert_eq!(intersections(&app, mesh_2d), 0);
+        assert_eq!(intersections(&app, simplified), 0);
+
+        app.update();
+        let aabb = app.world.get::<Aabb>(mesh_2d).unwrap();
+        assert_eq!(Vec3::from(aabb.center), Vec3::new(3.0, 0.0, 0.0));
+        // The AABB is the bounds of the rendered mesh, not of its simplified mesh.
+        let aabb = app.world.get::<Aabb>(simplified).unwrap();
+        assert_eq!(Vec3::from(aabb.center), Vec3::ZERO);
+        assert_eq!(intersections(&app, simplified), 0);
+    }
+
+    #[test]
+    fn raycasts_that_skip_frames_drop_modified_cached_meshes() {
+        let mut app = test_app();
+        let quad = |size| {
+            let mut mesh: Mesh = shape::Quad::new(Vec2::splat(size)).into();
+            mesh.remove_attribute(Mesh::ATTRIBUTE_NORMAL);
+            mesh
+        };
+        let entity = spawn_mesh(&mut app, quad(1.0), Transform::IDENTITY);
+        // The smoothed meshes of meshes without normals are cached per mesh asset.
+        let smooth = SmoothRaycastNormals { angle: 0.5 };
+        app.world.entity_mut(entity).insert(smooth);
+        let handle = app.world.get::<Handle<Mesh>>(entity).unwrap().clone();
+        let mut state = SystemState::<Raycast>::new(&mut app.world);
+        let settings = RaycastSettings::default().with_visibility(RaycastVisibility::Ignore);
+        let ray = |x| Ray3d::new(Vec3::new(x, 0.0, 5.0), Vec3::NEG_Z);
+        app.update();
+        let mut raycast = state.get_mut(&mut app.world);
+        let (hit_entity, hit) = &raycast.cast_ray(ray(0.25), &settings)[0];
+        assert_eq!(*hit_entity, entity);
+        assert_eq!(hit.provenance().geometry, HitGeometry::SmoothedMesh);
+        assert!(raycast.cast_ray(ray(0.75), &settings).is_empty());
+
+        app.world
+            .resource_mut::<Assets<Mesh>>()
+            .insert(&handle, quad(2.0));
+        app.world.run_system_once(Assets::<Mesh>::asset_events);
+        app.update();
+        // The asset events are dropped after two updates, before the next raycast.
+        for _ in 0..2 {
+            app.world
+                .resource_mut::<Events<AssetEvent<Mesh>>>()
+                .update();
+        }
+        let mut raycast = state.get_mut(&mut app.world);
+        assert_eq!(raycast.cast_ray(ray(0.75), &settings)[0].0, entity);
+    }
+
+    #[test]
+    fn modified_meshes_are_raycast_with_their_new_triangles() {
+        let mut app = test_app();
+        app.init_asset::<SkinnedMeshInverseBindposes>();
+        let quad = |size| {
+            let mut mesh: Mesh = shape::Quad::new(Vec2::splat(size)).into();
+            let joint_indices = VertexAttributeValues::Uint16x4(vec![[0; 4]; 4]);
+            mesh.insert_attribute(Mesh::ATTRIBUTE_JOINT_INDEX, joint_indices);
+            mesh.insert_attribute(Mesh::ATTRIBUTE_JOINT_WEIGHT, vec![[1.0, 0.0, 0.0, 0.0]; 4]);
+            mesh
+        };
+        let plain = spawn_mesh(&mut app, quad(1.0), Transform::IDENTITY);
+        let handle = app.world.get::<Handle<Mesh>>(plain).unwrap().clone();
+        // The posed triangles of a skinned mesh are cached while its joints don't move.
+        let inverse_bindposes = app
+            .world
+            .resource_mut::<Assets<SkinnedMeshInverseBindposes>>()
+            .add(vec![Mat4::IDENTITY].into());
+        let joint = app
+            .world
+            .spawn(GlobalTransform::from_xyz(0.0, 0.0, -1.0))
+            .id();
+        let skinned = app
+            .world
+            .spawn((
+                handle.clone(),
+                Aabb::from_min_max(Vec3::splat(-0.5), Vec3::splat(0.5)),
+                GlobalTransform::from_xyz(0.0, 0.0, -1.0),
+                InheritedVisibility::VISIBLE,
+                ViewVisibility::default(),
+                RaycastMesh::<TestSet>::default(),
+                RaycastSkinnedMesh,
+                SkinnedMesh {
+                    inverse_bindposes,
+                    joints: vec![joint],
+                },
+            ))
+            .id();
+        let inside = spawn_source(&mut app, Transform::from_xyz(0.25, 0.0, 5.0));
+        let outside = spawn_source(&mut app, Transform::from_xyz(0.75, 0.0, 5.0));
+        for source in [inside, outside] {
+            app.world
+                .get_mut::<RaycastSource<TestSet>>(source)
+                .unwrap()
+                .should_early_exit = false;
+        }
+        let hit_entities = |app: &App, source| -> Vec<_> {
+            let hits = intersections(app, source);
+            hits.iter().map(|(entity, _)| *entity).collect()
+        };
+        app.update();
+        assert_eq!(hit_entities(&app, inside), [plain, skinned]);
+        assert!(hit_entities(&app, outside).is_empty());
+
+        // Deform the mesh in place, like a CPU-side edit, which sends its events before the next
+        // frame.
+        app.world
+            .resource_mut::<Assets<Mesh>>()
+            .insert(&handle, quad(2.0));
+        app.world.run_system_once(Assets::<Mesh>::asset_events);
+        app.update();
+        assert_eq!(hit_entities(&app, outside), [plain, skinned]);
+
+        // Once the asset is removed, its entities are skipped.
+        app.world.resource_mut::<Assets<Mesh>>().remove(&handle);
+        app.world.run_system_once(Assets::<Mesh>::asset_events);
+        app.update();
+        assert!(hit_entities(&app, inside).is_empty());
+        assert!(hit_entities(&app, outside).is_empty());
+    }
+
+    #[test]
+    fn streamed_hits_match_cast_ray() {
+        let mut app = test_app();
+        spawn_mesh(&mut app, shape::Cube::default().into(), Transform::IDENTITY);
+        // The bounds of this long, tilted quad are entered before the cube's, but it is hit behind
+        // the cube.
+        spawn_mesh(
+            &mut app,
+            shape::Quad::new(Vec2::new(20.0, 1.0)).into(),
+            Transform::from_xyz(0.0, 0.0, -2.0).with_rotation(Quat::from_rotation_y(1.4)),
+        );
+        spawn_mesh(
+            &mut app,
+            shape::Cube::default().into(),
+            Transform::from_xyz(0.0, 0.0, -5.0),
+        );
+        app.update();
+
+        let mut state = SystemState::<Raycast>::new(&mut app.world);
+        let mut raycast = state.get_mut(&mut app.world);
+        let settings = RaycastSettings::default()
+            .with_visibility(RaycastVisibility::Ignore)
+            .never_early_exit();
+        let ray = Ray3d::new(Vec3::new(0.0, 0.0, 5.0), Vec3::NEG_Z);
+        let expected: Vec<_> = raycast
+            .cast_ray(ray, &settings)
+            .iter()
+            .map(|(entity, hit)| (*entity, hit.distance()))
+            .collect();
+        assert_eq!(expected.len(), 3);
+
+        let mut streamed = Vec::new();
+        raycast.cast_ray_with(ray, &settings, |entity, hit| {
+            streamed.push((entity, hit.distance()));
+            ControlFlow::Continue(())
+        });
+        assert_eq!(streamed, expected);
+
+        streamed.clear();
+        raycast.cast_ray_with(ray, &settings, |entity, hit| {
+            streamed.push((entity, hit.distance()));
+            if streamed.len() == 2 {
+                ControlFlow::Break(())
+            } else {
+                ControlFlow::Continue(())
+            }
+        });
+        assert_eq!(streamed, expected[..2]);
+    }
+
+    #[test]
+    fn drained_intersections_keep_their_buffer() {
+        let mut app = test_app();
+        for i in 0..3 {
+            let transform = Transform::from_xyz(0.0, 0.0, -(i as f32));
+            spawn_mesh(&mut app, shape::Quad::default().into(), transform);
+        }
+        let source = spawn_source(&mut app, Transform::from_xyz(0.0, 0.0, 5.0));
+        app.world
+            .get_mut::<RaycastSource<TestSet>>(source)
+            .unwrap()
+            .should_early_exit = false;
+        app.update();
+
+        let summary = |hits: &[(Entity, IntersectionData)]| -> Vec<_> {
+            hits.iter().map(|(e, hit)| (*e, hit.distance())).collect()
+        };
+        let expected = summary(intersections(&app, source));
+        assert_eq!(expected.len(), 3);
+        let mut pick_source = app.world.get_mut::<RaycastSource<TestSet>>(source).unwrap();
+        let capacity = pick_source.intersections.capacity();
+        let drained: Vec<_> = pick_source.drain_intersections().collect();
+        assert_eq!(summary(&drained), expected);
+        assert!(pick_source.intersections().is_empty());
+        assert_eq!(pick_source.intersections.capacity(), capacity);
+
+        app.update();
+        assert_eq!(summary(intersections(&app, source)), expected);
+        let pick_source = app.world.get::<RaycastSource<TestSet>>(source).unwrap();
+        assert_eq!(pick_source.intersections.capacity(), capacity);
+    }
+
+    #[test]
+    fn provenance_describes_hits() {
+        let mut app = test_app();
+        let mesh = spawn_mesh(&mut app, shape::Cube::default().into(), Transform::IDENTITY);
+        let volume = spawn_mesh(
+            &mut app,
+            shape::Cube::default().into(),
+            Transform::from_xyz(3.0, 0.0, 0.0),
+        );
+        app.world.entity_mut(volume).insert(RaycastVolumeOnly);
+        let simplified = spawn_mesh(
+            &mut app,
+            shape::Cube::default().into(),
+            Transform::from_xyz(6.0, 0.0, 0.0),
+        );
+        let proxy = app
+            .world
+            .resource_mut::<Assets<Mesh>>()
+            .add(shape::Cube::default().into());
+        app.world
+            .entity_mut(simplified)
+            .insert((SimplifiedMesh { mesh: proxy }, NoBackfaceCulling));
+        let sources =
+            [0.0, 3.0, 6.0].map(|x| spawn_source(&mut app, Transform::from_xyz(x, 0.0, 5.0)));
+        app.update();
+
+        let expected = [
+            (
+                mesh,
+                RaycastAlgorithm::MollerTrumbore,
+                HitGeometry::Mesh,
+                true,
+            ),
+            (
+                volume,
+                RaycastAlgorithm::SlabTest,
+                HitGeometry::Volume,
+                false,
+            ),
+            (
+                simplified,
+                RaycastAlgorithm::MollerTrumbore,
+                HitGeometry::SimplifiedMesh,
+                false,
+            ),
+        ];
+        for (source, (entity, algorithm, geometry, backface_culling)) in
+            sources.into_iter().zip(expected)
+        {
+            let (hit_entity, hit) = &intersections(&app, source)[0];
+            assert_eq!(*hit_entity, entity);
+            let provenance = HitProvenance {
+                algorithm,
+                geometry,
+                backface_culling,
+            };
+            assert_eq!(hit.provenance(), provenance);
+        }
+    }
+
+    #[test]
+    fn stale_intersections_expire() {
+        let mut app = test_app();
+        spawn_mesh(&mut app, shape::Cube::default().into(), Transform::IDENTITY);
+        let source = spawn_source(&mut app, Transform::from_xyz(0.0, 0.0, 5.0));
+        app.update();
+        let mut time = *app.world.resource::<Time>();
+        let raycast_source = app.world.get::<RaycastSource<TestSet>>(source).unwrap();
+        assert_eq!(raycast_source.intersections_time(), Some(time.elapsed()));
+        assert_eq!(
+            raycast_source.intersections_newer_than(0.25, &time).len(),
+            1
+        );
+
+        time.advance_by(Duration::from_secs_f32(0.5));
+        assert!(raycast_source
+            .intersections_newer_than(0.25, &time)
+            .is_empty());
+        assert_eq!(raycast_source.intersections_newer_than(1.0, &time).len(), 1);
+        assert_eq!(raycast_source.intersections().len(), 1);
+
+        // A clock that is behind the intersections, such as a newly reset one, sees them as new.
+        app.world
+            .resource_mut::<Time>()
+            .advance_by(Duration::from_secs(1));
+        app.update();
+        let raycast_source = app.world.get::<RaycastSource<TestSet>>(source).unwrap();
+        let reset = Time::<()>::default();
+        assert_eq!(
+            raycast_source.intersections_newer_than(0.25, &reset).len(),
+            1
+        );
+    }
+
+    #[test]
+    fn last_hit_is_kept_until_its_entity_despawns() {
+        let mut app = test_app();
+        let cube = spawn_mesh(&mut app, shape::Cube::default().into(), Transform::IDENTITY);
+        let transform = Transform::from_xyz(0.0, 0.0, 5.0);
+        let strict = spawn_source(&mut app, transform);
+        let sticky = spawn_source(&mut app, transform);
+        app.world
+            .get_mut::<RaycastSource<TestSet>>(sticky)
+            .unwrap()
+            .keep_last_hit = true;
+        app.update();
+        let hit = intersections(&app, sticky)[0].clone();
+        let time = app.world.resource::<Time>().elapsed();
+
+        let move_cube = |app: &mut App, x| {
+            *app.world.get_mut::<GlobalTransform>(cube).unwrap() =
+                GlobalTransform::from_xyz(x, 0.0, 0.0);
+            app.update();
+        };
+        move_cube(&mut app, 5.0);
+        assert!(intersections(&app, strict).is_empty());
+        let source = app.world.get::<RaycastSource<TestSet>>(sticky).unwrap();
+        assert!(!source.is_current());
+        assert_eq!(source.intersections_time(), Some(time));
+        let (entity, kept) = &source.intersections()[0];
+        assert_eq!((*entity, kept.position()), (cube, hit.1.position()));
+
+        move_cube(&mut app, 0.25);
+        let source = app.world.get::<RaycastSource<TestSet>>(sticky).unwrap();
+        assert!(source.is_current());
+        assert_eq!(source.intersections().len(), 1);
+        assert_eq!(intersections(&app, strict).len(), 1);
+
+        move_cube(&mut app, 5.0);
+        app.world.despawn(cube);
+        app.update();
+        let source = app.world.get::<RaycastSource<TestSet>>(sticky).unwrap();
+        assert!(source.is_current());
+        assert!(source.intersections().is_empty());
+    }
+
+    #[test]
+    fn spawned_at_hit_follows_the_parent() {
+        let mut app = test_app();
+        app.add_plugins(bevy::transform::TransformPlugin);
+        let transform = Transform::from_rotation(Quat::from_rotation_y(0.5))
+            .with_scale(Vec3::new(3.0, 1.0, 0.5));
+        let cube = spawn_mesh(&mut app, shape::Cube::default().into(), transform);
+        app.world.entity_mut(cube).insert(transform);
+        let source = spawn_source(&mut app, Transform::from_xyz(0.2, 0.3, 5.0));
+        app.update();
+        let (hit_entity, hit) = intersections(&app, source)[0].clone();
+        assert_eq!(hit_entity, cube);
+
+        let mut queue = CommandQueue::default();
+        let child = Commands::new(&mut queue, &app.world)
+            .spawn_at_hit(Name::new("hole"), cube, &hit)
+            .id();
+        queue.apply(&mut app.world);
+        let pivot = app.world.get::<Parent>(child).unwrap().get();
+        assert_eq!(app.world.get::<Parent>(pivot).unwrap().get(), cube);
+
+        // The transform set by the command is the one computed by transform propagation.
+        let spawned = *app.world.get::<GlobalTransform>(child).unwrap();
+        app.update();
+        let propagated = *app.world.get::<GlobalTransform>(child).unwrap();
+        assert!(spawned.affine().abs_diff_eq(propagated.affine(), 1e-5));
+        assert!(propagated.translation().distance(hit.position()) < 1e-5);
+        let axes = propagated.affine().matrix3;
+        assert!(Vec3::from(axes.z_axis).distance(hit.normal()) < 1e-5);
+        assert!(axes.x_axis.dot(axes.y_axis).abs() < 1e-5);
+
+        let child = Commands::new(&mut queue, &app.world)
+            .spawn_at_hit_with(Name::new("sticker"), cube, &hit, Vec3::Y, 2.0)
+            .id();
+        queue.apply(&mut app.world);
+        let axes = app
+            .world
+            .get::<GlobalTransform>(child)
+            .unwrap()
+            .affine()
+            .matrix3;
+        assert!(Vec3::from(axes.y_axis).distance(2.0 * hit.normal()) < 1e-5);
+        assert!((axes.x_axis.length() - 2.0).abs() < 1e-5);
     }
 }
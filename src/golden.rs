@@ -0,0 +1,350 @@
+//! Utilities for regression testing picking against stored "golden" results.
+//!
+//! A list of [`PickSample`]s describes where a camera was and where the cursor pointed. Running
+//! [`simulate_picks`] on an app containing a scene builds the ray for each sample on a headless
+//! window and camera, the same way as a cursor [`RaycastSource`](crate::deferred::RaycastSource),
+//! without a renderer, and casts it into the scene. The resulting [`PickRecord`]s can be written
+//! with [`format_golden`], and later compared to the stored text with [`compare_golden`]:
+//!
+//! ```
+//! # use bevy::prelude::*;
+//! # use bevy_mod_raycast::{golden::*, prelude::*};
+//! # let mut app = App::new();
+//! # app.add_plugins((MinimalPlugins, AssetPlugin::default())).init_asset::<Mesh>();
+//! let viewport = Vec2::new(1280.0, 720.0);
+//! let samples = [PickSample::new(Transform::IDENTITY, default(), viewport, viewport / 2.0)];
+//! let settings = RaycastSettings::default().with_visibility(RaycastVisibility::Ignore);
+//! let picks = simulate_picks(&mut app, &samples, &settings);
+//! compare_golden(&picks, "miss\n", 1e-4).unwrap();
+//! ```
+//!
+//! The golden text has one line per sample: `miss`, or the position, distance, and name of the
+//! nearest hit, separated by spaces.
+
+use std::fmt;
+
+use bevy_app::App;
+use bevy_asset::{AssetEvent, Assets};
+use bevy_core::Name;
+use bevy_ecs::prelude::*;
+use bevy_ecs::system::{RunSystemOnce, SystemState};
+use bevy_math::{Vec2, Vec3};
+use bevy_render::{
+    camera::{camera_system, Camera, ManualTextureViews, Projection, RenderTarget},
+    texture::Image,
+};
+use bevy_transform::components::{GlobalTransform, Transform};
+use bevy_utils::default;
+use bevy_window::{Window, WindowCreated, WindowRef, WindowResized, WindowResolution};
+
+use crate::{immediate::Raycast, immediate::RaycastSettings, primitives::Ray3d};
+
+/// A recorded camera pose and cursor position, used to build a ray with [`PickSample::ray`].
+#[derive(Clone, Debug)]
+pub struct PickSample {
+    pub camera: Transform,
+    pub projection: Projection,
+    /// The logical size of the viewport, in pixels.
+    pub viewport_size: Vec2,
+    /// The cursor position, in logical pixels from the top left of the viewport.
+    pub cursor: Vec2,
+    /// The scale factor of the window, which is 1 by default.
+    pub scale_factor: f32,
+}
+
+impl PickSample {
+    pub fn new(
+        camera: Transform,
+        projection: Projection,
+        viewport_size: Vec2,
+        cursor: Vec2,
+    ) -> Self {
+        Self {
+            camera,
+            projection,
+            viewport_size,
+            cursor,
+            scale_factor: 1.0,
+        }
+    }
+
+    /// Set the scale factor of the window the sample is picked on.
+    pub fn with_scale_factor(mut self, scale_factor: f32) -> Self {
+        self.scale_factor = scale_factor;
+        self
+    }
+
+    /// Builds the ray under the cursor with [`Ray3d::from_screenspace`], like a cursor raycast
+    /// source, on a window and a camera spawned in the `world` for the sample and despawned
+    /// afterwards. The camera's viewport is computed by running Bevy's `camera_system`, so no
+    /// renderer is needed, and the resources it reads are added to the `world` if it is missing
+    /// them.
+    pub fn ray(&self, world: &mut World) -> Option<Ray3d> {
+        world.init_resource::<Assets<Image>>();
+        world.init_resource::<ManualTextureViews>();
+        world.init_resource::<Events<AssetEvent<Image>>>();
+        world.init_resource::<Events<WindowCreated>>();
+        world.init_resource::<Events<WindowResized>>();
+
+        // The resolution is created from its physical size, before the scale factor is set.
+        let physical_size = self.viewport_size * self.scale_factor;
+        let resolution = WindowResolution::new(physical_size.x, physical_size.y)
+            .with_scale_factor_override(self.scale_factor as f64);
+        let window = world
+            .spawn(Window {
+                resolution,
+                ..default()
+            })
+            .id();
+        let camera = Camera {
+            target: RenderTarget::Window(WindowRef::Entity(window)),
+            ..default()
+        };
+        let transform = GlobalTransform::from(self.camera);
+        let camera = world
+            .spawn((camera, self.projection.clone(), transform))
+            .id();
+        world.run_system_once(camera_system::<Projection>);
+
+        let ray = Ray3d::from_screenspace(
+            self.cursor,
+            world.get::<Camera>(camera).unwrap(),
+            &transform,
+            world.get::<Window>(window).unwrap(),
+        );
+        world.despawn(camera);
+        world.despawn(window);
+        ray
+    }
+}
+
+/// The nearest hit of a [`PickSample`], as stored in a golden file.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PickRecord {
+    /// The [`Name`] of the entity that was hit, or its [`Entity`] if it has no name.
+    pub name: String,
+    pub position: Vec3,
+    pub distance: f32,
+}
+
+impl PickRecord {
+    fn approx_eq(&self, other: &Self, tolerance: f32) -> bool {
+        self.name == other.name
+            && self.position.abs_diff_eq(other.position, tolerance)
+            && (self.distance - other.distance).abs() <= tolerance
+    }
+}
+
+/// Casts the ray of each sample into the scene of `app`, returning the nearest hit of each sample.
+/// The app is updated once first, so the scene's startup systems have run and its transforms are
+/// propagated.
+pub fn simulate_picks(
+    app: &mut App,
+    samples: &[PickSample],
+    settings: &RaycastSettings,
+) -> Vec<Option<PickRecord>> {
+    app.update();
+    let rays: Vec<_> = samples
+        .iter()
+        .map(|sample| sample.ray(&mut app.world))
+        .collect();
+    let mut state = SystemState::<(Raycast, Query<&Name>)>::new(&mut app.world);
+    let (mut raycast, names) = state.get_mut(&mut app.world);
+    rays.into_iter()
+        .map(|ray| {
+            let ray = ray?;
+            let (entity, hit) = raycast.cast_ray(ray, settings).first()?;
+            Some(PickRecord {
+                name: names
+                    .get(*entity)
+                    .map_or_else(|_| format!("{entity:?}"), |name| name.to_string()),
+                position: hit.position(),
+                distance: hit.distance(),
+            })
+        })
+        .collect()
+}
+
+/// Formats picks as golden text, to be compared with [`compare_golden`].
+pub fn format_golden(picks: &[Option<PickRecord>]) -> String {
+    let mut text = String::new();
+    for pick in picks {
+        match pick {
+            Some(pick) => {
+                let Vec3 { x, y, z } = pick.position;
+                text += &format!("{x} {y} {z} {} {}\n", pick.distance, pick.name);
+            }
+            None => text += "miss\n",
+        }
+    }
+    text
+}
+
+fn parse_golden_line(line: &str) -> Option<Option<PickRecord>> {
+    if line == "miss" {
+        return Some(None);
+    }
+    let mut fields = line.splitn(5, ' ');
+    let mut number = || fields.next()?.parse::<f32>().ok();
+    let position = Vec3::new(number()?, number()?, number()?);
+    let distance = number()?;
+    let name = fields.next()?.to_string();
+    Some(Some(PickRecord {
+        name,
+        position,
+        distance,
+    }))
+}
+
+/// A sample whose pick differs from the golden text. See [`compare_golden`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct GoldenMismatch {
+    /// The index of the sample, or of the line in the golden text.
+    pub sample: usize,
+    /// The golden pick, or `None` if the golden text is missing this sample.
+    pub expected: Option<Option<PickRecord>>,
+    /// The pick, or `None` if there are fewer picks than lines in the golden text.
+    pub actual: Option<Option<PickRecord>>,
+}
+
+impl fmt::Display for GoldenMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "sample {}: expected {:?}, got {:?}",
+            self.sample, self.expected, self.actual
+        )
+    }
+}
+
+/// Compares picks to golden text written by [`format_golden`]. Positions and distances may differ
+/// from the golden values by at most `tolerance`. Lines of the golden text that can't be parsed
+/// are reported as mismatches with an `expected` value of `None`.
+pub fn compare_golden(
+    picks: &[Option<PickRecord>],
+    golden: &str,
+    tolerance: f32,
+) -> Result<(), Vec<GoldenMismatch>> {
+    let expected: Vec<_> = golden.lines().map(parse_golden_line).collect();
+    let mismatches: Vec<_> = (0..picks.len().max(expected.len()))
+        .filter_map(|sample| {
+            let expected = expected.get(sample).cloned().flatten();
+            let actual = picks.get(sample).cloned();
+            let matches = match (&expected, &actual) {
+                (Some(Some(expected)), Some(Some(actual))) => expected.approx_eq(actual, tolerance),
+                (Some(None), Some(None)) => true,
+                _ => false,
+            };
+            (!matches).then_some(GoldenMismatch {
+                sample,
+                expected,
+                actual,
+            })
+        })
+        .collect();
+    if mismatches.is_empty() {
+        Ok(())
+    } else {
+        Err(mismatches)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::{prelude::*, render::primitives::Aabb, transform::TransformPlugin};
+
+    use super::*;
+    use crate::immediate::RaycastVisibility;
+
+    const VIEWPORT: Vec2 = Vec2::new(1280.0, 720.0);
+
+    fn scene_app(name: &'static str, mesh: Mesh, transform: Transform) -> App {
+        let mut app = App::new();
+        app.add_plugins((MinimalPlugins, AssetPlugin::default(), TransformPlugin))
+            .init_asset::<Mesh>();
+        let aabb: Aabb = mesh.compute_aabb().unwrap();
+        let handle = app.world.resource_mut::<Assets<Mesh>>().add(mesh);
+        app.world.spawn((
+            Name::new(name),
+            handle,
+            aabb,
+            transform,
+            GlobalTransform::default(),
+            InheritedVisibility::VISIBLE,
+            ViewVisibility::default(),
+        ));
+        app
+    }
+
+    fn samples() -> Vec<PickSample> {
+        let cursors = [
+            VIEWPORT / 2.0,
+            Vec2::new(680.0, 390.0),
+            Vec2::new(600.0, 300.0),
+            Vec2::new(640.0, 250.0),
+            Vec2::new(100.0, 100.0),
+        ];
+        let camera =
+            Transform::from_xyz(0.0, 0.5, 0.0).looking_at(Vec3::new(0.0, 0.0, -6.0), Vec3::Y);
+        cursors
+            .into_iter()
+            .flat_map(|cursor| {
+                [Transform::IDENTITY, camera]
+                    .map(|camera| PickSample::new(camera, Projection::default(), VIEWPORT, cursor))
+            })
+            .collect()
+    }
+
+    fn check_scene(mut app: App, golden: &str) {
+        let settings = RaycastSettings::default().with_visibility(RaycastVisibility::Ignore);
+        // The cursor is in logical pixels, so the scale factor doesn't change the picks.
+        for scale_factor in [1.0, 2.0] {
+            let samples: Vec<_> = samples()
+                .into_iter()
+                .map(|sample| sample.with_scale_factor(scale_factor))
+                .collect();
+            let picks = simulate_picks(&mut app, &samples, &settings);
+            if let Err(mismatches) = compare_golden(&picks, golden, 1e-4) {
+                let mismatches: Vec<_> = mismatches.iter().map(ToString::to_string).collect();
+                panic!(
+                    "scale factor {scale_factor}:\n{}\nnew golden text:\n{}",
+                    mismatches.join("\n"),
+                    format_golden(&picks)
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn minimal_scene_golden() {
+        let mesh = shape::Capsule::default().into();
+        let app = scene_app("capsule", mesh, Transform::from_xyz(0.0, 0.0, -7.0));
+        check_scene(app, include_str!("golden/minimal.txt"));
+    }
+
+    #[test]
+    fn mouse_picking_scene_golden() {
+        let mesh = shape::UVSphere::default().into();
+        let app = scene_app("sphere", mesh, Transform::from_xyz(0.0, 0.0, -5.0));
+        check_scene(app, include_str!("golden/mouse_picking.txt"));
+    }
+
+    #[test]
+    fn golden_round_trips() {
+        let picks = vec![
+            Some(PickRecord {
+                name: "a named entity".to_string(),
+                position: Vec3::new(0.5, -1.0, 2.25),
+                distance: 3.0,
+            }),
+            None,
+        ];
+        let golden = format_golden(&picks);
+        assert_eq!(compare_golden(&picks, &golden, 0.0), Ok(()));
+
+        let mismatches = compare_golden(&picks[..1], &golden, 0.0).unwrap_err();
+        assert_eq!(mismatches.len(), 1);
+        assert_eq!(mismatches[0].sample, 1);
+        assert_eq!(mismatches[0].actual, None);
+    }
+}
@@ -7,33 +7,73 @@
 //! when you call the `cast_ray` method. See the [`Raycast`] documentation for more details. You
 //! don't even need to add a plugin to your application.
 
-use bevy_asset::{Assets, Handle};
+use std::{num::NonZeroUsize, ops::ControlFlow};
+
+use bevy_asset::{AssetEvent, AssetId, Assets, Handle};
 use bevy_ecs::{prelude::*, system::lifetimeless::Read, system::SystemParam};
+use bevy_math::{Mat4, Vec2, Vec3, Vec3A};
 use bevy_reflect::Reflect;
-use bevy_render::{prelude::*, primitives::Aabb};
-use bevy_transform::components::GlobalTransform;
-use bevy_utils::{tracing::*, FloatOrd};
+use bevy_render::{
+    mesh::{
+        morph::MeshMorphWeights,
+        skinning::{SkinnedMesh, SkinnedMeshInverseBindposes},
+    },
+    prelude::*,
+    primitives::{Aabb, Frustum, HalfSpace},
+};
+use bevy_transform::components::{GlobalTransform, Transform};
+use bevy_utils::{tracing::*, Duration, FloatOrd, HashMap, HashSet, Instant};
+use bevy_window::Window;
 
 #[cfg(feature = "debug")]
-use {
-    bevy_gizmos::gizmos::Gizmos,
-    bevy_math::{Quat, Vec3},
-};
+use {bevy_gizmos::gizmos::Gizmos, bevy_math::Quat};
 
-use crate::prelude::*;
+use crate::{
+    prelude::*,
+    primitives::{
+        has_invertible_projection, screenspace_to_ndc, HitGeometry, HitProvenance,
+        RaycastAlgorithm, Triangle,
+    },
+    raycast::{
+        blend_morph_targets, check_mesh_layout, check_mesh_normals, decode_mesh_positions,
+        is_line_topology, is_triangle_topology, mesh_morph_targets, mesh_triangles_transformed,
+        ray_any_intersection_over_mesh, ray_intersection_over_mesh,
+        ray_intersection_over_mesh_instances, ray_intersections_over_mesh,
+        ray_line_mesh_intersection, ray_mesh_closest_approach, ray_mesh_intersections, skin_mesh,
+        smooth_normals, MaxSlope, MeshExtractError, MeshRaycastOptions,
+    },
+    MeshAssetChanges,
+};
 
 /// How a raycast should handle visibility
-#[derive(Clone, Copy, Reflect)]
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Reflect)]
 pub enum RaycastVisibility {
-    /// Completely ignore visibility checks. Hidden items can still be raycasted against.
+    /// Completely ignore visibility checks. Hidden items can still be raycasted against, and so can
+    /// entities without visibility components, such as invisible trigger volumes.
     Ignore,
     /// Only raycast against entities that are visible in the hierarchy; see [`Visibility`].
+    /// Entities without an [`InheritedVisibility`] are never visible.
     MustBeVisible,
     /// Only raycast against entities that are visible in the hierarchy and visible to a camera or
-    /// light; see [`Visibility`].
+    /// light; see [`Visibility`]. Entities without a [`ViewVisibility`] are never in view.
     MustBeVisibleAndInView,
 }
 
+impl RaycastVisibility {
+    /// Returns `true` if an entity with this visibility should be raycasted.
+    fn allows(
+        self,
+        inherited: Option<&InheritedVisibility>,
+        view: Option<&ViewVisibility>,
+    ) -> bool {
+        match self {
+            RaycastVisibility::Ignore => true,
+            RaycastVisibility::MustBeVisible => inherited.is_some_and(|inherited| inherited.get()),
+            RaycastVisibility::MustBeVisibleAndInView => view.is_some_and(|view| view.get()),
+        }
+    }
+}
+
 /// Settings for a raycast.
 #[derive(Clone)]
 pub struct RaycastSettings<'a> {
@@ -45,6 +85,63 @@ pub struct RaycastSettings<'a> {
     /// A function that is run every time a hit is found. Raycasting will continue to check for hits
     /// along the ray as long as this returns false.
     pub early_exit_test: &'a dyn Fn(Entity) -> bool,
+    /// How to treat backfaces of entities that don't have a [`NoBackfaceCulling`] or [`HitFaces`]
+    /// component.
+    pub backfaces: Backfaces,
+    /// Grows the AABB of every entity by this distance, in world space, before checking if the ray
+    /// intersects it.
+    pub aabb_inflation: f32,
+    /// The maximum amount of time to spend testing meshes for intersections. Once the budget is
+    /// exceeded, the remaining entities are skipped.
+    pub time_budget: Option<Duration>,
+    /// When set, only these entities are raycasted. Short lists are culled directly, without
+    /// iterating over every entity in the world. Entities in the list that were despawned or can't
+    /// be raycasted, such as those without a mesh, are skipped, and entities listed more than once
+    /// are only raycasted once.
+    pub targets: Option<&'a [Entity]>,
+    /// Meshes with more triangles than this are slow to raycast, and a warning is logged the first
+    /// time each one is tested. Use `None` to disable the warning.
+    pub large_mesh_threshold: Option<usize>,
+    /// When set, mesh triangles that are steeper than this are skipped, and the ray continues to
+    /// the triangles behind them.
+    pub max_slope: Option<MaxSlope>,
+    /// Entities and triangles closer than this along the ray are ignored, such as the body of a
+    /// character the ray starts inside of. An entity whose AABB extends beyond this distance is
+    /// still tested, even if the ray enters the AABB before it.
+    pub min_distance: f32,
+    /// When set, entities and triangles farther than this along the ray are ignored. An entity
+    /// whose AABB the ray enters within this distance is still tested, even if the AABB extends
+    /// beyond it.
+    pub max_distance: Option<f32>,
+    /// When `true`, the raycast stops at the first intersection it finds, and returns only that
+    /// one, without testing the rest of the entities or triangles in the path of the ray. This is
+    /// much faster when only whether anything is hit matters, such as for line of sight checks.
+    ///
+    /// The returned intersection is unordered: it can be any intersection along the ray, not
+    /// necessarily the nearest. Entities are tested in the order the ray enters their AABBs, and
+    /// triangles in the order they are stored in, so the same scene and ray always return the same
+    /// intersection.
+    pub any_hit: bool,
+    /// The maximum number of intersections recorded with the triangles of each mesh, nearest
+    /// first. Defaults to 1, so only the nearest intersection of each mesh is recorded. Larger
+    /// values record every triangle the ray passes through, such as where it enters and exits a
+    /// closed mesh, along with backfaces if they are included. Intersections beyond the nearest
+    /// hit of a blocking entity are still discarded, so disable the early exit test to find where a
+    /// ray exits the nearest entity.
+    pub max_hits_per_mesh: usize,
+    /// When `true`, only the AABBs of entities are tested, as if every entity had a
+    /// [`RaycastVolumeOnly`] component.
+    pub volume_only: bool,
+    /// Hits closer to the ray origin than this are ignored, so a ray cast from a previous hit, such
+    /// as when bouncing a ray, doesn't hit the same surface again at a distance of zero. This is
+    /// scaled by the largest coordinate of the ray origin, when it is larger than one, to match
+    /// the precision of the origin. Defaults to [`DEFAULT_SELF_HIT_EPSILON`].
+    pub self_hit_epsilon: f32,
+    /// When set, meshes made of lines, such as a `LineList` or a `LineStrip`, are hit where the ray
+    /// passes within this distance of one of their segments, in world space. See
+    /// [`ray_line_mesh_intersection`]. Line meshes are skipped when this is `None`, the default.
+    /// Triangle meshes are not affected.
+    pub line_tolerance: Option<f32>,
 }
 
 impl<'a> RaycastSettings<'a> {
@@ -66,6 +163,89 @@ impl<'a> RaycastSettings<'a> {
         self
     }
 
+    /// Set how backfaces are treated for entities without a [`NoBackfaceCulling`] or [`HitFaces`]
+    /// component.
+    pub fn with_backfaces(mut self, backfaces: Backfaces) -> Self {
+        self.backfaces = backfaces;
+        self
+    }
+
+    /// Set the distance AABBs are grown by before checking if the ray intersects them.
+    pub fn with_aabb_inflation(mut self, aabb_inflation: f32) -> Self {
+        self.aabb_inflation = aabb_inflation;
+        self
+    }
+
+    /// Set the maximum amount of time to spend testing meshes for intersections.
+    pub fn with_time_budget(mut self, time_budget: Option<Duration>) -> Self {
+        self.time_budget = time_budget;
+        self
+    }
+
+    /// Only raycast against the `targets` entities.
+    pub fn with_targets(mut self, targets: &'a [Entity]) -> Self {
+        self.targets = Some(targets);
+        self
+    }
+
+    /// Set the triangle count above which a warning is logged for slow meshes, or `None` to disable
+    /// the warning.
+    pub fn with_large_mesh_threshold(mut self, large_mesh_threshold: Option<usize>) -> Self {
+        self.large_mesh_threshold = large_mesh_threshold;
+        self
+    }
+
+    /// Only hit mesh triangles with a normal within `angle` radians of `up`.
+    pub fn with_max_slope(mut self, angle: f32, up: Vec3) -> Self {
+        self.max_slope = Some(MaxSlope::new(angle, up));
+        self
+    }
+
+    /// Ignore entities and triangles closer than `min_distance` along the ray.
+    pub fn with_min_distance(mut self, min_distance: f32) -> Self {
+        self.min_distance = min_distance;
+        self
+    }
+
+    /// Ignore entities and triangles farther than `max_distance` along the ray.
+    pub fn with_max_distance(mut self, max_distance: f32) -> Self {
+        self.max_distance = Some(max_distance);
+        self
+    }
+
+    /// Stop at the first intersection found, instead of finding the nearest one. See
+    /// [`RaycastSettings::any_hit`].
+    pub fn with_any_hit(mut self, any_hit: bool) -> Self {
+        self.any_hit = any_hit;
+        self
+    }
+
+    /// Record up to `max_hits_per_mesh` intersections with the triangles of each mesh. See
+    /// [`RaycastSettings::max_hits_per_mesh`].
+    pub fn with_max_hits_per_mesh(mut self, max_hits_per_mesh: usize) -> Self {
+        self.max_hits_per_mesh = max_hits_per_mesh;
+        self
+    }
+
+    /// Only test the AABBs of entities, instead of their meshes.
+    pub fn with_volume_only(mut self, volume_only: bool) -> Self {
+        self.volume_only = volume_only;
+        self
+    }
+
+    /// Ignore mesh hits closer to the ray origin than this. See
+    /// [`RaycastSettings::self_hit_epsilon`].
+    pub fn with_self_hit_epsilon(mut self, self_hit_epsilon: f32) -> Self {
+        self.self_hit_epsilon = self_hit_epsilon;
+        self
+    }
+
+    /// Pick line meshes within this distance of the ray. See [`RaycastSettings::line_tolerance`].
+    pub fn with_line_tolerance(mut self, line_tolerance: Option<f32>) -> Self {
+        self.line_tolerance = line_tolerance;
+        self
+    }
+
     /// This raycast should exit as soon as the nearest hit is found.
     pub fn always_early_exit(self) -> Self {
         self.with_early_exit_test(&|_| true)
@@ -83,14 +263,156 @@ impl<'a> Default for RaycastSettings<'a> {
             visibility: RaycastVisibility::MustBeVisibleAndInView,
             filter: &|_| true,
             early_exit_test: &|_| true,
+            backfaces: Backfaces::Cull,
+            aabb_inflation: 0.0,
+            time_budget: None,
+            targets: None,
+            large_mesh_threshold: Some(DEFAULT_LARGE_MESH_THRESHOLD),
+            max_slope: None,
+            min_distance: 0.0,
+            max_distance: None,
+            any_hit: false,
+            max_hits_per_mesh: 1,
+            volume_only: false,
+            self_hit_epsilon: DEFAULT_SELF_HIT_EPSILON,
+            line_tolerance: None,
         }
     }
 }
 
+/// The result of [`Raycast::cast_ray_with_outcome`]: the intersections, along with the number of
+/// entities that could not be tested. A raycast that skipped entities may have missed hits, even if
+/// [`hits`](Self::hits) is not empty.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct CastOutcome<'a> {
+    /// The intersections, sorted nearest first, as returned by [`Raycast::cast_ray`].
+    pub hits: &'a [(Entity, IntersectionData)],
+    /// The number of entities that were skipped because their mesh asset was not loaded yet.
+    pub skipped_unready: usize,
+    /// The number of entities that were skipped because the
+    /// [`PrimitiveTopology`](bevy_render::render_resource::PrimitiveTopology) of their mesh isn't
+    /// `TriangleList` or `TriangleStrip`.
+    pub skipped_unsupported_topology: usize,
+    /// The number of entities that were skipped because their mesh can't be read, such as a mesh
+    /// without positions. See [`HitExplanation::InvalidMesh`].
+    pub skipped_invalid_mesh: usize,
+    /// `true` if the [`RaycastSettings::time_budget`] ran out before every entity was tested.
+    pub truncated_by_budget: bool,
+}
+
+impl<'a> CastOutcome<'a> {
+    /// Returns `true` if every entity whose AABB was intersected was tested.
+    pub fn is_complete(&self) -> bool {
+        self.skipped_unready == 0
+            && self.skipped_unsupported_topology == 0
+            && self.skipped_invalid_mesh == 0
+            && !self.truncated_by_budget
+    }
+}
+
+/// The first intersection along a polyline, found with [`Raycast::cast_polyline`].
+#[derive(Clone, Debug)]
+pub struct PolylineHit {
+    /// The entity that was hit.
+    pub entity: Entity,
+    /// The intersection, where the distance is measured from the start of the segment that hit.
+    pub intersection: IntersectionData,
+    /// The index of the segment that hit, where segment `i` goes from point `i` to point `i + 1`.
+    pub segment: usize,
+    /// How far along the segment the hit is, from `0.0` at its start to `1.0` at its end.
+    pub segment_fraction: f32,
+    /// The distance along the whole polyline to the hit.
+    pub distance: f32,
+}
+
+/// The geometry passed through by a segment, found with [`Raycast::cast_thickness`].
+#[derive(Clone, Debug, Default)]
+pub struct SegmentThickness {
+    /// The total length of the segment that is inside geometry.
+    pub total: f32,
+    /// The thickness of each entity that the segment passed through, sorted by the distance to
+    /// where the segment first hits it.
+    pub entities: Vec<EntityThickness>,
+}
+
+/// The thickness of one entity passed through by a segment. See [`SegmentThickness`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct EntityThickness {
+    pub entity: Entity,
+    /// The length of the segment that is inside this entity.
+    pub thickness: f32,
+    /// `true` if a hit on this entity could not be paired with an exit, because the mesh isn't
+    /// closed or the segment starts or ends inside it. Each unpaired hit adds the fallback
+    /// thickness instead.
+    pub is_open: bool,
+}
+
+/// Why an entity was or wasn't hit by a raycast, found with [`Raycast::explain`]. A raycast tests an
+/// entity in stages, in the order of these variants, and the explanation is the first stage that
+/// the entity didn't pass.
+#[derive(Clone, Debug)]
+pub enum HitExplanation {
+    /// The entity isn't in the raycasting set of the source, because it doesn't have a
+    /// [`RaycastMesh<T>`](crate::deferred::RaycastMesh). This is only reported by
+    /// [`RaycastSource::explanation`](crate::deferred::RaycastSource::explanation).
+    NotInRaycastSet,
+    /// The entity is missing a component needed to be raycasted: a mesh, an [`Aabb`], or a
+    /// [`GlobalTransform`]. Entities with an [`InstancedRaycast`] are only tested by
+    /// [`Raycast::cast_ray_instanced`].
+    NotRaycastable,
+    /// The entity was skipped by the [`RaycastSettings::visibility`] setting.
+    Invisible,
+    /// The entity was excluded by the [`RaycastSettings::filter`] or
+    /// [`RaycastSettings::targets`].
+    Filtered,
+    /// The ray doesn't intersect the entity's [`Aabb`], missing it by about `miss_distance`,
+    /// measured from the point on the ray nearest the center of the AABB.
+    OutsideBounds { miss_distance: f32 },
+    /// The ray enters the entity's [`Aabb`] `distance` along the ray, beyond the
+    /// [`RaycastSettings::max_distance`].
+    BeyondMaxDistance { distance: f32 },
+    /// The ray exits the entity's [`Aabb`] `distance` along the ray, before the
+    /// [`RaycastSettings::min_distance`].
+    BeforeMinDistance { distance: f32 },
+    /// The entity's mesh asset isn't loaded.
+    MeshNotLoaded,
+    /// The [`PrimitiveTopology`](bevy_render::render_resource::PrimitiveTopology) of the entity's
+    /// mesh isn't `TriangleList` or `TriangleStrip`.
+    UnsupportedTopology,
+    /// The entity's mesh can't be read, such as a mesh without a
+    /// [`Mesh::ATTRIBUTE_POSITION`], for this reason.
+    InvalidMesh(MeshExtractError),
+    /// The [`RaycastSettings::time_budget`] ran out before the entity was tested.
+    TimeBudgetExceeded,
+    /// The ray intersects the entity's AABB, but none of its triangles.
+    NoTriangleHit,
+    /// An entity that blocks the ray, according to the [`RaycastSettings::early_exit_test`], was
+    /// hit `distance` along the ray, closer than any hit on this entity.
+    Occluded { by: Entity, distance: f32 },
+    /// The entity was hit.
+    Hit(Box<IntersectionData>),
+}
+
+/// The default [`RaycastSettings::self_hit_epsilon`], a few times the precision of an `f32`.
+pub const DEFAULT_SELF_HIT_EPSILON: f32 = 4.0 * f32::EPSILON;
+
+/// The default [`RaycastSettings::large_mesh_threshold`].
+pub const DEFAULT_LARGE_MESH_THRESHOLD: usize = 100_000;
+
+/// Hits on the same entity closer than this are treated as one hit by [`Raycast::cast_thickness`].
+const THICKNESS_EPSILON: f32 = 1e-5;
+
+/// Target lists up to this length are culled by looking up each entity, instead of iterating over
+/// every entity in parallel.
+const SMALL_TARGET_LIST: usize = 32;
+
 #[cfg(feature = "2d")]
-type MeshFilter = Or<(With<Handle<Mesh>>, With<bevy_sprite::Mesh2dHandle>)>;
+type MeshFilter = (
+    Or<(With<Handle<Mesh>>, With<bevy_sprite::Mesh2dHandle>)>,
+    Without<InstancedRaycast>,
+);
 #[cfg(not(feature = "2d"))]
-type MeshFilter = With<Handle<Mesh>>;
+type MeshFilter = (With<Handle<Mesh>>, Without<InstancedRaycast>);
 
 /// Add this raycasting [`SystemParam`] to your system to raycast into the world with an
 /// immediate-mode API. Call `cast_ray` to immediately perform a raycast and get a result. Under the
@@ -154,8 +476,8 @@ pub struct Raycast<'w, 's> {
         'w,
         's,
         (
-            Read<InheritedVisibility>,
-            Read<ViewVisibility>,
+            Option<Read<InheritedVisibility>>,
+            Option<Read<ViewVisibility>>,
             Read<Aabb>,
             Read<GlobalTransform>,
             Entity,
@@ -173,6 +495,76 @@ pub struct Raycast<'w, 's> {
             Read<GlobalTransform>,
         ),
     >,
+    #[doc(hidden)]
+    pub volume_only_query: Query<'w, 's, (), With<RaycastVolumeOnly>>,
+    #[doc(hidden)]
+    pub images: Option<Res<'w, Assets<Image>>>,
+    #[doc(hidden)]
+    pub morph_query: Query<'w, 's, Read<MeshMorphWeights>, With<RaycastMorphTargets>>,
+    #[doc(hidden)]
+    pub morphed_meshes: Local<'s, HashMap<Entity, (AssetId<Mesh>, Vec<f32>, Mesh)>>,
+    #[doc(hidden)]
+    pub skin_query: Query<'w, 's, Read<SkinnedMesh>, With<RaycastSkinnedMesh>>,
+    #[doc(hidden)]
+    pub joint_query: Query<'w, 's, Read<GlobalTransform>>,
+    #[doc(hidden)]
+    pub inverse_bindposes: Option<Res<'w, Assets<SkinnedMeshInverseBindposes>>>,
+    #[doc(hidden)]
+    pub skinned_meshes: Local<'s, HashMap<Entity, (AssetId<Mesh>, Vec<Mat4>, Vec<f32>, Mesh)>>,
+    #[doc(hidden)]
+    pub incomplete_skins: Local<'s, HashSet<Entity>>,
+    #[doc(hidden)]
+    pub large_meshes: Local<'s, HashMap<AssetId<Mesh>, (Entity, usize)>>,
+    // The setting named by the warning about large meshes, when it isn't
+    // `RaycastSettings::large_mesh_threshold`.
+    #[doc(hidden)]
+    pub large_mesh_setting: Local<'s, Option<&'static str>>,
+    #[doc(hidden)]
+    pub unraycastable_meshes: Local<'s, HashSet<AssetId<Mesh>>>,
+    #[doc(hidden)]
+    pub sections_query: Query<'w, 's, Read<RaycastSections>>,
+    #[doc(hidden)]
+    pub smooth_query: Query<'w, 's, Read<SmoothRaycastNormals>>,
+    #[doc(hidden)]
+    pub winding_query: Query<'w, 's, Read<RaycastWinding>>,
+    #[doc(hidden)]
+    pub offset_query: Query<'w, 's, Read<RaycastTransformOffset>>,
+    #[doc(hidden)]
+    pub screen_size_query: Query<'w, 's, Read<RaycastScreenSizeScale>>,
+    #[doc(hidden)]
+    pub hit_faces_query: Query<'w, 's, Read<HitFaces>>,
+    #[doc(hidden)]
+    pub swept_query: Query<'w, 's, Read<RaycastSweptTarget>>,
+    #[doc(hidden)]
+    pub smoothed_meshes: Local<'s, HashMap<AssetId<Mesh>, (f32, Mesh)>>,
+    #[doc(hidden)]
+    pub decoder_query: Query<'w, 's, Read<RaycastPositionDecoder>>,
+    #[doc(hidden)]
+    pub decoded_meshes: Local<'s, HashMap<AssetId<Mesh>, (RaycastPositionDecoder, Mesh)>>,
+    #[doc(hidden)]
+    pub mesh_events: EventReader<'w, 's, AssetEvent<Mesh>>,
+    #[doc(hidden)]
+    pub mesh_changes: Option<Res<'w, MeshAssetChanges>>,
+    #[doc(hidden)]
+    pub seen_mesh_changes: Local<'s, HashMap<AssetId<Mesh>, u32>>,
+    #[doc(hidden)]
+    pub instance_hits: Local<'s, Vec<(Entity, usize, IntersectionData)>>,
+    #[doc(hidden)]
+    pub instanced_query: Query<
+        'w,
+        's,
+        (
+            Read<InstancedRaycast>,
+            Option<Read<Handle<Mesh>>>,
+            Option<Read<SimplifiedMesh>>,
+            Option<Read<NoBackfaceCulling>>,
+            Option<Read<Aabb>>,
+            Option<Read<InheritedVisibility>>,
+            Option<Read<ViewVisibility>>,
+            Read<GlobalTransform>,
+            Entity,
+        ),
+    >,
     #[cfg(feature = "2d")]
     #[doc(hidden)]
     pub mesh2d_query: Query<
@@ -228,98 +620,1475 @@ impl<'w, 's> Raycast<'w, 's> {
         ray: Ray3d,
         settings: &RaycastSettings,
     ) -> &[(Entity, IntersectionData)] {
-        let ray_cull = info_span!("ray culling");
-        let ray_cull_guard = ray_cull.enter();
+        self.cast_ray_with_outcome(ray, settings).hits
+    }
 
-        self.hits.clear();
-        self.culled_list.clear();
-        self.output.clear();
+    /// Like [`Raycast::cast_ray`], but also reports whether any entities were skipped, so an empty
+    /// list of hits can be told apart from a raycast that was incomplete. See [`CastOutcome`].
+    pub fn cast_ray_with_outcome(
+        &mut self,
+        ray: Ray3d,
+        settings: &RaycastSettings,
+    ) -> CastOutcome<'_> {
+        self.cull_aabbs(ray, settings, 0.0);
+        self.cast_ray_explained(ray, settings, None, None).0
+    }
 
-        // Check all entities to see if the ray intersects the AABB, use this to build a short list
-        // of entities that are in the path of the ray.
-        let (aabb_hits_tx, aabb_hits_rx) = crossbeam_channel::unbounded::<(FloatOrd, Entity)>();
-        let visibility_setting = settings.visibility;
-        self.culling_query.par_iter().for_each(
-            |(inherited_visibility, view_visibility, aabb, transform, entity)| {
-                let should_raycast = match visibility_setting {
-                    RaycastVisibility::Ignore => true,
-                    RaycastVisibility::MustBeVisible => inherited_visibility.get(),
-                    RaycastVisibility::MustBeVisibleAndInView => view_visibility.get(),
-                };
-                if should_raycast {
-                    if let Some([near, _]) = ray
-                        .intersects_aabb(aabb, &transform.compute_matrix())
-                        .filter(|[_, far]| *far >= 0.0)
-                    {
-                        aabb_hits_tx.send((FloatOrd(near), entity)).ok();
-                    }
+    /// Casts the `ray` like [`Raycast::cast_ray`], but instead of building a list of intersections,
+    /// calls `on_hit` with each of them, nearest first, as soon as no nearer intersection can be
+    /// found. The raycast stops when `on_hit` returns [`ControlFlow::Break`], so entities further
+    /// away are never tested.
+    pub fn cast_ray_with(
+        &mut self,
+        ray: Ray3d,
+        settings: &RaycastSettings,
+        mut on_hit: impl FnMut(Entity, &IntersectionData) -> ControlFlow<()>,
+    ) {
+        self.cull_aabbs(ray, settings, 0.0);
+        self.cast_ray_explained(ray, settings, None, Some(&mut on_hit));
+    }
+
+    /// Casts the `ray` like [`Raycast::cast_ray`], but without its optimizations, as a reference to
+    /// check its results against. Every target is tested against its mesh, whether or not the ray
+    /// hits its AABB, all of the hits are found before the early exit test is applied, and the time
+    /// budget and [`RaycastSettings::any_hit`] are ignored. This is much slower than [`Raycast::cast_ray`], see
+    /// [`RaycastPluginSettings::audit`](crate::deferred::RaycastPluginSettings::audit).
+    pub fn cast_ray_reference(
+        &mut self,
+        ray: Ray3d,
+        settings: &RaycastSettings,
+    ) -> &[(Entity, IntersectionData)] {
+        let targets = settings
+            .targets
+            .map(|t| t.iter().copied().collect::<HashSet<_>>());
+        self.culled_list.clear();
+        for (inherited, view, aabb, transform, entity) in self.culling_query.iter() {
+            if !settings.visibility.allows(inherited, view)
+                || targets.as_ref().is_some_and(|t| !t.contains(&entity))
+            {
+                continue;
+            }
+            // The AABB is only needed for entities that are raycast as a volume, as it is the
+            // geometry of those entities.
+            let near = if settings.volume_only || self.volume_only_query.contains(entity) {
+                let offset = self.offset_query.get(entity).ok();
+                let screen_size = self.screen_size_query.get(entity).ok();
+                let swept = self.swept_query.get(entity).ok();
+                let inflation = settings.aabb_inflation;
+                match intersect_bounds(ray, aabb, transform, offset, screen_size, swept, inflation)
+                {
+                    Some([near, far]) if far >= 0.0 && is_within(settings, [near, far]) => near,
+                    _ => continue,
                 }
-            },
-        );
-        *self.culled_list = aabb_hits_rx.try_iter().collect();
-        self.culled_list.sort_by_key(|(aabb_near, _)| *aabb_near);
-        drop(ray_cull_guard);
+            } else {
+                f32::NEG_INFINITY
+            };
+            self.culled_list.push((FloatOrd(near), entity));
+        }
+        let never = |_| false;
+        let exhaustive = RaycastSettings {
+            time_budget: None,
+            any_hit: false,
+            ..settings.clone()
+        }
+        .with_early_exit_test(&never);
+        self.cast_ray_explained(ray, &exhaustive, None, None);
+        let nearest_blocking_hit = self
+            .output
+            .iter()
+            .find(|(entity, _)| (settings.early_exit_test)(*entity))
+            .map_or(f32::INFINITY, |(_, hit)| hit.distance());
+        self.output
+            .retain(|(_, hit)| hit.distance() <= nearest_blocking_hit);
+        self.output.as_ref()
+    }
 
+    /// Explains why the `target` entity was or wasn't hit by casting the `ray` with these
+    /// `settings`, by reporting the first stage of the raycast it didn't pass. See
+    /// [`HitExplanation`].
+    ///
+    /// This performs the raycast again, recording the decisions made about the `target`, so it
+    /// should only be used to debug a raycast.
+    pub fn explain(
+        &mut self,
+        ray: Ray3d,
+        target: Entity,
+        settings: &RaycastSettings,
+    ) -> HitExplanation {
+        #[cfg(feature = "2d")]
+        let has_mesh = self.mesh_query.contains(target) || self.mesh2d_query.contains(target);
+        #[cfg(not(feature = "2d"))]
+        let has_mesh = self.mesh_query.contains(target);
+        let Ok((inherited, view, aabb, transform, _)) = self.culling_query.get(target) else {
+            return HitExplanation::NotRaycastable;
+        };
+        if !has_mesh {
+            return HitExplanation::NotRaycastable;
+        }
+        if !settings.visibility.allows(inherited, view) {
+            return HitExplanation::Invisible;
+        }
+        if settings.targets.is_some_and(|t| !t.contains(&target)) || !(settings.filter)(target) {
+            return HitExplanation::Filtered;
+        }
+        let offset = self.offset_query.get(target).ok();
+        let screen_size = self.screen_size_query.get(target).ok();
+        let swept = self.swept_query.get(target).ok();
+        let inflation =
+            settings.aabb_inflation + self.line_inflation(target, settings.line_tolerance);
+        let bounds = intersect_bounds(ray, aabb, transform, offset, screen_size, swept, inflation);
+        if !bounds.is_some_and(|[_, far]| far >= 0.0) {
+            let transform = offset_transform(transform, offset, screen_size, ray.origin());
+            let aabb = inflated_aabb(aabb, &transform, inflation);
+            let matrix = transform.compute_matrix();
+            // Find the point on the ray nearest the center of the AABB, in the AABB's local space.
+            let inverse = matrix.inverse();
+            let origin = inverse.transform_point3(ray.origin());
+            let direction = inverse.transform_vector3(ray.direction());
+            let center = Vec3::from(aabb.center);
+            let t = ((center - origin).dot(direction) / direction.length_squared()).max(0.0);
+            let nearest = origin + direction * t;
+            let half_extents = Vec3::from(aabb.half_extents);
+            let on_aabb = nearest.clamp(center - half_extents, center + half_extents);
+            let miss_distance = matrix
+                .transform_point3(nearest)
+                .distance(matrix.transform_point3(on_aabb));
+            return HitExplanation::OutsideBounds { miss_distance };
+        }
+        if let Some([_, far]) = bounds.filter(|[_, far]| *far < settings.min_distance) {
+            return HitExplanation::BeforeMinDistance { distance: far };
+        }
+        if let Some([near, _]) = bounds.filter(|bounds| !is_within(settings, *bounds)) {
+            return HitExplanation::BeyondMaxDistance { distance: near };
+        }
+        self.cull_aabbs(ray, settings, 0.0);
+        let (_, explanation) = self.cast_ray_explained(ray, settings, Some(target), None);
+        explanation.unwrap_or(HitExplanation::NotRaycastable)
+    }
+
+    /// Casts the ray like [`Raycast::cast_ray_with_outcome`] against the entities in the culled
+    /// list, also explaining the result for the `explain` entity, if any. This assumes the entity
+    /// passed the culling stages.
+    fn cast_ray_explained(
+        &mut self,
+        ray: Ray3d,
+        settings: &RaycastSettings,
+        explain: Option<Entity>,
+        mut on_hit: Option<&mut dyn FnMut(Entity, &IntersectionData) -> ControlFlow<()>>,
+    ) -> (CastOutcome<'_>, Option<HitExplanation>) {
+        self.hits.clear();
+        self.output.clear();
+        // Mesh hits closer than the epsilon are skipped by starting the ray that far along, and
+        // correcting the distances of the hits.
+        let skipped = settings.self_hit_epsilon * ray.origin().abs().max_element().max(1.0);
+        let surface_ray = Ray3d::new(ray.position(skipped), ray.direction());
+        let morph_query = &self.morph_query;
+        self.morphed_meshes
+            .retain(|entity, _| morph_query.contains(*entity));
+        let skin_query = &self.skin_query;
+        self.skinned_meshes
+            .retain(|entity, _| skin_query.contains(*entity));
+        self.invalidate_mesh_caches();
+
+        let start = Instant::now();
         let mut nearest_blocking_hit = FloatOrd(f32::INFINITY);
+        let mut blocking_entity = None;
+        let mut outcome = CastOutcome::default();
+        let mut explanation = None;
         let raycast_guard = debug_span!("raycast");
-        self.culled_list
+        // The number of hits that have been passed to `on_hit`, which are the nearest hits.
+        let mut streamed = 0;
+        let mut is_stopped = false;
+        let mut candidates = self
+            .culled_list
             .iter()
             .filter(|(_, entity)| (settings.filter)(*entity))
-            .for_each(|(aabb_near, entity)| {
+            .peekable();
+        'candidates: while let Some((aabb_near, entity)) = candidates.next() {
+            'entity: {
+                // Is it even possible the mesh could be closer than the current best? The
+                // candidates are sorted by the distance to their AABBs, so none of the rest can be.
+                if *aabb_near > nearest_blocking_hit {
+                    break 'candidates;
+                }
+
+                if settings
+                    .time_budget
+                    .is_some_and(|budget| start.elapsed() > budget)
+                {
+                    outcome.truncated_by_budget = true;
+                    if explain == Some(*entity) {
+                        explanation = Some(HitExplanation::TimeBudgetExceeded);
+                    }
+                    break 'entity;
+                }
+
+                let mut push_hit = |intersection: IntersectionData| {
+                    let distance = FloatOrd(intersection.distance());
+                    if (settings.early_exit_test)(*entity) && distance < nearest_blocking_hit {
+                        // The reason we don't just return here is because right now we are going
+                        // through the AABBs in order, but that doesn't mean that an AABB that
+                        // starts further away cant end up with a closer hit than an AABB that
+                        // starts closer. We need to keep checking AABBs that could possibly
+                        // contain a nearer hit.
+                        nearest_blocking_hit = distance;
+                        blocking_entity = Some(*entity);
+                    }
+                    self.hits.push((distance, (*entity, intersection)));
+                };
+                let mut record_miss = |reason: HitExplanation| {
+                    match reason {
+                        HitExplanation::MeshNotLoaded => outcome.skipped_unready += 1,
+                        HitExplanation::UnsupportedTopology => {
+                            outcome.skipped_unsupported_topology += 1;
+                        }
+                        HitExplanation::InvalidMesh(_) => outcome.skipped_invalid_mesh += 1,
+                        _ => (),
+                    }
+                    if explain == Some(*entity) {
+                        explanation = Some(reason);
+                    }
+                };
+
+                if settings.volume_only || self.volume_only_query.contains(*entity) {
+                    // Skip the mesh entirely, and treat the AABB entry point as the intersection.
+                    // The volume is solid, so a ray entering it before the minimum distance hits it
+                    // at the minimum distance.
+                    let distance = aabb_near.0.max(settings.min_distance).max(0.0);
+                    let position = ray.position(distance);
+                    let mut intersection =
+                        IntersectionData::new(position, -ray.direction(), distance, None);
+                    intersection.set_provenance(HitProvenance {
+                        algorithm: RaycastAlgorithm::SlabTest,
+                        geometry: HitGeometry::Volume,
+                        backface_culling: false,
+                    });
+                    push_hit(intersection);
+                    break 'entity;
+                }
+
                 let mut raycast_mesh =
                     |mesh_handle: &Handle<Mesh>,
                      simplified_mesh: Option<&SimplifiedMesh>,
                      no_backface_culling: Option<&NoBackfaceCulling>,
                      transform: &GlobalTransform| {
-                        // Is it even possible the mesh could be closer than the current best?
-                        if *aabb_near > nearest_blocking_hit {
-                            return;
-                        }
-
                         // Does the mesh handle resolve?
                         let mesh_handle = simplified_mesh.map(|m| &m.mesh).unwrap_or(mesh_handle);
-                        let Some(mesh) = self.meshes.get(mesh_handle) else {
-                            return;
+                        let mut geometry = match simplified_mesh {
+                            Some(_) => HitGeometry::SimplifiedMesh,
+                            None => HitGeometry::Mesh,
+                        };
+                        let Some(mut mesh) = self.meshes.get(mesh_handle) else {
+                            return Err(HitExplanation::MeshNotLoaded);
                         };
+                        let topology = mesh.primitive_topology();
+                        let line_tolerance = settings
+                            .line_tolerance
+                            .filter(|_| is_line_topology(topology));
+                        if !is_triangle_topology(topology) && line_tolerance.is_none() {
+                            warn_unraycastable_mesh(
+                                &mut self.unraycastable_meshes,
+                                *entity,
+                                mesh_handle.id(),
+                                MeshExtractError::UnsupportedTopology(topology),
+                            );
+                            return Err(HitExplanation::UnsupportedTopology);
+                        }
+                        check_large_mesh(
+                            &mut self.large_meshes,
+                            *self.large_mesh_setting,
+                            settings.large_mesh_threshold,
+                            *entity,
+                            mesh_handle.id(),
+                            mesh,
+                        );
+                        if let Ok(decoder) = self.decoder_query.get(*entity) {
+                            let decoded = decoded_mesh(
+                                &mut self.decoded_meshes,
+                                mesh_handle.id(),
+                                mesh,
+                                decoder,
+                            );
+                            if let Some(decoded) = decoded {
+                                mesh = decoded;
+                            }
+                        } else if let (Ok(weights), Some(images), None) = (
+                            self.morph_query.get(*entity),
+                            self.images.as_deref(),
+                            simplified_mesh,
+                        ) {
+                            let morphed = morphed_mesh(
+                                &mut self.morphed_meshes,
+                                *entity,
+                                mesh_handle.id(),
+                                mesh,
+                                images,
+                                weights.weights(),
+                            );
+                            if let Some(morphed) = morphed {
+                                mesh = morphed;
+                                geometry = HitGeometry::MorphedMesh;
+                            }
+                        } else if let Ok(smooth) = self.smooth_query.get(*entity) {
+                            if mesh.attribute(Mesh::ATTRIBUTE_NORMAL).is_none() {
+                                let smoothed = smoothed_mesh(
+                                    &mut self.smoothed_meshes,
+                                    mesh_handle.id(),
+                                    mesh,
+                                    smooth.angle,
+                                );
+                                if let Some(smoothed) = smoothed {
+                                    mesh = smoothed;
+                                    geometry = HitGeometry::SmoothedMesh;
+                                }
+                            }
+                        }
+                        // The posed vertices are in world space, so they aren't transformed again.
+                        let mut is_skinned = false;
+                        if let (Ok(skin), None) = (self.skin_query.get(*entity), simplified_mesh) {
+                            let morph_weights = self.morph_query.get(*entity);
+                            let skinned = skinned_mesh(
+                                &mut self.skinned_meshes,
+                                &mut self.incomplete_skins,
+                                *entity,
+                                mesh_handle.id(),
+                                mesh,
+                                skin,
+                                morph_weights.map_or(&[][..], MeshMorphWeights::weights),
+                                &self.joint_query,
+                                self.inverse_bindposes.as_deref(),
+                            );
+                            if let Some(skinned) = skinned {
+                                mesh = skinned;
+                                geometry = HitGeometry::SkinnedMesh;
+                                is_skinned = true;
+                            }
+                        }
+                        if let Err(error) = check_mesh_layout(mesh) {
+                            warn_unraycastable_mesh(
+                                &mut self.unraycastable_meshes,
+                                *entity,
+                                mesh_handle.id(),
+                                error,
+                            );
+                            return Err(HitExplanation::InvalidMesh(error));
+                        }
+                        if let Err(error) = check_mesh_normals(mesh) {
+                            warn_unraycastable_mesh(
+                                &mut self.unraycastable_meshes,
+                                *entity,
+                                mesh_handle.id(),
+                                error,
+                            );
+                        }
 
                         let _raycast_guard = raycast_guard.enter();
-                        let backfaces = match no_backface_culling {
-                            Some(_) => Backfaces::Include,
-                            None => Backfaces::Cull,
+                        let hit_faces = self.hit_faces_query.get(*entity).ok();
+                        let backfaces = match (hit_faces, no_backface_culling) {
+                            (Some(hit_faces), _) => hit_faces.backfaces(),
+                            (None, Some(_)) => Backfaces::Include,
+                            (None, None) => settings.backfaces,
+                        };
+                        let offset = self.offset_query.get(*entity).ok();
+                        let screen_size = self.screen_size_query.get(*entity).ok();
+                        let winding = self.winding_query.get(*entity).ok();
+                        let winding = winding.copied().unwrap_or_default();
+                        let min_distance = settings.min_distance - skipped;
+                        let max_distance = settings.max_distance.map(|distance| distance - skipped);
+                        let options = MeshRaycastOptions {
+                            backfaces,
+                            winding,
+                            max_slope: settings.max_slope,
+                            min_distance,
+                            max_distance,
+                            ..Default::default()
                         };
-                        let transform = transform.compute_matrix();
-                        let intersection =
-                            ray_intersection_over_mesh(mesh, &transform, &ray, backfaces);
-                        if let Some(intersection) = intersection {
-                            let distance = FloatOrd(intersection.distance());
-                            if (settings.early_exit_test)(*entity)
-                                && distance < nearest_blocking_hit
-                            {
-                                // The reason we don't just return here is because right now we are
-                                // going through the AABBs in order, but that doesn't mean that an
-                                // AABB that starts further away cant end up with a closer hit than
-                                // an AABB that starts closer. We need to keep checking AABBs that
-                                // could possibly contain a nearer hit.
-                                nearest_blocking_hit = distance.min(nearest_blocking_hit);
+                        let cast = |transform: &GlobalTransform| -> Vec<IntersectionData> {
+                            let transform =
+                                offset_transform(transform, offset, screen_size, ray.origin());
+                            let matrix = match is_skinned {
+                                true => Mat4::IDENTITY,
+                                false => transform.compute_matrix(),
+                            };
+                            if let Some(tolerance) = line_tolerance {
+                                return ray_line_mesh_intersection(
+                                    mesh,
+                                    &matrix,
+                                    &surface_ray,
+                                    tolerance,
+                                    min_distance,
+                                    max_distance,
+                                )
+                                .into_iter()
+                                .collect();
+                            }
+                            if settings.max_hits_per_mesh > 1 && !settings.any_hit {
+                                return ray_intersections_over_mesh(
+                                    mesh,
+                                    &matrix,
+                                    &surface_ray,
+                                    &options.with_max_hits(
+                                        NonZeroUsize::new(settings.max_hits_per_mesh)
+                                            .unwrap_or(NonZeroUsize::MIN),
+                                    ),
+                                );
                             }
-                            self.hits.push((distance, (*entity, intersection)));
+                            let cast_mesh = match settings.any_hit {
+                                true => ray_any_intersection_over_mesh,
+                                false => ray_intersection_over_mesh,
+                            };
+                            cast_mesh(mesh, &matrix, &surface_ray, &options)
+                                .into_iter()
+                                .collect()
                         };
+                        // Fast moving entities that were missed are tested in the poses they moved
+                        // through since the last frame.
+                        let swept = self.swept_query.get(*entity).ok();
+                        let mut intersections = cast(transform);
+                        if let (true, Some(swept)) = (intersections.is_empty(), swept) {
+                            let swept_hits = swept.poses(transform).find_map(|(t, pose)| {
+                                let mut hits = cast(&pose);
+                                for hit in &mut hits {
+                                    hit.set_time_of_impact(t);
+                                }
+                                (!hits.is_empty()).then_some(hits)
+                            });
+                            intersections = swept_hits.unwrap_or_default();
+                        }
+                        if intersections.is_empty() {
+                            return Err(HitExplanation::NoTriangleHit);
+                        }
+                        let sections = self.sections_query.get(*entity).ok();
+                        for intersection in &mut intersections {
+                            intersection.set_distance(intersection.distance() + skipped);
+                            intersection.set_provenance(HitProvenance {
+                                geometry,
+                                ..intersection.provenance()
+                            });
+                            if let Some(sections) = sections {
+                                intersection.set_section(&sections.0, topology);
+                            }
+                        }
+                        Ok(intersections)
                     };
 
                 if let Ok((mesh, simp_mesh, culling, transform)) = self.mesh_query.get(*entity) {
-                    raycast_mesh(mesh, simp_mesh, culling, transform);
+                    match raycast_mesh(mesh, simp_mesh, culling, transform) {
+                        Ok(intersections) => intersections.into_iter().for_each(&mut push_hit),
+                        Err(reason) => record_miss(reason),
+                    }
                 }
 
                 #[cfg(feature = "2d")]
                 if let Ok((mesh, simp_mesh, transform)) = self.mesh2d_query.get(*entity) {
-                    raycast_mesh(&mesh.0, simp_mesh, Some(&NoBackfaceCulling), transform);
+                    let culling = Some(&NoBackfaceCulling);
+                    match raycast_mesh(&mesh.0, simp_mesh, culling, transform) {
+                        Ok(intersections) => intersections.into_iter().for_each(&mut push_hit),
+                        Err(reason) => record_miss(reason),
+                    }
                 }
-            });
+            }
+
+            // Any hit will do, so the rest of the entities don't need to be tested.
+            let is_done = settings.any_hit && !self.hits.is_empty();
+            if let Some(on_hit) = on_hit.as_mut() {
+                // Entities are tested in the order the ray enters their AABBs, so no hit found
+                // later can be nearer than the next AABB.
+                let horizon = candidates
+                    .peek()
+                    .filter(|_| !is_done)
+                    .map_or(FloatOrd(f32::INFINITY), |(near, _)| *near)
+                    .min(nearest_blocking_hit);
+                self.hits[streamed..].sort_by_key(|(k, _)| *k);
+                while let Some((distance, (entity, hit))) = self.hits.get(streamed) {
+                    if *distance > horizon {
+                        break;
+                    }
+                    streamed += 1;
+                    is_stopped = on_hit(*entity, hit).is_break();
+                    if is_stopped {
+                        break;
+                    }
+                }
+                if is_stopped {
+                    break;
+                }
+            }
+            if is_done {
+                break;
+            }
+        }
+        if on_hit.is_some() {
+            return (
+                CastOutcome {
+                    hits: &[],
+                    ..outcome
+                },
+                None,
+            );
+        }
 
         self.hits.retain(|(dist, _)| *dist <= nearest_blocking_hit);
+        self.hits.sort_by_key(|(k, _)| *k);
+        // Entities that were tested without being explained were either hit, or skipped or beaten
+        // by a closer blocking hit.
+        let explanation = explain.map(|target| {
+            let hit = self.hits.iter().find(|(_, (entity, _))| *entity == target);
+            match (explanation, hit, blocking_entity) {
+                (Some(explanation), _, _) => explanation,
+                (None, Some((_, (_, hit))), _) => HitExplanation::Hit(Box::new(hit.clone())),
+                (None, None, Some(by)) => HitExplanation::Occluded {
+                    by,
+                    distance: nearest_blocking_hit.0,
+                },
+                (None, None, None) => HitExplanation::NotRaycastable,
+            }
+        });
+        let hits = self.hits.iter().map(|(_, (e, i))| (*e, i.to_owned()));
+        *self.output = hits.collect();
+        let outcome = CastOutcome {
+            hits: self.output.as_ref(),
+            ..outcome
+        };
+        (outcome, explanation)
+    }
+
+    /// Casts the `ray` against every instance of the entities with an [`InstancedRaycast`]
+    /// component, and returns a sorted list of `(entity, instance_index, intersection)`, nearest
+    /// first.
+    ///
+    /// Each instance is culled by the entity's [`Aabb`], transformed by the instance's transform.
+    /// Entities without an [`Aabb`], such as those with `NoFrustumCulling`, use the bounds of their
+    /// mesh instead.
+    ///
+    /// Instances are only occluded by each other, not by the entities hit by
+    /// [`Raycast::cast_ray`]. To ignore the instances behind those, set
+    /// [`RaycastSettings::max_distance`] to the distance of the nearest of their hits, as
+    /// [`RaycastSource`]s do.
+    pub fn cast_ray_instanced(
+        &mut self,
+        ray: Ray3d,
+        settings: &RaycastSettings,
+    ) -> &[(Entity, usize, IntersectionData)] {
+        self.instance_hits.clear();
+        // Like `cast_ray`, hits closer than the epsilon are skipped by starting the ray that far
+        // along, and correcting the distances of the hits.
+        let skipped = settings.self_hit_epsilon * ray.origin().abs().max_element().max(1.0);
+        let surface_ray = Ray3d::new(ray.position(skipped), ray.direction());
+
+        let start = Instant::now();
+        let raycast_guard = debug_span!("instanced raycast");
+        for (instances, mesh, simplified_mesh, culling, aabb, inherited, view, transform, entity) in
+            self.instanced_query.iter()
+        {
+            let should_raycast = settings.visibility.allows(inherited, view);
+            let is_target = settings.targets.map_or(true, |t| t.contains(&entity));
+            if !should_raycast || !is_target || !(settings.filter)(entity) {
+                continue;
+            }
+            if settings
+                .time_budget
+                .is_some_and(|budget| start.elapsed() > budget)
+            {
+                break;
+            }
+
+            let mesh_handle = simplified_mesh.map(|m| &m.mesh).or(mesh);
+            // Like `cast_ray`, 2D meshes are raycast from both sides.
+            #[cfg(feature = "2d")]
+            let (mesh_handle, culling) = match mesh_handle {
+                Some(handle) => (Some(handle), culling),
+                None => (
+                    self.mesh2d_query.get(entity).ok().map(|(mesh, ..)| &mesh.0),
+                    Some(&NoBackfaceCulling),
+                ),
+            };
+            let Some(mesh_handle) = mesh_handle else {
+                continue;
+            };
+            let Some(mesh) = self.meshes.get(mesh_handle) else {
+                continue;
+            };
+            // Like `cast_ray`, meshes that can't be raycast are skipped with a warning.
+            let topology = mesh.primitive_topology();
+            let layout = match is_triangle_topology(topology) {
+                true => check_mesh_layout(mesh),
+                false => Err(MeshExtractError::UnsupportedTopology(topology)),
+            };
+            if let Err(error) = layout {
+                warn_unraycastable_mesh(
+                    &mut self.unraycastable_meshes,
+                    entity,
+                    mesh_handle.id(),
+                    error,
+                );
+                continue;
+            }
+            if let Err(error) = check_mesh_normals(mesh) {
+                warn_unraycastable_mesh(
+                    &mut self.unraycastable_meshes,
+                    entity,
+                    mesh_handle.id(),
+                    error,
+                );
+            }
+            check_large_mesh(
+                &mut self.large_meshes,
+                *self.large_mesh_setting,
+                settings.large_mesh_threshold,
+                entity,
+                mesh_handle.id(),
+                mesh,
+            );
+            let Some(aabb) = aabb.copied().or_else(|| mesh.compute_aabb()) else {
+                continue;
+            };
+
+            let _raycast_guard = raycast_guard.enter();
+            let offset = self.offset_query.get(entity).ok();
+            let screen_size = self.screen_size_query.get(entity).ok();
+            let transform =
+                offset_transform(transform, offset, screen_size, ray.origin()).compute_matrix();
+            let culled_instances = instances
+                .transforms
+                .iter()
+                .map(|instance| transform * *instance)
+                .enumerate()
+                .filter(|(_, instance)| {
+                    let mut aabb = aabb;
+                    if settings.aabb_inflation > 0.0 {
+                        // The AABB is in model space, so the inflation needs to account for scale.
+                        let (scale, _, _) = instance.to_scale_rotation_translation();
+                        let scale = scale.abs().min_element().max(f32::EPSILON);
+                        aabb.half_extents += Vec3A::splat(settings.aabb_inflation / scale);
+                    }
+                    ray.intersects_aabb(&aabb, instance)
+                        .is_some_and(|[near, far]| far >= 0.0 && is_within(settings, [near, far]))
+                });
+            let backfaces = match (self.hit_faces_query.get(entity).ok(), culling) {
+                (Some(hit_faces), _) => hit_faces.backfaces(),
+                (None, Some(_)) => Backfaces::Include,
+                (None, None) => settings.backfaces,
+            };
+            let winding = self.winding_query.get(entity).ok();
+            let hits = ray_intersection_over_mesh_instances(
+                mesh,
+                culled_instances,
+                &surface_ray,
+                &MeshRaycastOptions {
+                    backfaces,
+                    winding: winding.copied().unwrap_or_default(),
+                    max_slope: settings.max_slope,
+                    min_distance: settings.min_distance - skipped,
+                    max_distance: settings.max_distance.map(|distance| distance - skipped),
+                    ..Default::default()
+                },
+            );
+            self.instance_hits
+                .extend(hits.into_iter().map(|(index, mut intersection)| {
+                    intersection.set_distance(intersection.distance() + skipped);
+                    (entity, index, intersection)
+                }));
+        }
+
+        self.instance_hits
+            .sort_by(|(_, _, a), (_, _, b)| a.distance().total_cmp(&b.distance()));
+        let nearest_blocking_hit = self
+            .instance_hits
+            .iter()
+            .find(|(entity, _, _)| (settings.early_exit_test)(*entity))
+            .map_or(f32::INFINITY, |(_, _, intersection)| {
+                intersection.distance()
+            });
+        self.instance_hits
+            .retain(|(_, _, intersection)| intersection.distance() <= nearest_blocking_hit);
+        self.instance_hits.as_ref()
+    }
+
+    /// Casts a "beam" with the given `radius` along the `ray`, and returns a list of all entities
+    /// whose geometry comes within `radius` of the ray, sorted by distance along the ray.
+    ///
+    /// Unlike [`Raycast::cast_ray`], the position of each [`IntersectionData`] is the point on the
+    /// mesh closest to the ray, and the distance is the distance along the ray to the point of
+    /// closest approach. Every entity within the beam is reported, so the early exit test in
+    /// `settings` is ignored. Points of closest approach outside the minimum and maximum distances
+    /// in `settings` are ignored.
+    pub fn cast_beam(
+        &mut self,
+        ray: Ray3d,
+        radius: f32,
+        settings: &RaycastSettings,
+    ) -> &[(Entity, IntersectionData)] {
+        self.hits.clear();
+        self.output.clear();
+        self.cull_aabbs(ray, settings, radius);
+        self.invalidate_mesh_caches();
+
+        let raycast_guard = debug_span!("beam cast");
+        for (_, entity) in self.culled_list.iter() {
+            if !(settings.filter)(*entity) {
+                continue;
+            }
+            let mut mesh_handles = Vec::with_capacity(1);
+            if let Ok((mesh, simplified_mesh, _, transform)) = self.mesh_query.get(*entity) {
+                let mesh = simplified_mesh.map(|m| &m.mesh).unwrap_or(mesh);
+                mesh_handles.push((mesh, transform));
+            }
+            #[cfg(feature = "2d")]
+            if let Ok((mesh, simplified_mesh, transform)) = self.mesh2d_query.get(*entity) {
+                let mesh = simplified_mesh.map(|m| &m.mesh).unwrap_or(&mesh.0);
+                mesh_handles.push((mesh, transform));
+            }
+
+            for (mesh_handle, transform) in mesh_handles {
+                let Some(mut mesh) = self.meshes.get(mesh_handle) else {
+                    continue;
+                };
+                if let Ok(decoder) = self.decoder_query.get(*entity) {
+                    let decoded =
+                        decoded_mesh(&mut self.decoded_meshes, mesh_handle.id(), mesh, decoder);
+                    if let Some(decoded) = decoded {
+                        mesh = decoded;
+                    }
+                }
+                check_large_mesh(
+                    &mut self.large_meshes,
+                    *self.large_mesh_setting,
+                    settings.large_mesh_threshold,
+                    *entity,
+                    mesh_handle.id(),
+                    mesh,
+                );
+                let _raycast_guard = raycast_guard.enter();
+                let offset = self.offset_query.get(*entity).ok();
+                let screen_size = self.screen_size_query.get(*entity).ok();
+                let transform =
+                    offset_transform(transform, offset, screen_size, ray.origin()).compute_matrix();
+                let closest = ray_mesh_closest_approach(mesh, &transform, &ray);
+                if let Some((intersection, separation)) = closest {
+                    let distance = intersection.distance();
+                    if separation <= radius && is_within(settings, [distance, distance]) {
+                        let distance = FloatOrd(intersection.distance());
+                        self.hits.push((distance, (*entity, intersection)));
+                    }
+                }
+            }
+        }
+
         self.hits.sort_by_key(|(k, _)| *k);
         let hits = self.hits.iter().map(|(_, (e, i))| (*e, i.to_owned()));
         *self.output = hits.collect();
         self.output.as_ref()
     }
+
+    /// Casts a path made of straight segments between each of the `points`, in order, and returns
+    /// the first place where it hits something, along with how far along the path the hit is.
+    ///
+    /// Each segment is tested like [`Raycast::cast_ray`] with the same `settings`, except that hits
+    /// past the end of the segment are ignored. Entities are culled once for the whole path, by
+    /// testing their bounds against every segment, so only entities near the path are tested by
+    /// each segment.
+    pub fn cast_polyline(
+        &mut self,
+        points: &[Vec3],
+        settings: &RaycastSettings,
+    ) -> Option<PolylineHit> {
+        let segments: Vec<(Ray3d, f32)> = points
+            .windows(2)
+            .map(|pair| {
+                (
+                    Ray3d::new(pair[0], pair[1] - pair[0]),
+                    pair[0].distance(pair[1]),
+                )
+            })
+            .filter(|(_, length)| *length > f32::EPSILON)
+            .collect();
+        let targets = settings
+            .targets
+            .map(|t| t.iter().copied().collect::<HashSet<_>>());
+        let candidates: Vec<Entity> = self
+            .culling_query
+            .iter()
+            .filter(
+                |(inherited_visibility, view_visibility, aabb, transform, entity)| {
+                    if !settings
+                        .visibility
+                        .allows(*inherited_visibility, *view_visibility)
+                        || targets.as_ref().is_some_and(|t| !t.contains(entity))
+                        || !(settings.filter)(*entity)
+                    {
+                        return false;
+                    }
+                    // The bounds are culled like those of a ray, for each segment.
+                    let offset = self.offset_query.get(*entity).ok();
+                    let screen_size = self.screen_size_query.get(*entity).ok();
+                    let swept = self.swept_query.get(*entity).ok();
+                    let inflation = settings.aabb_inflation
+                        + self.line_inflation(*entity, settings.line_tolerance);
+                    segments.iter().any(|(ray, length)| {
+                        let bounds = intersect_bounds(
+                            *ray,
+                            aabb,
+                            transform,
+                            offset,
+                            screen_size,
+                            swept,
+                            inflation,
+                        );
+                        bounds.is_some_and(|[near, far]| far >= 0.0 && near <= *length)
+                    })
+                },
+            )
+            .map(|(.., entity)| entity)
+            .collect();
+        let segment_settings = RaycastSettings {
+            targets: Some(&candidates),
+            ..settings.clone()
+        };
+
+        let mut travelled = 0.0;
+        for (segment, pair) in points.windows(2).enumerate() {
+            let (start, end) = (pair[0], pair[1]);
+            let length = start.distance(end);
+            if length <= f32::EPSILON {
+                continue;
+            }
+            let hit = self
+                .cast_ray(Ray3d::new(start, end - start), &segment_settings)
+                .first()
+                .filter(|(_, hit)| hit.distance() <= length)
+                .cloned();
+            if let Some((entity, intersection)) = hit {
+                return Some(PolylineHit {
+                    entity,
+                    segment,
+                    segment_fraction: intersection.distance() / length,
+                    distance: travelled + intersection.distance(),
+                    intersection,
+                });
+            }
+            travelled += length;
+        }
+        None
+    }
+
+    /// Finds how much geometry the segment from `start` to `end` passes through, such as the walls
+    /// between an audio listener and a sound, for muffling the sound.
+    ///
+    /// Every hit with each entity along the segment is found, including backfaces. Sorted by
+    /// distance, the hits are paired up as entries and exits of a closed mesh, and the distance
+    /// between each pair is added to the thickness of the entity. A hit left without a pair, such
+    /// as on a mesh that isn't closed, adds `open_thickness` instead, and the entity is flagged with
+    /// [`EntityThickness::is_open`]. The visibility, filter, and target `settings` are respected,
+    /// and the remaining settings are ignored.
+    pub fn cast_thickness(
+        &mut self,
+        start: Vec3,
+        end: Vec3,
+        open_thickness: f32,
+        settings: &RaycastSettings,
+    ) -> SegmentThickness {
+        let mut thickness = SegmentThickness::default();
+        let length = start.distance(end);
+        if length <= f32::EPSILON {
+            return thickness;
+        }
+        let ray = Ray3d::new(start, end - start);
+        self.cull_aabbs(ray, settings, 0.0);
+        self.invalidate_mesh_caches();
+
+        let raycast_guard = debug_span!("thickness cast");
+        for (aabb_near, entity) in self.culled_list.iter() {
+            if aabb_near.0 > length || !(settings.filter)(*entity) {
+                continue;
+            }
+            let mut mesh_handles = Vec::with_capacity(1);
+            if let Ok((mesh, simplified_mesh, _, transform)) = self.mesh_query.get(*entity) {
+                let mesh = simplified_mesh.map(|m| &m.mesh).unwrap_or(mesh);
+                mesh_handles.push((mesh, transform));
+            }
+            #[cfg(feature = "2d")]
+            if let Ok((mesh, simplified_mesh, transform)) = self.mesh2d_query.get(*entity) {
+                let mesh = simplified_mesh.map(|m| &m.mesh).unwrap_or(&mesh.0);
+                mesh_handles.push((mesh, transform));
+            }
+
+            for (mesh_handle, transform) in mesh_handles {
+                let Some(mut mesh) = self.meshes.get(mesh_handle) else {
+                    continue;
+                };
+                if let Ok(decoder) = self.decoder_query.get(*entity) {
+                    let decoded =
+                        decoded_mesh(&mut self.decoded_meshes, mesh_handle.id(), mesh, decoder);
+                    if let Some(decoded) = decoded {
+                        mesh = decoded;
+                    }
+                }
+                let _raycast_guard = raycast_guard.enter();
+                let offset = self.offset_query.get(*entity).ok();
+                let screen_size = self.screen_size_query.get(*entity).ok();
+                let transform =
+                    offset_transform(transform, offset, screen_size, ray.origin()).compute_matrix();
+                let winding = self.winding_query.get(*entity).ok();
+                let winding = winding.copied().unwrap_or_default();
+                let mut distances: Vec<f32> =
+                    ray_mesh_intersections(mesh, &transform, &ray, winding)
+                        .iter()
+                        .map(IntersectionData::distance)
+                        .filter(|distance| *distance <= length)
+                        .collect();
+                // A ray through a shared edge or vertex hits every triangle that touches it.
+                distances.dedup_by(|a, b| (*a - *b).abs() <= THICKNESS_EPSILON);
+                if distances.is_empty() {
+                    continue;
+                }
+                let pairs = distances.chunks_exact(2);
+                let unpaired = pairs.remainder().len();
+                let entity_thickness = pairs.map(|pair| pair[1] - pair[0]).sum::<f32>()
+                    + unpaired as f32 * open_thickness;
+                thickness.total += entity_thickness;
+                thickness.entities.push(EntityThickness {
+                    entity: *entity,
+                    thickness: entity_thickness,
+                    is_open: unpaired > 0,
+                });
+            }
+        }
+        thickness
+    }
+
+    /// Finds the entities whose AABBs are intersected by the `ray`, along with the distance along
+    /// the ray at which it enters each AABB, sorted nearest first. No meshes are tested.
+    ///
+    /// This is the broad phase of [`Raycast::cast_ray`], and respects the same visibility, filter,
+    /// target, and AABB inflation settings, so it returns exactly the entities whose meshes a
+    /// raycast with the same `settings` would test. It can be used as the broad phase of custom
+    /// intersection tests.
+    pub fn cull(&mut self, ray: Ray3d, settings: &RaycastSettings) -> Vec<(Entity, f32)> {
+        self.cull_aabbs(ray, settings, 0.0);
+        self.culled_list
+            .iter()
+            .filter(|(_, entity)| (settings.filter)(*entity))
+            .map(|(near, entity)| (*entity, near.0))
+            .collect()
+    }
+
+    /// Finds the entities inside the rectangle between two `corners` on the `window`, in logical
+    /// pixels, as seen by the `camera`, such as for a drag-select box. The entities are returned in
+    /// no particular order.
+    ///
+    /// An entity is selected if its AABB intersects the frustum of the rectangle and, unless the
+    /// `settings` are [`volume_only`](RaycastSettings::volume_only) or the entity has a
+    /// [`RaycastVolumeOnly`] component, part of one of its triangles is inside the frustum. The
+    /// visibility, filter, target, and AABB inflation `settings` are respected, and the
+    /// `max_distance` limits how far in front of the camera entities are selected. Use a filter to
+    /// only select the entities of one raycasting set, such as those with a `RaycastMesh<T>`.
+    ///
+    /// A rectangle without a width or a height selects the entities hit by a ray through its center
+    /// instead, see [`Raycast::cast_ray`].
+    pub fn select_rect(
+        &mut self,
+        corners: [Vec2; 2],
+        camera: &Camera,
+        camera_transform: &GlobalTransform,
+        window: &Window,
+        settings: &RaycastSettings,
+    ) -> Vec<Entity> {
+        let [Some(a), Some(b)] = corners.map(|corner| screenspace_to_ndc(corner, camera, window))
+        else {
+            return Vec::new();
+        };
+        let (min, max) = (a.min(b), a.max(b));
+        let (size, center) = (max - min, (min + max) / 2.0);
+        if size.min_element() <= f32::EPSILON {
+            let Some(ray) = Ray3d::from_ndc(center, camera, camera_transform) else {
+                return Vec::new();
+            };
+            let mut entities: Vec<_> = self
+                .cast_ray(ray, settings)
+                .iter()
+                .map(|(e, _)| *e)
+                .collect();
+            entities.sort();
+            entities.dedup();
+            return entities;
+        }
+        if !has_invertible_projection(camera) {
+            return Vec::new();
+        }
+
+        // Stretching the rectangle over the whole viewport makes the frustum of the camera the
+        // frustum of the rectangle.
+        let rect_to_viewport = Mat4::from_scale((2.0 / size).extend(1.0))
+            * Mat4::from_translation((-center).extend(0.0));
+        let view_projection = rect_to_viewport
+            * camera.projection_matrix()
+            * camera_transform.compute_matrix().inverse();
+        let has_far = settings.max_distance.is_some();
+        let frustum = Frustum::from_view_projection_custom_far(
+            &view_projection,
+            &camera_transform.translation(),
+            &camera_transform.back(),
+            settings.max_distance.unwrap_or(0.0),
+        );
+        let half_spaces = &frustum.half_spaces[..if has_far { 6 } else { 5 }];
+
+        let origin = camera_transform.translation();
+        let targets = settings
+            .targets
+            .map(|t| t.iter().copied().collect::<HashSet<_>>());
+        let candidates: Vec<(Entity, GlobalTransform)> = self
+            .culling_query
+            .iter()
+            .filter(|(inherited_visibility, view_visibility, _, _, entity)| {
+                settings
+                    .visibility
+                    .allows(*inherited_visibility, *view_visibility)
+                    && targets.as_ref().map_or(true, |t| t.contains(entity))
+                    && (settings.filter)(*entity)
+            })
+            .filter_map(|(_, _, aabb, transform, entity)| {
+                let offset = self.offset_query.get(entity).ok();
+                let screen_size = self.screen_size_query.get(entity).ok();
+                let transform = offset_transform(transform, offset, screen_size, origin);
+                let aabb = inflated_aabb(aabb, &transform, settings.aabb_inflation);
+                frustum
+                    .intersects_obb(&aabb, &transform.affine(), true, has_far)
+                    .then_some((entity, transform))
+            })
+            .collect();
+        if settings.volume_only {
+            return candidates.into_iter().map(|(entity, _)| entity).collect();
+        }
+
+        self.invalidate_mesh_caches();
+        let mut selected = Vec::new();
+        for (entity, transform) in candidates {
+            if self.volume_only_query.contains(entity) {
+                selected.push(entity);
+                continue;
+            }
+            let mut mesh_handles = Vec::with_capacity(1);
+            if let Ok((mesh, simplified_mesh, ..)) = self.mesh_query.get(entity) {
+                mesh_handles.push(simplified_mesh.map(|m| &m.mesh).unwrap_or(mesh));
+            }
+            #[cfg(feature = "2d")]
+            if let Ok((mesh, simplified_mesh, _)) = self.mesh2d_query.get(entity) {
+                mesh_handles.push(simplified_mesh.map(|m| &m.mesh).unwrap_or(&mesh.0));
+            }
+            let transform = transform.compute_matrix();
+            let is_inside = mesh_handles.into_iter().any(|mesh_handle| {
+                let Some(mut mesh) = self.meshes.get(mesh_handle) else {
+                    return false;
+                };
+                if let Ok(decoder) = self.decoder_query.get(entity) {
+                    let decoded =
+                        decoded_mesh(&mut self.decoded_meshes, mesh_handle.id(), mesh, decoder);
+                    if let Some(decoded) = decoded {
+                        mesh = decoded;
+                    }
+                }
+                mesh_triangles_transformed(mesh, &transform).is_ok_and(|mut triangles| {
+                    triangles.any(|triangle| triangle_in_half_spaces(&triangle, half_spaces))
+                })
+            });
+            if is_inside {
+                selected.push(entity);
+            }
+        }
+        selected
+    }
+
+    /// The distance to grow the AABB of an entity by, so a ray passing within the
+    /// [`RaycastSettings::line_tolerance`] of its lines isn't culled. This is zero unless the mesh
+    /// of the entity is made of lines.
+    fn line_inflation(&self, entity: Entity, line_tolerance: Option<f32>) -> f32 {
+        let Some(tolerance) = line_tolerance else {
+            return 0.0;
+        };
+        let handle = self
+            .mesh_query
+            .get(entity)
+            .ok()
+            .map(|(mesh, simplified_mesh, ..)| simplified_mesh.map_or(mesh, |m| &m.mesh));
+        #[cfg(feature = "2d")]
+        let handle = handle.or_else(|| {
+            let (mesh, simplified_mesh, _) = self.mesh2d_query.get(entity).ok()?;
+            Some(simplified_mesh.map_or(&mesh.0, |m| &m.mesh))
+        });
+        let is_line = handle
+            .and_then(|handle| self.meshes.get(handle))
+            .is_some_and(|mesh| is_line_topology(mesh.primitive_topology()));
+        if is_line {
+            tolerance
+        } else {
+            0.0
+        }
+    }
+
+    /// Removes modified and removed meshes from the caches of meshes derived from mesh assets.
+    ///
+    /// The asset events are only read when no plugin counts the changes of meshes in
+    /// [`MeshAssetChanges`], as they are missed by raycasts that don't run every frame.
+    fn invalidate_mesh_caches(&mut self) {
+        let mut stale = Vec::new();
+        match &self.mesh_changes {
+            Some(changes) => {
+                self.mesh_events.clear();
+                if changes.is_changed() {
+                    for (id, count) in changes.0.iter() {
+                        if self.seen_mesh_changes.insert(*id, *count) != Some(*count) {
+                            stale.push(*id);
+                        }
+                    }
+                }
+            }
+            None => stale.extend(self.mesh_events.read().filter_map(|event| match event {
+                AssetEvent::Modified { id } | AssetEvent::Removed { id } => Some(*id),
+                _ => None,
+            })),
+        }
+        for id in &stale {
+            self.smoothed_meshes.remove(id);
+            self.decoded_meshes.remove(id);
+            self.large_meshes.remove(id);
+            self.unraycastable_meshes.remove(id);
+            self.morphed_meshes.retain(|_, (mesh, ..)| mesh != id);
+            self.skinned_meshes.retain(|_, (mesh, ..)| mesh != id);
+        }
+    }
+
+    /// Checks all entities to see if the ray intersects their AABB, and uses this to build a short
+    /// list of entities that are in the path of the ray, sorted by the distance to the AABB.
+    ///
+    /// The AABBs are grown by `inflation`, in world space, before being tested.
+    fn cull_aabbs(&mut self, ray: Ray3d, settings: &RaycastSettings, inflation: f32) {
+        let ray_cull = info_span!("ray culling");
+        let _ray_cull_guard = ray_cull.enter();
+
+        self.culled_list.clear();
+        let visibility_setting = settings.visibility;
+        let min_distance = settings.min_distance;
+        let max_distance = settings.max_distance;
+        let inflation = inflation + settings.aabb_inflation;
+        let offsets = &self.offset_query;
+        let screen_sizes = &self.screen_size_query;
+        let swept_targets = &self.swept_query;
+        let line_tolerance = settings.line_tolerance;
+        let cull_entity = |(inherited_visibility, view_visibility, aabb, transform, entity): (
+            Option<&InheritedVisibility>,
+            Option<&ViewVisibility>,
+            &Aabb,
+            &GlobalTransform,
+            Entity,
+        )| {
+            if !visibility_setting.allows(inherited_visibility, view_visibility) {
+                return None;
+            }
+            let offset = offsets.get(entity).ok();
+            let screen_size = screen_sizes.get(entity).ok();
+            let swept = swept_targets.get(entity).ok();
+            let inflation = inflation + self.line_inflation(entity, line_tolerance);
+            intersect_bounds(ray, aabb, transform, offset, screen_size, swept, inflation)
+                .filter(|[near, far]| {
+                    *far >= min_distance.max(0.0) && max_distance.map_or(true, |max| *near <= max)
+                })
+                .map(|[near, _]| (FloatOrd(near), entity))
+        };
+
+        match settings.targets {
+            Some(targets) if targets.len() <= SMALL_TARGET_LIST => {
+                let culled = self
+                    .culling_query
+                    .iter_many(targets)
+                    .filter_map(cull_entity);
+                *self.culled_list = culled.collect();
+            }
+            targets => {
+                let targets: Option<HashSet<Entity>> = targets.map(|t| t.iter().copied().collect());
+                let (aabb_hits_tx, aabb_hits_rx) = crossbeam_channel::unbounded();
+                self.culling_query.par_iter().for_each(|item| {
+                    if targets.as_ref().is_some_and(|t| !t.contains(&item.4)) {
+                        return;
+                    }
+                    if let Some(hit) = cull_entity(item) {
+                        aabb_hits_tx.send(hit).ok();
+                    }
+                });
+                *self.culled_list = aabb_hits_rx.try_iter().collect();
+            }
+        }
+        // Ties are broken by entity, so the order doesn't depend on the order of the parallel
+        // iteration.
+        self.culled_list
+            .sort_by_key(|(aabb_near, entity)| (*aabb_near, *entity));
+        // A target listed more than once is culled once for each time, and its copies are now next
+        // to each other.
+        self.culled_list.dedup();
+    }
+}
+
+/// Returns `true` if part of the `triangle` is on the inner side of all of the `half_spaces`, by
+/// clipping it with each of them in turn.
+fn triangle_in_half_spaces(triangle: &Triangle, half_spaces: &[HalfSpace]) -> bool {
+    let mut polygon = vec![triangle.v0, triangle.v1, triangle.v2];
+    for half_space in half_spaces {
+        let distance = |point: Vec3A| half_space.normal().dot(point) + half_space.d();
+        let mut clipped = Vec::with_capacity(polygon.len() + 1);
+        for (i, &a) in polygon.iter().enumerate() {
+            let b = polygon[(i + 1) % polygon.len()];
+            let (distance_a, distance_b) = (distance(a), distance(b));
+            if distance_a >= 0.0 {
+                clipped.push(a);
+            }
+            if (distance_a >= 0.0) != (distance_b >= 0.0) {
+                clipped.push(a + (b - a) * (distance_a / (distance_a - distance_b)));
+            }
+        }
+        if clipped.is_empty() {
+            return false;
+        }
+        polygon = clipped;
+    }
+    true
+}
+
+/// Returns `true` if an AABB the ray enters `near` and exits `far` along it overlaps the
+/// [`RaycastSettings::min_distance`] and [`RaycastSettings::max_distance`].
+fn is_within(settings: &RaycastSettings, [near, far]: [f32; 2]) -> bool {
+    far >= settings.min_distance && settings.max_distance.map_or(true, |max| near <= max)
+}
+
+/// Composes the [`RaycastTransformOffset`] of an entity, if it has one, with its transform, then
+/// applies its [`RaycastScreenSizeScale`], if it has one, for a ray starting at `origin`.
+fn offset_transform(
+    transform: &GlobalTransform,
+    offset: Option<&RaycastTransformOffset>,
+    screen_size: Option<&RaycastScreenSizeScale>,
+    origin: Vec3,
+) -> GlobalTransform {
+    let transform = match offset {
+        Some(offset) => transform.mul_transform(offset.0),
+        None => *transform,
+    };
+    match screen_size {
+        Some(screen_size) => {
+            let scale = screen_size.scale(transform.translation(), origin);
+            transform.mul_transform(Transform::from_scale(Vec3::splat(scale)))
+        }
+        None => transform,
+    }
+}
+
+/// Intersects the ray with the AABB of an entity, grown by `inflation`, returning the distances to
+/// where the ray enters and exits it. For a moving [`RaycastSweptTarget`], the ray is intersected
+/// with the world space bounds of the AABB in every pose it is tested in.
+fn intersect_bounds(
+    ray: Ray3d,
+    aabb: &Aabb,
+    transform: &GlobalTransform,
+    offset: Option<&RaycastTransformOffset>,
+    screen_size: Option<&RaycastScreenSizeScale>,
+    swept: Option<&RaycastSweptTarget>,
+    inflation: f32,
+) -> Option<[f32; 2]> {
+    let current = offset_transform(transform, offset, screen_size, ray.origin());
+    let current_aabb = inflated_aabb(aabb, &current, inflation);
+    let swept = swept.filter(|swept| swept.previous_transform().is_some());
+    let Some(swept) = swept else {
+        return ray.intersects_aabb(&current_aabb, &current.compute_matrix());
+    };
+    let (mut min, mut max) = world_bounds(&current_aabb, &current);
+    for (_, pose) in swept.poses(transform) {
+        let pose = offset_transform(&pose, offset, screen_size, ray.origin());
+        let (pose_min, pose_max) = world_bounds(&inflated_aabb(aabb, &pose, inflation), &pose);
+        min = min.min(pose_min);
+        max = max.max(pose_max);
+    }
+    ray.intersects_aabb(&Aabb::from_min_max(min, max), &Mat4::IDENTITY)
+}
+
+/// The corners of the world space box that bounds a model space `aabb`.
+fn world_bounds(aabb: &Aabb, transform: &GlobalTransform) -> (Vec3, Vec3) {
+    let corners = (0..8).map(|i| {
+        let sign = Vec3A::new(
+            if i & 1 == 0 { -1.0 } else { 1.0 },
+            if i & 2 == 0 { -1.0 } else { 1.0 },
+            if i & 4 == 0 { -1.0 } else { 1.0 },
+        );
+        transform.transform_point((aabb.center + aabb.half_extents * sign).into())
+    });
+    corners.fold((Vec3::MAX, Vec3::MIN), |(min, max), corner| {
+        (min.min(corner), max.max(corner))
+    })
+}
+
+/// Grows a model space `aabb` by `inflation` in world space, accounting for the scale of the
+/// `transform`.
+fn inflated_aabb(aabb: &Aabb, transform: &GlobalTransform, inflation: f32) -> Aabb {
+    let mut aabb = *aabb;
+    if inflation > 0.0 {
+        let scale = transform.compute_transform().scale.abs().min_element();
+        aabb.half_extents += Vec3A::splat(inflation / scale.max(f32::EPSILON));
+    }
+    aabb
+}
+
+/// Logs a warning the first time a mesh with more triangles than the `threshold` is raycasted, and
+/// records it in `large_meshes`. The warning names the `setting` holding the threshold.
+fn check_large_mesh(
+    large_meshes: &mut HashMap<AssetId<Mesh>, (Entity, usize)>,
+    setting: Option<&str>,
+    threshold: Option<usize>,
+    entity: Entity,
+    mesh_id: AssetId<Mesh>,
+    mesh: &Mesh,
+) {
+    let Some(threshold) = threshold else {
+        return;
+    };
+    if large_meshes.contains_key(&mesh_id) {
+        return;
+    }
+    let triangles = mesh.indices().map_or(mesh.count_vertices(), |i| i.len()) / 3;
+    if triangles > threshold {
+        warn!(
+            "Raycasting against entity {entity:?}, whose mesh has {triangles} triangles. This is \
+            slow, consider adding a `SimplifiedMesh` with fewer triangles, or `RaycastVolumeOnly` \
+            if only the bounds are needed. This warning can be disabled with `{}`.",
+            setting.unwrap_or("RaycastSettings::large_mesh_threshold")
+        );
+        large_meshes.insert(mesh_id, (entity, triangles));
+    }
+}
+
+/// Logs a warning the first time a mesh that can't be raycast, such as a `LineList` or a mesh
+/// without positions, is skipped, or the first time the face normals of a mesh are used because its
+/// vertex normals don't match its positions, and records it in `unraycastable_meshes`.
+fn warn_unraycastable_mesh(
+    unraycastable_meshes: &mut HashSet<AssetId<Mesh>>,
+    entity: Entity,
+    mesh_id: AssetId<Mesh>,
+    error: MeshExtractError,
+) {
+    if !unraycastable_meshes.insert(mesh_id) {
+        return;
+    }
+    match error {
+        MeshExtractError::NormalCountMismatch { .. } => {
+            warn!("Using face normals for raycasts against entity {entity:?}: {error}");
+        }
+        _ => warn!(
+            "Skipping raycasts against entity {entity:?}, whose mesh can't be raycast: {error}"
+        ),
+    }
+}
+
+/// Returns the copy of a skinned mesh posed by its joints, see [`skin_mesh`]. The result is cached
+/// per entity, and only recomputed when its mesh, the matrices of its joints, or its morph target
+/// `weights` change.
+///
+/// Returns `None`, logging a warning the first time for each entity, if the joints, their inverse
+/// bind poses, or the joint attributes of the mesh are missing.
+#[allow(clippy::too_many_arguments)]
+fn skinned_mesh<'a>(
+    cache: &'a mut HashMap<Entity, (AssetId<Mesh>, Vec<Mat4>, Vec<f32>, Mesh)>,
+    incomplete_skins: &mut HashSet<Entity>,
+    entity: Entity,
+    mesh_id: AssetId<Mesh>,
+    mesh: &Mesh,
+    skin: &SkinnedMesh,
+    weights: &[f32],
+    joints: &Query<&GlobalTransform>,
+    inverse_bindposes: Option<&Assets<SkinnedMeshInverseBindposes>>,
+) -> Option<&'a Mesh> {
+    let joint_matrices = inverse_bindposes
+        .and_then(|bindposes| bindposes.get(&skin.inverse_bindposes))
+        .filter(|bindposes| bindposes.len() >= skin.joints.len())
+        .and_then(|bindposes| {
+            skin.joints
+                .iter()
+                .zip(bindposes.iter())
+                .map(|(joint, bindpose)| {
+                    Some(joints.get(*joint).ok()?.compute_matrix() * *bindpose)
+                })
+                .collect::<Option<Vec<Mat4>>>()
+        });
+    let is_cached = cache
+        .get(&entity)
+        .is_some_and(|(id, matrices, cached_weights, _)| {
+            *id == mesh_id && Some(matrices) == joint_matrices.as_ref() && cached_weights == weights
+        });
+    if !is_cached {
+        let posed = joint_matrices
+            .as_ref()
+            .and_then(|matrices| skin_mesh(mesh, matrices));
+        let (Some(joint_matrices), Some(posed)) = (joint_matrices, posed) else {
+            if incomplete_skins.insert(entity) {
+                warn!(
+                    "The skinning data of entity {entity:?} is incomplete, raycasting its mesh in \
+                    its bind pose instead"
+                );
+            }
+            cache.remove(&entity);
+            return None;
+        };
+        cache.insert(entity, (mesh_id, joint_matrices, weights.to_vec(), posed));
+    }
+    cache.get(&entity).map(|(.., mesh)| mesh)
+}
+
+/// Returns the smoothed copy of a mesh without normals. The result is cached per mesh asset, and
+/// only recomputed when the angle changes or the mesh is modified.
+fn smoothed_mesh<'a>(
+    cache: &'a mut HashMap<AssetId<Mesh>, (f32, Mesh)>,
+    mesh_id: AssetId<Mesh>,
+    mesh: &Mesh,
+    angle: f32,
+) -> Option<&'a Mesh> {
+    let is_cached = cache
+        .get(&mesh_id)
+        .is_some_and(|(cached_angle, _)| *cached_angle == angle);
+    if !is_cached {
+        cache.insert(mesh_id, (angle, smooth_normals(mesh, angle)?));
+    }
+    cache.get(&mesh_id).map(|(_, mesh)| mesh)
+}
+
+/// Returns the copy of a mesh with decoded positions. The result is cached per mesh asset, and only
+/// recomputed when the decoder changes or the mesh is modified.
+fn decoded_mesh<'a>(
+    cache: &'a mut HashMap<AssetId<Mesh>, (RaycastPositionDecoder, Mesh)>,
+    mesh_id: AssetId<Mesh>,
+    mesh: &Mesh,
+    decoder: &RaycastPositionDecoder,
+) -> Option<&'a Mesh> {
+    let is_cached = cache
+        .get(&mesh_id)
+        .is_some_and(|(cached_decoder, _)| cached_decoder == decoder);
+    if !is_cached {
+        cache.insert(mesh_id, (*decoder, decode_mesh_positions(mesh, decoder)?));
+    }
+    cache.get(&mesh_id).map(|(_, mesh)| mesh)
+}
+
+/// Returns the mesh of an entity with its morph targets blended by `weights`. The result is cached
+/// per entity, and only recomputed when the mesh or the weights change.
+fn morphed_mesh<'a>(
+    cache: &'a mut HashMap<Entity, (AssetId<Mesh>, Vec<f32>, Mesh)>,
+    entity: Entity,
+    mesh_id: AssetId<Mesh>,
+    mesh: &Mesh,
+    images: &Assets<Image>,
+    weights: &[f32],
+) -> Option<&'a Mesh> {
+    let is_cached = cache
+        .get(&entity)
+        .is_some_and(|(id, cached_weights, _)| *id == mesh_id && cached_weights == weights);
+    if !is_cached {
+        let morph_targets = images.get(mesh_morph_targets(mesh)?)?;
+        let morphed = blend_morph_targets(mesh, morph_targets, weights)?;
+        cache.insert(entity, (mesh_id, weights.to_vec(), morphed));
+    }
+    cache.get(&entity).map(|(_, _, mesh)| mesh)
 }
@@ -1,4 +1,4 @@
-//! A small `bevy` plugin for raycasting against [`Mesh`](bevy_render::mesh::Mesh)es.
+//! A small `bevy` plugin for raycasting against [`Mesh`]es.
 //!
 //! ```
 //! # use bevy::prelude::*;
@@ -18,7 +18,7 @@
 //! - An [immediate-mode API](immediate), which allows you to raycast into the scene on-demand in
 //!   any system. Intersections are returned immediately as a sorted `Vec`.
 //! - A [deferred API](deferred), where raycasts are performed once every frame based on
-//!    entities tagged with specific components. Intersections can be queried from the ECS.
+//!   entities tagged with specific components. Intersections can be queried from the ECS.
 //!
 //! ## Choosing an API
 //!
@@ -44,39 +44,207 @@
 //!
 //! ## Limitations
 //!
-//! This plugin runs entirely on the CPU, with minimal acceleration structures, and without support
-//! for skinned meshes. However, there is a good chance that this simply won't be an issue for your
-//! application. The provided `stress_test` example is a worst-case scenario that can help you judge
-//! if the plugin will meet your performance needs. Using a laptop with an i7-11800H, I am able to
-//! reach 110-530 fps in the stress test, raycasting against 1,000 monkey meshes.
+//! This plugin runs entirely on the CPU, with minimal acceleration structures. Skinned meshes are
+//! raycast in their bind pose, unless they opt into the slower CPU skinning of
+//! [`RaycastSkinnedMesh`]. However, there is a good chance that this simply won't be an issue for
+//! your application. The provided `stress_test` example is a worst-case scenario that can help you
+//! judge if the plugin will meet your performance needs. Using a laptop with an i7-11800H, I am able
+//! to reach 110-530 fps in the stress test, raycasting against 1,000 monkey meshes.
+//!
+//! # Modules
+//!
+//! The [`prelude`] contains the types needed for everyday raycasting. Everything else is available
+//! from the module that defines it, and these paths are stable:
+//! - [`immediate`] and [`deferred`] contain the two raycasting APIs.
+//! - [`primitives`] contains rays, intersections, and other geometric primitives.
+//! - [`raycast`] contains the low level functions for intersecting rays with meshes and triangles.
+//! - [`markers`] contains the components that change how entities are raycasted.
+//! - [`golden`] contains utilities for regression testing picking against stored results.
+//! - `debug` contains the systems for visualizing raycasts, with the `debug` feature.
 
 #![allow(clippy::type_complexity)]
 
 pub mod deferred;
+pub mod golden;
 pub mod immediate;
 pub mod markers;
 pub mod primitives;
 pub mod raycast;
 
+#[cfg(feature = "debug")]
+pub use deferred::debug;
+
 use bevy_app::prelude::*;
+use bevy_asset::{AssetEvent, AssetId, Assets, Handle};
 use bevy_derive::Deref;
 use bevy_ecs::prelude::*;
-use bevy_render::camera::Camera;
+use bevy_render::{camera::Camera, mesh::Mesh, primitives::Aabb};
 use bevy_transform::components::GlobalTransform;
-use bevy_utils::default;
+use bevy_utils::{default, HashMap, HashSet};
 use bevy_window::Window;
 
 #[allow(unused_imports)] // Needed for docs
 use prelude::*;
 
+/// The types needed for everyday raycasting. Lower level items, such as the functions in
+/// [`raycast`], are not included, and should be imported from their modules.
 pub mod prelude {
     pub use crate::{
-        deferred::*, immediate::*, markers::*, primitives::*, raycast::*, CursorRay,
-        DefaultRaycastingPlugin,
+        deferred::{
+            CastRefinement, CrossGroupRaycastPlugin, CursorBounds, DeferredRaycastingPlugin,
+            HitEventMode, InterpolatedHit, PickableBundle, RaycastCameraCursorBundle,
+            RaycastCommandsExt, RaycastDebugInfo, RaycastEntityCommandsExt, RaycastFrameSnapshot,
+            RaycastGroups, RaycastHit, RaycastHitStats, RaycastMesh, RaycastMethod, RaycastMetrics,
+            RaycastPluginSettings, RaycastPluginState, RaycastPointerBundle, RaycastSceneRoot,
+            RaycastSource, RaycastSystem, TargetFilter, VirtualPointer,
+        },
+        immediate::{CastOutcome, Raycast, RaycastSettings, RaycastVisibility},
+        markers::{
+            HitFaces, InstancedRaycast, NoBackfaceCulling, RaycastLayers, RaycastMorphTargets,
+            RaycastPositionDecoder, RaycastScreenSizeScale, RaycastSections, RaycastSkinnedMesh,
+            RaycastSweptTarget, RaycastTransformOffset, RaycastVolumeOnly, RaycastWinding,
+            SimplifiedMesh, SmoothRaycastNormals, TrackHitLocally,
+        },
+        primitives::{
+            HitAttachment, IntersectionData, LocalIntersection, OutputSpaces, Primitive3d, Ray3d,
+        },
+        raycast::Backfaces,
+        CursorRay, DefaultRaycastingPlugin,
     };
 
+    // The items below used to be exported from the prelude, and will be removed in the next
+    // release.
+
+    #[deprecated(
+        since = "0.17.0",
+        note = "use `bevy_mod_raycast::primitives::Triangle`"
+    )]
+    pub type Triangle = crate::primitives::Triangle;
+
+    #[deprecated(since = "0.17.0", note = "use `bevy_mod_raycast::raycast::RayHit`")]
+    pub type RayHit = crate::raycast::RayHit;
+
+    #[deprecated(
+        since = "0.17.0",
+        note = "use `bevy_mod_raycast::primitives::PrimitiveIntersection`"
+    )]
+    pub type PrimitiveIntersection = crate::primitives::PrimitiveIntersection;
+
+    #[deprecated(
+        since = "0.17.0",
+        note = "use `bevy_mod_raycast::raycast::ray_intersection_over_mesh`"
+    )]
+    pub fn ray_intersection_over_mesh(
+        mesh: &bevy_render::mesh::Mesh,
+        mesh_transform: &bevy_math::Mat4,
+        ray: &Ray3d,
+        backface_culling: Backfaces,
+    ) -> Option<IntersectionData> {
+        crate::raycast::ray_intersection_over_mesh(
+            mesh,
+            mesh_transform,
+            ray,
+            &crate::raycast::MeshRaycastOptions {
+                backfaces: backface_culling,
+                ..Default::default()
+            },
+        )
+    }
+
+    #[deprecated(
+        since = "0.17.0",
+        note = "use `bevy_mod_raycast::raycast::ray_mesh_intersection`"
+    )]
+    pub fn ray_mesh_intersection(
+        mesh_transform: &bevy_math::Mat4,
+        vertex_positions: &[[f32; 3]],
+        vertex_normals: Option<&[[f32; 3]]>,
+        ray: &Ray3d,
+        indices: Option<&Vec<impl crate::raycast::IntoUsize>>,
+        backface_culling: Backfaces,
+    ) -> Option<IntersectionData> {
+        crate::raycast::ray_mesh_intersection(
+            mesh_transform,
+            vertex_positions,
+            vertex_normals,
+            ray,
+            indices,
+            &crate::raycast::MeshRaycastOptions {
+                backfaces: backface_culling,
+                ..Default::default()
+            },
+        )
+    }
+
+    #[deprecated(
+        since = "0.17.0",
+        note = "use `bevy_mod_raycast::raycast::ray_triangle_intersection`"
+    )]
+    pub fn ray_triangle_intersection(
+        ray: &Ray3d,
+        triangle: &impl crate::raycast::TriangleTrait,
+        backface_culling: Backfaces,
+    ) -> Option<crate::raycast::RayHit> {
+        crate::raycast::ray_triangle_intersection(
+            ray,
+            triangle,
+            backface_culling,
+            Default::default(),
+        )
+    }
+
+    #[deprecated(
+        since = "0.17.0",
+        note = "use `bevy_mod_raycast::primitives::rays`, or `Ray3d` from this prelude"
+    )]
+    pub mod rays {
+        #[deprecated(since = "0.17.0", note = "use `bevy_mod_raycast::primitives::Ray3d`")]
+        pub type Ray3d = crate::primitives::Ray3d;
+
+        #[deprecated(
+            since = "0.17.0",
+            note = "use `bevy_mod_raycast::primitives::PrimitiveIntersection`"
+        )]
+        pub type PrimitiveIntersection = crate::primitives::PrimitiveIntersection;
+    }
+
+    /// Deprecated alias of [`raycast::IntoUsize`](crate::raycast::IntoUsize), which is
+    /// implemented for every type implementing it.
+    #[deprecated(since = "0.17.0", note = "use `bevy_mod_raycast::raycast::IntoUsize`")]
+    pub trait IntoUsize: crate::raycast::IntoUsize {}
+
+    #[allow(deprecated)]
+    impl<T: crate::raycast::IntoUsize> IntoUsize for T {}
+
+    /// Deprecated alias of [`raycast::TriangleTrait`](crate::raycast::TriangleTrait), which is
+    /// implemented for every type implementing it.
+    #[deprecated(
+        since = "0.17.0",
+        note = "use `bevy_mod_raycast::raycast::TriangleTrait`"
+    )]
+    pub trait TriangleTrait: crate::raycast::TriangleTrait {}
+
+    #[allow(deprecated)]
+    impl<T: crate::raycast::TriangleTrait> TriangleTrait for T {}
+
+    // These systems are deprecated in the prelude, but are re-exported rather than wrapped, so
+    // ordering against them keeps ordering against the plugin's systems. `#[deprecated]` has no
+    // effect on re-exports.
     #[cfg(feature = "debug")]
-    pub use crate::debug::*;
+    pub use crate::debug::{print_intersections, update_debug_cursor};
+    pub use crate::deferred::{build_rays, update_raycast, update_target_intersections};
+
+    #[deprecated(
+        since = "0.17.0",
+        note = "use `bevy_mod_raycast::raycast::raycast_moller_trumbore`"
+    )]
+    pub fn raycast_moller_trumbore(
+        ray: &Ray3d,
+        triangle: &impl crate::raycast::TriangleTrait,
+        backface_culling: Backfaces,
+    ) -> Option<crate::raycast::RayHit> {
+        crate::raycast::raycast_moller_trumbore(ray, triangle, backface_culling)
+    }
 }
 
 #[derive(Default)]
@@ -89,6 +257,109 @@ impl Plugin for DefaultRaycastingPlugin {
                 update_cursor_ray.after(bevy_transform::TransformSystem::TransformPropagate),
             )
             .init_resource::<CursorRay>();
+        if !app.is_plugin_added::<SweptTargetPlugin>() {
+            app.add_plugins(SweptTargetPlugin);
+        }
+        if !app.is_plugin_added::<MeshBoundsPlugin>() {
+            app.add_plugins(MeshBoundsPlugin);
+        }
+    }
+}
+
+/// Keeps the AABBs of meshes, and the meshes cached by [`Raycast`], up to date when their mesh
+/// assets are modified. This is added by the raycasting plugins, and only once if several of them
+/// are added.
+pub(crate) struct MeshBoundsPlugin;
+impl Plugin for MeshBoundsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<MeshAssetChanges>().add_systems(
+            First,
+            (update_modified_mesh_bounds, count_mesh_asset_changes),
+        );
+    }
+}
+
+/// The number of times each mesh asset was modified or removed. Asset events are dropped after two
+/// frames, so a [`Raycast`] that doesn't raycast every frame compares these counts with the ones it
+/// last saw to find its stale cached meshes instead.
+#[doc(hidden)]
+#[derive(Resource, Default)]
+pub struct MeshAssetChanges(pub HashMap<AssetId<Mesh>, u32>);
+
+/// Counts the modifications and removals of every mesh asset in [`MeshAssetChanges`].
+pub fn count_mesh_asset_changes(
+    mut mesh_events: EventReader<AssetEvent<Mesh>>,
+    mut changes: ResMut<MeshAssetChanges>,
+) {
+    for event in mesh_events.read() {
+        if let AssetEvent::Modified { id } | AssetEvent::Removed { id } = event {
+            *changes.0.entry(*id).or_default() += 1;
+        }
+    }
+}
+
+/// Recomputes the [`Aabb`] of every entity whose mesh asset was modified, e.g. by hot reloading.
+/// Bevy only computes the AABB of an entity that doesn't have one yet, so without this the AABB
+/// keeps the bounds of the old mesh, and raycasts cull the new mesh with them. This runs in
+/// [`First`], before the raycasting systems, so the first raycast after a mesh is modified already
+/// uses its new bounds.
+///
+/// The AABB is the bounds of the rendered mesh, a `Handle<Mesh>` or a `Mesh2dHandle`, so it isn't
+/// changed when the [`SimplifiedMesh`] of an entity is modified.
+pub fn update_modified_mesh_bounds(
+    mut mesh_events: EventReader<AssetEvent<Mesh>>,
+    meshes: Res<Assets<Mesh>>,
+    mut bounds: Query<(&Handle<Mesh>, &mut Aabb)>,
+    #[cfg(feature = "2d")] mut bounds_2d: Query<
+        (&bevy_sprite::Mesh2dHandle, &mut Aabb),
+        Without<Handle<Mesh>>,
+    >,
+) {
+    let modified: HashSet<AssetId<Mesh>> = mesh_events
+        .read()
+        .filter_map(|event| match event {
+            AssetEvent::Modified { id } => Some(*id),
+            _ => None,
+        })
+        .collect();
+    if modified.is_empty() {
+        return;
+    }
+    let bounds = bounds.iter_mut().map(|(handle, aabb)| (handle.id(), aabb));
+    #[cfg(feature = "2d")]
+    let bounds = bounds.chain(
+        bounds_2d
+            .iter_mut()
+            .map(|(handle, aabb)| (handle.0.id(), aabb)),
+    );
+    for (id, mut aabb) in bounds {
+        if !modified.contains(&id) {
+            continue;
+        }
+        if let Some(new_aabb) = meshes.get(id).and_then(Mesh::compute_aabb) {
+            *aabb = new_aabb;
+        }
+    }
+}
+
+/// Records the previous transforms of [`RaycastSweptTarget`]s. This is added by the raycasting
+/// plugins, and only once if several of them are added.
+pub(crate) struct SweptTargetPlugin;
+impl Plugin for SweptTargetPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            PostUpdate,
+            update_swept_targets.before(bevy_transform::TransformSystem::TransformPropagate),
+        );
+    }
+}
+
+/// Records the transform of every [`RaycastSweptTarget`] before it is updated for the next frame.
+/// Targets added this frame may not have a propagated transform yet, so they record nothing.
+pub fn update_swept_targets(mut targets: Query<(&mut RaycastSweptTarget, &GlobalTransform)>) {
+    for (mut target, transform) in &mut targets {
+        let previous = (!target.is_added()).then_some(*transform);
+        target.set_previous_transform(previous);
     }
 }
 
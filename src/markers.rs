@@ -1,10 +1,337 @@
+//! Components that change how an entity is raycasted, such as using a simplified mesh or testing
+//! every instance of an instanced mesh.
+
 use bevy_asset::Handle;
 use bevy_ecs::component::Component;
+use bevy_math::{Mat4, Vec3};
+use bevy_reflect::Reflect;
+use bevy_render::mesh::{Mesh, MeshVertexAttributeId};
+use bevy_transform::components::{GlobalTransform, Transform};
+use std::ops::Range;
+
+use crate::raycast::Backfaces;
 
 #[derive(Component)]
 pub struct SimplifiedMesh {
-    pub mesh: Handle<bevy_render::mesh::Mesh>,
+    pub mesh: Handle<Mesh>,
 }
 
 #[derive(Component)]
 pub struct NoBackfaceCulling;
+
+/// The winding order of the front faces of this entity's mesh, for meshes that don't use bevy's
+/// counter-clockwise convention, such as meshes imported from a left-handed pipeline. This decides
+/// which faces are culled as backfaces, and the direction of flat normals.
+///
+/// The winding is compared in the mesh's own space, so it composes with mirrored transforms: a
+/// clockwise mesh with a negative scale is still hit on its outside.
+#[derive(Component, Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Reflect)]
+pub enum RaycastWinding {
+    /// Triangles are wound counter-clockwise when seen from the front, like bevy's meshes.
+    #[default]
+    CounterClockwise,
+    /// Triangles are wound clockwise when seen from the front.
+    Clockwise,
+    /// Both faces of every triangle are front faces, so backfaces are never culled, like
+    /// [`NoBackfaceCulling`].
+    TwoSided,
+}
+
+impl RaycastWinding {
+    /// `-1.0` if the winding reverses the normals computed with the counter-clockwise convention.
+    pub(crate) fn sign(self) -> f32 {
+        match self {
+            RaycastWinding::Clockwise => -1.0,
+            RaycastWinding::CounterClockwise | RaycastWinding::TwoSided => 1.0,
+        }
+    }
+}
+
+/// Which faces of this entity's mesh can be hit, overriding the backface setting of the raycast and
+/// [`NoBackfaceCulling`]. This lets a single surface, like water, be hit from above while rays from
+/// below pass through it. The front faces are decided by the [`RaycastWinding`] of the mesh, and
+/// [`RaycastWinding::TwoSided`] meshes have no back faces.
+#[derive(Component, Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Reflect)]
+pub enum HitFaces {
+    /// Only front faces are hit.
+    #[default]
+    Front,
+    /// Only back faces are hit.
+    Back,
+    /// Both faces are hit.
+    Both,
+}
+
+impl HitFaces {
+    /// The backface setting that hits these faces.
+    pub fn backfaces(self) -> Backfaces {
+        match self {
+            HitFaces::Front => Backfaces::Cull,
+            HitFaces::Back => Backfaces::Only,
+            HitFaces::Both => Backfaces::Include,
+        }
+    }
+}
+
+/// Decodes the vertex positions of this entity's mesh, for meshes that store quantized positions in
+/// a custom [`attribute`](Self::attribute), such as [`VertexAttributeValues::Snorm16x4`], and scale
+/// them back in a shader. Normalized formats are first mapped to `-1.0..=1.0` or `0.0..=1.0`, and
+/// integer formats are used as is. The positions are then multiplied by [`scale`](Self::scale) and
+/// moved by [`offset`](Self::offset).
+///
+/// The decoded positions are cached per mesh asset, and replace the mesh's
+/// [`Mesh::ATTRIBUTE_POSITION`] when raycasting. Bevy can't compute an [`Aabb`] for these meshes,
+/// so the entity's `Aabb` must be added by hand, and bound the decoded positions. These meshes are
+/// not [morphed](RaycastMorphTargets) or [smoothed](SmoothRaycastNormals).
+///
+/// [`VertexAttributeValues::Snorm16x4`]: bevy_render::mesh::VertexAttributeValues::Snorm16x4
+/// [`Aabb`]: bevy_render::primitives::Aabb
+#[derive(Component, Clone, Copy, Debug, PartialEq)]
+pub struct RaycastPositionDecoder {
+    /// The attribute holding the quantized positions.
+    pub attribute: MeshVertexAttributeId,
+    pub scale: Vec3,
+    pub offset: Vec3,
+}
+
+impl RaycastPositionDecoder {
+    pub fn new(attribute: impl Into<MeshVertexAttributeId>, scale: Vec3, offset: Vec3) -> Self {
+        Self {
+            attribute: attribute.into(),
+            scale,
+            offset,
+        }
+    }
+
+    /// Decodes a normalized position.
+    pub fn decode(&self, position: Vec3) -> Vec3 {
+        position * self.scale + self.offset
+    }
+}
+
+/// Raycasts against this entity also test the poses it moved through since the last frame, so a
+/// fast moving entity that crossed the ray between frames is still hit. When the ray misses the
+/// entity's current pose, [`samples`](Self::samples) poses interpolated between its previous and
+/// current [`GlobalTransform`] are tested, and the earliest hit is reported with its
+/// [`IntersectionData::time_of_impact`](crate::primitives::IntersectionData::time_of_impact).
+///
+/// The previous transform is recorded every frame by the
+/// [`DefaultRaycastingPlugin`](crate::DefaultRaycastingPlugin) and the
+/// [`DeferredRaycastingPlugin`](crate::deferred::DeferredRaycastingPlugin), so there is no motion to
+/// test in the frame the component is added.
+#[derive(Component, Clone, Copy, Debug)]
+pub struct RaycastSweptTarget {
+    /// The number of poses tested between the previous and current pose, including the previous
+    /// pose.
+    pub samples: u8,
+    previous: Option<GlobalTransform>,
+}
+
+impl RaycastSweptTarget {
+    pub fn new(samples: u8) -> Self {
+        Self {
+            samples,
+            previous: None,
+        }
+    }
+
+    /// The transform of the entity in the previous frame.
+    pub fn previous_transform(&self) -> Option<GlobalTransform> {
+        self.previous
+    }
+
+    pub(crate) fn set_previous_transform(&mut self, previous: Option<GlobalTransform>) {
+        self.previous = previous;
+    }
+
+    /// The poses tested between the previous pose and the `current` pose, along with how far from
+    /// the previous pose they are, from `0.0` up to, but not including, the current pose at `1.0`.
+    pub fn poses(
+        &self,
+        current: &GlobalTransform,
+    ) -> impl Iterator<Item = (f32, GlobalTransform)> + '_ {
+        let current = current.compute_transform();
+        let samples = self.samples;
+        self.previous.into_iter().flat_map(move |previous| {
+            let previous = previous.compute_transform();
+            (0..samples).map(move |i| {
+                let t = i as f32 / samples as f32;
+                let pose = Transform {
+                    translation: previous.translation.lerp(current.translation, t),
+                    rotation: previous.rotation.slerp(current.rotation, t),
+                    scale: previous.scale.lerp(current.scale, t),
+                };
+                (t, GlobalTransform::from(pose))
+            })
+        })
+    }
+}
+
+impl Default for RaycastSweptTarget {
+    fn default() -> Self {
+        Self::new(8)
+    }
+}
+
+/// Raycasts against this entity only test its AABB, instead of its mesh. The intersection is placed
+/// where the ray enters the AABB, with a normal pointing back along the ray. This is much cheaper
+/// for large numbers of simple entities, where triangle accuracy is not needed.
+#[derive(Component)]
+pub struct RaycastVolumeOnly;
+
+/// Raycasts against every instance of this entity's mesh, for entities that render many instances
+/// of one mesh with a custom instancing setup. Each transform places one instance relative to the
+/// entity's [`GlobalTransform`]. The mesh is the entity's `Handle<Mesh>`, or its `Mesh2dHandle`
+/// with the `2d` feature.
+///
+/// Instanced entities are not hit by [`Raycast::cast_ray`](crate::immediate::Raycast::cast_ray);
+/// use [`Raycast::cast_ray_instanced`](crate::immediate::Raycast::cast_ray_instanced) instead,
+/// which also reports the index of the instance that was hit.
+#[derive(Component, Clone, Default)]
+pub struct InstancedRaycast {
+    pub transforms: Vec<Mat4>,
+}
+
+/// Raycasts against this entity use its mesh deformed by its current morph target weights, from
+/// its [`MeshMorphWeights`](bevy_render::mesh::morph::MeshMorphWeights), instead of the bind pose.
+/// The deformed vertex positions are computed on the CPU, only when the entity's AABB is hit by a
+/// ray, and are reused until the weights change.
+///
+/// The entity's AABB is still computed from the bind pose, so it may need to be enlarged to contain
+/// the deformed mesh.
+#[derive(Component)]
+pub struct RaycastMorphTargets;
+
+/// Raycasts against this entity use its mesh posed by its current joint transforms, from its
+/// [`SkinnedMesh`](bevy_render::mesh::skinning::SkinnedMesh), instead of the bind pose. The posed
+/// vertex positions are computed on the CPU with [`skin_mesh`](crate::raycast::skin_mesh), only
+/// when the entity's AABB is hit by a ray, and are reused until a joint moves. Meshes with
+/// [morph targets](RaycastMorphTargets) are morphed before they are posed.
+///
+/// If the joints, their inverse bind poses, or the joint attributes of the mesh are missing, a
+/// warning is logged and the bind pose is raycast instead. The entity's AABB is still computed
+/// from the bind pose, so it may need to be enlarged to contain the posed mesh.
+#[derive(Component)]
+pub struct RaycastSkinnedMesh;
+
+/// Records the intersections with this entity in its local frame, at the time of the raycast, in
+/// [`RaycastMesh::local_intersections`](crate::deferred::RaycastMesh::local_intersections). This is
+/// useful for entities that move after they are raycasted, such as moving platforms: the world
+/// space position of the hit can be recomputed from the entity's latest transform, without drift.
+/// The debug cursor also draws these hits relative to the entity's latest transform.
+#[derive(Component)]
+pub struct TrackHitLocally;
+
+/// Splits the mesh of this entity into sections, such as the parts of a multi-material mesh, given
+/// as ranges of its index buffer, or its vertex buffer if it has no indices. Intersections with
+/// this entity report which section was hit with
+/// [`IntersectionData::section`](crate::primitives::IntersectionData::section).
+#[derive(Component, Clone, Debug, Default)]
+pub struct RaycastSections(pub Vec<Range<u32>>);
+
+/// Computes smooth normals for raycasts against this entity when its mesh has no
+/// [`Mesh::ATTRIBUTE_NORMAL`](bevy_render::mesh::Mesh::ATTRIBUTE_NORMAL), instead of reporting the
+/// flat normal of each triangle. Triangles meeting at an angle of at most `angle` radians are
+/// smoothed together, and sharper edges stay hard. See
+/// [`smooth_normals`](crate::raycast::smooth_normals).
+///
+/// The smoothed mesh is computed once per mesh asset and cached. This is not applied to meshes
+/// deformed with [`RaycastMorphTargets`].
+#[derive(Component, Clone, Copy, Debug)]
+pub struct SmoothRaycastNormals {
+    pub angle: f32,
+}
+
+/// Offsets the geometry of this entity for raycasting, relative to its [`GlobalTransform`]. This is
+/// useful for meshes that are displaced when rendered, such as meshes with a pivot offset that is
+/// corrected in a shader, so rays hit the surface that is visible. The offset is applied to the
+/// entity's AABB and mesh before they are tested, as if it were the transform of a child entity.
+///
+/// Entities without this component are not affected, and an identity offset gives the same hits.
+#[derive(Component, Clone, Copy, Debug, Default)]
+pub struct RaycastTransformOffset(pub Transform);
+
+/// Scales the geometry of this entity for raycasting by `k` times its distance from the origin of
+/// the ray, about the entity's origin. This matches handles and gizmos that a shader scales by
+/// `distance * k` to keep a constant size on screen, so they stay as easy to hit as they look, even
+/// when their mesh is tiny. The entity's AABB is scaled the same way before it is tested.
+///
+/// The scale is applied after any [`RaycastTransformOffset`], and only makes sense for rays cast
+/// from a perspective camera, whose origin is the camera. Each segment of a polyline, see
+/// [`Raycast::cast_polyline`](crate::immediate::Raycast::cast_polyline), is scaled for the ray
+/// along that segment.
+#[derive(Component, Clone, Copy, Debug, PartialEq)]
+pub struct RaycastScreenSizeScale {
+    /// The scale of the entity per unit of distance from the ray origin.
+    pub k: f32,
+}
+
+impl RaycastScreenSizeScale {
+    /// Picks `k` so a mesh `mesh_size` units across is hit as if it were `pixels` across on screen,
+    /// for a camera with a vertical field of view of `fov` radians and a viewport `viewport_height`
+    /// pixels tall.
+    pub fn from_pixel_size(pixels: f32, mesh_size: f32, fov: f32, viewport_height: f32) -> Self {
+        Self {
+            k: pixels * 2.0 * (fov / 2.0).tan() / (viewport_height * mesh_size),
+        }
+    }
+
+    /// The scale of an entity at `position` for a ray starting at `origin`.
+    #[must_use]
+    pub fn scale(&self, position: Vec3, origin: Vec3) -> f32 {
+        self.k * position.distance(origin)
+    }
+}
+
+impl Default for SmoothRaycastNormals {
+    fn default() -> Self {
+        Self {
+            angle: 60f32.to_radians(),
+        }
+    }
+}
+
+/// The raycast layers this entity is in, as a bitmask of up to 32 layers. A
+/// [`RaycastSource`](crate::deferred::RaycastSource) only hits entities that are in at least one of
+/// the layers of its [`layer_mask`](crate::deferred::RaycastSource::layer_mask). Entities without
+/// this component are in every layer.
+///
+/// This is an additional filter within the sources and meshes of a type `T`, which can be changed
+/// at runtime, such as to move an entity between "selectable" and "ghost" layers.
+#[derive(Component, Clone, Copy, Debug, PartialEq, Eq, Hash, Reflect)]
+pub struct RaycastLayers(pub u32);
+
+impl RaycastLayers {
+    /// In every layer, like entities without a [`RaycastLayers`] component.
+    pub const ALL: Self = Self(u32::MAX);
+    /// In no layer, so this entity is never hit by a source.
+    pub const NONE: Self = Self(0);
+
+    /// Only in the layer `layer`, from 0 to 31.
+    pub const fn layer(layer: u32) -> Self {
+        Self(1 << layer)
+    }
+
+    /// Also in the layer `layer`, from 0 to 31.
+    #[must_use]
+    pub const fn with(self, layer: u32) -> Self {
+        Self(self.0 | 1 << layer)
+    }
+
+    /// Not in the layer `layer`, from 0 to 31.
+    #[must_use]
+    pub const fn without(self, layer: u32) -> Self {
+        Self(self.0 & !(1 << layer))
+    }
+
+    /// Returns `true` if this entity is in at least one of the layers of `mask`.
+    pub const fn intersects(&self, mask: u32) -> bool {
+        self.0 & mask != 0
+    }
+}
+
+impl Default for RaycastLayers {
+    fn default() -> Self {
+        Self::ALL
+    }
+}
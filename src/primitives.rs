@@ -1,9 +1,19 @@
-use bevy_math::{Vec3, Vec3A};
+//! Rays, intersections, and the other geometric primitives used by raycasts.
+
+use bevy_math::{Mat4, Quat, Vec2, Vec3, Vec3A, Vec4};
 use bevy_reflect::Reflect;
+use bevy_render::{
+    mesh::{Mesh, VertexAttributeValues},
+    render_resource::PrimitiveTopology,
+};
+use bevy_transform::components::{GlobalTransform, Transform};
+
+use crate::raycast::{mesh_triangle_vertices, triangle_first_index, vertex_positions};
 
 pub use rays::*;
 
 #[non_exhaustive]
+#[derive(Debug, Clone, Copy, Reflect)]
 pub enum Primitive3d {
     Plane { point: Vec3, normal: Vec3 },
 }
@@ -14,19 +24,89 @@ pub struct IntersectionData {
     normal: Vec3,
     distance: f32,
     triangle: Option<Triangle>,
+    triangle_index: Option<usize>,
+    barycentric_coords: Option<Vec3>,
+    uv: Option<Vec2>,
+    vertex_color: Option<Vec4>,
+    local_position: Option<Vec3>,
+    section: Option<usize>,
+    depth_view: Option<f32>,
+    depth_ndc: Option<f32>,
+    view_position: Option<Vec3>,
+    view_normal: Option<Vec3>,
+    ndc_position: Option<Vec3>,
+    is_swept: bool,
+    is_approximate: bool,
+    is_backface: bool,
+    time_of_impact: Option<f32>,
+    provenance: HitProvenance,
 }
 
 impl From<rays::PrimitiveIntersection> for IntersectionData {
     fn from(data: rays::PrimitiveIntersection) -> Self {
-        Self {
-            position: data.position(),
-            normal: data.normal(),
-            distance: data.distance(),
-            triangle: None,
-        }
+        let mut intersection = Self::new(data.position(), data.normal(), data.distance(), None);
+        intersection.set_provenance(HitProvenance {
+            algorithm: RaycastAlgorithm::Analytic,
+            geometry: HitGeometry::Primitive,
+            backface_culling: false,
+        });
+        intersection
     }
 }
 
+/// How an intersection was computed, for debugging. See [`IntersectionData::provenance`].
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash, Reflect)]
+pub struct HitProvenance {
+    /// The algorithm that found the intersection.
+    pub algorithm: RaycastAlgorithm,
+    /// The geometry that was intersected.
+    pub geometry: HitGeometry,
+    /// `true` if triangles facing away from the ray were ignored.
+    pub backface_culling: bool,
+}
+
+/// The algorithm used to find an intersection. See [`HitProvenance`].
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash, Reflect)]
+pub enum RaycastAlgorithm {
+    /// The Möller–Trumbore ray-triangle intersection test, used for mesh raycasts.
+    #[default]
+    MollerTrumbore,
+    /// The closest point between the ray and each triangle, used by beams.
+    ClosestApproach,
+    /// The ray-AABB slab test, used when only the bounding volume is tested.
+    SlabTest,
+    /// An exact intersection with a [`Primitive3d`].
+    Analytic,
+}
+
+/// The geometry an intersection was found on. See [`HitProvenance`].
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash, Reflect)]
+pub enum HitGeometry {
+    /// The entity's own mesh.
+    #[default]
+    Mesh,
+    /// The mesh of the entity's [`SimplifiedMesh`](crate::markers::SimplifiedMesh).
+    SimplifiedMesh,
+    /// The entity's mesh, deformed by its morph targets. See
+    /// [`RaycastMorphTargets`](crate::markers::RaycastMorphTargets).
+    MorphedMesh,
+    /// The entity's mesh, posed by its joints. See
+    /// [`RaycastSkinnedMesh`](crate::markers::RaycastSkinnedMesh).
+    SkinnedMesh,
+    /// The entity's mesh, with smoothed normals. See
+    /// [`SmoothRaycastNormals`](crate::markers::SmoothRaycastNormals).
+    SmoothedMesh,
+    /// The entity's AABB, instead of its mesh. See
+    /// [`RaycastVolumeOnly`](crate::markers::RaycastVolumeOnly).
+    Volume,
+    /// A [`Primitive3d`] shape.
+    Primitive,
+}
+
+/// How far above the surface [`IntersectionData::reflected_ray`] starts, relative to the size of
+/// the coordinates of the hit.
+const REFLECTION_OFFSET: f32 = 1e-4;
+
 impl IntersectionData {
     pub fn new(position: Vec3, normal: Vec3, distance: f32, triangle: Option<Triangle>) -> Self {
         Self {
@@ -34,6 +114,141 @@ impl IntersectionData {
             normal,
             distance,
             triangle,
+            triangle_index: None,
+            barycentric_coords: None,
+            uv: None,
+            vertex_color: None,
+            local_position: None,
+            section: None,
+            depth_view: None,
+            depth_ndc: None,
+            view_position: None,
+            view_normal: None,
+            ndc_position: None,
+            is_swept: false,
+            is_approximate: false,
+            is_backface: false,
+            time_of_impact: None,
+            provenance: HitProvenance::default(),
+        }
+    }
+
+    pub(crate) fn set_distance(&mut self, distance: f32) {
+        self.distance = distance;
+    }
+
+    pub(crate) fn set_swept(&mut self, is_swept: bool) {
+        self.is_swept = is_swept;
+    }
+
+    pub(crate) fn set_approximate(&mut self, is_approximate: bool) {
+        self.is_approximate = is_approximate;
+    }
+
+    pub(crate) fn set_backface(&mut self, is_backface: bool) {
+        self.is_backface = is_backface;
+    }
+
+    pub(crate) fn set_time_of_impact(&mut self, time_of_impact: f32) {
+        self.time_of_impact = Some(time_of_impact);
+    }
+
+    pub(crate) fn set_provenance(&mut self, provenance: HitProvenance) {
+        self.provenance = provenance;
+    }
+
+    pub(crate) fn set_triangle_index(&mut self, triangle_index: usize) {
+        self.triangle_index = Some(triangle_index);
+    }
+
+    pub(crate) fn set_barycentric_coords(&mut self, barycentric_coords: Option<Vec3>) {
+        self.barycentric_coords = barycentric_coords;
+    }
+
+    pub(crate) fn set_local_position(&mut self, local_position: Vec3) {
+        self.local_position = Some(local_position);
+    }
+
+    /// Interpolates the [`Mesh::ATTRIBUTE_UV_0`] and [`Mesh::ATTRIBUTE_COLOR`] of the triangle
+    /// that was hit in `mesh`. Each is left as `None` if the mesh doesn't have it, or if it is
+    /// stored in a layout that isn't supported, see [`uv`](Self::uv) and
+    /// [`vertex_color`](Self::vertex_color).
+    pub(crate) fn set_vertex_attributes_from_mesh(&mut self, mesh: &Mesh) {
+        self.uv = self.interpolate_uv(mesh);
+        self.vertex_color = self.interpolate_vertex_color(mesh);
+    }
+
+    fn interpolate_uv(&self, mesh: &Mesh) -> Option<Vec2> {
+        let weights = self.barycentric_coords?;
+        let Some(VertexAttributeValues::Float32x2(uvs)) = mesh.attribute(Mesh::ATTRIBUTE_UV_0)
+        else {
+            return None;
+        };
+        let vertices = mesh_triangle_vertices(mesh, self.triangle_index?)?;
+        let uv = |corner: usize| uvs.get(vertices[corner]).map(|&uv| Vec2::from(uv));
+        Some(uv(0)? * weights.x + uv(1)? * weights.y + uv(2)? * weights.z)
+    }
+
+    fn interpolate_vertex_color(&self, mesh: &Mesh) -> Option<Vec4> {
+        let weights = self.barycentric_coords?;
+        let colors = mesh.attribute(Mesh::ATTRIBUTE_COLOR)?;
+        let vertices = mesh_triangle_vertices(mesh, self.triangle_index?)?;
+        let color = |corner: usize| {
+            let index = vertices[corner];
+            match colors {
+                VertexAttributeValues::Float32x4(colors) => colors.get(index).map(|&c| c.into()),
+                VertexAttributeValues::Float32x3(colors) => {
+                    colors.get(index).map(|&c| Vec3::from(c).extend(1.0))
+                }
+                _ => None,
+            }
+        };
+        Some(color(0)? * weights.x + color(1)? * weights.y + color(2)? * weights.z)
+    }
+
+    /// Finds the section containing the triangle that was hit, given the ranges of the index
+    /// buffer covered by each section, and the `topology` of the mesh. See
+    /// [`RaycastSections`](crate::markers::RaycastSections).
+    pub(crate) fn set_section(
+        &mut self,
+        sections: &[std::ops::Range<u32>],
+        topology: PrimitiveTopology,
+    ) {
+        self.section = self.triangle_index.and_then(|triangle| {
+            let first_index = triangle_first_index(topology, triangle) as u32;
+            sections
+                .iter()
+                .position(|range| range.contains(&first_index))
+        });
+    }
+
+    /// Computes the depth of the intersection as seen by a camera, given the camera's
+    /// `world_to_view` and `projection` matrices.
+    pub fn set_camera_depth(&mut self, world_to_view: &Mat4, projection: &Mat4) {
+        let view_position = world_to_view.transform_point3(self.position);
+        self.depth_view = Some(-view_position.z);
+        self.depth_ndc = Some(projection.project_point3(view_position).z);
+    }
+
+    /// Computes the representations of the intersection in the requested `spaces` of a camera,
+    /// given the camera's `world_to_view` and `projection` matrices.
+    pub fn set_output_spaces(
+        &mut self,
+        world_to_view: &Mat4,
+        projection: &Mat4,
+        spaces: OutputSpaces,
+    ) {
+        let view_position = world_to_view.transform_point3(self.position);
+        if spaces.contains(OutputSpaces::VIEW) {
+            self.view_position = Some(view_position);
+            self.view_normal = Some(
+                world_to_view
+                    .transform_vector3(self.normal)
+                    .normalize_or_zero(),
+            );
+        }
+        if spaces.contains(OutputSpaces::NDC) {
+            self.ndc_position = Some(projection.project_point3(view_position));
         }
     }
 
@@ -44,12 +259,26 @@ impl IntersectionData {
     }
 
     /// Get the intersection data's normal.
+    ///
+    /// For hits on meshes, the normal always faces the origin of the ray. When backfaces are
+    /// included, the normal of a triangle that was hit from behind is flipped; when they are
+    /// culled, only front faces can be hit, so this is always the normal of the front face. The
+    /// normal of a mesh hit is normalized, and stays perpendicular to the surface when the mesh is
+    /// scaled non-uniformly.
+    ///
+    /// If the mesh has a [`Mesh::ATTRIBUTE_NORMAL`] with one normal per vertex, the normal is
+    /// interpolated from the normals of the triangle's vertices with the
+    /// [`barycentric_coords`](Self::barycentric_coords), so curved surfaces don't look faceted.
+    /// Otherwise, this is the flat normal of the triangle.
     #[must_use]
     pub fn normal(&self) -> Vec3 {
         self.normal
     }
 
-    /// Get the intersection data's distance.
+    /// Get the intersection data's distance along the ray that was cast. The direction of a
+    /// [`Ray3d`] is normalized, so this is both the euclidean distance from the ray origin and the
+    /// parameter of the ray at the hit: for raycasts, `ray.position(distance)` is the
+    /// [`position`](Self::position) of the intersection.
     #[must_use]
     pub fn distance(&self) -> f32 {
         self.distance
@@ -60,6 +289,296 @@ impl IntersectionData {
     pub fn triangle(&self) -> Option<Triangle> {
         self.triangle
     }
+
+    /// Get the index of the triangle that was hit, in the mesh's index buffer, or its vertex buffer
+    /// if the mesh has no indices. Triangle `i` is made of the vertices at indices `3i..3i + 3`, or
+    /// `i..i + 3` if the mesh is a [`TriangleStrip`](bevy_render::mesh::PrimitiveTopology).
+    #[must_use]
+    pub fn triangle_index(&self) -> Option<usize> {
+        self.triangle_index
+    }
+
+    /// Get the barycentric coordinates of the intersection within the triangle that was hit, i.e.
+    /// the weights of the triangle's three vertices, in the same order as the vertices of the
+    /// [`triangle`](Self::triangle). The weights sum to one, so they can be used to interpolate any
+    /// vertex attribute, such as UVs or colors, at the intersection. Together with the
+    /// [`triangle_index`](Self::triangle_index), this locates the hit on the mesh independently of
+    /// the entity's transform. See [`resolve_world_position`](Self::resolve_world_position).
+    #[must_use]
+    pub fn barycentric_coords(&self) -> Option<Vec3> {
+        self.barycentric_coords
+    }
+
+    /// Get the texture coordinates of the intersection, interpolated from the
+    /// [`Mesh::ATTRIBUTE_UV_0`] of the triangle that was hit with its
+    /// [`barycentric_coords`](Self::barycentric_coords).
+    ///
+    /// This is `None` if the intersection was not with a mesh, or if the mesh has no UVs stored as
+    /// [`VertexAttributeValues::Float32x2`].
+    #[must_use]
+    pub fn uv(&self) -> Option<Vec2> {
+        self.uv
+    }
+
+    /// Get the vertex color of the intersection, interpolated from the [`Mesh::ATTRIBUTE_COLOR`] of
+    /// the triangle that was hit with its [`barycentric_coords`](Self::barycentric_coords). Colors
+    /// stored as [`VertexAttributeValues::Float32x3`] have an alpha of 1.
+    ///
+    /// This is `None` if the intersection was not with a mesh, or if the mesh has no vertex colors
+    /// stored as [`VertexAttributeValues::Float32x4`] or [`VertexAttributeValues::Float32x3`].
+    #[must_use]
+    pub fn vertex_color(&self) -> Option<Vec4> {
+        self.vertex_color
+    }
+
+    /// Get the position of the intersection in the local space of the mesh, the space its vertex
+    /// positions are in, e.g. for mesh editing. This is the exact point found by the raycast, so it
+    /// doesn't depend on the entity's transform changing after the raycast, unlike
+    /// [`to_local`](Self::to_local).
+    ///
+    /// This is `None` if the intersection was not with a mesh.
+    #[must_use]
+    pub fn local_position(&self) -> Option<Vec3> {
+        self.local_position
+    }
+
+    /// Recomputes the world space position of the intersection from the triangle that was hit,
+    /// using the current vertex positions of the `mesh` and the current `transform` of the entity.
+    /// Unlike [`position`](Self::position), this stays on the surface of the mesh if the entity has
+    /// moved, rotated, or scaled since the raycast.
+    ///
+    /// Returns `None` if the intersection was not with a triangle of a mesh, or if the triangle
+    /// doesn't exist in this `mesh`.
+    pub fn resolve_world_position(&self, mesh: &Mesh, transform: &GlobalTransform) -> Option<Vec3> {
+        let weights = self.barycentric_coords?;
+        let positions = vertex_positions(mesh).ok()?;
+        let vertices = mesh_triangle_vertices(mesh, self.triangle_index?)?;
+        let vertex = |corner: usize| positions.get(vertices[corner]).map(|&p| Vec3::from(p));
+        let local = vertex(0)? * weights.x + vertex(1)? * weights.y + vertex(2)? * weights.z;
+        Some(transform.transform_point(local))
+    }
+
+    /// Get the index of the section of the mesh containing the triangle that was hit, for entities
+    /// with a [`RaycastSections`](crate::markers::RaycastSections) component. This is `None` if no
+    /// section contains the triangle.
+    #[must_use]
+    pub fn section(&self) -> Option<usize> {
+        self.section
+    }
+
+    /// Get the depth of the intersection in the view space of the camera that cast the ray, i.e.
+    /// the distance in front of the camera along its forward axis. This differs from the
+    /// [`distance`](Self::distance) for hits away from the center of the screen.
+    ///
+    /// This is `None` if the ray was not cast from a camera.
+    #[must_use]
+    pub fn depth_view(&self) -> Option<f32> {
+        self.depth_view
+    }
+
+    /// Get the depth of the intersection in the normalized device coordinates of the camera that
+    /// cast the ray.
+    ///
+    /// This is `None` if the ray was not cast from a camera.
+    #[must_use]
+    pub fn depth_ndc(&self) -> Option<f32> {
+        self.depth_ndc
+    }
+
+    /// Get the position of the intersection in the view space of the camera that cast the ray.
+    ///
+    /// This is `None` unless [`OutputSpaces::VIEW`] was requested by a source with a camera.
+    #[must_use]
+    pub fn view_position(&self) -> Option<Vec3> {
+        self.view_position
+    }
+
+    /// Get the normal of the intersection in the view space of the camera that cast the ray.
+    ///
+    /// This is `None` unless [`OutputSpaces::VIEW`] was requested by a source with a camera.
+    #[must_use]
+    pub fn view_normal(&self) -> Option<Vec3> {
+        self.view_normal
+    }
+
+    /// Get the position of the intersection in the normalized device coordinates of the camera
+    /// that cast the ray.
+    ///
+    /// This is `None` unless [`OutputSpaces::NDC`] was requested by a source with a camera.
+    #[must_use]
+    pub fn ndc_position(&self) -> Option<Vec3> {
+        self.ndc_position
+    }
+
+    /// Returns `true` if this intersection was not hit by the ray itself, but by a ray interpolated
+    /// between the previous and current ray of a sweeping
+    /// [`RaycastSource`](crate::deferred::RaycastSource).
+    #[must_use]
+    pub fn is_swept(&self) -> bool {
+        self.is_swept
+    }
+
+    /// Returns `true` if this intersection is only with the AABB of the entity, because a
+    /// [`RaycastSource`](crate::deferred::RaycastSource) with
+    /// [`CastRefinement::Progressive`](crate::deferred::CastRefinement::Progressive) has not
+    /// settled on the entity yet. The position and normal will be refined once it settles.
+    #[must_use]
+    pub fn is_approximate(&self) -> bool {
+        self.is_approximate
+    }
+
+    /// Returns `true` if the ray hit the back of a triangle, such as the inside of a closed mesh,
+    /// which can only happen when backfaces aren't culled. The [`normal`](Self::normal) of these
+    /// hits is flipped to face the ray. The side is found in the space of the mesh, so it isn't
+    /// swapped by transforms with a negative scale.
+    ///
+    /// Triangles of meshes with [`RaycastWinding::TwoSided`](crate::markers::RaycastWinding) have
+    /// no back, so this is always `false` for them, as it is for hits that aren't with a mesh.
+    #[must_use]
+    pub fn is_backface(&self) -> bool {
+        self.is_backface
+    }
+
+    /// Get when an entity with a [`RaycastSweptTarget`](crate::markers::RaycastSweptTarget) was
+    /// hit while moving from its previous to its current pose, from `0.0` at the previous pose to
+    /// `1.0` at the current pose. This is approximate, as only a few poses in between are tested.
+    ///
+    /// This is `None` if the entity was hit in its current pose.
+    #[must_use]
+    pub fn time_of_impact(&self) -> Option<f32> {
+        self.time_of_impact
+    }
+
+    /// Get how this intersection was computed: the algorithm, the geometry that was hit, and
+    /// whether backfaces were culled. This is useful for debugging differences between hits.
+    #[must_use]
+    pub fn provenance(&self) -> HitProvenance {
+        self.provenance
+    }
+
+    /// Returns the ray that bounces off the surface at this intersection, for a ray that arrived
+    /// travelling in the `direction`. The direction is mirrored about the normal, and the ray
+    /// starts slightly above the surface, so casting it doesn't hit the same surface again.
+    #[must_use]
+    pub fn reflected_ray(&self, direction: Vec3) -> Ray3d {
+        let normal = self.normal.normalize_or_zero();
+        let direction = direction.normalize_or_zero();
+        let reflected = direction - 2.0 * direction.dot(normal) * normal;
+        // Normals face the ray origin, so the reflected ray leaves from the same side.
+        let offset = REFLECTION_OFFSET * self.position.abs().max_element().max(1.0);
+        Ray3d::new(self.position + normal * offset, reflected)
+    }
+
+    /// Converts the position and normal of this intersection into the local frame of the entity
+    /// that was hit, given its `transform` at the time of the raycast. If the entity moves later,
+    /// [`LocalIntersection::world_position`] gives the position of the hit on the moved entity.
+    #[must_use]
+    pub fn to_local(&self, transform: &GlobalTransform) -> LocalIntersection {
+        let affine = transform.affine();
+        LocalIntersection {
+            position: affine.inverse().transform_point3(self.position),
+            // Normals transform by the inverse transpose, so going back to local space is the
+            // transpose.
+            normal: (affine.matrix3.transpose() * Vec3A::from(self.normal))
+                .normalize_or_zero()
+                .into(),
+        }
+    }
+
+    /// Computes where to attach a child to the entity that was hit, given its `transform`, so the
+    /// child sits at this intersection with its `axis` along the normal, such as a bullet hole or
+    /// a sticker, and moves with the entity. The child has a uniform `scale` in world space. See
+    /// [`HitAttachment`].
+    #[must_use]
+    pub fn attachment(&self, transform: &GlobalTransform, axis: Vec3, scale: f32) -> HitAttachment {
+        let (target_scale, target_rotation, _) = transform.to_scale_rotation_translation();
+        let position = transform.affine().inverse().transform_point3(self.position);
+        let world_rotation = Quat::from_rotation_arc(axis.normalize(), self.normal);
+        let rotation = target_rotation.inverse() * world_rotation;
+        let max_scale = target_scale.abs().max_element();
+        let is_uniform = target_scale.abs_diff_eq(Vec3::splat(target_scale.x), max_scale * 1e-5);
+        if is_uniform {
+            HitAttachment {
+                pivot: None,
+                child: Transform {
+                    translation: position,
+                    rotation,
+                    scale: Vec3::splat(scale / target_scale.x),
+                },
+            }
+        } else {
+            HitAttachment {
+                pivot: Some(Transform {
+                    translation: position,
+                    rotation: Quat::IDENTITY,
+                    scale: target_scale.recip(),
+                }),
+                child: Transform {
+                    translation: Vec3::ZERO,
+                    rotation,
+                    scale: Vec3::splat(scale),
+                },
+            }
+        }
+    }
+}
+
+/// The local transforms of a child attached at an intersection, see
+/// [`IntersectionData::attachment`].
+///
+/// A child rotated relative to an entity with a non-uniform scale is sheared, so for such entities
+/// the child is attached to an intermediate `pivot` entity, a child of the entity that was hit,
+/// whose scale cancels the scale of the entity. The child is then not sheared, as long as the
+/// scale of the entity doesn't change.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct HitAttachment {
+    /// The transform of the pivot relative to the entity that was hit, or `None` if the entity is
+    /// scaled uniformly, and the child can be attached directly to it.
+    pub pivot: Option<Transform>,
+    /// The transform of the child relative to the pivot, or to the entity that was hit if there is
+    /// no pivot.
+    pub child: Transform,
+}
+
+bitflags::bitflags! {
+    /// Camera spaces that an intersection is also represented in, in addition to world space. See
+    /// [`IntersectionData::set_output_spaces`].
+    #[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+    pub struct OutputSpaces: u8 {
+        /// The position and normal in the camera's view space. See
+        /// [`IntersectionData::view_position`] and [`IntersectionData::view_normal`].
+        const VIEW = 1 << 0;
+        /// The position in the camera's normalized device coordinates. See
+        /// [`IntersectionData::ndc_position`].
+        const NDC = 1 << 1;
+    }
+}
+
+/// The position and normal of an intersection, in the local frame of the entity that was hit. See
+/// [`IntersectionData::to_local`].
+#[derive(Debug, Clone, Copy, PartialEq, Reflect)]
+pub struct LocalIntersection {
+    pub position: Vec3,
+    pub normal: Vec3,
+}
+
+impl LocalIntersection {
+    /// Get the position of this intersection in world space, using the current `transform` of the
+    /// entity that was hit.
+    #[must_use]
+    pub fn world_position(&self, transform: &GlobalTransform) -> Vec3 {
+        transform.transform_point(self.position)
+    }
+
+    /// Get the normal of this intersection in world space, using the current `transform` of the
+    /// entity that was hit.
+    #[must_use]
+    pub fn world_normal(&self, transform: &GlobalTransform) -> Vec3 {
+        let normal_matrix = transform.affine().matrix3.inverse().transpose();
+        (normal_matrix * Vec3A::from(self.normal))
+            .normalize_or_zero()
+            .into()
+    }
 }
 
 /// Encapsulates Ray3D, preventing use of struct literal syntax. This allows us to guarantee that
@@ -132,10 +651,22 @@ pub mod rays {
             self.direction.into()
         }
 
+        /// The point `distance` along the ray from its origin. This is the same as the parameter of
+        /// the ray, because the direction is normalized.
         pub fn position(&self, distance: f32) -> Vec3 {
             (self.origin + self.direction * distance).into()
         }
 
+        /// Interpolates between this ray and `other`, linearly interpolating the origin and
+        /// spherically interpolating the direction. `t` should be in the range `[0, 1]`.
+        pub fn interpolate(&self, other: &Ray3d, t: f32) -> Self {
+            let rotation = Quat::from_rotation_arc(self.direction(), other.direction());
+            Ray3d::new(
+                self.origin().lerp(other.origin(), t),
+                Quat::IDENTITY.slerp(rotation, t) * self.direction(),
+            )
+        }
+
         pub fn to_transform(self) -> Mat4 {
             self.to_aligned_transform([0., 1., 0.].into())
         }
@@ -150,27 +681,105 @@ pub mod rays {
             Mat4::from_rotation_translation(new_rotation, position)
         }
 
+        /// Builds the ray from the `origin` towards the `target`. Returns `None` if the points are
+        /// the same, or aren't finite, as the ray would have no direction.
+        pub fn from_points(origin: Vec3, target: Vec3) -> Option<Self> {
+            let direction = (target - origin).try_normalize()?;
+            Some(Ray3d::new(origin, direction))
+        }
+
+        /// Builds the ray from the translation of the `transform` along its forward axis, local
+        /// -Z, the same direction a camera looks in. See [`Ray3d::from_transform_axis`].
         pub fn from_transform(transform: Mat4) -> Self {
-            let pick_position_ndc = Vec3::from([0.0, 0.0, -1.0]);
-            let pick_position = transform.project_point3(pick_position_ndc);
-            let (_, _, source_origin) = transform.to_scale_rotation_translation();
-            let ray_direction = pick_position - source_origin;
-            Ray3d::new(source_origin, ray_direction)
+            Ray3d::from_transform_axis(transform, Vec3::NEG_Z)
+        }
+
+        /// Builds the ray from the translation of the `transform` along the local `axis`. The
+        /// direction only depends on the rotation of the transform, so it isn't skewed by a
+        /// non-uniform scale.
+        pub fn from_transform_axis(transform: Mat4, axis: Vec3) -> Self {
+            let (_, rotation, source_origin) = transform.to_scale_rotation_translation();
+            Ray3d::new(source_origin, rotation * axis)
         }
 
+        /// Builds the ray under a cursor position on the `window`, in logical pixels, the same unit
+        /// as [`Window::cursor_position`] and `CursorMoved` events. Returns `None` if the window or
+        /// the camera's viewport is empty, if the camera's render target hasn't been computed yet,
+        /// or if the camera's projection can't be inverted, see [`has_invertible_projection`].
+        ///
+        /// The position is converted with the current scale factor of the window, so it stays
+        /// correct when the window moves to a monitor with a different scale factor, even before
+        /// the camera has picked up the change.
         pub fn from_screenspace(
             cursor_pos_screen: Vec2,
             camera: &Camera,
             camera_transform: &GlobalTransform,
             window: &Window,
         ) -> Option<Self> {
-            let mut viewport_pos = cursor_pos_screen;
-            if let Some(viewport) = &camera.viewport {
-                viewport_pos -= viewport.physical_position.as_vec2() / window.scale_factor() as f32;
+            let ndc = screenspace_to_ndc(cursor_pos_screen, camera, window)?;
+            Ray3d::from_ndc(ndc, camera, camera_transform)
+        }
+
+        /// Builds the ray through a point of the camera's viewport in normalized device
+        /// coordinates, where `(0, 0)` is the center of the viewport and `(1, 1)` its top right
+        /// corner. Unlike [`Ray3d::from_screenspace`], this doesn't need a window. Returns `None` if
+        /// the camera's projection can't be inverted, see [`has_invertible_projection`].
+        pub fn from_ndc(
+            ndc: Vec2,
+            camera: &Camera,
+            camera_transform: &GlobalTransform,
+        ) -> Option<Self> {
+            if !has_invertible_projection(camera) {
+                return None;
+            }
+            Ray3d::from_projection(
+                ndc,
+                camera.projection_matrix(),
+                camera_transform.compute_matrix(),
+            )
+        }
+
+        /// Builds the ray through a point of the camera's render target, from `(0, 0)` at its top
+        /// left corner to `(1, 1)` at its bottom right corner, the same as texture UVs. This works
+        /// for any render target, and takes the camera's viewport into account. Returns `None` if
+        /// the camera's render target hasn't been computed yet, or if its projection can't be
+        /// inverted.
+        pub fn from_target_uv(
+            uv: Vec2,
+            camera: &Camera,
+            camera_transform: &GlobalTransform,
+        ) -> Option<Self> {
+            let target_size = camera.physical_target_size()?.as_vec2();
+            let viewport = camera.physical_viewport_rect()?;
+            let viewport_size = viewport.size().as_vec2();
+            if viewport_size.cmple(Vec2::ZERO).any() {
+                return None;
             }
-            camera
-                .viewport_to_world(camera_transform, viewport_pos)
-                .map(Ray3d::from)
+            let viewport_pos = uv * target_size - viewport.min.as_vec2();
+            let mut ndc = viewport_pos * 2.0 / viewport_size - Vec2::ONE;
+            ndc.y = -ndc.y;
+            Ray3d::from_ndc(ndc, camera, camera_transform)
+        }
+
+        /// Builds the ray through `ndc` for a camera with the `projection` matrix, placed in the
+        /// world with `camera_to_world`.
+        pub(crate) fn from_projection(
+            ndc: Vec2,
+            projection: Mat4,
+            camera_to_world: Mat4,
+        ) -> Option<Self> {
+            let ndc_to_world = camera_to_world * projection.inverse();
+            // The near plane is at a depth of 1, and a depth of 0 is infinitely far away.
+            let near = ndc_to_world.project_point3(ndc.extend(1.0));
+            // An orthographic projection is affine, every ray points along the view axis. Taking
+            // the direction from the matrix avoids subtracting points that may be very far apart.
+            let direction = if projection.row(3) == Vec4::W {
+                ndc_to_world.transform_vector3(Vec3::NEG_Z)
+            } else {
+                ndc_to_world.project_point3(ndc.extend(f32::EPSILON)) - near
+            };
+            (near.is_finite() && direction.is_finite() && direction != Vec3::ZERO)
+                .then(|| Ray3d::new(near, direction))
         }
 
         /// Checks if the ray intersects with an AABB of a mesh, returning `[near, far]` if it does.
@@ -253,6 +862,49 @@ pub mod rays {
             Ray3d::new(ray.origin, ray.direction)
         }
     }
+
+    /// Converts a position on the `window`, in logical pixels, to the normalized device coordinates
+    /// of the camera's viewport, see [`Ray3d::from_ndc`]. The position is converted with the current
+    /// scale factor of the window. Returns `None` if the window or the camera's viewport is empty, or
+    /// if the camera's render target hasn't been computed yet.
+    pub fn screenspace_to_ndc(position: Vec2, camera: &Camera, window: &Window) -> Option<Vec2> {
+        // The render target of a camera is only computed after it is spawned.
+        camera.logical_viewport_size()?;
+        let scale_factor = window.scale_factor() as f32;
+        let (viewport_pos, viewport_size) = match &camera.viewport {
+            Some(viewport) => (
+                viewport.physical_position.as_vec2() / scale_factor,
+                viewport.physical_size.as_vec2() / scale_factor,
+            ),
+            None => (Vec2::ZERO, Vec2::new(window.width(), window.height())),
+        };
+        if viewport_size.cmple(Vec2::ZERO).any() {
+            return None;
+        }
+        let mut ndc = (position - viewport_pos) * 2.0 / viewport_size - Vec2::ONE;
+        ndc.y = -ndc.y;
+        Some(ndc)
+    }
+
+    /// Returns `true` if the projection matrix of the `camera` is finite and can be inverted, which
+    /// is needed to build rays from screen positions. This is not the case for a camera whose
+    /// projection hasn't been computed yet, or for a degenerate projection, such as a perspective
+    /// projection with a field of view of zero.
+    pub fn has_invertible_projection(camera: &Camera) -> bool {
+        let projection = camera.projection_matrix();
+        // The determinant is compared to the largest it can be for columns of these lengths, so the
+        // test doesn't depend on the scale of the projection, like the size of an orthographic one.
+        let columns = [
+            projection.x_axis,
+            projection.y_axis,
+            projection.z_axis,
+            projection.w_axis,
+        ];
+        let bound: f32 = columns.iter().map(|column| column.length()).product();
+        projection.is_finite()
+            && projection.determinant().abs() > f32::EPSILON * bound
+            && projection.inverse().is_finite()
+    }
 }
 
 #[derive(Debug, PartialEq, Copy, Clone, Reflect)]
@@ -273,7 +925,7 @@ impl From<(Vec3A, Vec3A, Vec3A)> for Triangle {
 impl From<Vec<Vec3A>> for Triangle {
     fn from(vertices: Vec<Vec3A>) -> Self {
         Triangle {
-            v0: *vertices.get(0).unwrap(),
+            v0: *vertices.first().unwrap(),
             v1: *vertices.get(1).unwrap(),
             v2: *vertices.get(2).unwrap(),
         }
@@ -288,3 +940,128 @@ impl From<[Vec3A; 3]> for Triangle {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use bevy::math::{Mat4, Vec3};
+
+    use super::*;
+
+    #[test]
+    fn camera_depth_off_center() {
+        let world_to_view = Mat4::IDENTITY;
+        let projection = Mat4::perspective_infinite_reverse_rh(1.0, 1.0, 0.1);
+        let angle = 0.4_f32;
+
+        let mut center = IntersectionData::new(Vec3::new(0.0, 0.0, -5.0), Vec3::Z, 5.0, None);
+        let position = Vec3::new(angle.sin(), 0.0, -angle.cos()) * 5.0;
+        let mut edge = IntersectionData::new(position, Vec3::Z, 5.0, None);
+        center.set_camera_depth(&world_to_view, &projection);
+        edge.set_camera_depth(&world_to_view, &projection);
+
+        let (center_depth, edge_depth) = (center.depth_view().unwrap(), edge.depth_view().unwrap());
+        assert!((center_depth - 5.0).abs() < 1e-5);
+        assert!((edge_depth - 5.0 * angle.cos()).abs() < 1e-5);
+        // With a reversed-z projection, closer points have a larger depth in NDC.
+        assert!(edge.depth_ndc().unwrap() > center.depth_ndc().unwrap());
+    }
+
+    #[test]
+    fn output_spaces() {
+        let camera = Mat4::from_translation(Vec3::new(1.0, 2.0, 3.0)) * Mat4::from_rotation_y(0.5);
+        let world_to_view = camera.inverse();
+        let projection = Mat4::perspective_infinite_reverse_rh(1.0, 1.0, 0.1);
+        let hit = IntersectionData::new(Vec3::new(0.0, 1.0, -4.0), Vec3::X, 5.0, None);
+
+        for spaces in [
+            OutputSpaces::empty(),
+            OutputSpaces::VIEW,
+            OutputSpaces::all(),
+        ] {
+            let mut hit = hit.clone();
+            hit.set_output_spaces(&world_to_view, &projection, spaces);
+            assert_eq!(hit.position(), Vec3::new(0.0, 1.0, -4.0));
+            assert_eq!(
+                hit.view_position().is_some(),
+                spaces.contains(OutputSpaces::VIEW)
+            );
+            assert_eq!(
+                hit.ndc_position().is_some(),
+                spaces.contains(OutputSpaces::NDC)
+            );
+            if let (Some(position), Some(normal)) = (hit.view_position(), hit.view_normal()) {
+                assert!(camera.transform_point3(position).distance(hit.position()) < 1e-5);
+                assert!(camera.transform_vector3(normal).distance(Vec3::X) < 1e-5);
+            }
+            if let Some(ndc) = hit.ndc_position() {
+                let view = projection.inverse().project_point3(ndc);
+                assert!(camera.transform_point3(view).distance(hit.position()) < 1e-4);
+            }
+        }
+    }
+
+    #[test]
+    fn transform_rays_follow_the_rotation() {
+        let rotation =
+            bevy::math::Quat::from_rotation_y(0.7) * bevy::math::Quat::from_rotation_x(0.3);
+        let axis = Vec3::new(1.0, 0.0, -1.0);
+        for scale in [Vec3::ONE, Vec3::new(3.0, 0.5, 1.0)] {
+            let transform = Mat4::from_scale_rotation_translation(scale, rotation, Vec3::X);
+            let forward = Ray3d::from_transform(transform);
+            assert!(forward.origin().abs_diff_eq(Vec3::X, 1e-5));
+            assert!(forward
+                .direction()
+                .abs_diff_eq(rotation * Vec3::NEG_Z, 1e-5));
+            let ray = Ray3d::from_transform_axis(transform, axis);
+            assert!(ray
+                .direction()
+                .abs_diff_eq(rotation * axis.normalize(), 1e-5));
+        }
+    }
+
+    #[test]
+    fn local_intersection_round_trip() {
+        let transform = GlobalTransform::from(
+            bevy::prelude::Transform::from_xyz(1.0, 2.0, 3.0)
+                .with_rotation(bevy::math::Quat::from_rotation_y(0.7))
+                .with_scale(Vec3::new(2.0, 0.5, 1.0)),
+        );
+        let normal = Vec3::new(1.0, 1.0, 0.0).normalize();
+        let hit = IntersectionData::new(Vec3::new(0.5, -1.0, 4.0), normal, 1.0, None);
+
+        let local = hit.to_local(&transform);
+        assert!(local.world_position(&transform).distance(hit.position()) < 1e-5);
+        assert!(local.world_normal(&transform).distance(normal) < 1e-5);
+    }
+
+    #[test]
+    fn attachment_is_not_sheared() {
+        let rotation =
+            bevy::math::Quat::from_rotation_y(0.7) * bevy::math::Quat::from_rotation_x(0.3);
+        let normal = Vec3::new(1.0, 1.0, -0.5).normalize();
+        let hit = IntersectionData::new(Vec3::new(0.5, -1.0, 4.0), normal, 1.0, None);
+        for scale in [Vec3::splat(2.0), Vec3::new(3.0, 0.5, 1.0)] {
+            let transform = GlobalTransform::from(
+                Transform::from_xyz(1.0, 2.0, 3.0)
+                    .with_rotation(rotation)
+                    .with_scale(scale),
+            );
+            let attachment = hit.attachment(&transform, Vec3::Z, 0.25);
+            assert_eq!(attachment.pivot.is_some(), scale != Vec3::splat(2.0));
+            let pivot = attachment.pivot.unwrap_or_default();
+            let child = transform
+                .mul_transform(pivot)
+                .mul_transform(attachment.child);
+
+            let axes = child.affine().matrix3;
+            assert!(axes.x_axis.dot(axes.y_axis).abs() < 1e-5);
+            assert!(axes.y_axis.dot(axes.z_axis).abs() < 1e-5);
+            assert!(axes.z_axis.dot(axes.x_axis).abs() < 1e-5);
+            for axis in [axes.x_axis, axes.y_axis, axes.z_axis] {
+                assert!((axis.length() - 0.25).abs() < 1e-5);
+            }
+            assert!(Vec3::from(axes.z_axis).normalize().distance(normal) < 1e-5);
+            assert!(child.translation().distance(hit.position()) < 1e-5);
+        }
+    }
+}
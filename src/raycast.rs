@@ -1,100 +1,1157 @@
-use std::f32::EPSILON;
+//! Low level functions for intersecting rays with meshes and triangles. These are used by the
+//! [`immediate`](crate::immediate) and [`deferred`](crate::deferred) APIs, and can be used directly
+//! to raycast a [`Mesh`] without the ECS.
 
-use bevy_math::{Mat4, Vec3A};
+use std::{fmt, num::NonZeroUsize};
+
+use bevy_asset::Handle;
+use bevy_math::{Mat3, Mat4, Vec3, Vec3A};
+use bevy_reflect::{Reflect, Struct};
 use bevy_render::{
-    mesh::{Indices, Mesh, VertexAttributeValues},
-    render_resource::PrimitiveTopology,
+    mesh::{morph::MorphAttributes, Indices, Mesh, VertexAttributeValues},
+    render_resource::{PrimitiveTopology, VertexFormat},
+    texture::Image,
+};
+use bevy_utils::tracing::{debug, warn};
+
+use crate::{
+    markers::{RaycastPositionDecoder, RaycastWinding},
+    primitives::*,
 };
-use bevy_utils::tracing::{error, warn};
 
-use crate::primitives::*;
+/// How a ray is cast on a mesh by the functions of this module, such as
+/// [`ray_intersection_over_mesh`].
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct MeshRaycastOptions {
+    /// Whether the back faces of triangles are hit, see [`Backfaces`].
+    pub backfaces: Backfaces,
+    /// Which faces of the triangles are front faces, see [`RaycastWinding`].
+    pub winding: RaycastWinding,
+    /// Only hit triangles that are flat enough, see [`MaxSlope`]. The ray continues to any
+    /// triangles behind those that are too steep.
+    pub max_slope: Option<MaxSlope>,
+    /// Hits closer than this along the ray, in world space, are ignored. Triangles behind a hit
+    /// closer than this can still be hit.
+    pub min_distance: f32,
+    /// Hits farther than this along the ray, in world space, are ignored.
+    pub max_distance: Option<f32>,
+    /// The most intersections returned by [`ray_intersections_over_mesh`], which keeps the
+    /// nearest. There is no limit by default. The other functions return the nearest intersection
+    /// only.
+    pub max_hits: NonZeroUsize,
+}
+
+impl MeshRaycastOptions {
+    /// Set how backfaces are treated.
+    pub fn with_backfaces(mut self, backfaces: Backfaces) -> Self {
+        self.backfaces = backfaces;
+        self
+    }
+
+    /// Set the winding order of front facing triangles.
+    pub fn with_winding(mut self, winding: RaycastWinding) -> Self {
+        self.winding = winding;
+        self
+    }
+
+    /// Only hit triangles that are flat enough, or any triangle if `None`.
+    pub fn with_max_slope(mut self, max_slope: Option<MaxSlope>) -> Self {
+        self.max_slope = max_slope;
+        self
+    }
+
+    /// Ignore hits closer than `min_distance` along the ray.
+    pub fn with_min_distance(mut self, min_distance: f32) -> Self {
+        self.min_distance = min_distance;
+        self
+    }
+
+    /// Ignore hits farther than `max_distance` along the ray, if set.
+    pub fn with_max_distance(mut self, max_distance: Option<f32>) -> Self {
+        self.max_distance = max_distance;
+        self
+    }
+
+    /// Set the most intersections returned by [`ray_intersections_over_mesh`].
+    pub fn with_max_hits(mut self, max_hits: NonZeroUsize) -> Self {
+        self.max_hits = max_hits;
+        self
+    }
+}
+
+impl Default for MeshRaycastOptions {
+    fn default() -> Self {
+        Self {
+            backfaces: Backfaces::default(),
+            winding: RaycastWinding::default(),
+            max_slope: None,
+            min_distance: 0.0,
+            max_distance: None,
+            max_hits: NonZeroUsize::MAX,
+        }
+    }
+}
 
 /// Cast a ray on a mesh, and returns the intersection
 pub fn ray_intersection_over_mesh(
     mesh: &Mesh,
     mesh_transform: &Mat4,
     ray: &Ray3d,
-    backface_culling: Backfaces,
+    options: &MeshRaycastOptions,
 ) -> Option<IntersectionData> {
-    if mesh.primitive_topology() != PrimitiveTopology::TriangleList {
-        error!(
-            "Invalid intersection check: `TriangleList` is the only supported `PrimitiveTopology`"
-        );
-        return None;
-    }
-    // Get the vertex positions from the mesh reference resolved from the mesh handle
-    let vertex_positions: &Vec<[f32; 3]> = match mesh.attribute(Mesh::ATTRIBUTE_POSITION) {
-        None => panic!("Mesh does not contain vertex positions"),
-        Some(vertex_values) => match &vertex_values {
-            VertexAttributeValues::Float32x3(positions) => positions,
-            _ => panic!("Unexpected types in {:?}", Mesh::ATTRIBUTE_POSITION),
-        },
+    intersection_over_mesh(mesh, mesh_transform, ray, options, TriangleSearch::Nearest)
+}
+
+/// Like [`ray_intersection_over_mesh`], but returns the first intersection that is found instead
+/// of the nearest one. See [`ray_mesh_any_intersection`].
+pub fn ray_any_intersection_over_mesh(
+    mesh: &Mesh,
+    mesh_transform: &Mat4,
+    ray: &Ray3d,
+    options: &MeshRaycastOptions,
+) -> Option<IntersectionData> {
+    intersection_over_mesh(mesh, mesh_transform, ray, options, TriangleSearch::Any)
+}
+
+/// Whether a mesh intersection finds the nearest hit, or stops at the first one.
+#[derive(Clone, Copy, PartialEq)]
+enum TriangleSearch {
+    Nearest,
+    Any,
+}
+
+fn intersection_over_mesh(
+    mesh: &Mesh,
+    mesh_transform: &Mat4,
+    ray: &Ray3d,
+    options: &MeshRaycastOptions,
+    search: TriangleSearch,
+) -> Option<IntersectionData> {
+    let (vertex_positions, vertex_normals) = match mesh_vertex_data(mesh) {
+        Ok(vertex_data) => vertex_data,
+        Err(error) => {
+            debug!("Invalid intersection check: {error}");
+            return None;
+        }
     };
-    let vertex_normals: Option<&[[f32; 3]]> =
-        if let Some(normal_values) = mesh.attribute(Mesh::ATTRIBUTE_NORMAL) {
-            match &normal_values {
-                VertexAttributeValues::Float32x3(normals) => Some(normals),
-                _ => None,
-            }
-        } else {
-            None
-        };
 
-    if let Some(indices) = &mesh.indices() {
+    let intersection = if let Some(indices) = &mesh.indices() {
         // Iterate over the list of pick rays that belong to the same group as this mesh
         match indices {
-            Indices::U16(vertex_indices) => ray_mesh_intersection(
+            Indices::U16(vertex_indices) => mesh_intersection(
                 mesh_transform,
                 vertex_positions,
                 vertex_normals,
                 ray,
                 Some(vertex_indices),
-                backface_culling,
+                mesh.primitive_topology(),
+                options,
+                search,
             ),
-            Indices::U32(vertex_indices) => ray_mesh_intersection(
+            Indices::U32(vertex_indices) => mesh_intersection(
                 mesh_transform,
                 vertex_positions,
                 vertex_normals,
                 ray,
                 Some(vertex_indices),
-                backface_culling,
+                mesh.primitive_topology(),
+                options,
+                search,
             ),
         }
     } else {
-        ray_mesh_intersection(
+        mesh_intersection(
             mesh_transform,
             vertex_positions,
             vertex_normals,
             ray,
             None::<&Vec<u32>>,
-            backface_culling,
+            mesh.primitive_topology(),
+            options,
+            search,
         )
+    };
+    intersection.map(|mut intersection| {
+        intersection.set_vertex_attributes_from_mesh(mesh);
+        intersection
+    })
+}
+
+/// Cast a ray on every instance of a mesh, where each instance is given as its index and its
+/// transform. Returns the index and nearest intersection of every instance that was hit.
+///
+/// The vertex data is only read from the mesh once, and is shared by all instances.
+pub fn ray_intersection_over_mesh_instances(
+    mesh: &Mesh,
+    instances: impl IntoIterator<Item = (usize, Mat4)>,
+    ray: &Ray3d,
+    options: &MeshRaycastOptions,
+) -> Vec<(usize, IntersectionData)> {
+    let (vertex_positions, vertex_normals) = match mesh_vertex_data(mesh) {
+        Ok(vertex_data) => vertex_data,
+        Err(error) => {
+            debug!("Invalid intersection check: {error}");
+            return Vec::new();
+        }
+    };
+    let indices = mesh.indices();
+    let topology = mesh.primitive_topology();
+
+    let intersect = |transform: &Mat4| match indices {
+        Some(Indices::U16(indices)) => mesh_intersection(
+            transform,
+            vertex_positions,
+            vertex_normals,
+            ray,
+            Some(indices),
+            topology,
+            options,
+            TriangleSearch::Nearest,
+        ),
+        Some(Indices::U32(indices)) => mesh_intersection(
+            transform,
+            vertex_positions,
+            vertex_normals,
+            ray,
+            Some(indices),
+            topology,
+            options,
+            TriangleSearch::Nearest,
+        ),
+        None => mesh_intersection(
+            transform,
+            vertex_positions,
+            vertex_normals,
+            ray,
+            None::<&Vec<u32>>,
+            topology,
+            options,
+            TriangleSearch::Nearest,
+        ),
+    };
+
+    instances
+        .into_iter()
+        .filter_map(|(index, transform)| {
+            let mut hit = intersect(&transform)?;
+            hit.set_vertex_attributes_from_mesh(mesh);
+            Some((index, hit))
+        })
+        .collect()
+}
+
+/// Get the handle of the image holding the morph targets of a mesh, if it has any.
+pub fn mesh_morph_targets(mesh: &Mesh) -> Option<&Handle<Image>> {
+    // `Mesh` has no getter for its morph targets, but the field is reflected.
+    mesh.field("morph_targets")?
+        .downcast_ref::<Option<Handle<Image>>>()?
+        .as_ref()
+}
+
+/// Blends the morph targets of a mesh into its vertex positions on the CPU, returning a copy of the
+/// mesh with the displaced positions. `morph_targets` is the image built by
+/// [`MorphTargetImage`](bevy_render::mesh::morph::MorphTargetImage), and `weights` are the current
+/// weights of each target, usually from
+/// [`MeshMorphWeights`](bevy_render::mesh::morph::MeshMorphWeights).
+///
+/// Returns `None` if the image does not match the layout of the mesh.
+pub fn blend_morph_targets(mesh: &Mesh, morph_targets: &Image, weights: &[f32]) -> Option<Mesh> {
+    let positions = vertex_positions(mesh).ok()?;
+    let size = morph_targets.texture_descriptor.size;
+    // Each target is one layer of the image, and each vertex is `COMPONENT_COUNT` floats, starting
+    // with the position displacement.
+    let layer_len = (size.width * size.height) as usize;
+    if layer_len < positions.len() * MorphAttributes::COMPONENT_COUNT {
+        return None;
+    }
+    let component = |index: usize| {
+        let bytes = morph_targets.data.get(index * 4..index * 4 + 4)?;
+        Some(f32::from_ne_bytes(bytes.try_into().ok()?))
+    };
+
+    let mut blended = positions.to_vec();
+    for (target, weight) in weights
+        .iter()
+        .enumerate()
+        .take(size.depth_or_array_layers as usize)
+    {
+        if *weight == 0.0 {
+            continue;
+        }
+        for (vertex, position) in blended.iter_mut().enumerate() {
+            let start = target * layer_len + vertex * MorphAttributes::COMPONENT_COUNT;
+            for (axis, value) in position.iter_mut().enumerate() {
+                *value += component(start + axis)? * weight;
+            }
+        }
+    }
+
+    let mut mesh = mesh.clone();
+    mesh.insert_attribute(Mesh::ATTRIBUTE_POSITION, blended);
+    Some(mesh)
+}
+
+/// Applies skinning to a mesh on the CPU, the same way Bevy does when rendering it, returning a copy
+/// of the mesh with its vertex positions and normals in world space. Each vertex is moved by the
+/// weighted sum of the matrices of up to four joints, given by its [`Mesh::ATTRIBUTE_JOINT_INDEX`]
+/// and [`Mesh::ATTRIBUTE_JOINT_WEIGHT`]. The matrix of each joint is its
+/// [`GlobalTransform`](bevy_transform::components::GlobalTransform) multiplied by its inverse bind
+/// pose.
+///
+/// Returns `None` if the mesh has no joint indices or weights for each of its vertices, or if a
+/// vertex refers to a joint past the end of `joint_matrices`.
+pub fn skin_mesh(mesh: &Mesh, joint_matrices: &[Mat4]) -> Option<Mesh> {
+    let positions = vertex_positions(mesh).ok()?;
+    let Some(VertexAttributeValues::Uint16x4(joints)) = mesh.attribute(Mesh::ATTRIBUTE_JOINT_INDEX)
+    else {
+        return None;
+    };
+    let Some(VertexAttributeValues::Float32x4(weights)) =
+        mesh.attribute(Mesh::ATTRIBUTE_JOINT_WEIGHT)
+    else {
+        return None;
+    };
+    if joints.len() != positions.len() || weights.len() != positions.len() {
+        return None;
+    }
+    let skin_matrices = joints
+        .iter()
+        .zip(weights)
+        .map(|(joints, weights)| {
+            joints
+                .iter()
+                .zip(weights)
+                .try_fold(Mat4::ZERO, |matrix, (&joint, &weight)| {
+                    Some(matrix + *joint_matrices.get(joint as usize)? * weight)
+                })
+        })
+        .collect::<Option<Vec<Mat4>>>()?;
+
+    let mut skinned = mesh.clone();
+    let skinned_positions: Vec<[f32; 3]> = positions
+        .iter()
+        .zip(&skin_matrices)
+        .map(|(&position, matrix)| matrix.transform_point3(position.into()).to_array())
+        .collect();
+    skinned.insert_attribute(Mesh::ATTRIBUTE_POSITION, skinned_positions);
+    if let Some(VertexAttributeValues::Float32x3(normals)) = mesh.attribute(Mesh::ATTRIBUTE_NORMAL)
+    {
+        let skinned_normals: Vec<[f32; 3]> = normals
+            .iter()
+            .zip(&skin_matrices)
+            .map(|(&normal, matrix)| {
+                let normal_matrix = Mat3::from_mat4(*matrix).inverse().transpose();
+                (normal_matrix * Vec3::from(normal))
+                    .normalize_or_zero()
+                    .to_array()
+            })
+            .collect();
+        skinned.insert_attribute(Mesh::ATTRIBUTE_NORMAL, skinned_normals);
+    }
+    Some(skinned)
+}
+
+/// Positions closer than this are treated as the same vertex by [`smooth_normals`].
+const SMOOTHING_EPSILON: f32 = 1e-5;
+
+/// Computes smooth normals for a mesh without authored normals, so raycasts against it report
+/// interpolated normals instead of faceted ones. Returns a copy of the mesh without indices, with
+/// three vertices per triangle in the original triangle order, and an [`Mesh::ATTRIBUTE_NORMAL`].
+/// The [`Mesh::ATTRIBUTE_UV_0`] and [`Mesh::ATTRIBUTE_COLOR`] of the mesh are kept, so hits still
+/// report their UVs and vertex colors.
+///
+/// Each corner of a triangle is smoothed with the corners of other triangles at the same position,
+/// as long as the angle between the two triangles is at most `angle` radians. Contributions are
+/// weighted by the angle of each triangle at that corner. Edges sharper than `angle` stay hard, so
+/// a cube keeps flat faces with an `angle` below 90 degrees.
+///
+/// Returns `None` if the triangles of the mesh can't be read, see [`mesh_triangles`].
+pub fn smooth_normals(mesh: &Mesh, angle: f32) -> Option<Mesh> {
+    let corners: Vec<[f32; 3]> = mesh_triangles(mesh)
+        .ok()?
+        .flat_map(|triangle| [triangle.v0, triangle.v1, triangle.v2].map(|v| v.to_array()))
+        .collect();
+    let corners = &corners[..];
+    let uvs: Option<Vec<[f32; 2]>> = match mesh.attribute(Mesh::ATTRIBUTE_UV_0) {
+        Some(VertexAttributeValues::Float32x2(uvs)) => match mesh.indices() {
+            Some(indices) => indices.iter().map(|i| uvs.get(i).copied()).collect(),
+            None => Some(uvs.clone()),
+        },
+        _ => None,
+    };
+    let colors: Option<Vec<[f32; 4]>> = match mesh.attribute(Mesh::ATTRIBUTE_COLOR) {
+        Some(VertexAttributeValues::Float32x4(colors)) => match mesh.indices() {
+            Some(indices) => indices.iter().map(|i| colors.get(i).copied()).collect(),
+            None => Some(colors.clone()),
+        },
+        Some(VertexAttributeValues::Float32x3(colors)) => {
+            let color = |i: usize| colors.get(i).map(|&[r, g, b]| [r, g, b, 1.0]);
+            match mesh.indices() {
+                Some(indices) => indices.iter().map(color).collect(),
+                None => (0..colors.len()).map(color).collect(),
+            }
+        }
+        _ => None,
+    };
+
+    let mut face_normals = Vec::with_capacity(corners.len() / 3);
+    let mut corner_angles = Vec::with_capacity(corners.len());
+    for triangle in corners.chunks_exact(3) {
+        let [a, b, c] = [0, 1, 2].map(|i| Vec3::from(triangle[i]));
+        face_normals.push((b - a).cross(c - a).normalize_or_zero());
+        corner_angles.extend([
+            (b - a).angle_between(c - a),
+            (c - b).angle_between(a - b),
+            (a - c).angle_between(b - c),
+        ]);
+    }
+
+    // Sort the corners by position, so coincident corners are next to each other.
+    let key = |corner: usize| corners[corner].map(|axis| (axis / SMOOTHING_EPSILON).round() as i64);
+    let mut sorted: Vec<usize> = (0..corners.len()).collect();
+    sorted.sort_unstable_by_key(|&corner| key(corner));
+
+    let min_cos = angle.cos();
+    let mut normals = vec![[0.0; 3]; corners.len()];
+    let mut start = 0;
+    while start < sorted.len() {
+        let group_key = key(sorted[start]);
+        let len = sorted[start..]
+            .iter()
+            .take_while(|&&corner| key(corner) == group_key)
+            .count();
+        let group = &sorted[start..start + len];
+        start += len;
+        for &corner in group {
+            let face_normal = face_normals[corner / 3];
+            let normal: Vec3 = group
+                .iter()
+                .filter(|&&other| face_normal.dot(face_normals[other / 3]) >= min_cos)
+                .map(|&other| face_normals[other / 3] * corner_angles[other])
+                .sum();
+            let normal = normal.try_normalize().unwrap_or(face_normal);
+            normals[corner] = normal.to_array();
+        }
+    }
+
+    let mut smoothed = Mesh::new(PrimitiveTopology::TriangleList);
+    smoothed.insert_attribute(Mesh::ATTRIBUTE_POSITION, corners.to_vec());
+    smoothed.insert_attribute(Mesh::ATTRIBUTE_NORMAL, normals);
+    if let Some(mut uvs) = uvs {
+        uvs.truncate(corners.len());
+        smoothed.insert_attribute(Mesh::ATTRIBUTE_UV_0, uvs);
+    }
+    if let Some(mut colors) = colors {
+        colors.truncate(corners.len());
+        smoothed.insert_attribute(Mesh::ATTRIBUTE_COLOR, colors);
+    }
+    Some(smoothed)
+}
+
+/// Decodes quantized vertex positions into `f32` positions, see [`RaycastPositionDecoder`]. Only the
+/// first three components of a position are used. Returns `None` for formats with fewer than three
+/// components.
+pub fn decode_positions(
+    values: &VertexAttributeValues,
+    decoder: &RaycastPositionDecoder,
+) -> Option<Vec<[f32; 3]>> {
+    fn xyz<T: Copy, const N: usize>(values: &[[T; N]], f: impl Fn(T) -> f32) -> Vec<Vec3> {
+        values
+            .iter()
+            .map(|v| Vec3::new(f(v[0]), f(v[1]), f(v[2])))
+            .collect()
+    }
+    let snorm16 = |v: i16| (v as f32 / i16::MAX as f32).max(-1.0);
+    let unorm16 = |v: u16| v as f32 / u16::MAX as f32;
+    let snorm8 = |v: i8| (v as f32 / i8::MAX as f32).max(-1.0);
+    let unorm8 = |v: u8| v as f32 / u8::MAX as f32;
+    let positions = match values {
+        VertexAttributeValues::Float32x3(v) => xyz(v, |v| v),
+        VertexAttributeValues::Float32x4(v) => xyz(v, |v| v),
+        VertexAttributeValues::Sint32x3(v) => xyz(v, |v| v as f32),
+        VertexAttributeValues::Sint32x4(v) => xyz(v, |v| v as f32),
+        VertexAttributeValues::Uint32x3(v) => xyz(v, |v| v as f32),
+        VertexAttributeValues::Uint32x4(v) => xyz(v, |v| v as f32),
+        VertexAttributeValues::Sint16x4(v) => xyz(v, |v| v as f32),
+        VertexAttributeValues::Uint16x4(v) => xyz(v, |v| v as f32),
+        VertexAttributeValues::Snorm16x4(v) => xyz(v, snorm16),
+        VertexAttributeValues::Unorm16x4(v) => xyz(v, unorm16),
+        VertexAttributeValues::Sint8x4(v) => xyz(v, |v| v as f32),
+        VertexAttributeValues::Uint8x4(v) => xyz(v, |v| v as f32),
+        VertexAttributeValues::Snorm8x4(v) => xyz(v, snorm8),
+        VertexAttributeValues::Unorm8x4(v) => xyz(v, unorm8),
+        _ => return None,
+    };
+    let decoded = positions.into_iter().map(|p| decoder.decode(p).to_array());
+    Some(decoded.collect())
+}
+
+/// Returns a copy of the mesh with the quantized positions of the decoder's attribute decoded into
+/// its `f32` positions, so it can be raycasted. See [`decode_positions`]. Returns `None` if the mesh
+/// doesn't have the attribute, or if it can't be decoded.
+pub fn decode_mesh_positions(mesh: &Mesh, decoder: &RaycastPositionDecoder) -> Option<Mesh> {
+    let positions = decode_positions(mesh.attribute(decoder.attribute)?, decoder)?;
+    let mut decoded = mesh.clone();
+    decoded.insert_attribute(Mesh::ATTRIBUTE_POSITION, positions);
+    Some(decoded)
+}
+
+/// Get the vertex positions and, if there are any, the vertex normals of a mesh.
+fn mesh_vertex_data(mesh: &Mesh) -> Result<(&[[f32; 3]], Option<&[[f32; 3]]>), MeshExtractError> {
+    let vertex_positions = mesh_positions(mesh)?;
+    // Without usable vertex normals, the flat normal of each triangle is used instead.
+    let vertex_normals = match mesh.attribute(Mesh::ATTRIBUTE_NORMAL) {
+        Some(VertexAttributeValues::Float32x3(normals))
+            if normals.len() == vertex_positions.len() =>
+        {
+            Some(&normals[..])
+        }
+        _ => None,
+    };
+    Ok((vertex_positions, vertex_normals))
+}
+
+/// Returns `true` if the triangles of meshes with this topology can be raycast, i.e. for
+/// [`PrimitiveTopology::TriangleList`] and [`PrimitiveTopology::TriangleStrip`].
+pub(crate) fn is_triangle_topology(topology: PrimitiveTopology) -> bool {
+    matches!(
+        topology,
+        PrimitiveTopology::TriangleList | PrimitiveTopology::TriangleStrip
+    )
+}
+
+/// Get the vertex positions of a mesh whose triangles can be raycast.
+fn mesh_positions(mesh: &Mesh) -> Result<&[[f32; 3]], MeshExtractError> {
+    let topology = mesh.primitive_topology();
+    if !is_triangle_topology(topology) {
+        return Err(MeshExtractError::UnsupportedTopology(topology));
+    }
+    vertex_positions(mesh)
+}
+
+/// Get the vertex positions of a mesh, whatever its topology. Every reader of positions goes
+/// through this, so meshes whose positions aren't [`VertexAttributeValues::Float32x3`] are skipped
+/// instead of misread. These can be made with a custom attribute that shares the id of
+/// [`Mesh::ATTRIBUTE_POSITION`], e.g. by other crates.
+pub(crate) fn vertex_positions(mesh: &Mesh) -> Result<&[[f32; 3]], MeshExtractError> {
+    match mesh.attribute(Mesh::ATTRIBUTE_POSITION) {
+        Some(VertexAttributeValues::Float32x3(positions)) => Ok(positions),
+        Some(values) => Err(MeshExtractError::UnsupportedPositionFormat(values.into())),
+        None => Err(MeshExtractError::MissingPositions),
+    }
+}
+
+/// Checks that the positions of a mesh can be read, and that a triangle list has whole triangles,
+/// without reading its indices. Indices past the end of the vertex buffer are skipped by raycasts.
+pub(crate) fn check_mesh_layout(mesh: &Mesh) -> Result<(), MeshExtractError> {
+    let vertex_count = vertex_positions(mesh)?.len();
+    let len = mesh.indices().map_or(vertex_count, Indices::len);
+    let is_list = mesh.primitive_topology() == PrimitiveTopology::TriangleList;
+    if is_list && len % 3 != 0 {
+        return Err(MeshExtractError::IncompleteTriangle { len });
+    }
+    Ok(())
+}
+
+/// Checks that a mesh has a vertex normal for each of its positions, if it has any. Raycasts use
+/// the face normals of meshes whose vertex normals don't match.
+pub(crate) fn check_mesh_normals(mesh: &Mesh) -> Result<(), MeshExtractError> {
+    let positions = vertex_positions(mesh)?.len();
+    match mesh.attribute(Mesh::ATTRIBUTE_NORMAL) {
+        Some(VertexAttributeValues::Float32x3(normals)) if normals.len() != positions => {
+            Err(MeshExtractError::NormalCountMismatch {
+                normals: normals.len(),
+                positions,
+            })
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Why the triangles of a mesh can't be read. See [`mesh_triangles`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MeshExtractError {
+    /// The mesh is not a [`PrimitiveTopology::TriangleList`] or a
+    /// [`PrimitiveTopology::TriangleStrip`].
+    UnsupportedTopology(PrimitiveTopology),
+    /// The mesh has no [`Mesh::ATTRIBUTE_POSITION`].
+    MissingPositions,
+    /// The positions of the mesh are not stored as [`VertexAttributeValues::Float32x3`].
+    UnsupportedPositionFormat(VertexFormat),
+    /// The number of indices, or of vertices if the mesh has no indices, is not a multiple of 3 in a
+    /// triangle list.
+    IncompleteTriangle { len: usize },
+    /// An index refers to a vertex past the end of the vertex buffer.
+    IndexOutOfBounds { index: usize, vertex_count: usize },
+    /// The mesh has vertex normals, but not one for each vertex position. The mesh can still be
+    /// raycast, with face normals.
+    NormalCountMismatch { normals: usize, positions: usize },
+}
+
+impl fmt::Display for MeshExtractError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MeshExtractError::UnsupportedTopology(topology) => write!(
+                f,
+                "only `TriangleList` and `TriangleStrip` meshes are supported, found `{topology:?}`"
+            ),
+            MeshExtractError::MissingPositions => write!(f, "the mesh has no vertex positions"),
+            MeshExtractError::UnsupportedPositionFormat(format) => write!(
+                f,
+                "vertex positions must be `Float32x3`, found `{format:?}`"
+            ),
+            MeshExtractError::IncompleteTriangle { len } => {
+                write!(f, "{len} indices or vertices is not a multiple of 3")
+            }
+            MeshExtractError::IndexOutOfBounds {
+                index,
+                vertex_count,
+            } => write!(
+                f,
+                "index {index} is out of bounds for a mesh with {vertex_count} vertices"
+            ),
+            MeshExtractError::NormalCountMismatch { normals, positions } => write!(
+                f,
+                "the mesh has {normals} vertex normals for {positions} vertex positions"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for MeshExtractError {}
+
+/// The vertex indices of the triangles of a mesh, read from its index buffer, or from its vertex
+/// buffer if it has no indices.
+///
+/// In a [`PrimitiveTopology::TriangleList`], triangle `i` is made of the vertices at indices
+/// `3i..3i + 3`, and any vertices after the last whole triangle are ignored. In a
+/// [`PrimitiveTopology::TriangleStrip`], triangle `i` is made of the vertices at indices
+/// `i..i + 3`, and the first two vertices of every other triangle are swapped, so the whole strip
+/// has the same winding. Strip triangles containing a restart index don't exist, and the winding
+/// starts over after each restart.
+#[derive(Clone)]
+struct TriangleVertices<'a, I> {
+    indices: Option<&'a [I]>,
+    vertex_count: usize,
+    /// The positions of the restart indices of a triangle strip, or `None` for a triangle list.
+    strip_restarts: Option<Vec<usize>>,
+}
+
+impl<'a, I: IntoUsize> TriangleVertices<'a, I> {
+    fn new(indices: Option<&'a [I]>, vertex_count: usize, topology: PrimitiveTopology) -> Self {
+        let strip_restarts = (topology == PrimitiveTopology::TriangleStrip).then(|| {
+            indices
+                .into_iter()
+                .flatten()
+                .enumerate()
+                .filter(|(_, index)| index.into_usize() == I::STRIP_RESTART)
+                .map(|(position, _)| position)
+                .collect()
+        });
+        Self {
+            indices,
+            vertex_count,
+            strip_restarts,
+        }
+    }
+
+    fn len(&self) -> usize {
+        let len = self.indices.map_or(self.vertex_count, <[I]>::len);
+        match self.strip_restarts {
+            Some(_) => len.saturating_sub(2),
+            None => len / 3,
+        }
+    }
+
+    fn get(&self, triangle: usize) -> Option<[usize; 3]> {
+        let vertex = |position: usize| {
+            self.indices
+                .map_or(position, |indices| indices[position].into_usize())
+        };
+        let Some(restarts) = &self.strip_restarts else {
+            return Some([0, 1, 2].map(|corner| vertex(triangle * 3 + corner)));
+        };
+        let restarts_before = restarts.partition_point(|&restart| restart < triangle);
+        if restarts
+            .get(restarts_before)
+            .is_some_and(|&restart| restart < triangle + 3)
+        {
+            return None;
+        }
+        let strip_start = match restarts_before {
+            0 => 0,
+            count => restarts[count - 1] + 1,
+        };
+        let corners = match (triangle - strip_start) % 2 == 0 {
+            true => [0, 1, 2],
+            false => [1, 0, 2],
+        };
+        Some(corners.map(|corner| vertex(triangle + corner)))
+    }
+}
+
+/// The [`TriangleVertices`] of a mesh, for either width of its indices.
+#[derive(Clone)]
+enum MeshIndices<'a> {
+    U16(TriangleVertices<'a, u16>),
+    U32(TriangleVertices<'a, u32>),
+}
+
+impl<'a> MeshIndices<'a> {
+    fn new(mesh: &'a Mesh, vertex_count: usize) -> Self {
+        let topology = mesh.primitive_topology();
+        match mesh.indices() {
+            Some(Indices::U16(indices)) => {
+                MeshIndices::U16(TriangleVertices::new(Some(indices), vertex_count, topology))
+            }
+            Some(Indices::U32(indices)) => {
+                MeshIndices::U32(TriangleVertices::new(Some(indices), vertex_count, topology))
+            }
+            None => MeshIndices::U32(TriangleVertices::new(None, vertex_count, topology)),
+        }
+    }
+
+    fn len(&self) -> usize {
+        match self {
+            MeshIndices::U16(vertices) => vertices.len(),
+            MeshIndices::U32(vertices) => vertices.len(),
+        }
+    }
+
+    fn get(&self, triangle: usize) -> Option<[usize; 3]> {
+        match self {
+            MeshIndices::U16(vertices) => vertices.get(triangle),
+            MeshIndices::U32(vertices) => vertices.get(triangle),
+        }
     }
 }
 
+/// The position of the first index of a triangle in the index buffer of a mesh with this
+/// `topology`, or in its vertex buffer if it has no indices. See [`TriangleVertices`].
+pub(crate) fn triangle_first_index(topology: PrimitiveTopology, triangle: usize) -> usize {
+    match topology {
+        PrimitiveTopology::TriangleStrip => triangle,
+        _ => triangle * 3,
+    }
+}
+
+/// Get the indices of the vertices of a triangle of a mesh, the same way raycasts read them, or
+/// `None` if the mesh has no such triangle.
+pub(crate) fn mesh_triangle_vertices(mesh: &Mesh, triangle: usize) -> Option<[usize; 3]> {
+    let vertices = MeshIndices::new(mesh, mesh.count_vertices());
+    (triangle < vertices.len())
+        .then(|| vertices.get(triangle))
+        .flatten()
+}
+
+/// An iterator over the triangles of a mesh, in the order of its index buffer. See
+/// [`mesh_triangles`].
+#[derive(Clone)]
+pub struct TriangleIter<'a> {
+    positions: &'a [[f32; 3]],
+    vertices: MeshIndices<'a>,
+    transform: Option<Mat4>,
+    triangles: std::ops::Range<usize>,
+}
+
+impl TriangleIter<'_> {
+    /// Returns the next triangle along with its index, skipping the triangles of a strip that
+    /// contain a restart index.
+    fn next_indexed(&mut self) -> Option<(usize, Triangle)> {
+        loop {
+            let triangle = self.triangles.next()?;
+            let Some(vertices) = self.vertices.get(triangle) else {
+                continue;
+            };
+            let vertices = vertices.map(|i| {
+                let position = Vec3A::from(self.positions[i]);
+                match self.transform {
+                    Some(transform) => transform.transform_point3a(position),
+                    None => position,
+                }
+            });
+            return Some((triangle, Triangle::from(vertices)));
+        }
+    }
+}
+
+impl Iterator for TriangleIter<'_> {
+    type Item = Triangle;
+
+    fn next(&mut self) -> Option<Triangle> {
+        self.next_indexed().map(|(_, triangle)| triangle)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (0, self.triangles.size_hint().1)
+    }
+}
+
+/// Iterates over the triangles of a mesh in local space, the same way raycasts read them. Meshes
+/// with `u16` indices, `u32` indices, or no indices are supported. The `i`th triangle of a
+/// triangle list has the [`triangle_index`](IntersectionData::triangle_index) `i`. The triangles
+/// of a triangle strip that contain a restart index are skipped, so triangle indices of a strip
+/// are only consecutive between restarts.
+///
+/// Returns an error, instead of panicking, if the mesh is not a
+/// [`PrimitiveTopology::TriangleList`] or a [`PrimitiveTopology::TriangleStrip`], if its positions
+/// are missing or not [`VertexAttributeValues::Float32x3`], or if its indices don't describe whole
+/// triangles within its vertex buffer.
+pub fn mesh_triangles(mesh: &Mesh) -> Result<TriangleIter<'_>, MeshExtractError> {
+    let positions = mesh_positions(mesh)?;
+    let vertex_count = positions.len();
+    let len = mesh.indices().map_or(vertex_count, Indices::len);
+    let vertices = MeshIndices::new(mesh, vertex_count);
+    let is_strip = mesh.primitive_topology() == PrimitiveTopology::TriangleStrip;
+    if !is_strip && len % 3 != 0 {
+        return Err(MeshExtractError::IncompleteTriangle { len });
+    }
+    let restart = match mesh.indices() {
+        Some(Indices::U16(_)) => u16::STRIP_RESTART,
+        _ => u32::STRIP_RESTART,
+    };
+    let max_index = mesh.indices().and_then(|indices| {
+        indices
+            .iter()
+            .filter(|&index| !is_strip || index != restart)
+            .max()
+    });
+    if let Some(index) = max_index {
+        if index >= vertex_count {
+            return Err(MeshExtractError::IndexOutOfBounds {
+                index,
+                vertex_count,
+            });
+        }
+    }
+    Ok(TriangleIter {
+        positions,
+        triangles: 0..vertices.len(),
+        vertices,
+        transform: None,
+    })
+}
+
+/// Like [`mesh_triangles`], but the triangles are transformed by `mesh_transform`, e.g. to world
+/// space.
+pub fn mesh_triangles_transformed<'a>(
+    mesh: &'a Mesh,
+    mesh_transform: &Mat4,
+) -> Result<TriangleIter<'a>, MeshExtractError> {
+    Ok(TriangleIter {
+        transform: Some(*mesh_transform),
+        ..mesh_triangles(mesh)?
+    })
+}
+
+/// Finds the point where a ray comes closest to a mesh, returning the closest approach as an
+/// intersection along with the separation between the ray and the mesh at that point. The position
+/// of the intersection is the point on the mesh closest to the ray, and the distance is the distance
+/// along the ray to the point of closest approach.
+///
+/// This is computed in world space, so the separation is not affected by the scale of the mesh.
+pub fn ray_mesh_closest_approach(
+    mesh: &Mesh,
+    mesh_transform: &Mat4,
+    ray: &Ray3d,
+) -> Option<(IntersectionData, f32)> {
+    let mut closest: Option<(ClosestApproach, Triangle, usize)> = None;
+    let mut triangles = mesh_triangles_transformed(mesh, mesh_transform).ok()?;
+    while let Some((triangle_index, triangle)) = triangles.next_indexed() {
+        let approach = ray_triangle_closest_approach(ray, &triangle);
+        let is_closer = closest.map_or(true, |(best, _, _)| {
+            (approach.separation, approach.ray_distance) < (best.separation, best.ray_distance)
+        });
+        if is_closer {
+            closest = Some((approach, triangle, triangle_index));
+        }
+    }
+
+    closest.map(|(approach, triangle, triangle_index)| {
+        let normal = (triangle.v1 - triangle.v0)
+            .cross(triangle.v2 - triangle.v0)
+            .normalize_or_zero();
+        let mut intersection = IntersectionData::new(
+            approach.position.into(),
+            normal.into(),
+            approach.ray_distance,
+            Some(triangle),
+        );
+        intersection.set_triangle_index(triangle_index);
+        intersection.set_provenance(HitProvenance {
+            algorithm: RaycastAlgorithm::ClosestApproach,
+            ..HitProvenance::default()
+        });
+        (intersection, approach.separation)
+    })
+}
+
+/// Returns `true` if meshes with this topology are made of line segments that can be picked with
+/// [`ray_line_mesh_intersection`].
+pub(crate) fn is_line_topology(topology: PrimitiveTopology) -> bool {
+    matches!(
+        topology,
+        PrimitiveTopology::LineList | PrimitiveTopology::LineStrip
+    )
+}
+
+/// Cast a ray on a mesh made of lines, a [`PrimitiveTopology::LineList`] or a
+/// [`PrimitiveTopology::LineStrip`], and returns the nearest segment the ray passes within
+/// `tolerance` of, in world space. A ray can't hit a line exactly, so this is how lines are picked.
+/// The position of the intersection is the point on the segment closest to the ray, its distance
+/// is the distance along the ray to the point of closest approach, and its normal faces the ray.
+///
+/// Returns `None` for meshes of other topologies, or without [`VertexAttributeValues::Float32x3`]
+/// positions. Indices past the end of the vertex buffer, such as restart indices, end a segment.
+pub fn ray_line_mesh_intersection(
+    mesh: &Mesh,
+    mesh_transform: &Mat4,
+    ray: &Ray3d,
+    tolerance: f32,
+    min_distance: f32,
+    max_distance: Option<f32>,
+) -> Option<IntersectionData> {
+    let positions = vertex_positions(mesh).ok()?;
+    let vertex = |position: usize| {
+        let index = match mesh.indices() {
+            Some(Indices::U16(indices)) => *indices.get(position)? as usize,
+            Some(Indices::U32(indices)) => *indices.get(position)? as usize,
+            None => position,
+        };
+        let position = Vec3A::from(*positions.get(index)?);
+        Some(mesh_transform.transform_point3a(position))
+    };
+    // Segment `i` starts at vertex `2i` of a list, or vertex `i` of a strip.
+    let len = mesh.indices().map_or(positions.len(), Indices::len);
+    let (segments, stride) = match mesh.primitive_topology() {
+        PrimitiveTopology::LineList => (len / 2, 2),
+        PrimitiveTopology::LineStrip => (len.saturating_sub(1), 1),
+        _ => return None,
+    };
+
+    let mut nearest: Option<ClosestApproach> = None;
+    for segment in 0..segments {
+        let (Some(a), Some(b)) = (vertex(segment * stride), vertex(segment * stride + 1)) else {
+            continue;
+        };
+        let approach = ray_segment_closest_approach(ray, a, b);
+        let is_within = approach.separation <= tolerance
+            && approach.ray_distance >= min_distance
+            && max_distance.map_or(true, |max| approach.ray_distance <= max);
+        if is_within && nearest.map_or(true, |nearest| approach.ray_distance < nearest.ray_distance)
+        {
+            nearest = Some(approach);
+        }
+    }
+
+    nearest.map(|approach| {
+        let mut intersection = IntersectionData::new(
+            approach.position.into(),
+            -ray.direction(),
+            approach.ray_distance,
+            None,
+        );
+        intersection.set_provenance(HitProvenance {
+            algorithm: RaycastAlgorithm::ClosestApproach,
+            ..HitProvenance::default()
+        });
+        intersection
+    })
+}
+
+/// Cast a ray on a mesh, and returns every intersection with its triangles instead of only the
+/// nearest, sorted by distance. Backfaces are always included, so a ray passing through a closed
+/// mesh reports both where it enters and where it exits. The `winding` of the mesh is only used to
+/// orient the normals of triangles without vertex normals.
+pub fn ray_mesh_intersections(
+    mesh: &Mesh,
+    mesh_transform: &Mat4,
+    ray: &Ray3d,
+    winding: RaycastWinding,
+) -> Vec<IntersectionData> {
+    let options = MeshRaycastOptions {
+        backfaces: Backfaces::Include,
+        winding,
+        ..Default::default()
+    };
+    ray_intersections_over_mesh(mesh, mesh_transform, ray, &options)
+}
+
+/// Cast a ray on a mesh, and returns up to [`max_hits`](MeshRaycastOptions::max_hits) of its
+/// intersections with the triangles of the mesh, nearest first, such as where a ray enters and
+/// exits a closed mesh. Only the nearest `max_hits` intersections are kept, so a ray grazing a
+/// large mesh can't collect a hit for each of its triangles.
+pub fn ray_intersections_over_mesh(
+    mesh: &Mesh,
+    mesh_transform: &Mat4,
+    ray: &Ray3d,
+    options: &MeshRaycastOptions,
+) -> Vec<IntersectionData> {
+    let &MeshRaycastOptions {
+        backfaces: backface_culling,
+        winding,
+        max_slope,
+        min_distance,
+        max_distance,
+        max_hits,
+    } = options;
+    let Ok((vertex_positions, vertex_normals)) = mesh_vertex_data(mesh) else {
+        return Vec::new();
+    };
+    let triangles = MeshIndices::new(mesh, vertex_positions.len());
+
+    let world_to_mesh = mesh_transform.inverse();
+    let mesh_space_ray = Ray3d::new(
+        world_to_mesh.transform_point3(ray.origin()),
+        world_to_mesh.transform_vector3(ray.direction()),
+    );
+    let mesh_scale = world_to_mesh.transform_vector3(ray.direction()).length();
+    let min_mesh_distance = min_distance * mesh_scale;
+    let max_mesh_distance = max_distance.map(|distance| distance * mesh_scale);
+
+    let mut intersections = Vec::new();
+    for triangle_index in 0..triangles.len() {
+        let Some(indices) = triangles.get(triangle_index) else {
+            continue;
+        };
+        let Some(tri_vertex_positions) = triangle_corners(vertex_positions, indices) else {
+            continue;
+        };
+        let tri_normals = vertex_normals.and_then(|normals| triangle_corners(normals, indices));
+        let intersection = triangle_intersection(
+            tri_vertex_positions,
+            tri_normals,
+            f32::MAX,
+            mesh_space_ray,
+            backface_culling,
+            winding,
+        );
+        let intersection = intersection.filter(|i| {
+            i.distance() >= min_mesh_distance
+                && max_mesh_distance.map_or(true, |max_distance| i.distance() <= max_distance)
+        });
+        if let Some(intersection) = intersection {
+            let intersection = accept_intersection(
+                &intersection,
+                triangle_index,
+                mesh_transform,
+                &mesh_space_ray,
+                backface_culling,
+                winding,
+            );
+            if max_slope.is_some_and(|slope| !slope.allows(intersection.normal())) {
+                continue;
+            }
+            intersections.push(intersection);
+            if intersections.len() > max_hits.get() {
+                let farthest = intersections
+                    .iter()
+                    .enumerate()
+                    .max_by(|(_, a), (_, b)| a.distance().total_cmp(&b.distance()))
+                    .map(|(index, _)| index);
+                intersections.swap_remove(farthest.unwrap_or_default());
+            }
+        }
+    }
+    for intersection in &mut intersections {
+        intersection.set_vertex_attributes_from_mesh(mesh);
+    }
+    intersections.sort_by(|a, b| a.distance().total_cmp(&b.distance()));
+    intersections
+}
+
 pub trait IntoUsize: Copy {
+    /// The index that restarts a [`PrimitiveTopology::TriangleStrip`], the largest value of the
+    /// index type.
+    const STRIP_RESTART: usize = usize::MAX;
+
     fn into_usize(self) -> usize;
 }
 impl IntoUsize for u16 {
+    const STRIP_RESTART: usize = u16::MAX as usize;
+
+    fn into_usize(self) -> usize {
+        self as usize
+    }
+}
+impl IntoUsize for u32 {
+    const STRIP_RESTART: usize = u32::MAX as usize;
+
     fn into_usize(self) -> usize {
         self as usize
     }
 }
-impl IntoUsize for u32 {
-    fn into_usize(self) -> usize {
-        self as usize
-    }
+
+/// Checks if a ray intersects a mesh, and returns the nearest intersection if one exists.
+///
+/// Hits closer than [`min_distance`](MeshRaycastOptions::min_distance) or farther than
+/// [`max_distance`](MeshRaycastOptions::max_distance) along the ray, in world space, are ignored.
+/// Triangles behind a hit closer than `min_distance` can still be hit.
+pub fn ray_mesh_intersection(
+    mesh_transform: &Mat4,
+    vertex_positions: &[[f32; 3]],
+    vertex_normals: Option<&[[f32; 3]]>,
+    ray: &Ray3d,
+    indices: Option<&Vec<impl IntoUsize>>,
+    options: &MeshRaycastOptions,
+) -> Option<IntersectionData> {
+    mesh_intersection(
+        mesh_transform,
+        vertex_positions,
+        vertex_normals,
+        ray,
+        indices,
+        PrimitiveTopology::TriangleList,
+        options,
+        TriangleSearch::Nearest,
+    )
+}
+
+/// Like [`ray_mesh_intersection`], but returns the first intersection that is found instead of
+/// the nearest one, so the rest of the triangles aren't tested. This is useful when only whether
+/// the mesh is hit matters, such as for line of sight checks.
+///
+/// The triangles are tested in the order they are stored in, so the same intersection is always
+/// returned for the same mesh and ray, but it can be any intersection along the ray.
+pub fn ray_mesh_any_intersection(
+    mesh_transform: &Mat4,
+    vertex_positions: &[[f32; 3]],
+    vertex_normals: Option<&[[f32; 3]]>,
+    ray: &Ray3d,
+    indices: Option<&Vec<impl IntoUsize>>,
+    options: &MeshRaycastOptions,
+) -> Option<IntersectionData> {
+    mesh_intersection(
+        mesh_transform,
+        vertex_positions,
+        vertex_normals,
+        ray,
+        indices,
+        PrimitiveTopology::TriangleList,
+        options,
+        TriangleSearch::Any,
+    )
 }
 
-/// Checks if a ray intersects a mesh, and returns the nearest intersection if one exists.
-pub fn ray_mesh_intersection(
+#[allow(clippy::too_many_arguments)]
+fn mesh_intersection(
     mesh_transform: &Mat4,
     vertex_positions: &[[f32; 3]],
     vertex_normals: Option<&[[f32; 3]]>,
     ray: &Ray3d,
     indices: Option<&Vec<impl IntoUsize>>,
-    backface_culling: Backfaces,
+    topology: PrimitiveTopology,
+    options: &MeshRaycastOptions,
+    search: TriangleSearch,
 ) -> Option<IntersectionData> {
+    let &MeshRaycastOptions {
+        backfaces: backface_culling,
+        winding,
+        max_slope,
+        min_distance,
+        max_distance,
+        ..
+    } = options;
     // The ray cast can hit the same mesh many times, so we need to track which hit is
     // closest to the camera, and record that.
     let mut min_pick_distance = f32::MAX;
@@ -106,129 +1163,170 @@ pub fn ray_mesh_intersection(
         world_to_mesh.transform_point3(ray.origin()),
         world_to_mesh.transform_vector3(ray.direction()),
     );
+    // The mesh space ray is normalized, so its distances are scaled by the length of the world
+    // space direction in mesh space.
+    let mesh_scale = world_to_mesh.transform_vector3(ray.direction()).length();
+    let min_mesh_distance = min_distance * mesh_scale;
+    let max_mesh_distance = max_distance.map(|distance| distance * mesh_scale);
 
-    if let Some(indices) = indices {
-        // Make sure this chunk has 3 vertices to avoid a panic.
-        if indices.len() % 3 != 0 {
-            warn!("Index list not a multiple of 3");
-            return None;
-        }
-        // Now that we're in the vector of vertex indices, we want to look at the vertex
-        // positions for each triangle, so we'll take indices in chunks of three, where each
-        // chunk of three indices are references to the three vertices of a triangle.
-        for index in indices.chunks(3) {
-            let tri_vertex_positions = [
-                Vec3A::from(vertex_positions[index[0].into_usize()]),
-                Vec3A::from(vertex_positions[index[1].into_usize()]),
-                Vec3A::from(vertex_positions[index[2].into_usize()]),
-            ];
-            let tri_normals = vertex_normals.map(|normals| {
-                [
-                    Vec3A::from(normals[index[0].into_usize()]),
-                    Vec3A::from(normals[index[1].into_usize()]),
-                    Vec3A::from(normals[index[2].into_usize()]),
-                ]
-            });
-            let intersection = triangle_intersection(
-                tri_vertex_positions,
-                tri_normals,
-                min_pick_distance,
-                mesh_space_ray,
-                backface_culling,
-            );
-            if let Some(i) = intersection {
-                pick_intersection = Some(IntersectionData::new(
-                    mesh_transform.transform_point3(i.position()),
-                    mesh_transform.transform_vector3(i.normal()),
-                    mesh_transform
-                        .transform_vector3(mesh_space_ray.direction() * i.distance())
-                        .length(),
-                    i.triangle().map(|tri| {
-                        Triangle::from([
-                            mesh_transform.transform_point3a(tri.v0),
-                            mesh_transform.transform_point3a(tri.v1),
-                            mesh_transform.transform_point3a(tri.v2),
-                        ])
-                    }),
-                ));
-                min_pick_distance = i.distance();
-            }
-        }
-    } else {
-        for i in (0..vertex_positions.len()).step_by(3) {
-            let tri_vertex_positions = [
-                Vec3A::from(vertex_positions[i]),
-                Vec3A::from(vertex_positions[i + 1]),
-                Vec3A::from(vertex_positions[i + 2]),
-            ];
-            let tri_normals = vertex_normals.map(|normals| {
-                [
-                    Vec3A::from(normals[i]),
-                    Vec3A::from(normals[i + 1]),
-                    Vec3A::from(normals[i + 2]),
-                ]
-            });
-            let intersection = triangle_intersection(
-                tri_vertex_positions,
-                tri_normals,
-                min_pick_distance,
-                mesh_space_ray,
+    // Make sure the index list has 3 indices per triangle to avoid a panic.
+    let is_list = topology == PrimitiveTopology::TriangleList;
+    if is_list && indices.is_some_and(|indices| indices.len() % 3 != 0) {
+        warn!("Index list not a multiple of 3");
+        return None;
+    }
+    // Each triangle is made of three vertices, given by three consecutive indices, or by three
+    // consecutive vertex positions if the mesh has no indices. Consecutive triangles of a strip
+    // share two of their vertices.
+    let triangles =
+        TriangleVertices::new(indices.map(Vec::as_slice), vertex_positions.len(), topology);
+    for triangle_index in 0..triangles.len() {
+        let Some(index) = triangles.get(triangle_index) else {
+            continue;
+        };
+        // Triangles with an index past the end of the vertex buffer are skipped.
+        let Some(tri_vertex_positions) = triangle_corners(vertex_positions, index) else {
+            continue;
+        };
+        let tri_normals = vertex_normals.and_then(|normals| triangle_corners(normals, index));
+        let intersection = triangle_intersection(
+            tri_vertex_positions,
+            tri_normals,
+            min_pick_distance,
+            mesh_space_ray,
+            backface_culling,
+            winding,
+        );
+        // The limits aren't used to skip triangles before they are tested, because a triangle can
+        // be hit within the limits even when all of its vertices are outside them.
+        let intersection = intersection.filter(|i| {
+            i.distance() >= min_mesh_distance
+                && max_mesh_distance.map_or(true, |max_distance| i.distance() <= max_distance)
+        });
+        if let Some(i) = intersection {
+            let accepted = accept_intersection(
+                &i,
+                triangle_index,
+                mesh_transform,
+                &mesh_space_ray,
                 backface_culling,
+                winding,
             );
-            if let Some(i) = intersection {
-                pick_intersection = Some(IntersectionData::new(
-                    mesh_transform.transform_point3(i.position()),
-                    mesh_transform.transform_vector3(i.normal()),
-                    mesh_transform
-                        .transform_vector3(mesh_space_ray.direction() * i.distance())
-                        .length(),
-                    i.triangle().map(|tri| {
-                        Triangle::from([
-                            mesh_transform.transform_point3a(tri.v0),
-                            mesh_transform.transform_point3a(tri.v1),
-                            mesh_transform.transform_point3a(tri.v2),
-                        ])
-                    }),
-                ));
+            // Skip triangles that are too steep, so those behind them can still be hit.
+            if max_slope.map_or(true, |slope| slope.allows(accepted.normal())) {
+                pick_intersection = Some(accepted);
                 min_pick_distance = i.distance();
+                if search == TriangleSearch::Any {
+                    break;
+                }
             }
         }
     }
     pick_intersection
 }
 
+/// Reads the values of the three corners of a triangle from a vertex attribute, or returns `None`
+/// if one of the `vertices` is out of bounds.
+fn triangle_corners(values: &[[f32; 3]], vertices: [usize; 3]) -> Option<[Vec3A; 3]> {
+    let [a, b, c] = vertices.map(|i| values.get(i).copied().map(Vec3A::from));
+    Some([a?, b?, c?])
+}
+
+/// Transforms an intersection found with the mesh space ray back into world space.
+///
+/// This is where the orientation of the normal is guaranteed: when backfaces are included, the
+/// normal is flipped if the ray hit the back of the triangle, so it always faces the ray origin.
+/// When backfaces are culled, only front faces can be hit, so the normal is never flipped.
+fn accept_intersection(
+    intersection: &IntersectionData,
+    triangle_index: usize,
+    mesh_transform: &Mat4,
+    mesh_space_ray: &Ray3d,
+    backface_culling: Backfaces,
+    winding: RaycastWinding,
+) -> IntersectionData {
+    let barycentric_coords = intersection.barycentric_coords();
+    let local_position = intersection.position();
+    let triangle = intersection.triangle().map(|tri| {
+        Triangle::from([
+            mesh_transform.transform_point3a(tri.v0),
+            mesh_transform.transform_point3a(tri.v1),
+            mesh_transform.transform_point3a(tri.v2),
+        ])
+    });
+    // Normals are transformed by the inverse transpose, so they stay perpendicular to the surface
+    // under non-uniform scale.
+    let normal_matrix = Mat3::from_mat4(*mesh_transform).inverse().transpose();
+    let mut normal = (normal_matrix * intersection.normal()).normalize_or_zero();
+    let culls = backface_culling == Backfaces::Cull && winding != RaycastWinding::TwoSided;
+    let mut is_backface = false;
+    if let Some(tri) = intersection.triangle().filter(|_| !culls) {
+        // The winding is checked in mesh space, the same way backfaces are culled, so this is
+        // still correct for mirrored meshes and for interpolated vertex normals. Two sided meshes
+        // have no front face, so the normal itself is turned to face the ray.
+        let front = match winding {
+            RaycastWinding::TwoSided => intersection.normal().into(),
+            _ => (tri.v1 - tri.v0).cross(tri.v2 - tri.v0) * winding.sign(),
+        };
+        if front.dot(mesh_space_ray.direction) > 0.0 {
+            normal = -normal;
+            is_backface = winding != RaycastWinding::TwoSided;
+        }
+    }
+    let mut intersection = IntersectionData::new(
+        mesh_transform.transform_point3(intersection.position()),
+        normal,
+        mesh_transform
+            .transform_vector3(mesh_space_ray.direction() * intersection.distance())
+            .length(),
+        triangle,
+    );
+    intersection.set_triangle_index(triangle_index);
+    intersection.set_barycentric_coords(barycentric_coords);
+    intersection.set_local_position(local_position);
+    intersection.set_backface(is_backface);
+    intersection.set_provenance(HitProvenance {
+        backface_culling: culls,
+        ..HitProvenance::default()
+    });
+    intersection
+}
+
 fn triangle_intersection(
     tri_vertices: [Vec3A; 3],
     tri_normals: Option<[Vec3A; 3]>,
     max_distance: f32,
     ray: Ray3d,
     backface_culling: Backfaces,
+    winding: RaycastWinding,
 ) -> Option<IntersectionData> {
     if tri_vertices
         .iter()
         .any(|&vertex| (vertex - ray.origin).length_squared() < max_distance.powi(2))
     {
         // Run the raycast on the ray and triangle
-        if let Some(ray_hit) = ray_triangle_intersection(&ray, &tri_vertices, backface_culling) {
+        let ray_hit = ray_triangle_intersection(&ray, &tri_vertices, backface_culling, winding);
+        if let Some(ray_hit) = ray_hit {
             let distance = *ray_hit.distance();
             if distance > 0.0 && distance < max_distance {
                 let position = ray.position(distance);
+                let (u, v) = *ray_hit.uv_coords();
+                let w = 1.0 - u - v;
                 let normal = if let Some(normals) = tri_normals {
-                    let u = ray_hit.uv_coords().0;
-                    let v = ray_hit.uv_coords().1;
-                    let w = 1.0 - u - v;
                     normals[1] * u + normals[2] * v + normals[0] * w
                 } else {
                     (tri_vertices.v1() - tri_vertices.v0())
                         .cross(tri_vertices.v2() - tri_vertices.v0())
                         .normalize()
+                        * winding.sign()
                 };
-                let intersection = IntersectionData::new(
+                let mut intersection = IntersectionData::new(
                     position,
                     normal.into(),
                     distance,
                     Some(tri_vertices.to_triangle()),
                 );
+                intersection.set_barycentric_coords(Some(Vec3::new(w, u, v)));
                 return Some(intersection);
             }
         }
@@ -275,11 +1373,39 @@ impl TriangleTrait for Triangle {
     }
 }
 
-#[derive(Copy, Clone, Default)]
+/// Determines how triangles that face away from the ray are treated.
+#[derive(Copy, Clone, Default, Debug, PartialEq, Eq, Hash, Reflect)]
 pub enum Backfaces {
     #[default]
     Cull,
     Include,
+    /// Only triangles that face away from the ray are hit.
+    Only,
+}
+
+/// Limits how steep a surface can be for it to be hit by a ray. Triangles with a normal further
+/// than [`angle`](Self::angle) from [`up`](Self::up) are skipped, and the ray continues to any
+/// triangles behind them.
+///
+/// The normal is the world space normal of the hit, as given by [`IntersectionData::normal`]. It is
+/// compared after the mesh's transform is applied, so rotated and mirrored meshes are handled.
+#[derive(Copy, Clone, Debug, PartialEq, Reflect)]
+pub struct MaxSlope {
+    /// The maximum angle between the normal and [`up`](Self::up), in radians.
+    pub angle: f32,
+    /// The direction of a perfectly flat surface's normal.
+    pub up: Vec3,
+}
+
+impl MaxSlope {
+    pub fn new(angle: f32, up: Vec3) -> Self {
+        Self { angle, up }
+    }
+
+    /// Returns `true` if a surface with this normal is flat enough to be hit.
+    pub fn allows(&self, normal: Vec3) -> bool {
+        normal.normalize_or_zero().dot(self.up.normalize_or_zero()) >= self.angle.cos()
+    }
 }
 
 /// Takes a ray and triangle and computes the intersection and normal
@@ -288,8 +1414,28 @@ pub fn ray_triangle_intersection(
     ray: &Ray3d,
     triangle: &impl TriangleTrait,
     backface_culling: Backfaces,
+    winding: RaycastWinding,
 ) -> Option<RayHit> {
-    raycast_moller_trumbore(ray, triangle, backface_culling)
+    match winding {
+        RaycastWinding::CounterClockwise => {
+            raycast_moller_trumbore(ray, triangle, backface_culling)
+        }
+        RaycastWinding::Clockwise => {
+            // Swapping two vertices reverses the winding, and swaps their barycentric coordinates.
+            let reversed = [triangle.v0(), triangle.v2(), triangle.v1()];
+            let hit = raycast_moller_trumbore(ray, &reversed, backface_culling)?;
+            let (u, v) = hit.uv_coords;
+            Some(RayHit {
+                uv_coords: (v, u),
+                ..hit
+            })
+        }
+        RaycastWinding::TwoSided => match backface_culling {
+            // Both faces of a two sided triangle are front faces, so there is no back face to hit.
+            Backfaces::Only => None,
+            _ => raycast_moller_trumbore(ray, triangle, Backfaces::Include),
+        },
+    }
 }
 
 #[derive(Default, Debug)]
@@ -310,6 +1456,11 @@ impl RayHit {
     }
 }
 
+/// How far outside a triangle, in barycentric coordinates, a hit is still accepted. Rounding can
+/// put a ray through a shared edge just outside of both triangles, so without this tolerance the
+/// ray would pass through the seam of a closed mesh.
+const BARYCENTRIC_TOLERANCE: f32 = 1e-6;
+
 /// Implementation of the Möller-Trumbore ray-triangle intersection test
 pub fn raycast_moller_trumbore(
     ray: &Ray3d,
@@ -327,13 +1478,19 @@ pub fn raycast_moller_trumbore(
             // if the determinant is negative the triangle is back facing
             // if the determinant is close to 0, the ray misses the triangle
             // This test checks both cases
-            if determinant < EPSILON {
+            if determinant < f32::EPSILON {
                 return None;
             }
         }
         Backfaces::Include => {
             // ray and triangle are parallel if det is close to 0
-            if determinant.abs() < EPSILON {
+            if determinant.abs() < f32::EPSILON {
+                return None;
+            }
+        }
+        Backfaces::Only => {
+            // The opposite of culling, front facing triangles have a positive determinant
+            if determinant > -f32::EPSILON {
                 return None;
             }
         }
@@ -343,13 +1500,13 @@ pub fn raycast_moller_trumbore(
 
     let t_vec = ray.origin - triangle.v0();
     let u = t_vec.dot(p_vec) * determinant_inverse;
-    if !(0.0..=1.0).contains(&u) {
+    if !(-BARYCENTRIC_TOLERANCE..=1.0 + BARYCENTRIC_TOLERANCE).contains(&u) {
         return None;
     }
 
     let q_vec = t_vec.cross(vector_v0_to_v1);
     let v = ray.direction.dot(q_vec) * determinant_inverse;
-    if v < 0.0 || u + v > 1.0 {
+    if v < -BARYCENTRIC_TOLERANCE || u + v > 1.0 + BARYCENTRIC_TOLERANCE {
         return None;
     }
 
@@ -362,9 +1519,106 @@ pub fn raycast_moller_trumbore(
     })
 }
 
+/// The point where a ray comes closest to some geometry.
+#[derive(Default, Debug, Clone, Copy)]
+pub struct ClosestApproach {
+    /// Distance along the ray to the point on the ray that is closest to the geometry.
+    pub ray_distance: f32,
+    /// The point on the geometry that is closest to the ray.
+    pub position: Vec3A,
+    /// The distance between the ray and the geometry at the point of closest approach.
+    pub separation: f32,
+}
+
+/// Finds the point where a ray comes closest to a triangle. Both faces of the triangle are
+/// considered, and the separation is zero if the ray intersects the triangle.
+pub fn ray_triangle_closest_approach(
+    ray: &Ray3d,
+    triangle: &impl TriangleTrait,
+) -> ClosestApproach {
+    let hit =
+        ray_triangle_intersection(ray, triangle, Backfaces::Include, RaycastWinding::TwoSided);
+    if let Some(hit) = hit {
+        if hit.distance >= 0.0 {
+            return ClosestApproach {
+                ray_distance: hit.distance,
+                position: ray.origin + ray.direction * hit.distance,
+                separation: 0.0,
+            };
+        }
+    }
+
+    let vertices = [triangle.v0(), triangle.v1(), triangle.v2()];
+    let mut closest = (0..3)
+        .map(|i| ray_segment_closest_approach(ray, vertices[i], vertices[(i + 1) % 3]))
+        .min_by(|a, b| a.separation.total_cmp(&b.separation))
+        .unwrap_or_default();
+
+    // The ray origin might be closer to the face of the triangle than to any of its edges.
+    let normal = (vertices[1] - vertices[0]).cross(vertices[2] - vertices[0]);
+    if normal.length_squared() > f32::EPSILON {
+        let normal = normal.normalize();
+        let projected = ray.origin - normal * normal.dot(ray.origin - vertices[0]);
+        let is_inside = (0..3).all(|i| {
+            let edge = vertices[(i + 1) % 3] - vertices[i];
+            edge.cross(projected - vertices[i]).dot(normal) >= 0.0
+        });
+        let separation = projected.distance(ray.origin);
+        if is_inside && separation < closest.separation {
+            closest = ClosestApproach {
+                ray_distance: 0.0,
+                position: projected,
+                separation,
+            };
+        }
+    }
+    closest
+}
+
+/// Finds the point where a ray comes closest to the line segment between `a` and `b`.
+fn ray_segment_closest_approach(ray: &Ray3d, a: Vec3A, b: Vec3A) -> ClosestApproach {
+    // Source: Real-Time Collision Detection, Christer Ericson, 5.1.9
+    let segment = b - a;
+    let r = ray.origin - a;
+    let e = segment.length_squared();
+    let b_dot = ray.direction.dot(segment);
+    let c = ray.direction.dot(r);
+    let f = segment.dot(r);
+
+    let (t, s) = if e <= f32::EPSILON {
+        ((-c).max(0.0), 0.0)
+    } else {
+        let denominator = e - b_dot * b_dot;
+        let t = if denominator > f32::EPSILON {
+            ((b_dot * f - c * e) / denominator).max(0.0)
+        } else {
+            0.0
+        };
+        let s = (b_dot * t + f) / e;
+        if s < 0.0 {
+            ((-c).max(0.0), 0.0)
+        } else if s > 1.0 {
+            ((b_dot - c).max(0.0), 1.0)
+        } else {
+            (t, s)
+        }
+    };
+
+    let position = a + segment * s;
+    ClosestApproach {
+        ray_distance: t,
+        position,
+        separation: position.distance(ray.origin + ray.direction * t),
+    }
+}
+
 #[cfg(test)]
 mod tests {
-    use bevy::math::Vec3;
+    use bevy::{
+        math::{Quat, Vec2, Vec3, Vec4},
+        prelude::{shape, GlobalTransform, Transform},
+        render::{mesh::MeshVertexAttribute, render_resource::VertexFormat},
+    };
 
     use super::*;
 
@@ -377,15 +1631,939 @@ mod tests {
     fn raycast_triangle_mt() {
         let triangle = Triangle::from([V0.into(), V1.into(), V2.into()]);
         let ray = Ray3d::new(Vec3::ZERO, Vec3::X);
-        let result = ray_triangle_intersection(&ray, &triangle, Backfaces::Include);
+        let result = ray_triangle_intersection(
+            &ray,
+            &triangle,
+            Backfaces::Include,
+            RaycastWinding::CounterClockwise,
+        );
         assert!(result.unwrap().distance - 1.0 <= f32::EPSILON);
     }
 
+    #[test]
+    fn raycast_triangle_mt_hits_rounded_edges() {
+        // A ray through the edge from V1 to V2, rounded just outside of the triangle.
+        let triangle = Triangle::from([V0.into(), V1.into(), V2.into()]);
+        let ray = Ray3d::new(Vec3::new(0.0, 0.0, -1.000001), Vec3::X);
+        let result = ray_triangle_intersection(
+            &ray,
+            &triangle,
+            Backfaces::Include,
+            RaycastWinding::CounterClockwise,
+        );
+        assert!(result.is_some());
+    }
+
     #[test]
     fn raycast_triangle_mt_culling() {
         let triangle = Triangle::from([V2.into(), V1.into(), V0.into()]);
         let ray = Ray3d::new(Vec3::ZERO, Vec3::X);
-        let result = ray_triangle_intersection(&ray, &triangle, Backfaces::Cull);
+        let result = ray_triangle_intersection(
+            &ray,
+            &triangle,
+            Backfaces::Cull,
+            RaycastWinding::CounterClockwise,
+        );
         assert!(result.is_none());
     }
+
+    fn mesh_intersection_normal(
+        vertices: [[f32; 3]; 3],
+        normals: Option<&[[f32; 3]]>,
+        transform: Mat4,
+        backfaces: Backfaces,
+    ) -> Option<Vec3> {
+        let ray = Ray3d::new(Vec3::ZERO, Vec3::X);
+        ray_mesh_intersection(
+            &transform,
+            &vertices,
+            normals,
+            &ray,
+            None::<&Vec<u32>>,
+            &MeshRaycastOptions {
+                backfaces,
+                ..Default::default()
+            },
+        )
+        .map(|intersection| intersection.normal())
+    }
+
+    #[test]
+    fn max_distance_is_measured_in_world_space() {
+        // A large triangle, with every vertex beyond the maximum distance, under a scale that
+        // halves distances along the ray in world space.
+        let vertices = [
+            [-100.0, 0.0, -100.0],
+            [0.0, 0.0, 100.0],
+            [100.0, 0.0, -100.0],
+        ];
+        let transform = Mat4::from_scale_rotation_translation(
+            Vec3::new(3.0, 0.5, 2.0),
+            Quat::IDENTITY,
+            Vec3::Y,
+        );
+        let ray = Ray3d::new(Vec3::Y * 5.0, Vec3::NEG_Y);
+        let hit = |max_distance| {
+            ray_mesh_intersection(
+                &transform,
+                &vertices,
+                None,
+                &ray,
+                None::<&Vec<u32>>,
+                &MeshRaycastOptions {
+                    max_distance: Some(max_distance),
+                    ..Default::default()
+                },
+            )
+            .map(|intersection| intersection.distance())
+        };
+        assert!((hit(4.5).unwrap() - 4.0).abs() < 1e-5);
+        assert!(hit(3.5).is_none());
+    }
+
+    #[test]
+    fn min_distance_skips_nearer_triangles() {
+        // Two flat triangles seen from above, under a scale that halves distances along the ray in
+        // world space.
+        let triangle = |y| [[-1.0, y, -1.0], [0.0, y, 1.0], [1.0, y, -1.0]];
+        let vertices = [triangle(0.0), triangle(2.0)].concat();
+        let transform = Mat4::from_scale(Vec3::new(1.0, 0.5, 1.0));
+        let ray = Ray3d::new(Vec3::Y * 5.0, Vec3::NEG_Y);
+        let hit = |min_distance| {
+            ray_mesh_intersection(
+                &transform,
+                &vertices,
+                None,
+                &ray,
+                None::<&Vec<u32>>,
+                &MeshRaycastOptions {
+                    min_distance,
+                    ..Default::default()
+                },
+            )
+            .map(|intersection| intersection.distance())
+        };
+        assert!((hit(0.0).unwrap() - 4.0).abs() < 1e-5);
+        assert!((hit(4.5).unwrap() - 5.0).abs() < 1e-5);
+        assert!(hit(5.5).is_none());
+    }
+
+    #[test]
+    fn any_intersection_stops_at_the_first_triangle() {
+        // Two flat triangles seen from above, the far one first.
+        let triangle = |y| [[-1.0, y, -1.0], [0.0, y, 1.0], [1.0, y, -1.0]];
+        let vertices = [triangle(0.0), triangle(1.0)].concat();
+        let ray = Ray3d::new(Vec3::Y * 5.0, Vec3::NEG_Y);
+        let cast = |any: bool| {
+            let ray_mesh_intersection = match any {
+                true => ray_mesh_any_intersection,
+                false => ray_mesh_intersection,
+            };
+            ray_mesh_intersection(
+                &Mat4::IDENTITY,
+                &vertices,
+                None,
+                &ray,
+                None::<&Vec<u32>>,
+                &MeshRaycastOptions::default(),
+            )
+            .map(|intersection| intersection.distance())
+        };
+        assert_eq!(cast(false), Some(4.0));
+        assert_eq!(cast(true), Some(5.0));
+    }
+
+    #[test]
+    fn intersections_are_capped_to_the_nearest() {
+        // A stack of flat triangles seen from above, the farthest first.
+        let positions: Vec<_> = (0..10)
+            .flat_map(|y| {
+                let y = y as f32;
+                [[-1.0, y, -1.0], [0.0, y, 1.0], [1.0, y, -1.0]]
+            })
+            .collect();
+        let mut mesh = Mesh::new(PrimitiveTopology::TriangleList);
+        mesh.insert_attribute(Mesh::ATTRIBUTE_POSITION, positions);
+        let ray = Ray3d::new(Vec3::Y * 20.0, Vec3::NEG_Y);
+        let distances = |max_hits| -> Vec<_> {
+            let max_hits = NonZeroUsize::new(max_hits).unwrap();
+            let options = MeshRaycastOptions::default().with_max_hits(max_hits);
+            ray_intersections_over_mesh(&mesh, &Mat4::IDENTITY, &ray, &options)
+                .iter()
+                .map(|intersection| intersection.distance())
+                .collect()
+        };
+        assert_eq!(distances(3), vec![11.0, 12.0, 13.0]);
+        assert_eq!(distances(100).len(), 10);
+    }
+
+    #[test]
+    fn max_slope_skips_steep_triangles() {
+        // A steep triangle above a flat one, seen from above.
+        let flat = [[-1.0, 0.0, -1.0], [0.0, 0.0, 1.0], [1.0, 0.0, -1.0]];
+        let steep = flat.map(|vertex| {
+            let vertex = Quat::from_rotation_x(1.0).mul_vec3(vertex.into());
+            (vertex + Vec3::Y * 2.0).to_array()
+        });
+        let vertices = [flat, steep].concat();
+        let max_slope = MaxSlope::new(0.5, Vec3::Y);
+
+        let rotation = Mat4::from_rotation_z(0.7);
+        for transform in [
+            Mat4::IDENTITY,
+            rotation,
+            Mat4::from_scale(Vec3::new(-1.0, 1.0, 1.0)),
+        ] {
+            let ray = Ray3d::new(
+                transform.transform_point3(Vec3::Y * 5.0),
+                transform.transform_vector3(Vec3::NEG_Y),
+            );
+            let max_slope = MaxSlope {
+                up: transform.transform_vector3(max_slope.up),
+                ..max_slope
+            };
+            let hit = |max_slope| {
+                ray_mesh_intersection(
+                    &transform,
+                    &vertices,
+                    None,
+                    &ray,
+                    None::<&Vec<u32>>,
+                    &MeshRaycastOptions {
+                        backfaces: Backfaces::Include,
+                        max_slope,
+                        ..Default::default()
+                    },
+                )
+                .map(|hit| hit.distance())
+            };
+            assert!(hit(None).unwrap() < 4.0);
+            assert!((hit(Some(max_slope)).unwrap() - 5.0).abs() < 1e-5);
+            // No triangle is flat enough.
+            let max_slope = MaxSlope::new(0.5, transform.transform_vector3(Vec3::X));
+            assert!(hit(Some(max_slope)).is_none());
+        }
+    }
+
+    #[test]
+    fn barycentric_coords_weight_triangle_vertices() {
+        let vertices = [[0.0, 0.0, 0.0], [2.0, 0.0, 0.0], [0.0, 1.0, 0.0]];
+        let transform = Mat4::from_scale_rotation_translation(
+            Vec3::new(2.0, 1.0, 1.0),
+            Quat::from_rotation_y(0.3),
+            Vec3::new(1.0, 2.0, 3.0),
+        );
+        let [v0, v1, v2] = vertices.map(Vec3::from);
+        let centroid = (v0 + v1 + v2) / 3.0;
+        // Points exactly on the edges of the triangle can be missed due to rounding, so they are
+        // moved towards its centroid slightly.
+        let inside = |point: Vec3| point.lerp(centroid, 1e-4);
+        let points = [
+            (v0, Vec3::X),
+            (v1, Vec3::Y),
+            (v2, Vec3::Z),
+            ((v0 + v1) / 2.0, Vec3::new(0.5, 0.5, 0.0)),
+            ((v1 + v2) / 2.0, Vec3::new(0.0, 0.5, 0.5)),
+            ((v2 + v0) / 2.0, Vec3::new(0.5, 0.0, 0.5)),
+            (centroid, Vec3::splat(1.0 / 3.0)),
+        ];
+        for (point, expected) in points {
+            let target = transform.transform_point3(inside(point));
+            let normal = transform.transform_vector3(Vec3::Z);
+            let ray = Ray3d::new(target + normal, -normal);
+            let hit = ray_mesh_intersection(
+                &transform,
+                &vertices,
+                None,
+                &ray,
+                None::<&Vec<u32>>,
+                &MeshRaycastOptions {
+                    backfaces: Backfaces::Include,
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+            let weights = hit.barycentric_coords().unwrap();
+            assert!(
+                weights.abs_diff_eq(expected, 1e-3),
+                "{weights} != {expected}"
+            );
+            assert!((weights.x + weights.y + weights.z - 1.0).abs() < 1e-5);
+            // The weights are in the order of the world space vertices of the hit triangle.
+            let triangle = hit.triangle().unwrap();
+            let interpolated = Vec3::from(triangle.v0) * weights.x
+                + Vec3::from(triangle.v1) * weights.y
+                + Vec3::from(triangle.v2) * weights.z;
+            assert!(interpolated.abs_diff_eq(hit.position(), 1e-4));
+        }
+    }
+
+    #[test]
+    fn triangle_index_locates_hit() {
+        // A quad split along its diagonal, with a larger triangle behind it.
+        let positions = vec![
+            [-1.0, -1.0, 0.0],
+            [1.0, -1.0, 0.0],
+            [1.0, 1.0, 0.0],
+            [-1.0, 1.0, 0.0],
+            [-4.0, -4.0, -5.0],
+            [4.0, -4.0, -5.0],
+            [0.0, 4.0, -5.0],
+        ];
+        let quad = [0, 1, 2, 0, 2, 3];
+        let behind = [4, 5, 6];
+        // The triangle behind is skipped without being tested when it comes after the quad, and hit
+        // first then replaced by the quad when it comes before.
+        let orders = [
+            (quad.into_iter().chain(behind).collect::<Vec<u32>>(), [0, 1]),
+            (behind.into_iter().chain(quad).collect(), [1, 2]),
+        ];
+        for (indices, expected) in orders {
+            let u16_indices = Indices::U16(indices.iter().map(|&i| i as u16).collect());
+            for indices in [Indices::U32(indices), u16_indices] {
+                let mut mesh = Mesh::new(PrimitiveTopology::TriangleList);
+                mesh.insert_attribute(Mesh::ATTRIBUTE_POSITION, positions.clone());
+                mesh.set_indices(Some(indices));
+                let triangle_index = |x, y| {
+                    let ray = Ray3d::new(Vec3::new(x, y, 5.0), Vec3::NEG_Z);
+                    let winding = RaycastWinding::CounterClockwise;
+                    let backfaces = Backfaces::Cull;
+                    ray_intersection_over_mesh(
+                        &mesh,
+                        &Mat4::IDENTITY,
+                        &ray,
+                        &MeshRaycastOptions {
+                            backfaces,
+                            winding,
+                            ..Default::default()
+                        },
+                    )
+                    .unwrap()
+                    .triangle_index()
+                };
+                assert_eq!(triangle_index(0.5, -0.5), Some(expected[0]));
+                assert_eq!(triangle_index(-0.5, 0.5), Some(expected[1]));
+            }
+        }
+    }
+
+    #[test]
+    fn mesh_triangles_read_every_layout() {
+        let quad = Mesh::from(shape::Quad::new(Vec2::new(2.0, 2.0)));
+        let triangles: Vec<_> = mesh_triangles(&quad).unwrap().collect();
+        assert_eq!(triangles.len(), 2);
+        let mut u16_quad = quad.clone();
+        let indices = quad.indices().unwrap().iter().map(|i| i as u16).collect();
+        u16_quad.set_indices(Some(Indices::U16(indices)));
+        let mut unindexed = quad.clone();
+        unindexed.duplicate_vertices();
+        for mesh in [&u16_quad, &unindexed] {
+            assert!(mesh_triangles(mesh).unwrap().eq(triangles.iter().copied()));
+        }
+
+        let transform = Mat4::from_translation(Vec3::new(1.0, 2.0, 3.0));
+        let world = mesh_triangles_transformed(&quad, &transform).unwrap();
+        assert_eq!(world.clone().count(), 2);
+        for (world, local) in world.zip(&triangles) {
+            assert_eq!(world.v0, transform.transform_point3a(local.v0));
+            assert_eq!(world.v2, transform.transform_point3a(local.v2));
+        }
+    }
+
+    #[test]
+    fn mesh_triangles_report_malformed_meshes() {
+        let quad = Mesh::from(shape::Quad::default());
+        let error = |mesh: &Mesh| mesh_triangles(mesh).err();
+
+        let lines = Mesh::new(PrimitiveTopology::LineList);
+        let topology = MeshExtractError::UnsupportedTopology(PrimitiveTopology::LineList);
+        assert_eq!(error(&lines), Some(topology));
+
+        let mut without_positions = quad.clone();
+        without_positions.remove_attribute(Mesh::ATTRIBUTE_POSITION);
+        assert_eq!(
+            error(&without_positions),
+            Some(MeshExtractError::MissingPositions)
+        );
+        let ray = Ray3d::new(Vec3::Z, Vec3::NEG_Z);
+        let winding = RaycastWinding::CounterClockwise;
+        let hit = |mesh| {
+            ray_intersection_over_mesh(
+                mesh,
+                &Mat4::IDENTITY,
+                &ray,
+                &MeshRaycastOptions {
+                    winding,
+                    ..Default::default()
+                },
+            )
+        };
+        assert!(hit(&without_positions).is_none());
+
+        let mut flat_positions = quad.clone();
+        let position_2d = MeshVertexAttribute::new("Vertex_Position", 0, VertexFormat::Float32x2);
+        assert_eq!(position_2d.id, Mesh::ATTRIBUTE_POSITION.id);
+        flat_positions.insert_attribute(position_2d, vec![[0.0; 2]; 4]);
+        let format = MeshExtractError::UnsupportedPositionFormat(VertexFormat::Float32x2);
+        assert_eq!(error(&flat_positions), Some(format));
+        assert!(hit(&flat_positions).is_none());
+
+        let mut incomplete = quad.clone();
+        incomplete.set_indices(Some(Indices::U32(vec![0, 1, 2, 0])));
+        let len = MeshExtractError::IncompleteTriangle { len: 4 };
+        assert_eq!(error(&incomplete), Some(len));
+        let mut incomplete = quad.clone();
+        incomplete.set_indices(None);
+        let len = MeshExtractError::IncompleteTriangle { len: 4 };
+        assert_eq!(error(&incomplete), Some(len));
+
+        let mut out_of_bounds = quad;
+        out_of_bounds.set_indices(Some(Indices::U16(vec![0, 1, 9])));
+        let bounds = MeshExtractError::IndexOutOfBounds {
+            index: 9,
+            vertex_count: 4,
+        };
+        assert_eq!(error(&out_of_bounds), Some(bounds));
+        assert!(smooth_normals(&out_of_bounds, 1.0).is_none());
+    }
+
+    #[test]
+    fn lines_are_hit_within_the_tolerance() {
+        let positions = vec![[-1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [1.0, 0.0, 0.0]];
+        let mut strip = Mesh::new(PrimitiveTopology::LineStrip);
+        strip.insert_attribute(Mesh::ATTRIBUTE_POSITION, positions.clone());
+        // A list of the same vertices only has the first segment of the strip.
+        let mut list = Mesh::new(PrimitiveTopology::LineList);
+        list.insert_attribute(Mesh::ATTRIBUTE_POSITION, positions);
+
+        // The ray passes 0.05 / sqrt(2) from the second segment.
+        let ray = Ray3d::new(Vec3::new(0.5, 0.45, 1.0), Vec3::NEG_Z);
+        let hit = |mesh: &Mesh, tolerance: f32| {
+            ray_line_mesh_intersection(mesh, &Mat4::IDENTITY, &ray, tolerance, 0.0, None)
+        };
+        let strip_hit = hit(&strip, 0.05).unwrap();
+        let nearest = Vec3::new(0.525, 0.475, 0.0);
+        assert!(strip_hit.position().abs_diff_eq(nearest, 1e-5));
+        assert!((strip_hit.distance() - 1.0).abs() < 1e-5);
+        assert_eq!(strip_hit.normal(), Vec3::Z);
+        assert!(hit(&strip, 0.01).is_none());
+        assert!(hit(&list, 0.05).is_none());
+        assert!(hit(&Mesh::from(shape::Quad::default()), 0.05).is_none());
+    }
+
+    #[test]
+    fn triangle_strips_are_hit_on_every_triangle() {
+        let mut strip = Mesh::new(PrimitiveTopology::TriangleStrip);
+        let positions = vec![
+            [-1.0, -1.0, 0.0],
+            [1.0, -1.0, 0.0],
+            [-1.0, 1.0, 0.0],
+            [1.0, 1.0, 0.0],
+        ];
+        let uvs: Vec<[f32; 2]> = positions.iter().map(|[x, y, _]| [*x, *y]).collect();
+        strip.insert_attribute(Mesh::ATTRIBUTE_POSITION, positions);
+        strip.insert_attribute(Mesh::ATTRIBUTE_UV_0, uvs);
+        // The same two triangles, split into two strips by a restart index.
+        let mut restarted = strip.clone();
+        restarted.set_indices(Some(Indices::U16(vec![0, 1, 2, u16::MAX, 1, 3, 2])));
+
+        let hit = |mesh: &Mesh, x: f32, y: f32| {
+            let ray = Ray3d::new(Vec3::new(x, y, 1.0), Vec3::NEG_Z);
+            let winding = RaycastWinding::CounterClockwise;
+            // Backfaces are culled, so the second triangle is only hit if its winding is flipped.
+            ray_intersection_over_mesh(
+                mesh,
+                &Mat4::IDENTITY,
+                &ray,
+                &MeshRaycastOptions {
+                    winding,
+                    ..Default::default()
+                },
+            )
+            .unwrap()
+        };
+        for (mesh, second_triangle) in [(&strip, 1), (&restarted, 4)] {
+            assert_eq!(mesh_triangles(mesh).unwrap().count(), 2);
+            for (x, y, triangle) in [(-0.5, -0.5, 0), (0.5, 0.5, second_triangle)] {
+                let hit = hit(mesh, x, y);
+                assert_eq!(hit.triangle_index(), Some(triangle));
+                assert!(hit.position().abs_diff_eq(Vec3::new(x, y, 0.0), 1e-5));
+                assert_eq!(hit.normal(), Vec3::Z);
+                assert!(hit.uv().unwrap().abs_diff_eq(Vec2::new(x, y), 1e-5));
+            }
+        }
+    }
+
+    #[test]
+    fn uv_is_interpolated_at_hit() {
+        // The UVs of a quad go from (0, 0) at its top left corner to (1, 1) at its bottom right.
+        let quad = Mesh::from(shape::Quad::default());
+        let mut unindexed = quad.clone();
+        unindexed.duplicate_vertices();
+        let uv = |mesh: &Mesh, x: f32, y: f32| {
+            let ray = Ray3d::new(Vec3::new(x, y, 1.0), Vec3::NEG_Z);
+            let winding = RaycastWinding::CounterClockwise;
+            ray_intersection_over_mesh(
+                mesh,
+                &Mat4::IDENTITY,
+                &ray,
+                &MeshRaycastOptions {
+                    winding,
+                    ..Default::default()
+                },
+            )
+            .unwrap()
+            .uv()
+        };
+        for (x, y) in [(0.0, 0.0), (-0.4, 0.3), (0.25, -0.1), (0.45, 0.45)] {
+            let expected = Vec2::new(x + 0.5, 0.5 - y);
+            for mesh in [&quad, &unindexed] {
+                let actual = uv(mesh, x, y).unwrap();
+                assert!(actual.abs_diff_eq(expected, 1e-5), "{actual} != {expected}");
+            }
+        }
+
+        let mut without_uvs = quad.clone();
+        without_uvs.remove_attribute(Mesh::ATTRIBUTE_UV_0);
+        assert_eq!(uv(&without_uvs, 0.1, 0.1), None);
+        let mut other_layout = quad;
+        let uvw = MeshVertexAttribute::new("Vertex_Uv", 2, VertexFormat::Float32x3);
+        assert_eq!(uvw.id, Mesh::ATTRIBUTE_UV_0.id);
+        other_layout.insert_attribute(uvw, vec![[0.0; 3]; 4]);
+        assert_eq!(uv(&other_layout, 0.1, 0.1), None);
+    }
+
+    #[test]
+    fn vertex_color_is_interpolated_at_hit() {
+        let mut triangle = Mesh::new(PrimitiveTopology::TriangleList);
+        let positions = vec![[-1.0, -1.0, 0.0], [1.0, -1.0, 0.0], [0.0, 1.0, 0.0]];
+        triangle.insert_attribute(Mesh::ATTRIBUTE_POSITION, positions);
+        let color = |mesh: &Mesh, x: f32, y: f32| {
+            let ray = Ray3d::new(Vec3::new(x, y, 1.0), Vec3::NEG_Z);
+            let winding = RaycastWinding::CounterClockwise;
+            ray_intersection_over_mesh(
+                mesh,
+                &Mat4::IDENTITY,
+                &ray,
+                &MeshRaycastOptions {
+                    winding,
+                    ..Default::default()
+                },
+            )
+            .unwrap()
+            .vertex_color()
+        };
+        assert_eq!(color(&triangle, 0.0, 0.0), None);
+
+        let mut rgba = triangle.clone();
+        let colors = vec![
+            [1.0, 0.0, 0.0, 1.0],
+            [0.0, 1.0, 0.0, 1.0],
+            [0.0, 0.0, 1.0, 0.5],
+        ];
+        rgba.insert_attribute(Mesh::ATTRIBUTE_COLOR, colors);
+        let mut rgb = triangle.clone();
+        let rgb_attribute = MeshVertexAttribute::new("Vertex_Color", 5, VertexFormat::Float32x3);
+        assert_eq!(rgb_attribute.id, Mesh::ATTRIBUTE_COLOR.id);
+        let colors = vec![[1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, 0.0, 1.0]];
+        rgb.insert_attribute(rgb_attribute, colors);
+        let smoothed = smooth_normals(&rgba, 1.0).unwrap();
+
+        // The centroid is an equal mix of the corners, and points near a corner take its color.
+        let centroid = Vec3::new(0.0, -1.0 / 3.0, 0.0);
+        let expected = Vec4::new(1.0, 1.0, 1.0, 2.5) / 3.0;
+        for mesh in [&rgba, &smoothed] {
+            let actual = color(mesh, centroid.x, centroid.y).unwrap();
+            assert!(actual.abs_diff_eq(expected, 1e-5), "{actual} != {expected}");
+        }
+        let actual = color(&rgb, centroid.x, centroid.y).unwrap();
+        assert!(actual.abs_diff_eq(Vec4::new(1.0, 1.0, 1.0, 3.0) / 3.0, 1e-5));
+        let near_green = color(&rgb, 0.99, -0.995).unwrap();
+        assert!(
+            near_green.abs_diff_eq(Vec4::new(0.0, 1.0, 0.0, 1.0), 0.02),
+            "{near_green}"
+        );
+
+        let mut other_layout = triangle;
+        let uv_colors = MeshVertexAttribute::new("Vertex_Color", 5, VertexFormat::Float32x2);
+        other_layout.insert_attribute(uv_colors, vec![[0.0; 2]; 3]);
+        assert_eq!(color(&other_layout, 0.0, 0.0), None);
+    }
+
+    #[test]
+    fn backface_hits_are_flagged() {
+        let cube = Mesh::from(shape::Cube { size: 1.0 });
+        let outside = Ray3d::new(Vec3::new(0.1, 0.2, 5.0), Vec3::NEG_Z);
+        let inside = Ray3d::new(Vec3::new(0.1, 0.2, 0.0), Vec3::NEG_Z);
+        let cast = |transform: Mat4, ray: Ray3d, backfaces, winding| {
+            ray_intersection_over_mesh(
+                &cube,
+                &transform,
+                &ray,
+                &MeshRaycastOptions {
+                    backfaces,
+                    winding,
+                    ..Default::default()
+                },
+            )
+        };
+        // A mirrored transform flips the winding of the triangles in world space.
+        for transform in [Mat4::IDENTITY, Mat4::from_scale(Vec3::new(-2.0, 1.0, 1.0))] {
+            let ccw = RaycastWinding::CounterClockwise;
+            let hit = cast(transform, outside, Backfaces::Include, ccw).unwrap();
+            assert!(!hit.is_backface());
+            let hit = cast(transform, inside, Backfaces::Include, ccw).unwrap();
+            assert!(hit.is_backface());
+            assert!(hit.normal().dot(inside.direction()) < 0.0);
+            assert!(cast(transform, inside, Backfaces::Cull, ccw).is_none());
+
+            // The back of a clockwise triangle is the front of a counter clockwise one.
+            let cw = RaycastWinding::Clockwise;
+            let hit = cast(transform, outside, Backfaces::Include, cw).unwrap();
+            assert!(hit.is_backface());
+            let two_sided = RaycastWinding::TwoSided;
+            let hit = cast(transform, inside, Backfaces::Cull, two_sided).unwrap();
+            assert!(!hit.is_backface());
+        }
+    }
+
+    #[test]
+    fn distance_is_the_ray_parameter() {
+        let cube = Mesh::from(shape::Cube { size: 1.0 });
+        let transform = Mat4::from_scale_rotation_translation(
+            Vec3::new(2.0, 0.5, 1.5),
+            Quat::from_rotation_y(0.4),
+            Vec3::new(0.5, -0.2, -6.0),
+        );
+        let plane = Primitive3d::Plane {
+            point: Vec3::new(0.0, 0.0, -20.0),
+            normal: Vec3::Z,
+        };
+        let winding = RaycastWinding::CounterClockwise;
+        // Directions that aren't normalized are normalized by the ray.
+        for direction in [
+            Vec3::new(0.001, 0.0, -0.01),
+            Vec3::new(0.3, 0.01, -7.0) * 100.0,
+        ] {
+            let ray = Ray3d::new(Vec3::new(0.0, -0.2, 2.0), direction);
+            let hit = ray_intersection_over_mesh(
+                &cube,
+                &transform,
+                &ray,
+                &MeshRaycastOptions {
+                    winding,
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+            assert!(ray.position(hit.distance()).distance(hit.position()) < 1e-5);
+            let hit = IntersectionData::from(ray.intersects_primitive(plane).unwrap());
+            assert!(ray.position(hit.distance()).distance(hit.position()) < 1e-4);
+            assert!((hit.distance() - hit.position().distance(ray.origin())).abs() < 1e-4);
+        }
+    }
+
+    #[test]
+    fn local_position_matches_world_position() {
+        let cube = Mesh::from(shape::Cube { size: 1.0 });
+        let transform = Transform::from_xyz(1.0, -2.0, 3.0)
+            .with_rotation(Quat::from_rotation_y(0.6) * Quat::from_rotation_x(-0.3))
+            .with_scale(Vec3::new(2.0, 0.5, 1.5));
+        let global = GlobalTransform::from(transform);
+        let winding = RaycastWinding::CounterClockwise;
+        for direction in [
+            Vec3::NEG_Z,
+            Vec3::new(1.0, 1.0, -1.0),
+            Vec3::new(-0.5, -1.0, 0.2),
+        ] {
+            let origin = transform.translation - direction.normalize() * 10.0;
+            let ray = Ray3d::new(origin + Vec3::new(0.1, 0.05, 0.0), direction);
+            let hit = ray_intersection_over_mesh(
+                &cube,
+                &transform.compute_matrix(),
+                &ray,
+                &MeshRaycastOptions {
+                    winding,
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+            let local = hit.local_position().unwrap();
+            // The local position is on the surface of the unit cube, in the space of its vertices.
+            assert!((local.abs().max_element() - 0.5).abs() < 1e-5, "{local}");
+            assert!(global.transform_point(local).distance(hit.position()) < 1e-4);
+            assert!(hit.to_local(&global).position.distance(local) < 1e-4);
+        }
+        let primitive = IntersectionData::new(Vec3::ZERO, Vec3::Y, 1.0, None);
+        assert_eq!(primitive.local_position(), None);
+    }
+
+    #[test]
+    fn normal_is_perpendicular_under_non_uniform_scale() {
+        let vertices = [[1.0, -1.0, -1.0], [1.0, -1.0, 1.0], [1.0, 1.0, 0.0]];
+        let transform = Mat4::from_scale(Vec3::new(1.0, 3.0, 1.0)) * Mat4::from_rotation_z(0.5);
+        let ray = Ray3d::new(Vec3::ZERO, Vec3::X);
+        let hit = ray_mesh_intersection(
+            &transform,
+            &vertices,
+            None,
+            &ray,
+            None::<&Vec<u32>>,
+            &MeshRaycastOptions::default(),
+        )
+        .unwrap();
+        let normal = hit.normal();
+        let triangle = hit.triangle().unwrap();
+        assert!((normal.length() - 1.0).abs() < 1e-5);
+        assert!(normal.dot((triangle.v1 - triangle.v0).into()).abs() < 1e-5);
+        assert!(normal.dot((triangle.v2 - triangle.v0).into()).abs() < 1e-5);
+        assert!(normal.dot(ray.direction()) < 0.0);
+    }
+
+    /// Raycasts the mesh from `origin` towards its center, returning the normal of the hit.
+    fn hit_normal(mesh: &Mesh, origin: Vec3) -> Vec3 {
+        let ray = Ray3d::new(origin, -origin);
+        ray_intersection_over_mesh(mesh, &Mat4::IDENTITY, &ray, &MeshRaycastOptions::default())
+            .unwrap()
+            .normal()
+    }
+
+    #[test]
+    fn resolved_position_follows_scale() {
+        let mesh = Mesh::from(shape::Cube::default());
+        let cast_transform = GlobalTransform::from_xyz(1.0, 0.0, 0.0);
+        let ray = Ray3d::new(Vec3::new(1.2, 0.3, 5.0), Vec3::NEG_Z);
+        let matrix = cast_transform.compute_matrix();
+        let hit = ray_intersection_over_mesh(&mesh, &matrix, &ray, &MeshRaycastOptions::default())
+            .unwrap();
+        let resolved = hit.resolve_world_position(&mesh, &cast_transform).unwrap();
+        assert!(resolved.distance(hit.position()) < 1e-5);
+
+        // The entity pulses after the raycast, and the hit stays on the scaled +Z face.
+        for scale in [0.5, 2.0] {
+            let transform = GlobalTransform::from(
+                Transform::from_xyz(1.0, 0.0, 0.0).with_scale(Vec3::splat(scale)),
+            );
+            let resolved = hit.resolve_world_position(&mesh, &transform).unwrap();
+            let expected = Vec3::new(1.0 + 0.2 * scale, 0.3 * scale, 0.5 * scale);
+            assert!(
+                resolved.distance(expected) < 1e-5,
+                "{resolved} != {expected}"
+            );
+        }
+    }
+
+    #[test]
+    fn clockwise_winding_hits_outside() {
+        let mut cube = Mesh::from(shape::Cube::default());
+        let Some(Indices::U32(indices)) = cube.indices() else {
+            panic!("cube is not indexed");
+        };
+        let reversed = indices.chunks(3).flat_map(|t| [t[0], t[2], t[1]]).collect();
+        cube.set_indices(Some(Indices::U32(reversed)));
+        let mut flat_cube = cube.clone();
+        flat_cube.remove_attribute(Mesh::ATTRIBUTE_NORMAL);
+
+        let mirror = Mat4::from_scale(Vec3::new(-1.0, 1.0, 1.0));
+        let ray = Ray3d::new(Vec3::new(0.2, 0.1, 5.0), Vec3::NEG_Z);
+        for (mesh, transform) in [
+            (&cube, Mat4::IDENTITY),
+            (&flat_cube, Mat4::IDENTITY),
+            (&cube, mirror),
+            (&flat_cube, mirror),
+        ] {
+            let hit = |winding| {
+                ray_intersection_over_mesh(
+                    mesh,
+                    &transform,
+                    &ray,
+                    &MeshRaycastOptions {
+                        winding,
+                        ..Default::default()
+                    },
+                )
+                .unwrap()
+            };
+            // Without the override, only the inside of the far face is a front face.
+            let hit_ccw = hit(RaycastWinding::CounterClockwise);
+            assert!((hit_ccw.position().z + 0.5).abs() < 1e-5);
+            for winding in [RaycastWinding::Clockwise, RaycastWinding::TwoSided] {
+                let hit = hit(winding);
+                assert!((hit.position().z - 0.5).abs() < 1e-5, "{winding:?}");
+                assert!(hit.normal().distance(Vec3::Z) < 1e-5, "{winding:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn hit_faces_select_quad_sides() {
+        use crate::markers::HitFaces;
+
+        // The quad faces +Z.
+        let quad = Mesh::from(shape::Quad::default());
+        let from_front = Ray3d::new(Vec3::new(0.1, 0.2, 1.0), Vec3::NEG_Z);
+        let from_back = Ray3d::new(Vec3::new(0.1, 0.2, -1.0), Vec3::Z);
+        for (hit_faces, hits_front, hits_back) in [
+            (HitFaces::Front, true, false),
+            (HitFaces::Back, false, true),
+            (HitFaces::Both, true, true),
+        ] {
+            let hit = |ray| {
+                let backfaces = hit_faces.backfaces();
+                let hit = ray_intersection_over_mesh(
+                    &quad,
+                    &Mat4::IDENTITY,
+                    &ray,
+                    &MeshRaycastOptions {
+                        backfaces,
+                        ..Default::default()
+                    },
+                );
+                if let Some(hit) = &hit {
+                    assert_eq!(hit.position(), Vec3::new(0.1, 0.2, 0.0), "{hit_faces:?}");
+                    assert!(hit.normal().dot(ray.direction()) < 0.0, "{hit_faces:?}");
+                }
+                hit.is_some()
+            };
+            assert_eq!(hit(from_front), hits_front, "{hit_faces:?}");
+            assert_eq!(hit(from_back), hits_back, "{hit_faces:?}");
+        }
+    }
+
+    #[test]
+    fn smooth_normals_keep_hard_edges() {
+        let mut cube = Mesh::from(shape::Cube::default());
+        cube.remove_attribute(Mesh::ATTRIBUTE_NORMAL);
+        let smoothed = smooth_normals(&cube, 60f32.to_radians()).unwrap();
+        // Close to an edge and a corner of the +Z face.
+        for target in [Vec3::new(0.49, 0.0, 0.5), Vec3::new(0.49, 0.49, 0.5)] {
+            let normal = hit_normal(&smoothed, target + Vec3::Z * 5.0);
+            assert!(normal.distance(Vec3::Z) < 1e-5, "{normal}");
+        }
+    }
+
+    #[test]
+    fn smooth_normals_smooth_spheres() {
+        let mut sphere = Mesh::from(shape::UVSphere {
+            radius: 1.0,
+            sectors: 8,
+            stacks: 6,
+        });
+        sphere.remove_attribute(Mesh::ATTRIBUTE_NORMAL);
+        let smoothed = smooth_normals(&sphere, 60f32.to_radians()).unwrap();
+        for origin in [Vec3::new(1.0, 0.3, 5.0), Vec3::new(-3.0, 2.0, 1.0)] {
+            let faceted = hit_normal(&sphere, origin);
+            let smooth = hit_normal(&smoothed, origin);
+            // The normal of a perfect sphere points away from its center.
+            let ray = Ray3d::new(origin, -origin);
+            let hit = ray_intersection_over_mesh(
+                &smoothed,
+                &Mat4::IDENTITY,
+                &ray,
+                &MeshRaycastOptions::default(),
+            );
+            let expected = hit.unwrap().position().normalize();
+            assert!(smooth.angle_between(expected) < faceted.angle_between(expected) * 0.5);
+        }
+    }
+
+    #[test]
+    fn vertex_normals_are_interpolated() {
+        let sphere = Mesh::from(shape::UVSphere {
+            radius: 1.0,
+            sectors: 8,
+            stacks: 6,
+        });
+        let mut faceted = sphere.clone();
+        faceted.remove_attribute(Mesh::ATTRIBUTE_NORMAL);
+        // Normals that don't match the positions are ignored, instead of indexed out of bounds.
+        let mut mismatched = sphere.clone();
+        let Some(VertexAttributeValues::Float32x3(normals)) =
+            sphere.attribute(Mesh::ATTRIBUTE_NORMAL)
+        else {
+            unreachable!()
+        };
+        mismatched.insert_attribute(
+            Mesh::ATTRIBUTE_NORMAL,
+            normals[..normals.len() / 2].to_vec(),
+        );
+        for origin in [Vec3::new(1.0, 0.3, 5.0), Vec3::new(-3.0, 2.0, 1.0)] {
+            let smooth = hit_normal(&sphere, origin);
+            let flat = hit_normal(&faceted, origin);
+            let ray = Ray3d::new(origin, -origin);
+            let hit = ray_intersection_over_mesh(
+                &sphere,
+                &Mat4::IDENTITY,
+                &ray,
+                &MeshRaycastOptions::default(),
+            );
+            let expected = hit.unwrap().position().normalize();
+            assert!(smooth.is_normalized());
+            assert!(smooth.angle_between(expected) < flat.angle_between(expected) * 0.5);
+            assert_eq!(hit_normal(&mismatched, origin), flat);
+        }
+        assert_eq!(check_mesh_normals(&sphere), Ok(()));
+        assert_eq!(check_mesh_normals(&faceted), Ok(()));
+        assert!(matches!(
+            check_mesh_normals(&mismatched),
+            Err(MeshExtractError::NormalCountMismatch { .. })
+        ));
+    }
+
+    #[test]
+    fn normal_faces_ray_for_both_windings() {
+        // Vertex normals that agree with the winding of each triangle.
+        for (vertices, vertex_normal) in [
+            ([V0, V1, V2], [-1.0, 0.0, 0.0]),
+            ([V2, V1, V0], [1.0, 0.0, 0.0]),
+        ] {
+            let normal =
+                mesh_intersection_normal(vertices, None, Mat4::IDENTITY, Backfaces::Include);
+            assert_eq!(normal, Some(Vec3::NEG_X));
+            let normals = [vertex_normal; 3];
+            let normal = mesh_intersection_normal(
+                vertices,
+                Some(&normals),
+                Mat4::IDENTITY,
+                Backfaces::Include,
+            );
+            assert_eq!(normal, Some(Vec3::NEG_X));
+        }
+    }
+
+    #[test]
+    fn normal_is_front_face_when_culling() {
+        let front = mesh_intersection_normal([V0, V1, V2], None, Mat4::IDENTITY, Backfaces::Cull);
+        assert_eq!(front, Some(Vec3::NEG_X));
+        let back = mesh_intersection_normal([V2, V1, V0], None, Mat4::IDENTITY, Backfaces::Cull);
+        assert_eq!(back, None);
+    }
+
+    #[test]
+    fn normal_faces_ray_when_mirrored() {
+        let mirror = Mat4::from_scale(Vec3::new(1.0, -1.0, 1.0));
+        for vertices in [[V0, V1, V2], [V2, V1, V0]] {
+            let normal = mesh_intersection_normal(vertices, None, mirror, Backfaces::Include);
+            assert_eq!(normal, Some(Vec3::NEG_X));
+        }
+    }
+
+    #[test]
+    fn closest_approach_intersecting() {
+        let triangle = Triangle::from([V0.into(), V1.into(), V2.into()]);
+        let ray = Ray3d::new(Vec3::ZERO, Vec3::X);
+        let approach = ray_triangle_closest_approach(&ray, &triangle);
+        assert!(approach.separation.abs() <= f32::EPSILON);
+        assert!((approach.ray_distance - 1.0).abs() <= f32::EPSILON);
+    }
+
+    #[test]
+    fn closest_approach_near_edge() {
+        let triangle = Triangle::from([V0.into(), V1.into(), V2.into()]);
+        let ray = Ray3d::new(Vec3::new(0.0, -1.5, 0.0), Vec3::X);
+        let approach = ray_triangle_closest_approach(&ray, &triangle);
+        assert!((approach.separation - 0.5).abs() <= 1e-5);
+        assert!((approach.ray_distance - 1.0).abs() <= 1e-5);
+        assert!(approach.position.distance(Vec3A::new(1.0, -1.0, 0.0)) <= 1e-5);
+    }
 }